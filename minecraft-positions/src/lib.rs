@@ -141,7 +141,7 @@ pub struct Rotation {
     pub z: f32,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct ChunkPosition {
     pub cx: i32,
     pub cy: i32,
@@ -184,3 +184,15 @@ impl ChunkColumnPosition {
         }
     }
 }
+
+impl std::ops::Add<BlockPositionInChunkColumn> for ChunkColumnPosition {
+    type Output = BlockPosition;
+
+    fn add(self, rhs: BlockPositionInChunkColumn) -> Self::Output {
+        BlockPosition {
+            x: self.cx * 16 + rhs.bx as i32,
+            y: rhs.y,
+            z: self.cz * 16 + rhs.bz as i32,
+        }
+    }
+}
@@ -142,6 +142,7 @@ pub fn generate_block_enum(data: serde_json::Value) {
     // Process a few fields
     let mut raw_harvest_tools: Vec<Vec<u32>> = Vec::new();
     let mut raw_materials: Vec<String> = Vec::new();
+    let mut raw_mineable_tools: Vec<Vec<String>> = Vec::new();
     for block in &blocks {
         raw_harvest_tools.push(
             block
@@ -163,6 +164,15 @@ pub fn generate_block_enum(data: serde_json::Value) {
             material = "unknown_material".to_string();
         }
         raw_materials.push(material.from_case(Case::Snake).to_case(Case::UpperCamel));
+        raw_mineable_tools.push(
+            block
+                .material
+                .iter()
+                .flat_map(|material| material.split(';'))
+                .filter_map(|part| part.strip_prefix("mineable/"))
+                .map(|tool| tool.to_string())
+                .collect(),
+        );
     }
 
     // Generate the MaterialBlock enum and array
@@ -195,6 +205,14 @@ pub fn generate_block_enum(data: serde_json::Value) {
     }
     harvest_tools.push(']');
 
+    // Generate the MINEABLE_TOOLS array
+    let mut mineable_tools = String::new();
+    mineable_tools.push('[');
+    for block_mineable_tools in raw_mineable_tools {
+        mineable_tools.push_str(&format!("&{:?}, ", block_mineable_tools));
+    }
+    mineable_tools.push(']');
+
     // Enumerate the air blocks
     let mut air_blocks = vec![false; expected as usize];
     for air_block in &[
@@ -290,6 +308,12 @@ impl Block {{
         unsafe {{*TEXT_IDS.get_unchecked((self as u32) as usize)}}
     }}
 
+    /// Finds a block by its text id, with or without the `minecraft:` namespace.
+    pub fn from_text_id(text_id: &str) -> Option<Block> {{
+        let text_id = text_id.strip_prefix("minecraft:").unwrap_or(text_id);
+        TEXT_IDS.iter().position(|t| *t == text_id).and_then(|id| Block::from_id(id as u32))
+    }}
+
     #[inline]
     pub fn default_state_id(self) -> u32 {{
         unsafe {{*DEFAULT_STATE_IDS.get_unchecked((self as u32) as usize)}}
@@ -347,6 +371,12 @@ impl Block {{
         unsafe {{*HARVEST_TOOLS.get_unchecked((self as u32) as usize)}}
     }}
 
+    /// The kinds of tools (`pickaxe`, `axe`, `shovel` or `hoe`) that mine this block faster.
+    #[inline]
+    pub fn mineable_tools(self) -> &'static [&'static str] {{
+        unsafe {{MINEABLE_TOOLS.get_unchecked((self as u32) as usize)}}
+    }}
+
     #[inline]
     pub fn light_emissions(self) -> u8 {{
         unsafe {{*LIGHT_EMISSIONS.get_unchecked((self as u32) as usize)}}
@@ -407,6 +437,7 @@ const ITEM_IDS: [u32; {max_value}] = {item_ids:?};
 const RESISTANCES: [f32; {max_value}] = {resistances:?};
 const MATERIALS: [Option<BlockMaterial>; {max_value}] = {materials};
 const HARVEST_TOOLS: [&[u32]; {max_value}] = {harvest_tools};
+const MINEABLE_TOOLS: [&[&str]; {max_value}] = {mineable_tools};
 const HARDNESSES: [f32; {max_value}] = {hardnesses:?};
 const LIGHT_EMISSIONS: [u8; {max_value}] = {light_emissions:?};
 const LIGHT_ABSORPTION: [u8; {max_value}] = {light_absorption:?};
@@ -432,6 +463,7 @@ const AIR_BLOCKS: [bool; {max_value}] = {air_blocks:?};
         materials = materials,
         resistances = blocks.iter().map(|b| b.resistance).collect::<Vec<_>>(),
         harvest_tools = harvest_tools,
+        mineable_tools = mineable_tools,
         hardnesses = blocks.iter().map(|b| b.hardness).collect::<Vec<_>>(),
         light_emissions = blocks.iter().map(|b| b.emit_light).collect::<Vec<_>>(),
         light_absorption = blocks.iter().map(|b| b.filter_light).collect::<Vec<_>>(),
@@ -740,6 +772,21 @@ pub fn generate_block_with_state_enum(data: serde_json::Value) {
         ));
     }
 
+    // Generate the property table used for string conversions
+    let mut properties = String::from("[");
+    for block in &blocks {
+        properties.push_str("&[");
+        for state in &block.states {
+            let values = match state.ty.as_str() {
+                "bool" => vec![String::from("true"), String::from("false")],
+                _ => state.values.clone().expect("No values for block state"),
+            };
+            properties.push_str(&format!("({:?}, &{:?}), ", state.name, values));
+        }
+        properties.push_str("], ");
+    }
+    properties.push(']');
+
     // Generate the code
     let code = format!(
         r#"//! Contains the [BlockWithState] enum to help with block state IDs.
@@ -779,6 +826,99 @@ impl BlockWithState {{
 {state_id_rebuild_arms}
         }}
     }}
+
+    /// Returns the properties of this block along with their possible values, as they appear in block state strings.
+    /// Properties are listed in the order used to compute state ids.
+    #[inline]
+    pub fn properties(&self) -> &'static [(&'static str, &'static [&'static str])] {{
+        PROPERTIES.get(self.block_id() as usize).copied().unwrap_or(&[])
+    }}
+
+    /// Returns the index of the value of each property, in the order of [BlockWithState::properties].
+    fn property_indexes(&self) -> Option<Vec<usize>> {{
+        let block = super::blocks::Block::from_id(self.block_id())?;
+        let mut offset = (self.block_state_id()? - block.state_id_range().start) as usize;
+        let properties = self.properties();
+        let mut indexes = vec![0; properties.len()];
+        for (i, (_, values)) in properties.iter().enumerate().rev() {{
+            indexes[i] = offset % values.len();
+            offset /= values.len();
+        }}
+        Some(indexes)
+    }}
+
+    fn from_property_indexes(block: super::blocks::Block, indexes: &[usize]) -> Option<BlockWithState> {{
+        let properties = PROPERTIES.get(block.id() as usize)?;
+        let mut offset = 0;
+        for ((_, values), index) in properties.iter().zip(indexes) {{
+            if *index >= values.len() {{
+                return None;
+            }}
+            offset = offset * values.len() + index;
+        }}
+        BlockWithState::from_state_id(block.state_id_range().start + offset as u32)
+    }}
+
+    /// Returns the value of a property, as it appears in block state strings (`facing` → `"north"`).
+    pub fn property(&self, name: &str) -> Option<&'static str> {{
+        let properties = self.properties();
+        let index = properties.iter().position(|(n, _)| *n == name)?;
+        let value_index = *self.property_indexes()?.get(index)?;
+        Some(properties[index].1[value_index])
+    }}
+
+    /// Returns this block with a property set to another value.
+    /// Returns None if the block has no such property or if the value is not valid for it.
+    pub fn with_property(&self, name: &str, value: &str) -> Option<BlockWithState> {{
+        let block = super::blocks::Block::from_id(self.block_id())?;
+        let properties = self.properties();
+        let index = properties.iter().position(|(n, _)| *n == name)?;
+        let value_index = properties[index].1.iter().position(|v| *v == value)?;
+        let mut indexes = self.property_indexes()?;
+        indexes[index] = value_index;
+        BlockWithState::from_property_indexes(block, &indexes)
+    }}
+}}
+
+/// Parses block state strings such as `minecraft:oak_stairs[facing=east,half=top]`.
+/// Properties that are not specified keep their default value.
+impl std::str::FromStr for BlockWithState {{
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<BlockWithState, &'static str> {{
+        let (text_id, properties) = match s.split_once('[') {{
+            Some((text_id, properties)) => (text_id, properties.strip_suffix(']').ok_or("Missing closing bracket in block state.")?),
+            None => (s, ""),
+        }};
+        let block = super::blocks::Block::from_text_id(text_id.trim()).ok_or("Unknown block in block state.")?;
+        let mut block_with_state = BlockWithState::from(block);
+        for property in properties.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {{
+            let (name, value) = property.split_once('=').ok_or("Invalid property in block state.")?;
+            block_with_state = block_with_state.with_property(name.trim(), value.trim()).ok_or("Invalid property value in block state.")?;
+        }}
+        Ok(block_with_state)
+    }}
+}}
+
+/// Formats the block state as `minecraft:oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]`.
+impl std::fmt::Display for BlockWithState {{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{
+        let block = super::blocks::Block::from_id(self.block_id()).ok_or(std::fmt::Error)?;
+        write!(f, "minecraft:{{}}", block.text_id())?;
+        let properties = self.properties();
+        if properties.is_empty() {{
+            return Ok(());
+        }}
+        let indexes = self.property_indexes().ok_or(std::fmt::Error)?;
+        write!(f, "[")?;
+        for (i, ((name, values), index)) in properties.iter().zip(indexes).enumerate() {{
+            if i > 0 {{
+                write!(f, ",")?;
+            }}
+            write!(f, "{{}}={{}}", name, values[index])?;
+        }}
+        write!(f, "]")
+    }}
 }}
 
 impl From<super::blocks::Block> for BlockWithState {{
@@ -803,6 +943,8 @@ impl<'a> MinecraftPacketPart<'a> for BlockWithState {{
     }}
 }}
 
+const PROPERTIES: [&[(&str, &[&str])]; {block_count}] = {properties};
+
 #[cfg(test)]
 mod tests {{
     use super::*;
@@ -815,13 +957,34 @@ mod tests {{
             assert_eq!(id, id_from_block);
         }}
     }}
+
+    #[test]
+    fn test_block_state_strings() {{
+        for id in 0..={max_block_state_id} {{
+            let block = BlockWithState::from_state_id(id).unwrap();
+            let parsed: BlockWithState = block.to_string().parse().unwrap();
+            assert_eq!(parsed.block_state_id(), Some(id));
+        }}
+
+        let stairs: BlockWithState = "minecraft:oak_stairs[facing=east,half=top]".parse().unwrap();
+        assert_eq!(stairs.property("facing"), Some("east"));
+        assert_eq!(stairs.property("half"), Some("top"));
+        assert_eq!(stairs.property("waterlogged"), Some("false"));
+        let stairs = stairs.with_property("facing", "west").unwrap();
+        assert_eq!(stairs.property("facing"), Some("west"));
+        assert_eq!(stairs.property("half"), Some("top"));
+        assert!(stairs.with_property("facing", "up").is_none());
+        assert!("minecraft:not_a_block".parse::<BlockWithState>().is_err());
+    }}
 }}
 "#,
         enum_definitions = enum_definitions_string,
         state_id_match_arms = state_id_match_arms,
         state_id_rebuild_arms = state_id_rebuild_arms,
         variants = variants,
-        max_block_state_id = blocks.last().unwrap().max_state_id
+        max_block_state_id = blocks.last().unwrap().max_state_id,
+        block_count = blocks.len(),
+        properties = properties,
     );
 
     File::create("src/ids/block_states.rs")
@@ -75,6 +75,12 @@ impl Item {{
         unsafe {{*TEXT_IDS.get_unchecked((self as u32) as usize)}}
     }}
 
+    /// Finds an item by its text id, with or without the `minecraft:` namespace.
+    pub fn from_text_id(text_id: &str) -> Option<Item> {{
+        let text_id = text_id.strip_prefix("minecraft:").unwrap_or(text_id);
+        TEXT_IDS.iter().position(|t| *t == text_id).and_then(|id| Item::from_id(id as u32))
+    }}
+
     #[inline]
     pub fn display_name(self) -> &'static str {{
         unsafe {{*DISPLAY_NAMES.get_unchecked((self as u32) as usize)}}
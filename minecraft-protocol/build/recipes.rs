@@ -288,9 +288,10 @@ pub fn generate_recipes(data: serde_json::Value, items: Vec<super::items::Item>)
         recipes_count += recipes.len();
     }
 
-    // Generate recipes
+    // Generate recipes, in the order of item ids as expected by the shortcuts
     let mut recipes_data = String::new();
-    for recipes in item_recipes.values() {
+    for item_id in 0..items.len() {
+        let Some(recipes) = item_recipes.get(&(item_id as u32)) else { continue };
         for recipe in recipes {
             match recipe {
                 Recipe::ShapeLess {
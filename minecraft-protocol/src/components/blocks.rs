@@ -1,20 +1,205 @@
-use crate::{*, nbt::NbtTag};
+use crate::{*, nbt::NbtTag, ids::blocks::Block};
 
 #[cfg_attr(test, derive(PartialEq))]
-#[derive(Debug, MinecraftPacketPart)]
+#[derive(Debug, Clone, MinecraftPacketPart)]
 pub struct BlockEntity {
     /// The packed section coordinates are relative to the chunk they are in values 0-15 are valid.
     /// ```python
     /// packed_xz = ((blockX & 15) << 4) | (blockZ & 15) # encode
     /// x = packed_xz >> 4, z = packed_xz & 15 # decode
     /// ```
-    packed_xz: u8,
+    pub packed_xz: u8,
     /// The height relative to the world
-    y: i16,
+    pub y: i16,
     /// The type of block entity
-    ty: VarInt,
+    pub ty: VarInt,
     /// The block entity's data, without the X, Y, and Z values
-    data: NbtTag,
+    pub data: NbtTag,
+}
+
+impl BlockEntity {
+    /// Takes the position of the block relatively to its chunk column (`x` and `z` are truncated to 0-15).
+    pub fn new(x: u8, y: i16, z: u8, ty: BlockEntityType, data: NbtTag) -> BlockEntity {
+        BlockEntity {
+            packed_xz: ((x & 15) << 4) | (z & 15),
+            y,
+            ty: VarInt(ty as i32),
+            data,
+        }
+    }
+
+    /// Returns the position of the block relatively to its chunk column.
+    pub fn position_in_chunk(&self) -> (u8, i16, u8) {
+        (self.packed_xz >> 4, self.y, self.packed_xz & 15)
+    }
+}
+
+/// The types of block entities, as registered in `minecraft:block_entity_type`.
+#[minecraft_enum(VarInt)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockEntityType {
+    Furnace,
+    Chest,
+    TrappedChest,
+    EnderChest,
+    Jukebox,
+    Dispenser,
+    Dropper,
+    Sign,
+    HangingSign,
+    MobSpawner,
+    Piston,
+    BrewingStand,
+    EnchantingTable,
+    EndPortal,
+    Beacon,
+    Skull,
+    DaylightDetector,
+    Hopper,
+    Comparator,
+    Banner,
+    StructureBlock,
+    EndGateway,
+    CommandBlock,
+    ShulkerBox,
+    Bed,
+    Conduit,
+    Barrel,
+    Smoker,
+    BlastFurnace,
+    Lectern,
+    Bell,
+    Jigsaw,
+    Campfire,
+    Beehive,
+    SculkSensor,
+    CalibratedSculkSensor,
+    SculkCatalyst,
+    SculkShrieker,
+    ChiseledBookshelf,
+    BrushableBlock,
+    DecoratedPot,
+}
+
+impl BlockEntityType {
+    /// All types, in registry order.
+    pub const ALL: [BlockEntityType; 41] = {
+        use BlockEntityType::*;
+        [
+            Furnace, Chest, TrappedChest, EnderChest, Jukebox, Dispenser, Dropper, Sign, HangingSign,
+            MobSpawner, Piston, BrewingStand, EnchantingTable, EndPortal, Beacon, Skull,
+            DaylightDetector, Hopper, Comparator, Banner, StructureBlock, EndGateway, CommandBlock,
+            ShulkerBox, Bed, Conduit, Barrel, Smoker, BlastFurnace, Lectern, Bell, Jigsaw, Campfire,
+            Beehive, SculkSensor, CalibratedSculkSensor, SculkCatalyst, SculkShrieker,
+            ChiseledBookshelf, BrushableBlock, DecoratedPot,
+        ]
+    };
+
+    /// Returns the namespaced identifier of this type, as used in NBT data.
+    pub fn text_id(self) -> &'static str {
+        use BlockEntityType::*;
+        match self {
+            Furnace => "minecraft:furnace",
+            Chest => "minecraft:chest",
+            TrappedChest => "minecraft:trapped_chest",
+            EnderChest => "minecraft:ender_chest",
+            Jukebox => "minecraft:jukebox",
+            Dispenser => "minecraft:dispenser",
+            Dropper => "minecraft:dropper",
+            Sign => "minecraft:sign",
+            HangingSign => "minecraft:hanging_sign",
+            MobSpawner => "minecraft:mob_spawner",
+            Piston => "minecraft:piston",
+            BrewingStand => "minecraft:brewing_stand",
+            EnchantingTable => "minecraft:enchanting_table",
+            EndPortal => "minecraft:end_portal",
+            Beacon => "minecraft:beacon",
+            Skull => "minecraft:skull",
+            DaylightDetector => "minecraft:daylight_detector",
+            Hopper => "minecraft:hopper",
+            Comparator => "minecraft:comparator",
+            Banner => "minecraft:banner",
+            StructureBlock => "minecraft:structure_block",
+            EndGateway => "minecraft:end_gateway",
+            CommandBlock => "minecraft:command_block",
+            ShulkerBox => "minecraft:shulker_box",
+            Bed => "minecraft:bed",
+            Conduit => "minecraft:conduit",
+            Barrel => "minecraft:barrel",
+            Smoker => "minecraft:smoker",
+            BlastFurnace => "minecraft:blast_furnace",
+            Lectern => "minecraft:lectern",
+            Bell => "minecraft:bell",
+            Jigsaw => "minecraft:jigsaw",
+            Campfire => "minecraft:campfire",
+            Beehive => "minecraft:beehive",
+            SculkSensor => "minecraft:sculk_sensor",
+            CalibratedSculkSensor => "minecraft:calibrated_sculk_sensor",
+            SculkCatalyst => "minecraft:sculk_catalyst",
+            SculkShrieker => "minecraft:sculk_shrieker",
+            ChiseledBookshelf => "minecraft:chiseled_bookshelf",
+            BrushableBlock => "minecraft:brushable_block",
+            DecoratedPot => "minecraft:decorated_pot",
+        }
+    }
+
+    /// Finds a type by its identifier, with or without the `minecraft:` namespace.
+    pub fn from_text_id(text_id: &str) -> Option<BlockEntityType> {
+        let text_id = text_id.strip_prefix("minecraft:").unwrap_or(text_id);
+        BlockEntityType::ALL.iter().copied().find(|ty| ty.text_id().strip_prefix("minecraft:") == Some(text_id))
+    }
+
+    /// Returns the type of block entity a block carries, if any.
+    pub fn from_block(block: Block) -> Option<BlockEntityType> {
+        use BlockEntityType::*;
+        let text_id = block.text_id();
+        let ty = match text_id {
+            "furnace" => Furnace,
+            "chest" => Chest,
+            "trapped_chest" => TrappedChest,
+            "ender_chest" => EnderChest,
+            "jukebox" => Jukebox,
+            "dispenser" => Dispenser,
+            "dropper" => Dropper,
+            "spawner" => MobSpawner,
+            "moving_piston" => Piston,
+            "brewing_stand" => BrewingStand,
+            "enchanting_table" => EnchantingTable,
+            "end_portal" => EndPortal,
+            "beacon" => Beacon,
+            "daylight_detector" => DaylightDetector,
+            "hopper" => Hopper,
+            "comparator" => Comparator,
+            "structure_block" => StructureBlock,
+            "end_gateway" => EndGateway,
+            "command_block" | "chain_command_block" | "repeating_command_block" => CommandBlock,
+            "conduit" => Conduit,
+            "barrel" => Barrel,
+            "smoker" => Smoker,
+            "blast_furnace" => BlastFurnace,
+            "lectern" => Lectern,
+            "bell" => Bell,
+            "jigsaw" => Jigsaw,
+            "campfire" | "soul_campfire" => Campfire,
+            "beehive" | "bee_nest" => Beehive,
+            "sculk_sensor" => SculkSensor,
+            "calibrated_sculk_sensor" => CalibratedSculkSensor,
+            "sculk_catalyst" => SculkCatalyst,
+            "sculk_shrieker" => SculkShrieker,
+            "chiseled_bookshelf" => ChiseledBookshelf,
+            "suspicious_sand" | "suspicious_gravel" => BrushableBlock,
+            "decorated_pot" => DecoratedPot,
+            "piston_head" => return None,
+            _ if text_id.ends_with("_hanging_sign") => HangingSign,
+            _ if text_id.ends_with("_sign") => Sign,
+            _ if text_id.ends_with("_banner") => Banner,
+            _ if text_id.ends_with("shulker_box") => ShulkerBox,
+            _ if text_id.ends_with("_bed") => Bed,
+            _ if text_id.ends_with("_skull") || text_id.ends_with("_head") => Skull,
+            _ => return None,
+        };
+        Some(ty)
+    }
 }
 
 #[cfg_attr(test, derive(PartialEq))]
@@ -40,9 +225,8 @@ pub enum DiggingState {
     SwapItemInHand,
 }
 
-#[cfg_attr(test, derive(PartialEq))]
 #[minecraft_enum(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockFace {
     Bottom,
     Top,
@@ -177,4 +361,21 @@ mod tests {
         let decoded = MultiBlockChange::decode_chunk_section_position(encoded);
         assert_eq!(position, decoded);
     }
+
+    #[test]
+    fn test_block_entity_type() {
+        assert_eq!(BlockEntityType::from_block(Block::Chest), Some(BlockEntityType::Chest));
+        assert_eq!(BlockEntityType::from_block(Block::OakSign), Some(BlockEntityType::Sign));
+        assert_eq!(BlockEntityType::from_block(Block::OakWallSign), Some(BlockEntityType::Sign));
+        assert_eq!(BlockEntityType::from_block(Block::OakHangingSign), Some(BlockEntityType::HangingSign));
+        assert_eq!(BlockEntityType::from_block(Block::WhiteBanner), Some(BlockEntityType::Banner));
+        assert_eq!(BlockEntityType::from_block(Block::Stone), None);
+
+        assert_eq!(BlockEntityType::from_text_id("minecraft:decorated_pot"), Some(BlockEntityType::DecoratedPot));
+        assert_eq!(BlockEntityType::from_text_id("furnace"), Some(BlockEntityType::Furnace));
+        assert_eq!(BlockEntityType::from_text_id("minecraft:stone"), None);
+
+        let block_entity = BlockEntity::new(3, -12, 14, BlockEntityType::Chest, NbtTag::Null);
+        assert_eq!(block_entity.position_in_chunk(), (3, -12, 14));
+    }
 }
@@ -0,0 +1,261 @@
+use crate::*;
+
+/// A node of the command graph.
+/// See [the wiki](https://wiki.vg/Command_Data) for more information.
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub struct CommandNode<'a> {
+    /// Whether the command can be executed if it stops at this node
+    pub executable: bool,
+    /// The indices of the children of this node
+    pub children: Vec<VarInt>,
+    /// The index of the node this node redirects to, used for aliases such as `/tp` and `/teleport`
+    pub redirect: Option<VarInt>,
+    pub kind: CommandNodeKind<'a>,
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub enum CommandNodeKind<'a> {
+    Root,
+    /// A keyword, such as the name of a command
+    Literal {
+        name: &'a str,
+    },
+    Argument {
+        name: &'a str,
+        parser: CommandParser<'a>,
+        /// Asks the client to get suggestions from somewhere, such as `minecraft:ask_server`
+        suggestions: Option<Identifier<'a>>,
+    },
+}
+
+impl<'a> MinecraftPacketPart<'a> for CommandNode<'a> {
+    fn serialize_minecraft_packet_part(self, output: &mut Vec<u8>) -> Result<(), &'static str> {
+        let mut flags: u8 = match self.kind {
+            CommandNodeKind::Root => 0,
+            CommandNodeKind::Literal { .. } => 1,
+            CommandNodeKind::Argument { .. } => 2,
+        };
+        if self.executable {
+            flags |= 0x04;
+        }
+        if self.redirect.is_some() {
+            flags |= 0x08;
+        }
+        if let CommandNodeKind::Argument { suggestions: Some(_), .. } = &self.kind {
+            flags |= 0x10;
+        }
+        flags.serialize_minecraft_packet_part(output)?;
+
+        VarInt::from(self.children.len()).serialize_minecraft_packet_part(output)?;
+        for child in self.children {
+            child.serialize_minecraft_packet_part(output)?;
+        }
+        if let Some(redirect) = self.redirect {
+            redirect.serialize_minecraft_packet_part(output)?;
+        }
+        match self.kind {
+            CommandNodeKind::Root => (),
+            CommandNodeKind::Literal { name } => name.serialize_minecraft_packet_part(output)?,
+            CommandNodeKind::Argument { name, parser, suggestions } => {
+                name.serialize_minecraft_packet_part(output)?;
+                parser.serialize_minecraft_packet_part(output)?;
+                if let Some(suggestions) = suggestions {
+                    suggestions.serialize_minecraft_packet_part(output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn deserialize_minecraft_packet_part(input: &'a [u8]) -> Result<(Self, &'a [u8]), &'static str> {
+        let (flags, input) = u8::deserialize_minecraft_packet_part(input)?;
+        let (children_count, mut input) = VarInt::deserialize_minecraft_packet_part(input)?;
+        if children_count.0 < 0 {
+            return Err("Negative children count");
+        }
+        let mut children = Vec::new();
+        for _ in 0..children_count.0 {
+            let (child, new_input) = VarInt::deserialize_minecraft_packet_part(input)?;
+            children.push(child);
+            input = new_input;
+        }
+        let redirect = if flags & 0x08 != 0 {
+            let (redirect, new_input) = VarInt::deserialize_minecraft_packet_part(input)?;
+            input = new_input;
+            Some(redirect)
+        } else {
+            None
+        };
+        let (kind, input) = match flags & 0x03 {
+            0 => (CommandNodeKind::Root, input),
+            1 => {
+                let (name, input) = <&str>::deserialize_minecraft_packet_part(input)?;
+                (CommandNodeKind::Literal { name }, input)
+            }
+            2 => {
+                let (name, input) = <&str>::deserialize_minecraft_packet_part(input)?;
+                let (parser, mut input) = CommandParser::deserialize_minecraft_packet_part(input)?;
+                let suggestions = if flags & 0x10 != 0 {
+                    let (suggestions, new_input) = <&str>::deserialize_minecraft_packet_part(input)?;
+                    input = new_input;
+                    Some(suggestions)
+                } else {
+                    None
+                };
+                (CommandNodeKind::Argument { name, parser, suggestions }, input)
+            }
+            _ => return Err("Invalid command node type"),
+        };
+        Ok((CommandNode { executable: flags & 0x04 != 0, children, redirect, kind }, input))
+    }
+}
+
+/// The bounds of a numeric argument
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone, Default)]
+pub struct NumberBounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<'a, T: MinecraftPacketPart<'a>> MinecraftPacketPart<'a> for NumberBounds<T> {
+    fn serialize_minecraft_packet_part(self, output: &mut Vec<u8>) -> Result<(), &'static str> {
+        let flags = self.min.is_some() as u8 | (self.max.is_some() as u8) << 1;
+        flags.serialize_minecraft_packet_part(output)?;
+        if let Some(min) = self.min {
+            min.serialize_minecraft_packet_part(output)?;
+        }
+        if let Some(max) = self.max {
+            max.serialize_minecraft_packet_part(output)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize_minecraft_packet_part(input: &'a [u8]) -> Result<(Self, &'a [u8]), &'static str> {
+        let (flags, mut input) = u8::deserialize_minecraft_packet_part(input)?;
+        let mut bounds = NumberBounds { min: None, max: None };
+        if flags & 0x01 != 0 {
+            let (min, new_input) = T::deserialize_minecraft_packet_part(input)?;
+            bounds.min = Some(min);
+            input = new_input;
+        }
+        if flags & 0x02 != 0 {
+            let (max, new_input) = T::deserialize_minecraft_packet_part(input)?;
+            bounds.max = Some(max);
+            input = new_input;
+        }
+        Ok((bounds, input))
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[minecraft_enum(VarInt)]
+#[derive(Debug, Clone)]
+pub enum StringBehavior {
+    /// Reads a single word
+    SingleWord,
+    /// Reads a single word, or a phrase between quotes
+    QuotablePhrase,
+    /// Reads the rest of the command
+    GreedyPhrase,
+}
+
+/// How the client parses an argument.
+/// See [the wiki](https://wiki.vg/Command_Data#Parsers) for more information.
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone, MinecraftPacketPart)]
+#[discriminant(VarInt)]
+pub enum CommandParser<'a> {
+    Bool,
+    Float { bounds: NumberBounds<f32> },
+    Double { bounds: NumberBounds<f64> },
+    Integer { bounds: NumberBounds<i32> },
+    Long { bounds: NumberBounds<i64> },
+    String { behavior: StringBehavior },
+    Entity {
+        /// 0x01 if only one entity is allowed, 0x02 if only players are allowed
+        flags: u8,
+    },
+    GameProfile,
+    BlockPos,
+    ColumnPos,
+    Vec3,
+    Vec2,
+    BlockState,
+    BlockPredicate,
+    ItemStack,
+    ItemPredicate,
+    Color,
+    Component,
+    Message,
+    Nbt,
+    NbtTag,
+    NbtPath,
+    Objective,
+    ObjectiveCriteria,
+    Operation,
+    Particle,
+    Angle,
+    Rotation,
+    ScoreboardSlot,
+    ScoreHolder {
+        /// 0x01 if multiple score holders are allowed
+        flags: u8,
+    },
+    Swizzle,
+    Team,
+    ItemSlot,
+    ResourceLocation,
+    Function,
+    EntityAnchor,
+    IntRange,
+    FloatRange,
+    Dimension,
+    Gamemode,
+    Time {
+        /// The minimum duration, in ticks
+        min: i32,
+    },
+    ResourceOrTag { registry: Identifier<'a> },
+    ResourceOrTagKey { registry: Identifier<'a> },
+    Resource { registry: Identifier<'a> },
+    ResourceKey { registry: Identifier<'a> },
+    TemplateMirror,
+    TemplateRotation,
+    Heightmap,
+    Uuid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_nodes() {
+        let nodes = vec![
+            CommandNode {
+                executable: true,
+                children: Vec::new(),
+                redirect: None,
+                kind: CommandNodeKind::Argument {
+                    name: "count",
+                    parser: CommandParser::Integer { bounds: NumberBounds { min: Some(1), max: None } },
+                    suggestions: Some("minecraft:ask_server"),
+                },
+            },
+            CommandNode { executable: true, children: vec![VarInt(0)], redirect: None, kind: CommandNodeKind::Literal { name: "summon" } },
+            CommandNode { executable: false, children: vec![VarInt(1)], redirect: None, kind: CommandNodeKind::Root },
+        ];
+        let mut serialized = Vec::new();
+        for node in nodes.clone() {
+            node.serialize_minecraft_packet_part(&mut serialized).unwrap();
+        }
+        assert_eq!(&serialized[..5], &[0x16, 0, 5, b'c', b'o']);
+
+        let (deserialized, rest) = CommandNode::deserialize_n(&serialized, 3).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized, nodes);
+    }
+}
@@ -1,8 +1,7 @@
 use crate::*;
 
-#[cfg_attr(test, derive(PartialEq))]
 #[minecraft_enum(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Difficulty {
     Peaceful,
     Easy,
@@ -7,6 +7,7 @@ pub mod boss_bar;
 pub mod chat;
 pub mod chunk;
 pub mod command_block;
+pub mod commands;
 pub mod difficulty;
 pub mod effect;
 pub mod entity;
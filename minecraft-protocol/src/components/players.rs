@@ -42,40 +42,40 @@ impl<'a> PlayerActions<'a> {
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct AddPlayersAction<'a> {
-    name: &'a str,
-    properties: Array<'a, Property<'a>, VarInt>,
+    pub name: &'a str,
+    pub properties: Array<'a, Property<'a>, VarInt>,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct InitializeChatAction<'a> {
-    initialize_chat: Option<InitializeChat<'a>>,
+    pub initialize_chat: Option<InitializeChat<'a>>,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct UpdateGamemodesAction {
-    gamemode: VarInt,
+    pub gamemode: VarInt,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct UpdateListedAction {
     /// Whether the player should be listed on the player list.
-    listed: bool,
+    pub listed: bool,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct PingAction {
     /// Measured in milliseconds
-    ping: VarInt,
+    pub ping: VarInt,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, MinecraftPacketPart)]
 pub struct UpdateDisplayNameAction<'a> {
-    display_name: Option<Chat<'a>>,
+    pub display_name: Option<Chat<'a>>,
 }
 
 /// The Property field looks as in the response of M[ojang API#UUID -> Profile + Skin/Cape](https://wiki.vg/Mojang_API#UUID_-.3E_Profile_.2B_Skin.2FCape), except of course using the protocol format instead of JSON.
@@ -173,7 +173,10 @@ impl<'a> MinecraftPacketPart<'a> for PlayersInfos<'a> {
             
             // Write the actions here and not in the PlayerInfos struct because the mask defines the action type
             // As the mask is not a prefix, we can't use the trait to deserialize the actions so we can't implement the trait for PlayerInfos
-            for action in player_info.actions {
+            // Actions are read in the order of the bits of the mask
+            let mut actions = player_info.actions;
+            actions.sort_by_key(|action| action.get_discriminant());
+            for action in actions {
                 match action {
                     PlayerActions::AddPlayer(action) => action.serialize_minecraft_packet_part(output)?,
                     PlayerActions::InitializeChat(action) => action.serialize_minecraft_packet_part(output)?,
@@ -192,11 +195,11 @@ impl<'a> MinecraftPacketPart<'a> for PlayersInfos<'a> {
     fn deserialize_minecraft_packet_part(input: &'a [u8])
         -> Result<(Self, &'a [u8]), &'static str> {
         // The first byte is the mask of actions
-        let (mut mask, input) = u8::deserialize_minecraft_packet_part(input)?;
+        let (actions_mask, input) = u8::deserialize_minecraft_packet_part(input)?;
         // The second byte is the number of players
         let (n_players, mut input) = VarInt::deserialize_minecraft_packet_part(input)?;
         // We will deserialize n_players times the player uuid and actions 
-        let mut players_infos = Vec::with_capacity(n_players.0 as usize);
+        let mut players_infos = Vec::with_capacity((n_players.0.max(0) as usize).min(input.len()));
         for _ in 0..n_players.0 {
             // The first part of the player infos is the uuid
             let (player_uuid, new_input) = UUID::deserialize_minecraft_packet_part(input)?;
@@ -207,6 +210,7 @@ impl<'a> MinecraftPacketPart<'a> for PlayersInfos<'a> {
             // the least significant bit of the mask corresponds to the first action
             // the most significant bit of the mask corresponds to the last action
             // So we need to iterate over the bits of the mask to know which actions are present
+            let mut mask = actions_mask;
             let mut current_bit = 0;
             while mask > 0 {
                 // If the bit is 1, the action is present
@@ -251,3 +255,34 @@ impl<'a> MinecraftPacketPart<'a> for PlayersInfos<'a> {
         Ok((Self { players_infos }, input))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players_infos() {
+        let player = |player_uuid: UUID, name| PlayerInfos {
+            player_uuid,
+            actions: vec![
+                PlayerActions::UpdateListed(UpdateListedAction { listed: true }),
+                PlayerActions::AddPlayer(AddPlayersAction { name, properties: Array::default() }),
+                PlayerActions::Ping(PingAction { ping: VarInt(12) }),
+            ],
+        };
+        let infos = PlayersInfos { players_infos: vec![player(1, "Alice"), player(2, "Bob")] };
+        let mut serialized = Vec::new();
+        infos.serialize_minecraft_packet_part(&mut serialized).unwrap();
+        assert_eq!(serialized[0], 0x01 | 0x08 | 0x10);
+
+        let (deserialized, rest) = PlayersInfos::deserialize_minecraft_packet_part(&serialized).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized.players_infos.len(), 2);
+        assert_eq!(deserialized.players_infos[1].player_uuid, 2);
+        assert_eq!(deserialized.players_infos[1].actions, vec![
+            PlayerActions::AddPlayer(AddPlayersAction { name: "Bob", properties: Array::default() }),
+            PlayerActions::UpdateListed(UpdateListedAction { listed: true }),
+            PlayerActions::Ping(PingAction { ping: VarInt(12) }),
+        ]);
+    }
+}
@@ -1,15 +1,13 @@
 use crate::{nbt::NbtTag, *};
 
 /// The [Slot] data structure is how Minecraft represents an item and its associated data in the [Minecraft Protocol](https://wiki.vg/Protocol).
-#[cfg_attr(test, derive(PartialEq))]
-#[derive(Debug, Clone, Default, MinecraftPacketPart)]
+#[derive(Debug, Clone, Default, PartialEq, MinecraftPacketPart)]
 pub struct Slot {
     /// `Some(item)` if there is an item in this slot; `None` if it is empty.
     pub item: Option<SlotItem>,
 }
 
-#[cfg_attr(test, derive(PartialEq))]
-#[derive(Debug, Clone, MinecraftPacketPart)]
+#[derive(Debug, Clone, PartialEq, MinecraftPacketPart)]
 pub struct SlotItem {
     /// The [item](crate::ids::items::Item).
     /// Item IDs are distinct from [block IDs](crate::ids::blocks::Block); see [crate::ids] for more information.
@@ -19,9 +17,8 @@ pub struct SlotItem {
     pub nbt_data: NbtTag,
 }
 
-#[cfg_attr(test, derive(PartialEq))]
 #[minecraft_enum(VarInt)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Hand {
     MainHand,
     OffHand,
@@ -110,9 +107,8 @@ impl<'a> MinecraftPacketPart<'a> for EquipmentSlotArray {
     }
 }
 
-#[cfg_attr(test, derive(PartialEq))]
 #[minecraft_enum(VarInt)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WindowType {
     OneRow,
     TwoRows,
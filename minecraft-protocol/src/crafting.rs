@@ -0,0 +1,266 @@
+//! Evaluation of the [crafting recipes](crate::ids::recipes) for servers and clients.
+//!
+//! [craft] matches a crafting grid against the recipes, [craftable] lists what can be crafted from an inventory,
+//! and [plan_crafting] finds the chain of crafts needed to obtain an item.
+
+use crate::ids::{
+    items::Item,
+    recipes::{CountedItem, Recipe, Shape},
+};
+use std::collections::BTreeMap;
+
+/// The outcome of a crafting grid matching a recipe
+#[derive(Debug, Clone)]
+pub struct Craft {
+    pub recipe: &'static Recipe,
+    /// The crafted item
+    pub result: CountedItem,
+    /// The grid slots that lose one item when the result is taken, with the item they contain
+    pub consumed: Vec<(usize, Item)>,
+}
+
+/// A step of a crafting plan: crafting a recipe several times in a row
+#[derive(Debug, Clone)]
+pub struct CraftingStep {
+    pub recipe: &'static Recipe,
+    pub times: u32,
+}
+
+/// Iterates over all the crafting recipes.
+fn all_recipes() -> impl Iterator<Item = &'static Recipe> {
+    (0..).map(Item::from_id).take_while(Option::is_some).flatten().flat_map(|item| item.crafting_recipes().iter())
+}
+
+/// Returns the rows of a recipe shape.
+fn shape_rows(shape: &Shape) -> Vec<Vec<Option<Item>>> {
+    match shape {
+        Shape::ThreeByThree(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::ThreeByTwo(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::ThreeByOne(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::TwoByThree(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::TwoByTwo(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::TwoByOne(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::OneByThree(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::OneByTwo(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+        Shape::OneByOne(rows) => rows.iter().map(|row| row.to_vec()).collect(),
+    }
+}
+
+/// Removes the empty rows and columns around a pattern.
+fn trim(rows: Vec<Vec<Option<Item>>>) -> Vec<Vec<Option<Item>>> {
+    let width = rows.first().map(|row| row.len()).unwrap_or(0);
+    let used_rows: Vec<usize> = (0..rows.len()).filter(|y| rows[*y].iter().any(Option::is_some)).collect();
+    let used_columns: Vec<usize> = (0..width).filter(|x| rows.iter().any(|row| row[*x].is_some())).collect();
+    match (used_rows.first(), used_rows.last(), used_columns.first(), used_columns.last()) {
+        (Some(top), Some(bottom), Some(left), Some(right)) => {
+            rows[*top..=*bottom].iter().map(|row| row[*left..=*right].to_vec()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn matches_recipe(recipe: &Recipe, pattern: &[Vec<Option<Item>>]) -> bool {
+    match recipe {
+        Recipe::Shaped { in_shape, .. } => {
+            let shape = trim(shape_rows(in_shape));
+            let mirrored: Vec<Vec<Option<Item>>> = shape.iter().map(|row| row.iter().rev().copied().collect()).collect();
+            shape == pattern || mirrored == pattern
+        }
+        Recipe::ShapeLess { ingredients, .. } => {
+            let mut ingredients: Vec<u32> = ingredients.iter().map(|item| *item as u32).collect();
+            let mut items: Vec<u32> = pattern.iter().flatten().flatten().map(|item| *item as u32).collect();
+            ingredients.sort_unstable();
+            items.sort_unstable();
+            ingredients == items
+        }
+    }
+}
+
+/// Matches a square crafting grid (2x2 or 3x3, rows first) against all the recipes.
+///
+/// Shaped recipes can be placed anywhere in the grid and mirrored.
+pub fn craft(grid: &[Option<Item>], width: usize) -> Option<Craft> {
+    if width == 0 {
+        return None;
+    }
+    let pattern = trim(grid.chunks(width).map(|row| row.to_vec()).collect());
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let recipe = all_recipes().find(|recipe| matches_recipe(recipe, &pattern))?;
+    Some(Craft {
+        recipe,
+        result: recipe.result().clone(),
+        consumed: grid.iter().enumerate().filter_map(|(i, item)| item.map(|item| (i, item))).collect(),
+    })
+}
+
+/// Returns the item left in the grid when an ingredient is consumed, such as the bucket of a milk bucket.
+pub fn crafting_remainder(item: Item) -> Option<Item> {
+    match item {
+        Item::MilkBucket | Item::WaterBucket | Item::LavaBucket => Some(Item::Bucket),
+        Item::HoneyBottle | Item::DragonBreath => Some(Item::GlassBottle),
+        _ => None,
+    }
+}
+
+/// Returns true if a recipe doesn't fit in the 2x2 grid of the player inventory.
+pub fn needs_crafting_table(recipe: &Recipe) -> bool {
+    match recipe {
+        Recipe::Shaped { in_shape, .. } => {
+            let (width, height) = in_shape.size();
+            width > 2 || height > 2
+        }
+        Recipe::ShapeLess { ingredients, .. } => ingredients.len() > 4,
+    }
+}
+
+/// Returns the items a recipe consumes, with their counts.
+pub fn ingredients(recipe: &Recipe) -> Vec<(Item, u32)> {
+    let items: Vec<Item> = match recipe {
+        Recipe::Shaped { in_shape, .. } => shape_rows(in_shape).into_iter().flatten().flatten().collect(),
+        Recipe::ShapeLess { ingredients, .. } => ingredients.to_vec(),
+    };
+    let mut counts: Vec<(Item, u32)> = Vec::new();
+    for item in items {
+        match counts.iter_mut().find(|(counted, _)| *counted == item) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((item, 1)),
+        }
+    }
+    counts
+}
+
+/// Sums the counts of an inventory by item id.
+fn count_items(inventory: &[(Item, u32)]) -> BTreeMap<u32, u32> {
+    let mut counts = BTreeMap::new();
+    for (item, count) in inventory {
+        *counts.entry(*item as u32).or_insert(0) += count;
+    }
+    counts
+}
+
+/// Lists the recipes that can be crafted from an inventory, with the number of times each can be crafted.
+///
+/// Recipes larger than 2x2 are only included if a crafting table is available.
+pub fn craftable(inventory: &[(Item, u32)], crafting_table: bool) -> Vec<(&'static Recipe, u32)> {
+    let counts = count_items(inventory);
+    all_recipes()
+        .filter(|recipe| crafting_table || !needs_crafting_table(recipe))
+        .filter_map(|recipe| {
+            let times = ingredients(recipe)
+                .iter()
+                .map(|(item, needed)| counts.get(&(*item as u32)).copied().unwrap_or(0) / needed)
+                .min()?;
+            if times > 0 {
+                Some((recipe, times))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn plan_into(
+    target: Item,
+    count: u32,
+    inventory: &mut BTreeMap<u32, u32>,
+    crafting_table: bool,
+    visiting: &mut Vec<Item>,
+    steps: &mut Vec<CraftingStep>,
+) -> bool {
+    let available = inventory.entry(target as u32).or_insert(0);
+    let taken = count.min(*available);
+    *available -= taken;
+    let missing = count - taken;
+    if missing == 0 {
+        return true;
+    }
+    if visiting.contains(&target) {
+        return false;
+    }
+
+    visiting.push(target);
+    for recipe in target.crafting_recipes() {
+        if needs_crafting_table(recipe) && !crafting_table {
+            continue;
+        }
+        let crafted = recipe.result().count as u32;
+        let times = missing.div_ceil(crafted);
+        let mut new_inventory = inventory.clone();
+        let mut new_steps = steps.clone();
+        // Counts overflow when recipes with large outputs feed each other, in which case the plan is out of reach anyway
+        let feasible = ingredients(recipe).into_iter().all(|(item, needed)| {
+            needed.checked_mul(times).is_some_and(|count| plan_into(item, count, &mut new_inventory, crafting_table, visiting, &mut new_steps))
+        });
+        if feasible {
+            new_steps.push(CraftingStep { recipe, times });
+            *new_inventory.entry(target as u32).or_insert(0) += times * crafted - missing;
+            *inventory = new_inventory;
+            *steps = new_steps;
+            visiting.pop();
+            return true;
+        }
+    }
+    visiting.pop();
+    false
+}
+
+/// Finds the crafts that turn an inventory into `count` items of `target`, in the order they have to be done.
+///
+/// Items already in the inventory are used first.
+/// Returns `None` if the target can't be obtained by crafting.
+pub fn plan_crafting(target: Item, count: u32, inventory: &[(Item, u32)], crafting_table: bool) -> Option<Vec<CraftingStep>> {
+    let mut inventory = count_items(inventory);
+    let mut steps = Vec::new();
+    if plan_into(target, count, &mut inventory, crafting_table, &mut Vec::new(), &mut steps) {
+        Some(steps)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_craft() {
+        let planks = Some(Item::OakPlanks);
+
+        // Shapeless
+        let crafted = craft(&[None, None, Some(Item::OakLog), None], 2).unwrap();
+        assert_eq!((crafted.result.item, crafted.result.count), (Item::OakPlanks, 4));
+        assert_eq!(crafted.consumed, vec![(2, Item::OakLog)]);
+
+        // Shaped, anywhere in the grid
+        let result = craft(&[None, None, planks, None, None, planks, None, None, None], 3).unwrap().result;
+        assert_eq!((result.item, result.count), (Item::Stick, 4));
+
+        // Shaped and mirrored
+        let result = craft(&[None, None, planks, None, planks, planks, planks, planks, planks], 3).unwrap().result;
+        assert_eq!((result.item, result.count), (Item::OakStairs, 4));
+
+        assert!(craft(&[planks, None, None, planks], 2).is_none());
+        assert!(craft(&[None, None, None, None], 2).is_none());
+    }
+
+    #[test]
+    fn test_craftable() {
+        let craftable = craftable(&[(Item::OakPlanks, 3)], false);
+        let sticks = craftable.iter().find(|(recipe, _)| recipe.result().item == Item::Stick).unwrap();
+        assert_eq!(sticks.1, 1);
+        assert!(craftable.iter().all(|(recipe, _)| !needs_crafting_table(recipe)));
+    }
+
+    #[test]
+    fn test_plan_crafting() {
+        let plan = plan_crafting(Item::WoodenPickaxe, 1, &[(Item::OakLog, 2)], true).unwrap();
+        let plan: Vec<(Item, u32)> = plan.iter().map(|step| (step.recipe.result().item, step.times)).collect();
+        assert_eq!(plan, vec![(Item::OakPlanks, 1), (Item::OakPlanks, 1), (Item::Stick, 1), (Item::WoodenPickaxe, 1)]);
+
+        assert!(plan_crafting(Item::WoodenPickaxe, 1, &[(Item::OakLog, 2)], false).is_none());
+        assert!(plan_crafting(Item::Diamond, 1, &[], true).is_none());
+    }
+}
@@ -65,6 +65,7 @@
 #![allow(clippy::upper_case_acronyms)]
 
 pub mod components;
+pub mod crafting;
 pub mod ids;
 pub mod nbt;
 pub mod network;
@@ -266,7 +266,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         7 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_byte_array(input).map_err(|_| "Invalid list item (bytes)")?;
@@ -277,7 +277,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         8 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_string(input).map_err(|_| "Invalid list item (string)")?;
@@ -288,7 +288,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         9 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_list(input).map_err(|_| "Invalid list item (list)")?;
@@ -299,7 +299,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         10 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_compound(input).map_err(|_| "Invalid list item (compound)")?;
@@ -310,7 +310,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         11 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_int_array(input).map_err(|_| "Invalid list item (int)")?;
@@ -321,7 +321,7 @@ pub fn parse_list(input: &[u8]) -> Result<(NbtList, &[u8]), &'static str> {
         }
         12 => {
             let mut input = &input[5..];
-            let mut list = Vec::with_capacity(len);
+            let mut list = Vec::with_capacity(len.min(input.len()));
             for _ in 0..len {
                 let (result, new_input) =
                     parse_long_array(input).map_err(|_| "Invalid list item (long)")?;
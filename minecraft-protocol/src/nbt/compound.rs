@@ -22,6 +22,9 @@ pub fn parse_compound(mut input: &[u8]) -> Result<(HashMap<String, NbtTag>, &[u8
 
         let len = len as usize;
         let new_input = &input[3..];
+        if new_input.len() < len {
+            return Err("A tag name cannot claim to contain more bytes than the remaining bytes.");
+        }
         let (bytes, new_input) = new_input.split_at(len);
         let name = String::from_utf8(bytes.to_vec())
             .map_err(|_| "A tag name should contain valid utf8 characters.")?;
@@ -47,6 +50,9 @@ pub fn parse_root_compound(
     let len: u16 = unsafe { u16::from_be_bytes(*(input.as_ptr() as *mut [u8; 2])) };
     let len = len as usize;
     input = &input[2..];
+    if input.len() < len {
+        return Err("A compound tag name cannot claim to contain more bytes than the remaining bytes.");
+    }
     let (bytes, new_input) = input.split_at(len);
     let name = String::from_utf8(bytes.to_vec())
         .map_err(|_| "A compound tag name should contain valid utf8 characters.")?;
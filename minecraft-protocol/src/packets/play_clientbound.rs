@@ -169,10 +169,9 @@ pub enum ClientboundPacket<'a> {
     /// Lists all of the commands on the server, and how they are parsed.
     /// This is a directed graph, with one root node. Each redirect or child node must refer only to nodes that have already been declared.
     DeclareCommands {
-        count: VarInt,
-        /// An array of [Node](https://wiki.vg/Command_Data) followed by the index of the `root` node in the array.
-        /// TODO: Parsing is unimplemented yet.
-        data: RawBytes<'a>,
+        nodes: Array<'a, commands::CommandNode<'a>, VarInt>,
+        /// The index of the `root` node in the array
+        root_index: VarInt,
     },
 
     /// This packet is sent from the server to the client when a window is forcibly closed, such as when a chest is destroyed while it's open.
@@ -24,7 +24,7 @@ pub trait MinecraftPacketPart<'a>: Sized
     }
 
     fn deserialize_n(mut input: &'a [u8], n: usize) -> Result<(Vec<Self>, &'a [u8]), &'static str> {
-        let mut result = Vec::with_capacity(n);
+        let mut result = Vec::with_capacity(n.min(input.len()));
         for _ in 0..n {
             let (item, new_input) = MinecraftPacketPart::deserialize_minecraft_packet_part(input)?;
             input = new_input;
@@ -56,7 +56,7 @@ pub trait MinecraftPacketPart<'a>: Sized + PartialEq
     }
 
     fn deserialize_n(mut input: &'a [u8], n: usize) -> Result<(Vec<Self>, &'a [u8]), &'static str> {
-        let mut result = Vec::with_capacity(n);
+        let mut result = Vec::with_capacity(n.min(input.len()));
         for _ in 0..n {
             let (item, new_input) = MinecraftPacketPart::deserialize_minecraft_packet_part(input)?;
             input = new_input;
@@ -458,16 +458,17 @@ mod integers {
             let mut num_read: u64 = 0;
 
             loop {
-                let (read, new_input) = input.split_first().unwrap();
+                if num_read >= 10 {
+                    return Err("VarLong is too big");
+                }
+                let (read, new_input) =
+                    input.split_first().ok_or("Not enough bytes for varlong!")?;
                 let read = *read;
                 input = new_input;
                 let value = (read & 0b01111111) as u64;
                 result |= value << (7 * num_read);
 
                 num_read += 1;
-                if num_read > 10 {
-                    return Err("VarLong is too big");
-                }
 
                 if read & 0b10000000 == 0 {
                     let result: i64 = unsafe { std::mem::transmute(result) };
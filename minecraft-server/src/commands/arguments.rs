@@ -0,0 +1,312 @@
+use minecraft_protocol::{components::commands::{CommandParser, NumberBounds, StringBehavior}, ids::blocks::Block};
+use super::*;
+
+/// Reads a command one token at a time
+pub(super) struct Reader<'a> {
+    input: &'a str,
+    pub cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a str) -> Reader<'a> {
+        Reader { input, cursor: 0 }
+    }
+
+    pub fn remaining(&self) -> &'a str {
+        &self.input[self.cursor..]
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.cursor >= self.input.len()
+    }
+
+    /// Reads until the next space.
+    pub fn read_word(&mut self) -> &'a str {
+        let remaining = self.remaining();
+        let word = remaining.split(' ').next().unwrap_or("");
+        self.cursor += word.len();
+        word
+    }
+
+    /// Reads until the next space that isn't between brackets, such as `@e[type=zombie, limit=1]`.
+    pub fn read_bracketed_word(&mut self) -> &'a str {
+        let remaining = self.remaining();
+        let mut depth = 0;
+        let len = remaining.char_indices().find(|(_, c)| match c {
+            '[' => { depth += 1; false }
+            ']' => { depth -= 1; false }
+            ' ' => depth <= 0,
+            _ => false,
+        }).map(|(i, _)| i).unwrap_or(remaining.len());
+        self.cursor += len;
+        &remaining[..len]
+    }
+
+    pub fn read_rest(&mut self) -> &'a str {
+        let remaining = self.remaining();
+        self.cursor = self.input.len();
+        remaining
+    }
+
+    /// Skips the space separating two tokens.
+    /// Returns false if there is none.
+    pub fn skip_space(&mut self) -> bool {
+        if self.remaining().starts_with(' ') {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A coordinate that is either absolute or relative to the sender, such as `~5`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate {
+    pub relative: bool,
+    pub value: f64,
+}
+
+impl Coordinate {
+    fn parse(token: &str) -> Result<Coordinate, String> {
+        if token.starts_with('^') {
+            return Err(String::from("Local coordinates are not supported"));
+        }
+        let (relative, value) = match token.strip_prefix('~') {
+            Some("") => (true, 0.0),
+            Some(offset) => (true, offset.parse::<f64>().map_err(|_| format!("Invalid coordinate {token:?}"))?),
+            None => (false, token.parse::<i32>().map_err(|_| format!("Invalid coordinate {token:?}"))? as f64),
+        };
+        Ok(Coordinate { relative, value })
+    }
+
+    fn resolve(&self, base: Option<f64>) -> Option<i32> {
+        match self.relative {
+            true => Some((base? + self.value).floor() as i32),
+            false => Some(self.value as i32),
+        }
+    }
+}
+
+/// A block position argument, such as `~ ~-1 ~`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockPosArgument(pub [Coordinate; 3]);
+
+impl BlockPosArgument {
+    /// Resolves relative coordinates from a position.
+    /// Returns `None` if coordinates are relative and there is no position.
+    pub fn resolve(&self, base: Option<&Position>) -> Option<BlockPosition> {
+        let [x, y, z] = &self.0;
+        Some(BlockPosition {
+            x: x.resolve(base.map(|base| base.x))?,
+            y: y.resolve(base.map(|base| base.y))?,
+            z: z.resolve(base.map(|base| base.z))?,
+        })
+    }
+}
+
+/// A range of integers such as `3..5`, `..5` or `3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IntRange {
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl IntRange {
+    pub fn contains(&self, value: i32) -> bool {
+        self.min.map(|min| value >= min).unwrap_or(true) && self.max.map(|max| value <= max).unwrap_or(true)
+    }
+}
+
+impl std::str::FromStr for IntRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IntRange, String> {
+        let parse_bound = |bound: &str| match bound {
+            "" => Ok(None),
+            bound => bound.parse::<i32>().map(Some).map_err(|_| format!("Invalid integer {bound:?}")),
+        };
+        let range = match s.split_once("..") {
+            Some((min, max)) => IntRange { min: parse_bound(min)?, max: parse_bound(max)? },
+            None => {
+                let value = parse_bound(s)?;
+                IntRange { min: value, max: value }
+            }
+        };
+        match range {
+            IntRange { min: None, max: None } => Err(String::from("Expected a range")),
+            IntRange { min: Some(min), max: Some(max) } if min > max => Err(String::from("The minimum of a range can't exceed its maximum")),
+            range => Ok(range),
+        }
+    }
+}
+
+/// What an argument of a command is
+#[derive(Debug, Clone)]
+pub enum ArgumentType {
+    Integer { min: Option<i32>, max: Option<i32> },
+    /// A single word
+    Word,
+    /// The rest of the command
+    GreedyString,
+    Entity { single: bool, players_only: bool },
+    BlockPos,
+    BlockState,
+    IntRange,
+    Gamemode,
+}
+
+/// The value of a parsed argument
+#[derive(Debug, Clone)]
+pub enum ArgumentValue {
+    Integer(i32),
+    String(String),
+    Entity(Box<EntitySelector>),
+    BlockPos(BlockPosArgument),
+    BlockState(BlockWithState),
+    IntRange(IntRange),
+    Gamemode(Gamemode),
+}
+
+pub(super) fn parse_gamemode(token: &str) -> Option<Gamemode> {
+    match token {
+        "survival" => Some(Gamemode::Survival),
+        "creative" => Some(Gamemode::Creative),
+        "adventure" => Some(Gamemode::Adventure),
+        "spectator" => Some(Gamemode::Spectator),
+        _ => None,
+    }
+}
+
+const GAMEMODES: [&str; 4] = ["survival", "creative", "adventure", "spectator"];
+
+impl ArgumentType {
+    /// Reads the argument at the cursor.
+    pub(super) fn parse(&self, reader: &mut Reader) -> Result<ArgumentValue, String> {
+        match self {
+            ArgumentType::Integer { min, max } => {
+                let word = reader.read_word();
+                let value = word.parse::<i32>().map_err(|_| format!("Invalid integer {word:?}"))?;
+                if let Some(min) = min.filter(|min| value < *min) {
+                    return Err(format!("Integer must not be less than {min}, found {value}"));
+                }
+                if let Some(max) = max.filter(|max| value > *max) {
+                    return Err(format!("Integer must not be more than {max}, found {value}"));
+                }
+                Ok(ArgumentValue::Integer(value))
+            }
+            ArgumentType::Word => match reader.read_word() {
+                "" => Err(String::from("Expected a word")),
+                word => Ok(ArgumentValue::String(word.to_string())),
+            },
+            ArgumentType::GreedyString => match reader.read_rest() {
+                "" => Err(String::from("Expected a message")),
+                rest => Ok(ArgumentValue::String(rest.to_string())),
+            },
+            ArgumentType::Entity { single, players_only } => {
+                let selector: EntitySelector = reader.read_bracketed_word().parse()?;
+                if *single && !selector.is_single() {
+                    return Err(String::from("Only one entity is allowed, but the provided selector allows more than one"));
+                }
+                if *players_only && !selector.is_players_only() {
+                    return Err(String::from("Only players may be affected by this command, but the provided selector includes entities"));
+                }
+                Ok(ArgumentValue::Entity(Box::new(selector)))
+            }
+            ArgumentType::BlockPos => {
+                let mut coordinates = [Coordinate { relative: false, value: 0.0 }; 3];
+                for (i, coordinate) in coordinates.iter_mut().enumerate() {
+                    if i > 0 && !reader.skip_space() {
+                        return Err(String::from("Expected three coordinates"));
+                    }
+                    *coordinate = Coordinate::parse(reader.read_word())?;
+                }
+                Ok(ArgumentValue::BlockPos(BlockPosArgument(coordinates)))
+            }
+            ArgumentType::BlockState => {
+                let word = reader.read_bracketed_word();
+                word.parse::<BlockWithState>().map(ArgumentValue::BlockState).map_err(|e| format!("{e} ({word:?})"))
+            }
+            ArgumentType::IntRange => Ok(ArgumentValue::IntRange(reader.read_word().parse()?)),
+            ArgumentType::Gamemode => {
+                let word = reader.read_word();
+                parse_gamemode(word).map(ArgumentValue::Gamemode).ok_or_else(|| format!("Unknown game mode {word:?}"))
+            }
+        }
+    }
+
+    /// Returns how the client parses the argument.
+    pub(super) fn parser(&self) -> CommandParser<'static> {
+        match self {
+            ArgumentType::Integer { min, max } => CommandParser::Integer { bounds: NumberBounds { min: *min, max: *max } },
+            ArgumentType::Word => CommandParser::String { behavior: StringBehavior::SingleWord },
+            ArgumentType::GreedyString => CommandParser::String { behavior: StringBehavior::GreedyPhrase },
+            ArgumentType::Entity { single, players_only } => CommandParser::Entity { flags: *single as u8 | (*players_only as u8) << 1 },
+            ArgumentType::BlockPos => CommandParser::BlockPos,
+            ArgumentType::BlockState => CommandParser::BlockState,
+            ArgumentType::IntRange => CommandParser::IntRange,
+            ArgumentType::Gamemode => CommandParser::Gamemode,
+        }
+    }
+
+    /// Returns the values starting with `partial` the argument could take.
+    pub(super) fn suggest(&self, partial: &str, player_names: &[String]) -> Vec<String> {
+        let candidates: Vec<String> = match self {
+            ArgumentType::Entity { .. } => ["@s", "@a", "@p", "@r", "@e"].iter().map(|s| s.to_string()).chain(player_names.iter().cloned()).collect(),
+            ArgumentType::BlockPos => vec![String::from("~ ~ ~")],
+            ArgumentType::BlockState => (0..)
+                .map(Block::from_id)
+                .take_while(Option::is_some)
+                .flatten()
+                .map(|block| format!("minecraft:{}", block.text_id()))
+                .collect(),
+            ArgumentType::Gamemode => GAMEMODES.iter().map(|s| s.to_string()).collect(),
+            ArgumentType::Integer { .. } | ArgumentType::Word | ArgumentType::GreedyString | ArgumentType::IntRange => Vec::new(),
+        };
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(partial) || candidate.strip_prefix("minecraft:").map(|c| c.starts_with(partial)).unwrap_or(false))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments() {
+        let parse = |ty: ArgumentType, input: &str| {
+            let mut reader = Reader::new(input);
+            ty.parse(&mut reader).map(|value| (value, reader.remaining().to_string()))
+        };
+
+        assert!(matches!(parse(ArgumentType::Integer { min: Some(1), max: None }, "12 rest"), Ok((ArgumentValue::Integer(12), rest)) if rest == " rest"));
+        assert!(parse(ArgumentType::Integer { min: Some(1), max: None }, "0").is_err());
+        assert!(matches!(parse(ArgumentType::GreedyString, "hello world"), Ok((ArgumentValue::String(s), _)) if s == "hello world"));
+        assert!(matches!(parse(ArgumentType::Gamemode, "creative"), Ok((ArgumentValue::Gamemode(Gamemode::Creative), _))));
+        assert!(parse(ArgumentType::Gamemode, "hardcore").is_err());
+
+        let Ok((ArgumentValue::BlockPos(position), _)) = parse(ArgumentType::BlockPos, "~ ~-1 12") else { panic!() };
+        let base = Position { x: 3.5, y: 64.0, z: -0.5 };
+        assert_eq!(position.resolve(Some(&base)), Some(BlockPosition { x: 3, y: 63, z: 12 }));
+        assert_eq!(position.resolve(None), None);
+        assert!(parse(ArgumentType::BlockPos, "1 2").is_err());
+
+        assert!(matches!(parse(ArgumentType::Entity { single: false, players_only: true }, "@a[limit=2] rest"), Ok((ArgumentValue::Entity(selector), rest)) if selector.kind == SelectorKind::AllPlayers && rest == " rest"));
+        assert!(parse(ArgumentType::Entity { single: true, players_only: true }, "@a").is_err());
+        assert!(parse(ArgumentType::Entity { single: false, players_only: true }, "@e").is_err());
+        assert!(matches!(parse(ArgumentType::Entity { single: true, players_only: true }, "Steve"), Ok((ArgumentValue::Entity(selector), _)) if selector.kind == SelectorKind::Name(String::from("Steve"))));
+    }
+
+    #[test]
+    fn test_int_range() {
+        assert_eq!("3..5".parse(), Ok(IntRange { min: Some(3), max: Some(5) }));
+        assert_eq!("..5".parse(), Ok(IntRange { min: None, max: Some(5) }));
+        assert_eq!("3".parse(), Ok(IntRange { min: Some(3), max: Some(3) }));
+        assert!("5..3".parse::<IntRange>().is_err());
+        assert!("..".parse::<IntRange>().is_err());
+        assert!(IntRange { min: Some(3), max: None }.contains(100));
+        assert!(!IntRange { min: Some(3), max: None }.contains(2));
+    }
+}
@@ -0,0 +1,204 @@
+use super::*;
+
+pub(super) fn commands() -> Vec<Command> {
+    vec![
+        Command::literal("help").executes(help),
+        Command::literal("say").permission(2)
+            .then(Command::argument("message", ArgumentType::GreedyString).executes(say)),
+        Command::literal("gamemode").permission(2)
+            .then(Command::argument("gamemode", ArgumentType::Gamemode).executes(gamemode)
+                .then(Command::argument("targets", ArgumentType::Entity { single: false, players_only: true }).executes(gamemode))),
+        Command::literal("setblock").permission(2)
+            .then(Command::argument("pos", ArgumentType::BlockPos)
+                .then(Command::argument("block", ArgumentType::BlockState).executes(setblock))),
+        Command::literal("summon").permission(2)
+            .then(Command::literal("zombie").executes(summon_zombie)
+                .then(Command::argument("pos", ArgumentType::BlockPos).executes(summon_zombie))),
+        Command::literal("stress").permission(2).executes(stress)
+            .then(Command::argument("count", ArgumentType::Integer { min: Some(1), max: Some(10000) }).executes(stress)),
+        Command::literal("tag").permission(2)
+            .then(Command::argument("targets", ArgumentType::Entity { single: false, players_only: false })
+                .then(Command::literal("add")
+                    .then(Command::argument("name", ArgumentType::Word).executes(tag_add)))
+                .then(Command::literal("remove")
+                    .then(Command::argument("name", ArgumentType::Word).executes(tag_remove)))),
+        Command::literal("mute").permission(3)
+            .then(Command::argument("targets", ArgumentType::Entity { single: false, players_only: true }).executes(mute)),
+        Command::literal("unmute").permission(3)
+            .then(Command::argument("targets", ArgumentType::Entity { single: false, players_only: true }).executes(unmute)),
+        Command::literal("kick").permission(3)
+            .then(Command::argument("targets", ArgumentType::Entity { single: false, players_only: true }).executes(kick)
+                .then(Command::argument("reason", ArgumentType::GreedyString).executes(kick))),
+        Command::literal("save-all").permission(4).executes(save_all),
+        Command::literal("stop").permission(4).executes(stop),
+        Command::literal("tps").permission(2).executes(tps),
+    ]
+}
+
+/// Returns the name of an entity for command feedback.
+async fn entity_name(world: &World, eid: Eid) -> String {
+    let uuid = world.entity_uuid(eid).await;
+    match uuid {
+        Some(uuid) => match world.player(uuid).await {
+            Some(entry) => entry.name,
+            None => format!("entity {eid}"),
+        },
+        None => format!("entity {eid}"),
+    }
+}
+
+async fn help(context: CommandContext) -> CommandResult {
+    let level = context.sender.permission_level(context.world).await;
+    let mut names: Vec<&str> = context.world.commands().commands().iter().filter(|command| command.permission <= level).map(|command| command.name).collect();
+    names.sort_unstable();
+    Ok(names.into_iter().map(|name| ChatText::plain(format!("/{name}"))).collect())
+}
+
+async fn say(context: CommandContext) -> CommandResult {
+    let name = match context.sender.eid() {
+        Some(eid) => entity_name(context.world, eid).await,
+        None => String::from("Server"),
+    };
+    let message = context.string("message").unwrap_or_default();
+    context.world.broadcast_message(ChatKind::System, ChatText::plain(format!("[{name}] {message}"))).await;
+    Ok(Vec::new())
+}
+
+async fn gamemode(context: CommandContext) -> CommandResult {
+    let gamemode = context.gamemode("gamemode").ok_or("Missing game mode")?;
+    let targets = match context.argument("targets") {
+        Some(_) => context.entities("targets").await?,
+        None => context.sender.eid().into_iter().collect(),
+    };
+    if targets.is_empty() {
+        return Err(String::from("A target is needed when not run by a player"));
+    }
+    let mut feedback = Vec::new();
+    for eid in targets {
+        Handler::<Player>::assume(eid, context.world).set_game_mode(gamemode.clone()).await;
+        feedback.push(ChatText::plain(format!("Set the game mode of {} to {gamemode:?}", entity_name(context.world, eid).await)));
+    }
+    Ok(feedback)
+}
+
+async fn setblock(context: CommandContext) -> CommandResult {
+    let position = context.block_pos("pos").await?;
+    let block = context.block_state("block").ok_or("Missing block")?;
+    context.world.set_block(position.clone(), block).await;
+    Ok(vec![ChatText::plain(format!("Changed the block at {} {} {}", position.x, position.y, position.z))])
+}
+
+async fn summon_zombie(context: CommandContext) -> CommandResult {
+    let position = match context.argument("pos") {
+        Some(_) => {
+            let position = context.block_pos("pos").await?;
+            Position { x: position.x as f64 + 0.5, y: position.y as f64, z: position.z as f64 + 0.5 }
+        }
+        None => context.sender.position().await.ok_or("A position is needed when not run by a player")?,
+    };
+    let mut zombie = Zombie::default();
+    zombie.get_entity_mut().position = position;
+    context.world.spawn_entity::<Zombie>(AnyEntity::Zombie(zombie)).await;
+    Ok(vec![ChatText::plain("Summoned a new zombie")])
+}
+
+/// Spawns many zombies above the sender, one per tick.
+async fn stress(context: CommandContext) -> CommandResult {
+    let count = context.integer("count").unwrap_or(1000);
+    let mut position = context.sender.position().await.ok_or("Only players can run this command")?;
+    position.y += 20.0;
+    let world = context.world;
+    tokio::spawn(async move {
+        for _ in 0..count {
+            let mut zombie = Zombie::default();
+            zombie.get_entity_mut().position = position.clone();
+            world.spawn_entity::<Zombie>(AnyEntity::Zombie(zombie)).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    });
+    Ok(vec![ChatText::plain(format!("Spawning {count} zombies"))])
+}
+
+/// Adds or removes a tag on the targets, returning the number of entities that changed.
+async fn update_tags(context: &CommandContext, add: bool) -> Result<usize, String> {
+    let name = context.string("name").ok_or("Missing tag")?.to_string();
+    let mut changed = 0;
+    for eid in context.entities("targets").await? {
+        let updated = Handler::<Entity>::assume(eid, context.world).mutate(|entity| {
+            let updated = match add {
+                true => entity.tags.insert(name.clone()),
+                false => entity.tags.remove(&name),
+            };
+            (updated, EntityChanges::other())
+        }).await;
+        if updated == Some(true) {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+async fn tag_add(context: CommandContext) -> CommandResult {
+    let changed = update_tags(&context, true).await?;
+    Ok(vec![ChatText::plain(format!("Added a tag to {changed} entities"))])
+}
+
+async fn tag_remove(context: CommandContext) -> CommandResult {
+    let changed = update_tags(&context, false).await?;
+    Ok(vec![ChatText::plain(format!("Removed a tag from {changed} entities"))])
+}
+
+async fn mute(context: CommandContext) -> CommandResult {
+    let mut feedback = Vec::new();
+    for eid in context.entities("targets").await? {
+        let Some(uuid) = context.world.entity_uuid(eid).await else { continue };
+        context.world.mute(uuid).await;
+        feedback.push(ChatText::plain(format!("Muted {}", entity_name(context.world, eid).await)));
+    }
+    Ok(feedback)
+}
+
+async fn unmute(context: CommandContext) -> CommandResult {
+    let mut feedback = Vec::new();
+    for eid in context.entities("targets").await? {
+        let Some(uuid) = context.world.entity_uuid(eid).await else { continue };
+        if context.world.unmute(uuid).await {
+            feedback.push(ChatText::plain(format!("Unmuted {}", entity_name(context.world, eid).await)));
+        }
+    }
+    Ok(feedback)
+}
+
+async fn kick(context: CommandContext) -> CommandResult {
+    let reason = context.string("reason").unwrap_or("Kicked by an operator").to_string();
+    let mut feedback = Vec::new();
+    for eid in context.entities("targets").await? {
+        Handler::<Player>::assume(eid, context.world).kick(reason.clone()).await;
+        feedback.push(ChatText::plain(format!("Kicked {}: {reason}", entity_name(context.world, eid).await)));
+    }
+    Ok(feedback)
+}
+
+async fn save_all(context: CommandContext) -> CommandResult {
+    for (_, entry) in context.world.players().await {
+        Handler::<Player>::assume(entry.eid, context.world).save_data().await;
+    }
+    context.world.save_all().await;
+    Ok(vec![ChatText::plain("Saved the game")])
+}
+
+async fn stop(context: CommandContext) -> CommandResult {
+    context.world.shutdown("Server closed");
+    Ok(vec![ChatText::plain("Stopping the server")])
+}
+
+async fn tps(context: CommandContext) -> CommandResult {
+    let stats = context.world.tick_stats().await;
+    let Some([tps_1m, tps_5m, tps_15m]) = stats.tps() else {
+        return Err(String::from("Not enough ticks were measured yet"));
+    };
+    Ok(vec![
+        ChatText::plain(format!("TPS from last 1m, 5m, 15m: {tps_1m:.1}, {tps_5m:.1}, {tps_15m:.1}")),
+        ChatText::plain(format!("Average tick time: {:.2}ms over the last ticks", stats.mspt())),
+    ])
+}
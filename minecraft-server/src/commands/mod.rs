@@ -0,0 +1,378 @@
+use std::sync::RwLock as SyncRwLock;
+use futures::future::BoxFuture;
+use minecraft_protocol::components::commands::{CommandNode as NetworkCommandNode, CommandNodeKind};
+use crate::prelude::*;
+
+mod arguments;
+pub use arguments::*;
+mod selector;
+pub use selector::*;
+mod builtin;
+
+/// The permission level of players who aren't listed as operators, who can only run the commands everyone can
+pub const DEFAULT_PERMISSION_LEVEL: u8 = 0;
+
+/// The output of a command, shown to the sender
+pub type CommandResult = Result<Vec<ChatText>, String>;
+
+pub type CommandExecutor = Box<dyn Fn(CommandContext) -> BoxFuture<'static, CommandResult> + Send + Sync>;
+
+/// Who runs a command
+#[derive(Clone)]
+pub enum CommandSender {
+    Player(Handler<Player>),
+    /// The server itself, with every permission
+    Console,
+}
+
+impl CommandSender {
+    pub fn eid(&self) -> Option<Eid> {
+        match self {
+            CommandSender::Player(handler) => Some(handler.eid),
+            CommandSender::Console => None,
+        }
+    }
+
+    pub async fn permission_level(&self, world: &World) -> u8 {
+        match self {
+            CommandSender::Player(handler) => match world.entity_uuid(handler.eid).await {
+                Some(uuid) => world.permission_level(uuid).await,
+                None => 0,
+            },
+            CommandSender::Console => 4,
+        }
+    }
+
+    pub async fn position(&self) -> Option<Position> {
+        match self {
+            CommandSender::Player(handler) => handler.observe(|player| player.get_entity().position.clone()).await,
+            CommandSender::Console => None,
+        }
+    }
+}
+
+/// What an executor gets to know about the command being run
+pub struct CommandContext {
+    pub world: &'static World,
+    pub sender: CommandSender,
+    arguments: HashMap<&'static str, ArgumentValue>,
+}
+
+impl CommandContext {
+    pub fn argument(&self, name: &str) -> Option<&ArgumentValue> {
+        self.arguments.get(name)
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i32> {
+        match self.arguments.get(name)? {
+            ArgumentValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.arguments.get(name)? {
+            ArgumentValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn gamemode(&self, name: &str) -> Option<Gamemode> {
+        match self.arguments.get(name)? {
+            ArgumentValue::Gamemode(gamemode) => Some(gamemode.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn block_state(&self, name: &str) -> Option<BlockWithState> {
+        match self.arguments.get(name)? {
+            ArgumentValue::BlockState(block) => Some(block.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn int_range(&self, name: &str) -> Option<IntRange> {
+        match self.arguments.get(name)? {
+            ArgumentValue::IntRange(range) => Some(*range),
+            _ => None,
+        }
+    }
+
+    /// Returns a block position argument, resolving relative coordinates from the position of the sender.
+    pub async fn block_pos(&self, name: &str) -> Result<BlockPosition, String> {
+        let Some(ArgumentValue::BlockPos(position)) = self.arguments.get(name) else {
+            return Err(format!("Missing argument {name}"));
+        };
+        position.resolve(self.sender.position().await.as_ref()).ok_or_else(|| String::from("Relative coordinates can only be used by players"))
+    }
+
+    /// Returns the entities targeted by an entity argument.
+    pub async fn entities(&self, name: &str) -> Result<Vec<Eid>, String> {
+        let Some(ArgumentValue::Entity(selector)) = self.arguments.get(name) else {
+            return Err(format!("Missing argument {name}"));
+        };
+        let eids = selector.select(self.world, self.sender.eid()).await;
+        match eids.is_empty() {
+            true => Err(String::from("No entity was found")),
+            false => Ok(eids),
+        }
+    }
+}
+
+/// A node of the command tree: a literal, such as the name of a command, or an argument
+pub struct Command {
+    name: &'static str,
+    argument: Option<ArgumentType>,
+    /// The level needed to use this node and its children
+    permission: u8,
+    children: Vec<Command>,
+    executor: Option<Arc<CommandExecutor>>,
+}
+
+impl Command {
+    pub fn literal(name: &'static str) -> Command {
+        Command { name, argument: None, permission: 0, children: Vec::new(), executor: None }
+    }
+
+    pub fn argument(name: &'static str, ty: ArgumentType) -> Command {
+        Command { name, argument: Some(ty), permission: 0, children: Vec::new(), executor: None }
+    }
+
+    pub fn permission(mut self, level: u8) -> Command {
+        self.permission = level;
+        self
+    }
+
+    pub fn then(mut self, child: Command) -> Command {
+        self.children.push(child);
+        self
+    }
+
+    /// Makes the command executable if it stops at this node.
+    pub fn executes<F, Fut>(mut self, executor: F) -> Command
+    where
+        F: Fn(CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = CommandResult> + Send + 'static,
+    {
+        let executor: CommandExecutor = Box::new(move |context| -> BoxFuture<'static, CommandResult> { Box::pin(executor(context)) });
+        self.executor = Some(Arc::new(executor));
+        self
+    }
+
+    /// Reads the token of this node, and the ones of its children.
+    /// Returns the executor of the deepest node reached when the input ends.
+    fn parse(&self, reader: &mut Reader, arguments: &mut HashMap<&'static str, ArgumentValue>, level: u8) -> Result<Arc<CommandExecutor>, String> {
+        match &self.argument {
+            None => {
+                let word = reader.read_word();
+                if word != self.name {
+                    return Err(format!("Unknown or incomplete command, expected {:?}", self.name));
+                }
+            }
+            Some(ty) => {
+                let value = ty.parse(reader)?;
+                arguments.insert(self.name, value);
+            }
+        }
+
+        if reader.at_end() {
+            return self.executor.clone().ok_or_else(|| String::from("Unknown or incomplete command"));
+        }
+        if !reader.skip_space() {
+            return Err(format!("Expected a space after {:?}", self.name));
+        }
+        parse_children(&self.children, reader, arguments, level)
+    }
+
+    /// Appends this node and its children to a command graph, children first.
+    /// Returns the index of this node.
+    fn declare(&self, nodes: &mut Vec<NetworkCommandNode<'static>>, level: u8) -> i32 {
+        let children = self.children.iter().filter(|child| child.permission <= level).map(|child| VarInt(child.declare(nodes, level))).collect();
+        nodes.push(NetworkCommandNode {
+            executable: self.executor.is_some(),
+            children,
+            redirect: None,
+            kind: match &self.argument {
+                None => CommandNodeKind::Literal { name: self.name },
+                Some(ty) => CommandNodeKind::Argument {
+                    name: self.name,
+                    parser: ty.parser(),
+                    suggestions: matches!(ty, ArgumentType::Word).then_some("minecraft:ask_server"),
+                },
+            },
+        });
+        nodes.len() as i32 - 1
+    }
+}
+
+/// Tries the children of a node in order, literals first.
+fn parse_children(children: &[Command], reader: &mut Reader, arguments: &mut HashMap<&'static str, ArgumentValue>, level: u8) -> Result<Arc<CommandExecutor>, String> {
+    let start = reader.cursor;
+    let mut error = String::from("Unknown or incomplete command");
+    let literals = children.iter().filter(|child| child.argument.is_none());
+    let others = children.iter().filter(|child| child.argument.is_some());
+    for child in literals.chain(others).filter(|child| child.permission <= level) {
+        reader.cursor = start;
+        if child.argument.is_none() {
+            if reader.read_word() != child.name {
+                continue;
+            }
+            reader.cursor = start;
+        }
+        match child.parse(reader, arguments, level) {
+            Ok(executor) => return Ok(executor),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// The commands available on the server
+pub struct CommandDispatcher {
+    commands: SyncRwLock<Vec<Arc<Command>>>,
+}
+
+impl CommandDispatcher {
+    /// Creates a dispatcher with the builtin commands.
+    pub fn new() -> CommandDispatcher {
+        let dispatcher = CommandDispatcher { commands: SyncRwLock::new(Vec::new()) };
+        for command in builtin::commands() {
+            dispatcher.register(command);
+        }
+        dispatcher
+    }
+
+    /// Adds a command, replacing any command with the same name.
+    pub fn register(&self, command: Command) {
+        let mut commands = self.commands.write().unwrap();
+        commands.retain(|existing| existing.name != command.name);
+        commands.push(Arc::new(command));
+    }
+
+    fn commands(&self) -> Vec<Arc<Command>> {
+        self.commands.read().unwrap().clone()
+    }
+
+    /// Parses and runs a command, without the leading `/`.
+    /// Returns the messages to show to the sender.
+    pub async fn execute(&self, world: &'static World, sender: CommandSender, input: &str) -> CommandResult {
+        let input = input.strip_prefix('/').unwrap_or(input);
+        let level = sender.permission_level(world).await;
+        let commands = self.commands();
+        let mut arguments = HashMap::new();
+
+        let mut reader = Reader::new(input);
+        let name = reader.read_word();
+        reader.cursor = 0;
+        let Some(command) = commands.iter().find(|command| command.name == name && command.permission <= level) else {
+            return Err(format!("Unknown command {name:?}"));
+        };
+        let executor = command.parse(&mut reader, &mut arguments, level)?;
+        executor(CommandContext { world, sender, arguments }).await
+    }
+
+    /// Returns the command graph for a player.
+    pub fn declare_commands(&self, level: u8) -> PlayClientbound<'static> {
+        let mut nodes = Vec::new();
+        let children = self.commands().iter().filter(|command| command.permission <= level).map(|command| VarInt(command.declare(&mut nodes, level))).collect();
+        nodes.push(NetworkCommandNode { executable: false, children, redirect: None, kind: CommandNodeKind::Root });
+        let root_index = VarInt(nodes.len() as i32 - 1);
+        PlayClientbound::DeclareCommands { nodes: Array::from(nodes), root_index }
+    }
+
+    /// Completes the last token of a partial command.
+    /// Returns where the replaced text starts in `input`, and the suggestions.
+    pub fn suggest(&self, input: &str, level: u8, player_names: &[String]) -> (usize, Vec<String>) {
+        let offset = input.len() - input.strip_prefix('/').unwrap_or(input).len();
+        let input = &input[offset..];
+        let commands = self.commands();
+        let mut nodes: Vec<&Command> = commands.iter().map(|command| command.as_ref()).filter(|command| command.permission <= level).collect();
+
+        // Follow the complete tokens down the tree
+        let mut reader = Reader::new(input);
+        loop {
+            let start = reader.cursor;
+            let next = nodes.iter().find_map(|node| {
+                reader.cursor = start;
+                let matches = match &node.argument {
+                    None => reader.read_word() == node.name,
+                    Some(ty) => ty.parse(&mut reader).is_ok(),
+                };
+                (matches && reader.skip_space()).then_some(*node)
+            });
+            match next {
+                Some(node) => nodes = node.children.iter().filter(|child| child.permission <= level).collect(),
+                None => {
+                    reader.cursor = start;
+                    break;
+                }
+            }
+        }
+
+        let partial = reader.remaining();
+        let mut suggestions = Vec::new();
+        for node in nodes {
+            match &node.argument {
+                None if node.name.starts_with(partial) => suggestions.push(node.name.to_string()),
+                None => (),
+                Some(ty) => suggestions.extend(ty.suggest(partial, player_names)),
+            }
+        }
+        suggestions.dedup();
+        (offset + reader.cursor, suggestions)
+    }
+}
+
+impl Default for CommandDispatcher {
+    fn default() -> Self {
+        CommandDispatcher::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_commands() -> CommandDispatcher {
+        let dispatcher = CommandDispatcher { commands: SyncRwLock::new(Vec::new()) };
+        dispatcher.register(Command::literal("echo")
+            .then(Command::argument("count", ArgumentType::Integer { min: Some(1), max: Some(10) })
+                .then(Command::argument("message", ArgumentType::GreedyString).executes(|context: CommandContext| async move {
+                    let message = context.string("message").unwrap_or_default().to_string();
+                    Ok(vec![ChatText::plain(message); context.integer("count").unwrap_or(1) as usize])
+                }))));
+        dispatcher.register(Command::literal("gamemode").permission(2)
+            .then(Command::argument("gamemode", ArgumentType::Gamemode).executes(|_| async { Ok(Vec::new()) })));
+        dispatcher
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let world: &'static World = Box::leak(Box::new(World::new()));
+        let dispatcher = test_commands();
+
+        let output = dispatcher.execute(world, CommandSender::Console, "/echo 2 hello world").await.unwrap();
+        assert_eq!(output.iter().map(ChatText::to_plain).collect::<Vec<_>>(), vec!["hello world", "hello world"]);
+        assert!(dispatcher.execute(world, CommandSender::Console, "echo 11 hello").await.is_err());
+        assert!(dispatcher.execute(world, CommandSender::Console, "echo 2").await.is_err());
+        assert!(dispatcher.execute(world, CommandSender::Console, "unknown").await.is_err());
+    }
+
+    #[test]
+    fn test_suggest() {
+        let dispatcher = test_commands();
+        assert_eq!(dispatcher.suggest("/ga", 4, &[]), (1, vec![String::from("gamemode")]));
+        assert_eq!(dispatcher.suggest("/ga", 0, &[]), (1, Vec::<String>::new()));
+        assert_eq!(dispatcher.suggest("/gamemode s", 4, &[]), (10, vec![String::from("survival"), String::from("spectator")]));
+    }
+
+    #[test]
+    fn test_declare_commands() {
+        let dispatcher = test_commands();
+        let PlayClientbound::DeclareCommands { nodes, root_index } = dispatcher.declare_commands(0) else { panic!() };
+        assert_eq!(nodes.items.len(), 4);
+        assert_eq!(root_index.0, 3);
+        assert!(matches!(nodes.items[0].kind, CommandNodeKind::Argument { name: "message", .. }));
+        assert_eq!(nodes.items[3].children.iter().map(|child| child.0).collect::<Vec<_>>(), vec![2]);
+    }
+}
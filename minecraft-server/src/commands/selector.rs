@@ -0,0 +1,377 @@
+use super::*;
+
+/// A range of numbers such as `2..5.5`, `..5` or `3`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FloatRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl FloatRange {
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.map(|min| value >= min).unwrap_or(true) && self.max.map(|max| value <= max).unwrap_or(true)
+    }
+}
+
+impl std::str::FromStr for FloatRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FloatRange, String> {
+        let parse_bound = |bound: &str| match bound {
+            "" => Ok(None),
+            bound => bound.parse::<f64>().map(Some).map_err(|_| format!("Invalid number {bound:?}")),
+        };
+        let range = match s.split_once("..") {
+            Some((min, max)) => FloatRange { min: parse_bound(min)?, max: parse_bound(max)? },
+            None => {
+                let value = parse_bound(s)?;
+                FloatRange { min: value, max: value }
+            }
+        };
+        match range {
+            FloatRange { min: None, max: None } => Err(String::from("Expected a range")),
+            FloatRange { min: Some(min), max: Some(max) } if min > max => Err(String::from("The minimum of a range can't exceed its maximum")),
+            range => Ok(range),
+        }
+    }
+}
+
+/// The entities a selector starts from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorKind {
+    /// A player by name
+    Name(String),
+    /// `@s`
+    Sender,
+    /// `@a`
+    AllPlayers,
+    /// `@p`
+    NearestPlayer,
+    /// `@r`
+    RandomPlayer,
+    /// `@e`
+    AllEntities,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorSort {
+    Nearest,
+    Furthest,
+    Random,
+    Arbitrary,
+}
+
+/// A value of a selector filter, which can be negated with `!`, as in `type=!zombie`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorFilter<T> {
+    pub value: T,
+    pub negated: bool,
+}
+
+impl<T: PartialEq> SelectorFilter<T> {
+    fn matches(&self, value: &T) -> bool {
+        (self.value == *value) != self.negated
+    }
+}
+
+/// A target selector such as `@e[type=zombie,distance=..10,limit=1]`, or a player name
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySelector {
+    pub kind: SelectorKind,
+    pub types: Vec<SelectorFilter<NetworkEntity>>,
+    pub distance: Option<FloatRange>,
+    /// Overrides the origin of the selector
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub z: Option<f64>,
+    /// Selects the entities in the box between the origin and the origin plus this size
+    pub dx: Option<f64>,
+    pub dy: Option<f64>,
+    pub dz: Option<f64>,
+    pub limit: Option<usize>,
+    pub sort: Option<SelectorSort>,
+    pub names: Vec<SelectorFilter<String>>,
+    /// An empty tag matches entities without tags
+    pub tags: Vec<SelectorFilter<String>>,
+    pub gamemodes: Vec<SelectorFilter<Gamemode>>,
+}
+
+fn entity_type_from_text_id(text_id: &str) -> Option<NetworkEntity> {
+    let text_id = text_id.strip_prefix("minecraft:").unwrap_or(text_id);
+    (0..).map(NetworkEntity::from_id).take_while(Option::is_some).flatten().find(|ty| ty.text_id() == text_id)
+}
+
+/// What a selector needs to know about an entity
+struct Candidate {
+    eid: Eid,
+    ty: Option<NetworkEntity>,
+    position: Position,
+    name: Option<String>,
+    tags: HashSet<String>,
+    gamemode: Option<Gamemode>,
+}
+
+impl EntitySelector {
+    fn new(kind: SelectorKind) -> EntitySelector {
+        EntitySelector {
+            kind,
+            types: Vec::new(),
+            distance: None,
+            x: None,
+            y: None,
+            z: None,
+            dx: None,
+            dy: None,
+            dz: None,
+            limit: None,
+            sort: None,
+            names: Vec::new(),
+            tags: Vec::new(),
+            gamemodes: Vec::new(),
+        }
+    }
+
+    fn parse_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let (negated, raw) = match value.strip_prefix('!') {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+        let raw = raw.trim_matches('"');
+        let parse_number = |value: &str| value.parse::<f64>().map_err(|_| format!("Invalid number {value:?} for {key}"));
+        if negated && !matches!(key, "type" | "name" | "tag" | "gamemode") {
+            return Err(format!("Option {key} can't be negated"));
+        }
+        match key {
+            "type" => {
+                let ty = entity_type_from_text_id(raw).ok_or_else(|| format!("Unknown entity type {raw:?}"))?;
+                if !negated && self.types.iter().any(|filter| !filter.negated) {
+                    return Err(String::from("Option type can only be specified once"));
+                }
+                self.types.push(SelectorFilter { value: ty, negated });
+            }
+            "distance" => {
+                let range: FloatRange = raw.parse()?;
+                if range.min.map(|min| min < 0.0).unwrap_or(false) {
+                    return Err(String::from("Distance can't be negative"));
+                }
+                self.distance = Some(range);
+            }
+            "x" => self.x = Some(parse_number(raw)?),
+            "y" => self.y = Some(parse_number(raw)?),
+            "z" => self.z = Some(parse_number(raw)?),
+            "dx" => self.dx = Some(parse_number(raw)?),
+            "dy" => self.dy = Some(parse_number(raw)?),
+            "dz" => self.dz = Some(parse_number(raw)?),
+            "limit" => match raw.parse::<usize>() {
+                Ok(0) | Err(_) => return Err(format!("Invalid limit {raw:?}")),
+                Ok(limit) => self.limit = Some(limit),
+            },
+            "sort" => self.sort = Some(match raw {
+                "nearest" => SelectorSort::Nearest,
+                "furthest" => SelectorSort::Furthest,
+                "random" => SelectorSort::Random,
+                "arbitrary" => SelectorSort::Arbitrary,
+                _ => return Err(format!("Unknown sort {raw:?}")),
+            }),
+            "name" => self.names.push(SelectorFilter { value: raw.to_string(), negated }),
+            "tag" => self.tags.push(SelectorFilter { value: raw.to_string(), negated }),
+            "gamemode" => {
+                let gamemode = parse_gamemode(raw).ok_or_else(|| format!("Unknown game mode {raw:?}"))?;
+                self.gamemodes.push(SelectorFilter { value: gamemode, negated });
+            }
+            _ => return Err(format!("Unknown option {key:?}")),
+        }
+        Ok(())
+    }
+
+    /// Returns true if the selector can't match more than one entity.
+    pub fn is_single(&self) -> bool {
+        match self.kind {
+            SelectorKind::Name(_) | SelectorKind::Sender => true,
+            SelectorKind::NearestPlayer | SelectorKind::RandomPlayer => self.limit.unwrap_or(1) == 1,
+            SelectorKind::AllPlayers | SelectorKind::AllEntities => self.limit == Some(1),
+        }
+    }
+
+    /// Returns true if the selector can only match players.
+    pub fn is_players_only(&self) -> bool {
+        match self.kind {
+            SelectorKind::AllEntities => self.types.iter().any(|filter| !filter.negated && filter.value == NetworkEntity::Player),
+            _ => true,
+        }
+    }
+
+    fn matches(&self, candidate: &Candidate, origin: &Position) -> bool {
+        if !self.types.iter().all(|filter| candidate.ty.map(|ty| filter.matches(&ty)).unwrap_or(filter.negated)) {
+            return false;
+        }
+        if let Some(distance) = &self.distance {
+            let squared = (candidate.position.x - origin.x).powi(2) + (candidate.position.y - origin.y).powi(2) + (candidate.position.z - origin.z).powi(2);
+            if !distance.contains(squared.sqrt()) {
+                return false;
+            }
+        }
+        if self.dx.is_some() || self.dy.is_some() || self.dz.is_some() {
+            let in_range = |value: f64, origin: f64, size: Option<f64>| {
+                let end = origin + size.unwrap_or(0.0);
+                value >= origin.min(end) && value < origin.max(end) + 1.0
+            };
+            if !in_range(candidate.position.x, origin.x, self.dx) || !in_range(candidate.position.y, origin.y, self.dy) || !in_range(candidate.position.z, origin.z, self.dz) {
+                return false;
+            }
+        }
+        if !self.names.iter().all(|filter| candidate.name.as_ref().map(|name| filter.matches(name)).unwrap_or(filter.negated)) {
+            return false;
+        }
+        for filter in &self.tags {
+            let has_tag = match filter.value.is_empty() {
+                true => candidate.tags.is_empty(),
+                false => candidate.tags.contains(&filter.value),
+            };
+            if has_tag == filter.negated {
+                return false;
+            }
+        }
+        if !self.gamemodes.iter().all(|filter| candidate.gamemode.as_ref().map(|gamemode| filter.matches(gamemode)).unwrap_or(false)) {
+            return false;
+        }
+        true
+    }
+
+    /// Finds the entities matched by the selector.
+    /// `sender` is the entity running the command, if any, which is also the default origin.
+    pub async fn select(&self, world: &World, sender: Option<Eid>) -> Vec<Eid> {
+        let sender_position = match sender {
+            Some(eid) => world.observe_entity(eid, |entity| entity.as_entity().position.clone()).await,
+            None => None,
+        };
+        let base = sender_position.unwrap_or(Position { x: 0.0, y: 0.0, z: 0.0 });
+        let origin = Position { x: self.x.unwrap_or(base.x), y: self.y.unwrap_or(base.y), z: self.z.unwrap_or(base.z) };
+
+        let players: HashMap<Eid, PlayerListEntry> = world.players().await.into_iter().map(|(_, entry)| (entry.eid, entry)).collect();
+        let players_only = !matches!(self.kind, SelectorKind::AllEntities | SelectorKind::Sender);
+        let mut candidates = world.observe_all_entities(|eid, entity| {
+            if players_only && !players.contains_key(&eid) {
+                return None;
+            }
+            if self.kind == SelectorKind::Sender && Some(eid) != sender {
+                return None;
+            }
+            let player = players.get(&eid);
+            Some(Candidate {
+                eid,
+                ty: entity.to_network(),
+                position: entity.as_entity().position.clone(),
+                name: player.map(|entry| entry.name.clone()).or_else(|| entity.as_entity().name.clone()),
+                tags: entity.as_entity().tags.clone(),
+                gamemode: player.map(|entry| entry.gamemode.clone()),
+            })
+        }).await;
+        if let SelectorKind::Name(name) = &self.kind {
+            candidates.retain(|candidate| candidate.name.as_ref() == Some(name));
+        }
+        candidates.retain(|candidate| self.matches(candidate, &origin));
+
+        let distance = |candidate: &Candidate| (candidate.position.x - origin.x).powi(2) + (candidate.position.y - origin.y).powi(2) + (candidate.position.z - origin.z).powi(2);
+        let default_sort = match self.kind {
+            SelectorKind::NearestPlayer => SelectorSort::Nearest,
+            SelectorKind::RandomPlayer => SelectorSort::Random,
+            _ => SelectorSort::Arbitrary,
+        };
+        match self.sort.unwrap_or(default_sort) {
+            SelectorSort::Nearest => candidates.sort_by(|a, b| distance(a).total_cmp(&distance(b))),
+            SelectorSort::Furthest => candidates.sort_by(|a, b| distance(b).total_cmp(&distance(a))),
+            SelectorSort::Random => {
+                use rand::seq::SliceRandom;
+                candidates.shuffle(&mut rand::thread_rng());
+            }
+            SelectorSort::Arbitrary => candidates.sort_by_key(|candidate| candidate.eid),
+        }
+
+        let limit = match self.kind {
+            SelectorKind::NearestPlayer | SelectorKind::RandomPlayer => self.limit.unwrap_or(1),
+            _ => self.limit.unwrap_or(usize::MAX),
+        };
+        candidates.into_iter().take(limit).map(|candidate| candidate.eid).collect()
+    }
+}
+
+impl std::str::FromStr for EntitySelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EntitySelector, String> {
+        let Some(selector) = s.strip_prefix('@') else {
+            return match s {
+                "" => Err(String::from("Expected a player name or a selector")),
+                name if name.len() <= 16 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => Ok(EntitySelector::new(SelectorKind::Name(name.to_string()))),
+                name => Err(format!("Invalid player name {name:?}")),
+            };
+        };
+        let (kind, options) = match selector.split_once('[') {
+            Some((kind, options)) => (kind, Some(options.strip_suffix(']').ok_or("Missing closing bracket in selector")?)),
+            None => (selector, None),
+        };
+        let kind = match kind {
+            "s" => SelectorKind::Sender,
+            "a" => SelectorKind::AllPlayers,
+            "p" => SelectorKind::NearestPlayer,
+            "r" => SelectorKind::RandomPlayer,
+            "e" => SelectorKind::AllEntities,
+            _ => return Err(format!("Unknown selector {s:?}")),
+        };
+        let mut selector = EntitySelector::new(kind);
+        for option in options.unwrap_or("").split(',').map(str::trim).filter(|option| !option.is_empty()) {
+            let (key, value) = option.split_once('=').ok_or_else(|| format!("Expected a value for option {option:?}"))?;
+            selector.parse_option(key.trim(), value.trim())?;
+        }
+        Ok(selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selector() {
+        let selector: EntitySelector = "@e[type=zombie, distance=..10, limit=2, sort=nearest, tag=!boss]".parse().unwrap();
+        assert_eq!(selector.kind, SelectorKind::AllEntities);
+        assert_eq!(selector.types, vec![SelectorFilter { value: NetworkEntity::Zombie, negated: false }]);
+        assert_eq!(selector.distance, Some(FloatRange { min: None, max: Some(10.0) }));
+        assert_eq!((selector.limit, selector.sort), (Some(2), Some(SelectorSort::Nearest)));
+        assert_eq!(selector.tags, vec![SelectorFilter { value: String::from("boss"), negated: true }]);
+        assert!(!selector.is_single() && !selector.is_players_only());
+
+        assert!("@p".parse::<EntitySelector>().unwrap().is_single());
+        assert!("@e[type=player]".parse::<EntitySelector>().unwrap().is_players_only());
+        assert!(matches!("Steve".parse::<EntitySelector>().unwrap().kind, SelectorKind::Name(name) if name == "Steve"));
+        assert!("@x".parse::<EntitySelector>().is_err());
+        assert!("@e[limit=0]".parse::<EntitySelector>().is_err());
+        assert!("@e[distance=!5]".parse::<EntitySelector>().is_err());
+        assert!("@e[type=zombie,type=cow]".parse::<EntitySelector>().is_err());
+        assert!("@e[color=red]".parse::<EntitySelector>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_select() {
+        let world: &'static World = Box::leak(Box::new(World::new()));
+        let mut eids = Vec::new();
+        for (x, tagged) in [(0.0, false), (5.0, true), (20.0, false)] {
+            let mut zombie = Zombie::default();
+            zombie.get_entity_mut().position = Position { x, y: 0.0, z: 0.0 };
+            if tagged {
+                zombie.get_entity_mut().tags.insert(String::from("boss"));
+            }
+            eids.push(world.spawn_entity::<Zombie>(AnyEntity::Zombie(zombie)).await);
+        }
+        let select = |selector: &'static str| async move { selector.parse::<EntitySelector>().unwrap().select(world, None).await };
+
+        assert_eq!(select("@e").await, eids);
+        assert_eq!(select("@e[type=zombie,distance=..10]").await, eids[..2]);
+        assert_eq!(select("@e[type=!zombie]").await, Vec::<Eid>::new());
+        assert_eq!(select("@e[tag=boss]").await, vec![eids[1]]);
+        assert_eq!(select("@e[tag=!boss,sort=furthest,limit=1]").await, vec![eids[2]]);
+        assert_eq!(select("@e[x=4,dx=2]").await, vec![eids[1]]);
+        assert_eq!(select("@a").await, Vec::<Eid>::new());
+    }
+}
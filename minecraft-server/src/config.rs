@@ -0,0 +1,339 @@
+use std::{net::IpAddr, path::Path};
+use tokio::time::MissedTickBehavior;
+use crate::prelude::*;
+
+/// The version name shown in the server list
+pub const VERSION_NAME: &str = "1.20.2";
+/// The protocol version this server speaks
+pub const PROTOCOL_VERSION: i32 = 764;
+
+/// The settings of the server, read from a `server.properties` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub server_ip: IpAddr,
+    pub server_port: u16,
+    /// Shown in the server list and in the pause menu
+    pub motd: String,
+    pub max_players: usize,
+    /// The maximum number of open connections, including players that are still logging in
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    /// The version name shown in the server list
+    pub version_name: String,
+    /// The maximum render distance of clients, in chunks
+    pub view_distance: u8,
+    /// The distance around players in which entities are ticked, in chunks
+    pub simulation_distance: u8,
+    pub gamemode: Gamemode,
+    pub difficulty: Difficulty,
+    pub hardcore: bool,
+    pub spawn_x: i32,
+    pub spawn_y: i32,
+    pub spawn_z: i32,
+    /// Sent to clients, which use it for biome noise
+    pub hashed_seed: u64,
+    /// The directory the world is saved in
+    pub level_name: String,
+    /// What happens to ticks that couldn't run in time
+    pub missed_ticks: MissedTickBehavior,
+    /// Where Prometheus metrics are served over HTTP, if anywhere
+    pub metrics_address: Option<SocketAddr>,
+    /// Whether the server can be administered remotely over RCON
+    pub enable_rcon: bool,
+    /// The port RCON listens on, on [ServerConfig::server_ip]
+    pub rcon_port: u16,
+    /// The password of RCON clients. RCON stays disabled while it's empty.
+    pub rcon_password: String,
+    /// Whether monitoring tools can query the server over UDP
+    pub enable_query: bool,
+    /// The UDP port queries are answered on, on [ServerConfig::server_ip]
+    pub query_port: u16,
+    /// The players with every permission, by UUID
+    pub ops: Vec<UUID>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            server_ip: IpAddr::from([127, 0, 0, 1]),
+            server_port: 25567,
+            motd: String::from("Minecraft rust server"),
+            max_players: 1000,
+            max_connections: 1100,
+            max_connections_per_ip: 10,
+            version_name: String::from(VERSION_NAME),
+            view_distance: 12,
+            simulation_distance: 8,
+            gamemode: Gamemode::Creative,
+            difficulty: Difficulty::Normal,
+            hardcore: false,
+            spawn_x: 0,
+            spawn_y: 60,
+            spawn_z: 0,
+            hashed_seed: 42,
+            level_name: String::from("world"),
+            missed_ticks: MissedTickBehavior::Skip,
+            metrics_address: None,
+            enable_rcon: false,
+            rcon_port: 25575,
+            rcon_password: String::new(),
+            enable_query: false,
+            query_port: 25565,
+            ops: Vec::new(),
+        }
+    }
+}
+
+fn parse_gamemode(value: &str) -> Option<Gamemode> {
+    match value {
+        "survival" | "0" => Some(Gamemode::Survival),
+        "creative" | "1" => Some(Gamemode::Creative),
+        "adventure" | "2" => Some(Gamemode::Adventure),
+        "spectator" | "3" => Some(Gamemode::Spectator),
+        _ => None,
+    }
+}
+
+fn gamemode_name(gamemode: &Gamemode) -> &'static str {
+    match gamemode {
+        Gamemode::Survival => "survival",
+        Gamemode::Creative => "creative",
+        Gamemode::Adventure => "adventure",
+        Gamemode::Spectator => "spectator",
+    }
+}
+
+fn parse_difficulty(value: &str) -> Option<Difficulty> {
+    match value {
+        "peaceful" | "0" => Some(Difficulty::Peaceful),
+        "easy" | "1" => Some(Difficulty::Easy),
+        "normal" | "2" => Some(Difficulty::Normal),
+        "hard" | "3" => Some(Difficulty::Hard),
+        _ => None,
+    }
+}
+
+fn difficulty_name(difficulty: &Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Peaceful => "peaceful",
+        Difficulty::Easy => "easy",
+        Difficulty::Normal => "normal",
+        Difficulty::Hard => "hard",
+    }
+}
+
+fn parse_missed_ticks(value: &str) -> Option<MissedTickBehavior> {
+    match value {
+        "burst" => Some(MissedTickBehavior::Burst),
+        "delay" => Some(MissedTickBehavior::Delay),
+        "skip" => Some(MissedTickBehavior::Skip),
+        _ => None,
+    }
+}
+
+fn missed_ticks_name(missed_ticks: MissedTickBehavior) -> &'static str {
+    match missed_ticks {
+        MissedTickBehavior::Burst => "burst",
+        MissedTickBehavior::Delay => "delay",
+        MissedTickBehavior::Skip => "skip",
+    }
+}
+
+/// Parses a UUID written in hexadecimal, with or without hyphens.
+fn parse_uuid(value: &str) -> Option<UUID> {
+    let hex = value.replace('-', "");
+    if hex.len() != 32 {
+        return None;
+    }
+    UUID::from_str_radix(&hex, 16).ok()
+}
+
+fn parse_in_range<T: std::str::FromStr + PartialOrd + std::fmt::Display>(key: &str, value: &str, min: T, max: T) -> Result<T, String> {
+    match value.parse::<T>() {
+        Ok(value) if value >= min && value <= max => Ok(value),
+        _ => Err(format!("{key} must be a number between {min} and {max}, found {value:?}")),
+    }
+}
+
+impl ServerConfig {
+    /// Returns the address the server listens on.
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.server_ip, self.server_port)
+    }
+
+    /// Returns the address RCON listens on.
+    pub fn rcon_address(&self) -> SocketAddr {
+        SocketAddr::new(self.server_ip, self.rcon_port)
+    }
+
+    /// Returns the address queries are answered on.
+    pub fn query_address(&self) -> SocketAddr {
+        SocketAddr::new(self.server_ip, self.query_port)
+    }
+
+    /// Returns where players appear when they join.
+    pub fn spawn_position(&self) -> Position {
+        Position { x: self.spawn_x as f64, y: self.spawn_y as f64, z: self.spawn_z as f64 }
+    }
+
+    /// Parses the content of a `server.properties` file.
+    /// Missing keys keep their default value, and unknown keys are ignored with a warning.
+    pub fn parse(content: &str) -> Result<ServerConfig, String> {
+        let mut config = ServerConfig::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').or_else(|| line.split_once(':')) else {
+                return Err(format!("Line {} is not a key=value pair", i + 1));
+            };
+            config.set(key.trim(), value.trim()).map_err(|e| format!("Line {}: {e}", i + 1))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the settings that depend on each other.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enable_rcon && self.rcon_password.is_empty() {
+            return Err(String::from("rcon.password can't be empty when enable-rcon is true"));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "server-ip" => self.server_ip = match value {
+                "" => IpAddr::from([0, 0, 0, 0]),
+                value => value.parse().map_err(|_| format!("Invalid server-ip {value:?}"))?,
+            },
+            "server-port" => self.server_port = parse_in_range(key, value, 0, u16::MAX)?,
+            "motd" => {
+                if value.chars().count() > 256 {
+                    return Err(String::from("motd can't be longer than 256 characters"));
+                }
+                self.motd = value.to_string();
+            }
+            "max-players" => self.max_players = parse_in_range(key, value, 1, 100_000)?,
+            "max-connections" => self.max_connections = parse_in_range(key, value, 1, 100_000)?,
+            "max-connections-per-ip" => self.max_connections_per_ip = parse_in_range(key, value, 1, 100_000)?,
+            "version-name" => self.version_name = value.to_string(),
+            "view-distance" => self.view_distance = parse_in_range(key, value, 2, 32)?,
+            "simulation-distance" => self.simulation_distance = parse_in_range(key, value, 2, 32)?,
+            "gamemode" => self.gamemode = parse_gamemode(value).ok_or_else(|| format!("Unknown gamemode {value:?}"))?,
+            "difficulty" => self.difficulty = parse_difficulty(value).ok_or_else(|| format!("Unknown difficulty {value:?}"))?,
+            "hardcore" => self.hardcore = value.parse().map_err(|_| format!("hardcore must be true or false, found {value:?}"))?,
+            "spawn-x" => self.spawn_x = parse_in_range(key, value, -30_000_000, 30_000_000)?,
+            "spawn-y" => self.spawn_y = parse_in_range(key, value, -64, 319)?,
+            "spawn-z" => self.spawn_z = parse_in_range(key, value, -30_000_000, 30_000_000)?,
+            "hashed-seed" => self.hashed_seed = value.parse().map_err(|_| format!("Invalid hashed-seed {value:?}"))?,
+            "level-name" => {
+                if value.is_empty() || value.contains("..") {
+                    return Err(format!("Invalid level-name {value:?}"));
+                }
+                self.level_name = value.to_string();
+            }
+            "missed-tick-behavior" => self.missed_ticks = parse_missed_ticks(value).ok_or_else(|| format!("missed-tick-behavior must be burst, delay or skip, found {value:?}"))?,
+            "metrics-address" => self.metrics_address = match value {
+                "" => None,
+                value => Some(value.parse().map_err(|_| format!("Invalid metrics-address {value:?}"))?),
+            },
+            "enable-rcon" => self.enable_rcon = value.parse().map_err(|_| format!("enable-rcon must be true or false, found {value:?}"))?,
+            "rcon.port" => self.rcon_port = parse_in_range(key, value, 0, u16::MAX)?,
+            "rcon.password" => self.rcon_password = value.to_string(),
+            "enable-query" => self.enable_query = value.parse().map_err(|_| format!("enable-query must be true or false, found {value:?}"))?,
+            "query.port" => self.query_port = parse_in_range(key, value, 0, u16::MAX)?,
+            "ops" => self.ops = value.split(',').map(str::trim).filter(|uuid| !uuid.is_empty())
+                .map(|uuid| parse_uuid(uuid).ok_or_else(|| format!("Invalid UUID {uuid:?} in ops")))
+                .collect::<Result<_, _>>()?,
+            key => warn!("Unknown setting {key:?} in server.properties"),
+        }
+        Ok(())
+    }
+
+    /// Serializes the config in the `server.properties` format.
+    pub fn to_properties(&self) -> String {
+        format!(
+            "# Minecraft server properties\n\
+            server-ip={}\n\
+            server-port={}\n\
+            motd={}\n\
+            max-players={}\n\
+            max-connections={}\n\
+            max-connections-per-ip={}\n\
+            version-name={}\n\
+            view-distance={}\n\
+            simulation-distance={}\n\
+            gamemode={}\n\
+            difficulty={}\n\
+            hardcore={}\n\
+            spawn-x={}\n\
+            spawn-y={}\n\
+            spawn-z={}\n\
+            hashed-seed={}\n\
+            level-name={}\n\
+            missed-tick-behavior={}\n\
+            metrics-address={}\n\
+            enable-rcon={}\n\
+            rcon.port={}\n\
+            rcon.password={}\n\
+            enable-query={}\n\
+            query.port={}\n\
+            ops={}\n",
+            self.server_ip, self.server_port, self.motd, self.max_players, self.max_connections, self.max_connections_per_ip, self.version_name, self.view_distance, self.simulation_distance,
+            gamemode_name(&self.gamemode), difficulty_name(&self.difficulty), self.hardcore, self.spawn_x, self.spawn_y, self.spawn_z,
+            self.hashed_seed, self.level_name, missed_ticks_name(self.missed_ticks),
+            self.metrics_address.map(|address| address.to_string()).unwrap_or_default(),
+            self.enable_rcon, self.rcon_port, self.rcon_password, self.enable_query, self.query_port,
+            self.ops.iter().map(|uuid| format!("{uuid:032x}")).collect::<Vec<_>>().join(","),
+        )
+    }
+
+    /// Reads the config from a file.
+    /// If the file doesn't exist, it is created with the default values.
+    pub fn load(path: impl AsRef<Path>) -> Result<ServerConfig, String> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(content) => ServerConfig::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let config = ServerConfig::default();
+                if let Err(e) = std::fs::write(path, config.to_properties()) {
+                    warn!("Couldn't write default config to {}: {e}", path.display());
+                }
+                Ok(config)
+            }
+            Err(e) => Err(format!("Couldn't read {}: {e}", path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = ServerConfig::parse("# comment\nserver-port=25565\nmotd=Hello = world\ngamemode=survival\nview-distance=10\nunknown=1\n").unwrap();
+        assert_eq!(config.server_port, 25565);
+        assert_eq!(config.motd, "Hello = world");
+        assert_eq!(config.gamemode, Gamemode::Survival);
+        assert_eq!(config.view_distance, 10);
+        assert_eq!(config.max_players, ServerConfig::default().max_players);
+
+        assert!(ServerConfig::parse("view-distance=100").is_err());
+        assert!(ServerConfig::parse("server-port=-1").is_err());
+        assert!(ServerConfig::parse("gamemode=hardcore").is_err());
+        assert!(ServerConfig::parse("not a pair").is_err());
+        assert!(ServerConfig::parse("missed-tick-behavior=catch-up").is_err());
+        assert!(ServerConfig::parse("metrics-address=9225").is_err());
+        assert_eq!(ServerConfig::parse("metrics-address=").unwrap().metrics_address, None);
+        assert!(ServerConfig::parse("enable-rcon=true\nrcon.password=").is_err());
+        assert!(ServerConfig::parse("enable-rcon=false\nrcon.password=").is_ok());
+        assert_eq!(ServerConfig::parse("ops=069a79f4-44e9-4726-a5be-fca90e38aaf5, 853c80ef3c3749fdaa49938b674adae6").unwrap().ops, vec![0x069a79f444e94726a5befca90e38aaf5, 0x853c80ef3c3749fdaa49938b674adae6]);
+        assert!(ServerConfig::parse("ops=Notch").is_err());
+
+        let config = ServerConfig { motd: String::from("Test"), gamemode: Gamemode::Adventure, missed_ticks: MissedTickBehavior::Burst, metrics_address: Some(SocketAddr::from(([127, 0, 0, 1], 9225))), enable_rcon: true, rcon_password: String::from("p4ss=word"), enable_query: true, query_port: 25566, ops: vec![1, 2], ..ServerConfig::default() };
+        assert_eq!(ServerConfig::parse(&config.to_properties()).unwrap(), config);
+    }
+}
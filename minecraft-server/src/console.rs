@@ -0,0 +1,186 @@
+use std::{io::{IsTerminal, Write}, panic::AssertUnwindSafe};
+use rustyline::{completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter, history::DefaultHistory, validate::Validator, Context, Editor, ExternalPrinter, Helper};
+use tokio::runtime::Handle;
+use crate::prelude::*;
+
+/// Completes commands typed in the console, like the chat does for players
+struct ConsoleHelper {
+    world: &'static World,
+    runtime: Handle,
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let player_names: Vec<String> = self.runtime.block_on(self.world.players()).into_iter().map(|(_, entry)| entry.name).collect();
+        Ok(self.world.commands().suggest(&line[..pos], 4, &player_names))
+    }
+}
+
+/// Unwinds out of [Editor::readline] once the server shuts down
+struct ConsoleClosed;
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+
+    /// Called whenever the prompt is redrawn, including when something is printed above it.
+    /// [Editor::readline] can't be interrupted otherwise, and it restores the terminal as it unwinds.
+    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if self.world.is_shutting_down() {
+            std::panic::resume_unwind(Box::new(ConsoleClosed));
+        }
+        None
+    }
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {}
+
+impl Helper for ConsoleHelper {}
+
+/// Sends logs to the console, which prints them above the prompt instead of in the middle of what is being typed
+struct LogPrinter<P: ExternalPrinter> {
+    printer: P,
+    /// The end of a line that was partially written
+    line: Vec<u8>,
+}
+
+impl<P: ExternalPrinter> Write for LogPrinter<P> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if let Some(end) = self.line.iter().rposition(|byte| *byte == b'\n') {
+            let lines: Vec<u8> = self.line.drain(..=end).collect();
+            self.printer.print(String::from_utf8_lossy(&lines).into_owned()).map_err(std::io::Error::other)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads commands typed in the terminal, with line editing, history and tab completion
+pub struct Console {
+    editor: Editor<ConsoleHelper, DefaultHistory>,
+    /// Whether the console runs in a terminal, where it can be woken up by printing above the prompt
+    interactive: bool,
+}
+
+impl Console {
+    /// Initializes the logger, so that logs don't corrupt the prompt when the server runs in a terminal.
+    /// If this fails, the logger is left uninitialized.
+    pub fn init() -> rustyline::Result<Console> {
+        let mut editor = Editor::new()?;
+        let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        if interactive {
+            let printer = editor.create_external_printer()?;
+            env_logger::Builder::from_default_env().target(env_logger::Target::Pipe(Box::new(LogPrinter { printer, line: Vec::new() }))).init();
+        } else {
+            env_logger::init();
+        }
+        Ok(Console { editor, interactive })
+    }
+
+    /// Runs the typed commands with every permission, until the input ends or the server shuts down.
+    /// Reading the terminal blocks, so this happens in a thread of its own.
+    /// Ctrl-C stops the server.
+    ///
+    /// Returns the thread to join once the server stopped, so that the terminal is restored before exiting.
+    /// Returns `None` if the input isn't a terminal, as the thread keeps waiting for input until it ends.
+    pub fn start(mut self, world: &'static World) -> Option<std::thread::JoinHandle<()>> {
+        let runtime = Handle::current();
+        self.editor.set_helper(Some(ConsoleHelper { world, runtime: runtime.clone() }));
+        if self.interactive {
+            match self.editor.create_external_printer() {
+                Ok(mut printer) => {
+                    runtime.spawn(async move {
+                        world.shutdown_requested().await;
+                        let _ = printer.print(String::from("Console closed\n"));
+                    });
+                }
+                Err(e) => {
+                    warn!("Console won't close on shutdown: {e}");
+                    self.interactive = false;
+                }
+            }
+        }
+        let interactive = self.interactive;
+        let thread = std::thread::spawn(move || {
+            while !world.is_shutting_down() {
+                let line = match std::panic::catch_unwind(AssertUnwindSafe(|| self.editor.readline("> "))) {
+                    Ok(Ok(line)) => line,
+                    Ok(Err(ReadlineError::Interrupted)) => {
+                        world.shutdown("Server closed");
+                        return;
+                    }
+                    Ok(Err(ReadlineError::Eof)) => return,
+                    Ok(Err(e)) => {
+                        error!("Console stopped reading commands: {e}");
+                        return;
+                    }
+                    Err(payload) if payload.is::<ConsoleClosed>() => {
+                        // The line with the prompt was left unfinished
+                        println!();
+                        return;
+                    }
+                    Err(payload) => std::panic::resume_unwind(payload),
+                };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let _ = self.editor.add_history_entry(line);
+                info!("Console ran command: {line}");
+                match runtime.block_on(world.commands().execute(world, CommandSender::Console, line)) {
+                    Ok(feedback) => {
+                        for text in feedback {
+                            println!("{}", text.to_plain());
+                        }
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            }
+        });
+        interactive.then_some(thread)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestPrinter(Vec<String>);
+
+    impl ExternalPrinter for TestPrinter {
+        fn print(&mut self, msg: String) -> rustyline::Result<()> {
+            self.0.push(msg);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_console_closed() {
+        let world: &'static World = Box::leak(Box::new(World::new()));
+        let helper = ConsoleHelper { world, runtime: Handle::current() };
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        assert_eq!(helper.hint("he", 2, &ctx), None);
+
+        world.shutdown("Test is over");
+        let payload = std::panic::catch_unwind(AssertUnwindSafe(|| helper.hint("he", 2, &ctx))).unwrap_err();
+        assert!(payload.is::<ConsoleClosed>());
+    }
+
+    #[test]
+    fn test_log_printer() {
+        let mut log_printer = LogPrinter { printer: TestPrinter(Vec::new()), line: Vec::new() };
+        log_printer.write_all(b"[INFO] Server").unwrap();
+        assert!(log_printer.printer.0.is_empty());
+        log_printer.write_all(b" started\n[WARN] Lag").unwrap();
+        log_printer.write_all(b"\n").unwrap();
+        assert_eq!(log_printer.printer.0, vec!["[INFO] Server started\n", "[WARN] Lag\n"]);
+    }
+}
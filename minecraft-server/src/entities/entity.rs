@@ -4,7 +4,7 @@ use super::*;
     inheritable,
     descendants { AbstractArrow..., Boat..., Display, FallingBlock, LlamaSpit, Painting, DragonFireball, Fireball..., FireworkRocket, SmallFireball, Interaction..., ItemEntity, ItemFrame..., LivingEntity... EndCrystal, EvokerFangs, WitherSkull, AreaEffectCloud, FishingHook, EyeOfEnder, ThrownItemProjectile... },
     defines {
-        init(self, server_msg_rcvr: BroadcastReceiver<ServerMessage>);
+        init(self, ticks: TickReceiver);
     }
 )]
 pub struct Entity {
@@ -26,11 +26,13 @@ pub struct Entity {
     pub has_no_gravity: bool,
     pub pose: Pose,
     pub ticks_frozen: u32,
+    /// Scoreboard tags, matched by selectors such as `@e[tag=foo]`
+    pub tags: HashSet<String>,
 }
 
 impl Handler<Entity> {
-    pub async fn init(self, server_msg_rcvr: BroadcastReceiver<ServerMessage>) {
-        self.insert_task("newton", tokio::spawn(newton_task(self.clone(), server_msg_rcvr))).await;
+    pub async fn init(self, ticks: TickReceiver) {
+        self.insert_task("newton", tokio::spawn(newton_task(self.clone(), ticks))).await;
     }
 }
 
@@ -55,6 +57,7 @@ impl Default for Entity {
             has_no_gravity: false,
             pose: Pose::Standing,
             ticks_frozen: 0,
+            tags: HashSet::new(),
         }
     }
 }
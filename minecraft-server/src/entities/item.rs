@@ -23,8 +23,246 @@ pub struct GlowingItemFrame {
 #[derive(Default)]
 #[MinecraftEntity(
     ancestors { Entity },
+    defines {
+        Entity.init(self, ticks: TickReceiver);
+    }
 )]
 pub struct ItemEntity {
     pub entity: Entity,
     pub item: Slot,
+    /// Ticks left before the item can be picked up
+    pub pickup_delay: u16,
+    /// Ticks since the item was spawned
+    pub age: u32,
+}
+
+/// Items disappear after 5 minutes
+const ITEM_DESPAWN_AGE: u32 = 6000;
+
+impl Handler<ItemEntity> {
+    pub async fn init(self, ticks: TickReceiver) {
+        self.insert_task("newton", tokio::spawn(newton_task_with_friction(self.clone(), ticks.resubscribe(), Some(Friction::ITEM)))).await;
+        self.insert_task("item", tokio::spawn(item_task(self.clone(), ticks))).await;
+    }
+
+    /// Gives the stack to a player standing close enough.
+    async fn try_pickup(&self, position: &Position) {
+        let players = observe_entities_around(self.world, position, 1.3, |eid, entity| {
+            let player = TryAsEntityRef::<Player>::try_as_entity_ref(entity)?;
+            let player_position = &player.get_entity().position;
+            let reached = (player_position.x - position.x).abs() <= 1.3
+                && (player_position.z - position.z).abs() <= 1.3
+                && position.y >= player_position.y - 0.5
+                && position.y <= player_position.y + 2.3;
+            reached.then_some(eid)
+        }).await;
+        let Some(collector) = players.into_iter().min() else { return };
+
+        // The stack is taken out of the entity so that it can't be merged meanwhile
+        let Some(Some(item)) = self.mutate(|item_entity| (item_entity.item.item.take(), EntityChanges::nothing())).await else { return };
+        let count = item.item_count;
+        let leftover = Handler::<Player>::assume(collector, self.world).pick_up_item(item).await;
+        let picked_up = count - leftover.as_ref().map(|item| item.item_count).unwrap_or(0);
+        if picked_up > 0 {
+            self.world.notify_item_pickup(position, self.eid, collector, picked_up).await;
+        }
+        match leftover {
+            Some(leftover) => self.put_back(leftover, picked_up > 0, position).await,
+            None => { self.world.remove_entity(self.eid).await; },
+        }
+    }
+
+    /// Puts a stack back into the entity, or spawns a new entity if that's not possible.
+    /// The new entity is spawned at `position` if this one was removed meanwhile.
+    async fn put_back(&self, item: SlotItem, changed: bool, position: &Position) {
+        let stack = item.clone();
+        let put_back = self.mutate(|item_entity| match item_entity.item.item {
+            None => {
+                item_entity.item.item = Some(stack);
+                let changes = if changed { EntityChanges::metadata() } else { EntityChanges::nothing() };
+                (true, changes)
+            }
+            Some(_) => (false, EntityChanges::nothing()),
+        }).await.unwrap_or(false);
+        if !put_back {
+            let position = self.observe(|item_entity| item_entity.get_entity().position.clone()).await.unwrap_or_else(|| position.clone());
+            self.world.spawn_item(position, Translation { x: 0.0, y: 0.0, z: 0.0 }, item, 0).await;
+        }
+    }
+
+    /// Absorbs a smaller identical stack lying nearby.
+    /// Of two stacks of the same size, the one with the lowest id absorbs the other.
+    async fn try_merge(&self, position: &Position) {
+        let Some(Some(stack)) = self.observe(|item_entity| item_entity.item.item.clone()).await else { return };
+        let max = stack.item_id.max_stack_size() as i8;
+        if stack.item_count >= max {
+            return;
+        }
+        let candidates = observe_entities_around(self.world, position, 0.5, |eid, entity| {
+            let other = TryAsEntityRef::<ItemEntity>::try_as_entity_ref(entity)?;
+            let other_stack = other.item.item.as_ref()?;
+            let other_position = &other.get_entity().position;
+            let close = (other_position.x - position.x).abs() <= 0.5
+                && (other_position.y - position.y).abs() <= 0.5
+                && (other_position.z - position.z).abs() <= 0.5;
+            let smaller = other_stack.item_count < stack.item_count || (other_stack.item_count == stack.item_count && eid > self.eid);
+            (eid != self.eid && close && smaller && can_stack(&stack, other_stack) && stack.item_count + other_stack.item_count <= max).then_some(eid)
+        }).await;
+        let Some(other) = candidates.into_iter().min() else { return };
+
+        let Some(other) = self.world.remove_entity(other).await else { return };
+        let AnyEntity::ItemEntity(other) = other else { return };
+        let Some(absorbed) = other.item.item else { return };
+        let merged = absorbed.clone();
+        let Some(merged_into_self) = self.mutate(|item_entity| {
+            let merged_into_self = match &mut item_entity.item.item {
+                Some(stack) if can_stack(stack, &merged) && stack.item_count + merged.item_count <= max => {
+                    stack.item_count += merged.item_count;
+                    true
+                }
+                _ => false,
+            };
+            item_entity.age = item_entity.age.min(other.age);
+            item_entity.pickup_delay = item_entity.pickup_delay.max(other.pickup_delay);
+            let changes = if merged_into_self { EntityChanges::metadata() } else { EntityChanges::nothing() };
+            (merged_into_self, changes)
+        }).await else {
+            self.world.spawn_item(other.entity.position, other.entity.velocity, absorbed, other.pickup_delay).await;
+            return;
+        };
+        if !merged_into_self {
+            self.world.spawn_item(other.entity.position, other.entity.velocity, absorbed, other.pickup_delay).await;
+        }
+    }
+}
+
+/// Observes the entities of the chunks that are within `radius` of a position.
+async fn observe_entities_around<R>(world: &World, position: &Position, radius: f64, mut observer: impl FnMut(Eid, &AnyEntity) -> Option<R>) -> Vec<R> {
+    let mut chunks = HashSet::new();
+    for dx in [-radius, radius] {
+        for dz in [-radius, radius] {
+            chunks.insert(Position { x: position.x + dx, y: position.y, z: position.z + dz }.chunk_column());
+        }
+    }
+    let mut results = Vec::new();
+    for chunk in chunks {
+        results.extend(world.observe_entities_with_eids(chunk, &mut observer).await);
+    }
+    results
+}
+
+/// Ages an item entity, lets players pick it up and merges it with identical stacks nearby.
+pub async fn item_task(h: Handler<ItemEntity>, mut ticks: TickReceiver) {
+    while ticks.next().await.is_some() {
+        let Some((age, pickup_delay, position)) = h.mutate(|item_entity| {
+            item_entity.age += 1;
+            item_entity.pickup_delay = item_entity.pickup_delay.saturating_sub(1);
+            ((item_entity.age, item_entity.pickup_delay, item_entity.get_entity().position.clone()), EntityChanges::nothing())
+        }).await else { return };
+
+        if age >= ITEM_DESPAWN_AGE {
+            h.world.remove_entity(h.eid).await;
+            return;
+        }
+        if age % 2 == 0 {
+            h.try_merge(&position).await;
+        }
+        if pickup_delay == 0 {
+            h.try_pickup(&position).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::ids::items::Item;
+    use crate::player_handler::test_client::*;
+    use super::*;
+
+    fn stack(item_id: Item, item_count: i8) -> SlotItem {
+        SlotItem { item_id, item_count, nbt_data: NbtTag::Null }
+    }
+
+    async fn manual_server() -> ServerBehavior {
+        ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .tick_source(TickSource::Manual)
+            .start().await.unwrap()
+    }
+
+    async fn item_stacks(world: &World) -> Vec<(Eid, SlotItem)> {
+        world.observe_all_entities(|eid, entity| match entity {
+            AnyEntity::ItemEntity(item_entity) => Some((eid, item_entity.item.item.clone()?)),
+            _ => None,
+        }).await
+    }
+
+    #[tokio::test]
+    async fn test_pickup() {
+        let server = manual_server().await;
+        let world = server.world();
+        let _client = connect(server.local_addr(), &join_packets("collector", 1)).await;
+        let (_, entry) = wait_for_player(world, "collector").await;
+
+        // Only 4 more diamonds fit in the inventory
+        let player = Handler::<Player>::assume(entry.eid, world);
+        assert!(player.pick_up_item(stack(Item::Diamond, 60)).await.is_none());
+        for _ in 0..35 {
+            assert!(player.pick_up_item(stack(Item::Stone, 64)).await.is_none());
+        }
+
+        let position = player.observe(|player| player.get_entity().position.clone()).await.unwrap();
+        let eid = world.spawn_item(position, Translation { x: 0.0, y: 0.0, z: 0.0 }, stack(Item::Diamond, 10), 0).await;
+        server.step(3).await;
+
+        let diamonds = player.observe(|player| player.inventory().slots().iter().filter_map(|slot| slot.item.as_ref()).filter(|item| item.item_id == Item::Diamond).map(|item| item.item_count).sum::<i8>()).await;
+        assert_eq!(diamonds, Some(64));
+        let leftovers = item_stacks(world).await;
+        assert!(matches!(leftovers.as_slice(), [(leftover, SlotItem { item_id: Item::Diamond, item_count: 6, .. })] if *leftover == eid));
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_merge() {
+        let server = manual_server().await;
+        let world = server.world();
+
+        let position = world.config().spawn_position();
+        let still = Translation { x: 0.0, y: 0.0, z: 0.0 };
+        let big = world.spawn_item(position.clone(), still.clone(), stack(Item::Dirt, 5), 100).await;
+        world.spawn_item(position.clone(), still.clone(), stack(Item::Dirt, 3), 100).await;
+        world.spawn_item(position.clone(), still.clone(), stack(Item::Stone, 1), 100).await;
+        server.step(2).await;
+
+        // The smaller dirt stack was absorbed, the stone can't stack with dirt
+        let mut stacks = item_stacks(world).await;
+        stacks.sort_by_key(|(eid, _)| *eid);
+        assert_eq!(stacks.len(), 2);
+        assert_eq!(stacks[0].0, big);
+        assert_eq!((stacks[0].1.item_id, stacks[0].1.item_count), (Item::Dirt, 8));
+        assert_eq!((stacks[1].1.item_id, stacks[1].1.item_count), (Item::Stone, 1));
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_friction_and_despawn() {
+        let server = manual_server().await;
+        let world = server.world();
+
+        let position = world.config().spawn_position();
+        let eid = world.spawn_item(position, Translation { x: 0.2, y: 0.0, z: 0.0 }, stack(Item::Dirt, 1), 10).await;
+        // The item falls through the air during the first ticks
+        server.step(10).await;
+        let velocity = world.observe_entity(eid, |entity| entity.as_entity().velocity.clone()).await.unwrap();
+        assert!((velocity.x - 0.2 * Friction::ITEM.air.powi(10)).abs() < 1e-9);
+
+        server.step(ITEM_DESPAWN_AGE as usize - 11).await;
+        assert!(world.observe_entity(eid, |_| ()).await.is_some());
+        server.step(1).await;
+        assert!(world.observe_entity(eid, |_| ()).await.is_none());
+
+        server.stop().await;
+    }
 }
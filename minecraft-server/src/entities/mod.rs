@@ -401,4 +401,14 @@ impl AnyEntity {
             AnyEntity::Tadpole(_) => Some(Tadpole),
         }
     }
+
+    /// Returns the metadata clients need to display the entity.
+    /// Only the fields that differ from the client defaults are included.
+    pub fn network_metadata(&self) -> BTreeMap<u8, EntityMetadataValue<'static>> {
+        let mut metadata = BTreeMap::new();
+        if let AnyEntity::ItemEntity(item_entity) = self {
+            metadata.insert(8, EntityMetadataValue::Slot { slot: item_entity.item.clone() });
+        }
+        metadata
+    }
 }
@@ -8,7 +8,7 @@ use super::*;
     ancestors { Monster, PathfinderMob, Mob, LivingEntity, Entity },
     descendants { ZombieVillager, Husk, Drowned, ZombifiedPiglin },
     defines {
-        Entity.init(self, server_msg_rcvr: BroadcastReceiver<ServerMessage>);
+        Entity.init(self, ticks: TickReceiver);
     }
 )]
 pub struct Zombie {
@@ -19,25 +19,17 @@ pub struct Zombie {
 }
 
 impl Handler<Zombie> {
-    pub async fn init(self, server_msg_rcvr: BroadcastReceiver<ServerMessage>) {
-        self.insert_task("newton", tokio::spawn(newton_task(self.clone(), server_msg_rcvr.resubscribe()))).await;
-        self.insert_task("zombie-ai", tokio::spawn(zombie_ai_task(self.clone(), server_msg_rcvr))).await;
-    }
-}
-
-pub async fn sleep_ticks(server_msg_rcvr: &mut BroadcastReceiver<ServerMessage>, t: usize) {
-    let mut i = 0;
-    while i < t {
-        let Ok(msg) = server_msg_rcvr.recv().await else {continue};
-        if matches!(&msg, &ServerMessage::Tick(_)) { i += 1; }
+    pub async fn init(self, ticks: TickReceiver) {
+        self.insert_task("newton", tokio::spawn(newton_task(self.clone(), ticks.resubscribe()))).await;
+        self.insert_task("zombie-ai", tokio::spawn(zombie_ai_task(self.clone(), ticks))).await;
     }
 }
 
 const ZOOMBIE_SPEED: f64 = 0.2; // Arbitrary value
 
-pub async fn zombie_ai_task<T: EntityDescendant + ZombieDescendant>(h: Handler<T>, mut server_msg_rcvr: BroadcastReceiver<ServerMessage>) where AnyEntity: TryAsEntityRef<T> {
+pub async fn zombie_ai_task<T: EntityDescendant + ZombieDescendant>(h: Handler<T>, mut ticks: TickReceiver) where AnyEntity: TryAsEntityRef<T> {
     loop {
-        sleep_ticks(&mut server_msg_rcvr, 1).await;
+        let Some(()) = ticks.sleep(1).await else { return };
 
         let mut self_position = h.observe(|e| e.get_entity().position.clone()).await.unwrap();
         let chunk = self_position.chunk_column();
@@ -48,7 +40,10 @@ pub async fn zombie_ai_task<T: EntityDescendant + ZombieDescendant>(h: Handler<T
             })
         }).await;
 
-        let Some((target_position, network_entity)) = player_positions.get(0) else { sleep_ticks(&mut server_msg_rcvr, 100).await; continue };
+        let Some((target_position, network_entity)) = player_positions.get(0) else {
+            let Some(()) = ticks.sleep(100).await else { return };
+            continue
+        };
         let target_object = CollisionShape {
             x1: target_position.x - network_entity.width() as f64 / 2.0,
             y1: target_position.y,
@@ -77,7 +72,7 @@ pub async fn zombie_ai_task<T: EntityDescendant + ZombieDescendant>(h: Handler<T
                 None => break,
             };
 
-            sleep_ticks(&mut server_msg_rcvr, 1).await; // TODO: do while
+            let Some(()) = ticks.sleep(1).await else { return }; // TODO: do while
         }
         
     }
@@ -0,0 +1,53 @@
+use std::time::Instant;
+use super::*;
+
+/// The longest message a client is allowed to send
+const MAX_MESSAGE_LENGTH: usize = 256;
+
+impl Handler<Player> {
+    /// Handles a message written by the player.
+    pub(super) async fn on_chat_message(&self, message: &str) {
+        if message.chars().count() > MAX_MESSAGE_LENGTH || message.chars().any(|c| c == '§' || c.is_control()) {
+            warn!("Player sent an invalid chat message");
+            return;
+        }
+        let Some((uuid, allowed)) = self.mutate(|player| {
+            let allowed = player.chat_limiter.allow(Instant::now());
+            ((player.info.uuid, allowed), EntityChanges::other())
+        }).await else { return };
+        if !allowed {
+            self.send_system_message(&ChatText::colored("You are sending messages too fast", "red"), false).await;
+            return;
+        }
+        match self.world.chat(uuid, message).await {
+            Ok(()) | Err(ChatError::Filtered) | Err(ChatError::UnknownSender) => (),
+            Err(ChatError::Muted) => self.send_system_message(&ChatText::colored("You are muted", "red"), false).await,
+        }
+    }
+
+    /// Shows a chat message to the player, depending on their chat settings.
+    pub(super) async fn on_chat(&self, kind: ChatKind, text: ChatText) {
+        let Some((shown, colors)) = self.observe(|player| {
+            let shown = match kind {
+                ChatKind::ActionBar => true,
+                ChatKind::System => !matches!(player.info.chat_mode, ChatMode::Hidden),
+                ChatKind::Player => matches!(player.info.chat_mode, ChatMode::Enabled),
+            };
+            (shown, player.info.chat_colors)
+        }).await else { return };
+        if shown {
+            self.send_system_message_with_colors(&text, kind == ChatKind::ActionBar, colors).await;
+        }
+    }
+
+    /// Sends a message to the player only, ignoring their chat mode.
+    pub(super) async fn send_system_message(&self, text: &ChatText, overlay: bool) {
+        let Some(colors) = self.observe(|player| player.info.chat_colors).await else { return };
+        self.send_system_message_with_colors(text, overlay, colors).await;
+    }
+
+    async fn send_system_message_with_colors(&self, text: &ChatText, overlay: bool, colors: bool) {
+        let content = text.to_json(colors);
+        self.send_packet(PlayClientbound::SystemChatMessage { content: &content, overlay }).await;
+    }
+}
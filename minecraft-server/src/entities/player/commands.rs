@@ -0,0 +1,41 @@
+use minecraft_protocol::components::{auto_completion::Match, game_state::GameState};
+use super::*;
+
+impl Handler<Player> {
+    /// Runs a command typed by the player, and shows them its output.
+    pub(super) async fn on_chat_command(&self, command: &str) {
+        match self.world.commands().execute(self.world, CommandSender::Player(self.clone()), command).await {
+            Ok(feedback) => {
+                for text in feedback {
+                    self.send_system_message(&text, false).await;
+                }
+            }
+            Err(error) => self.send_system_message(&ChatText::colored(error, "red"), false).await,
+        }
+    }
+
+    pub(super) async fn on_command_suggestions_request(&self, transaction_id: VarInt, text: &str) {
+        let Some(uuid) = self.world.entity_uuid(self.eid).await else { return };
+        let level = self.world.permission_level(uuid).await;
+        let player_names: Vec<String> = self.world.players().await.into_iter().map(|(_, entry)| entry.name).collect();
+        let (start, suggestions) = self.world.commands().suggest(text, level, &player_names);
+        self.send_packet(PlayClientbound::CommandSuggestionsResponse {
+            transaction_id,
+            start: VarInt(start as i32),
+            lenght: VarInt((text.len() - start) as i32),
+            matches: Array::from(suggestions.iter().map(|value| Match { value, tooltip: None }).collect::<Vec<_>>()),
+        }).await;
+    }
+
+    pub async fn set_game_mode(&self, game_mode: Gamemode) {
+        let Some(uuid) = self.mutate(|player| {
+            player.game_mode = game_mode.clone();
+            (player.info.uuid, EntityChanges::other())
+        }).await else { return };
+        self.send_packet(PlayClientbound::ChangeGameState {
+            reason: GameState::ChangeGamemode,
+            value: game_mode.clone() as u8 as f32,
+        }).await;
+        self.world.update_player(uuid, PlayerListUpdate::Gamemode(game_mode)).await;
+    }
+}
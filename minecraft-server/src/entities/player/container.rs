@@ -0,0 +1,351 @@
+use minecraft_protocol::components::slots::WindowType;
+use super::*;
+
+/// What a window opened on a block shows before the player inventory
+pub(super) enum WindowSource {
+    /// Block entities with `size` slots each, shown one after the other (two for double chests)
+    BlockEntities { positions: Vec<BlockPosition>, size: usize },
+    /// The output and the grid of a crafting table belong to the window, and are emptied when it's closed
+    CraftingTable { position: BlockPosition, grid: Vec<Slot> },
+}
+
+impl WindowSource {
+    fn shows(&self, position: &BlockPosition) -> bool {
+        match self {
+            WindowSource::BlockEntities { positions, .. } => positions.contains(position),
+            WindowSource::CraftingTable { position: table_position, .. } => table_position == position,
+        }
+    }
+
+    /// Returns true if the window can stay open after one of its blocks became `block`
+    fn allows(&self, block: &BlockWithState) -> bool {
+        let block = Block::from(block.clone());
+        match self {
+            WindowSource::BlockEntities { .. } => BlockEntityType::from_block(block).is_some(),
+            WindowSource::CraftingTable { .. } => block == Block::CraftingTable,
+        }
+    }
+
+    fn positions(&self) -> Vec<BlockPosition> {
+        match self {
+            WindowSource::BlockEntities { positions, .. } => positions.clone(),
+            WindowSource::CraftingTable { .. } => Vec::new(),
+        }
+    }
+}
+
+/// A window opened on a block, such as a chest or a furnace
+pub(super) struct OpenWindow {
+    pub(super) id: u8,
+    layout: WindowLayout,
+    source: WindowSource,
+    /// Increases every time the server sends the content of the window
+    state_id: i32,
+}
+
+impl Handler<Player> {
+    /// Opens the window of a chest, furnace, crafting table or other container block.
+    /// Returns false if the block has no window.
+    pub(super) async fn open_block_window(&self, position: BlockPosition) -> bool {
+        let Some(block) = self.world.get_block(position.clone()).await else { return false };
+        let block_type = Block::from(block.clone());
+        let (layout, window_type, title, source) = if block_type == Block::CraftingTable {
+            let source = WindowSource::CraftingTable { position: position.clone(), grid: vec![Slot::default(); 10] };
+            (WindowLayout::CraftingTable, WindowType::Crafting, "container.crafting", source)
+        } else {
+            let Some(ty) = BlockEntityType::from_block(block_type) else { return false };
+
+            // The right half of a double chest comes first
+            let mut positions = vec![position.clone()];
+            if let Some(partner_position) = chest_partner(&position, &block) {
+                let partner = self.world.get_block(partner_position.clone()).await;
+                let connected = partner.is_some_and(|partner| {
+                    Block::from(partner.clone()) == block_type && chest_partner(&partner_position, &partner).as_ref() == Some(&position)
+                });
+                match (connected, block.property("type")) {
+                    (true, Some("left")) => positions.insert(0, partner_position),
+                    (true, _) => positions.push(partner_position),
+                    (false, _) => (),
+                }
+            }
+
+            let (layout, window_type, title) = match ty {
+                BlockEntityType::Chest | BlockEntityType::TrappedChest if positions.len() == 2 => {
+                    (WindowLayout::Container { size: 54 }, WindowType::SixRows, "container.chestDouble")
+                }
+                BlockEntityType::Chest | BlockEntityType::TrappedChest => (WindowLayout::Container { size: 27 }, WindowType::ThreeRows, "container.chest"),
+                BlockEntityType::Barrel => (WindowLayout::Container { size: 27 }, WindowType::ThreeRows, "container.barrel"),
+                BlockEntityType::ShulkerBox => (WindowLayout::Container { size: 27 }, WindowType::ShulkerBox, "container.shulkerBox"),
+                BlockEntityType::Dispenser => (WindowLayout::Container { size: 9 }, WindowType::ThreeByThree, "container.dispenser"),
+                BlockEntityType::Dropper => (WindowLayout::Container { size: 9 }, WindowType::ThreeByThree, "container.dropper"),
+                BlockEntityType::Hopper => (WindowLayout::Container { size: 5 }, WindowType::Hopper, "container.hopper"),
+                BlockEntityType::Furnace => (WindowLayout::Furnace, WindowType::Furnace, "container.furnace"),
+                BlockEntityType::Smoker => (WindowLayout::Furnace, WindowType::Smoker, "container.smoker"),
+                BlockEntityType::BlastFurnace => (WindowLayout::Furnace, WindowType::BlastFurnace, "container.blast_furnace"),
+                _ => return false,
+            };
+            let size = layout.container_size() / positions.len();
+            (layout, window_type, title, WindowSource::BlockEntities { positions, size })
+        };
+
+        let Some(player_position) = self.observe(|player| player.get_entity().position.clone()).await else { return false };
+        let dx = position.x as f64 + 0.5 - player_position.x;
+        let dy = position.y as f64 + 0.5 - (player_position.y + 1.62);
+        let dz = position.z as f64 + 0.5 - player_position.z;
+        if dx * dx + dy * dy + dz * dz > 8.0 * 8.0 {
+            warn!("Player tried to open a container that is too far away");
+            return false;
+        }
+
+        let positions = source.positions();
+        let container_slots = match &source {
+            WindowSource::BlockEntities { .. } => match self.container_slots(&positions).await {
+                Some(slots) => slots,
+                None => return false,
+            },
+            WindowSource::CraftingTable { grid, .. } => grid.clone(),
+        };
+        if container_slots.len() != layout.container_size() {
+            warn!("Container at {position:?} has {} slots instead of {}", container_slots.len(), layout.container_size());
+            return false;
+        }
+        let custom_name = match self.world.get_block_entity(position.clone()).await.map(|block_entity| block_entity.data) {
+            Some(BlockEntityData::Container { custom_name, .. } | BlockEntityData::Furnace { custom_name, .. }) => custom_name,
+            _ => None,
+        };
+        let title = custom_name.unwrap_or_else(|| format!("{{\"translate\":\"{title}\"}}"));
+
+        self.close_block_window(false).await;
+        let Some((window_id, content)) = self.mutate(|player| {
+            player.window_counter = player.window_counter % 100 + 1;
+            let mut window = OpenWindow { id: player.window_counter, layout, source, state_id: 0 };
+            let slots = player.inventory.window_slots(&container_slots);
+            let content = content_packet(window.id, &mut window.state_id, &slots, player.inventory.cursor());
+            let window_id = window.id;
+            player.window = Some(window);
+            ((window_id, content), EntityChanges::other())
+        }).await else { return false };
+
+        self.send_packet(PlayClientbound::OpenWindow { window_id: VarInt(window_id as i32), window_type, window_title: &title }).await;
+        self.send_packet(content).await;
+        if layout == WindowLayout::Furnace {
+            let properties = self.world.get_block_entity(position).await.and_then(|block_entity| furnace_properties(&block_entity.data));
+            for (property, value) in properties.into_iter().flatten().enumerate() {
+                self.send_packet(PlayClientbound::SetContainerProperty { window_id, property: property as i16, value }).await;
+            }
+        }
+        for position in positions {
+            self.world.open_container(self.eid, position).await;
+        }
+        true
+    }
+
+    /// Closes the window opened on a block, giving back the items of the crafting grid and of the cursor.
+    /// The client is told to close it when `notify_client` is true, as when the block is destroyed.
+    pub(super) async fn close_block_window(&self, notify_client: bool) {
+        let Some((window_id, packet)) = self.release_block_window().await else { return };
+        if notify_client {
+            self.send_packet(PlayClientbound::CloseContainer { window_id }).await;
+        }
+        self.send_packet(packet).await;
+    }
+
+    /// Closes the window opened on a block without sending anything, as when the player leaves.
+    /// Returns the id of the window and the new content of the inventory, for the client.
+    pub(super) async fn release_block_window(&self) -> Option<(u8, PlayClientbound<'static>)> {
+        let Some(Some((window, leftovers, packet))) = self.mutate(|player| {
+            let Some(mut window) = player.window.take() else { return (None, EntityChanges::nothing()) };
+            let mut items: Vec<SlotItem> = player.inventory.take_cursor().into_iter().collect();
+            if let WindowSource::CraftingTable { grid, .. } = &mut window.source {
+                grid[0] = Slot::default();
+                items.extend(grid.iter_mut().filter_map(|slot| slot.item.take()));
+            }
+            let leftovers = items.into_iter().filter_map(|item| player.inventory.add_item(item)).collect::<Vec<_>>();
+            (Some((window, leftovers, player.inventory.content_packet())), EntityChanges::other())
+        }).await else { return None };
+
+        for position in window.source.positions() {
+            self.world.close_container(self.eid, position).await;
+        }
+        self.throw_items(leftovers).await;
+        Some((window.id, packet))
+    }
+
+    /// Reads the slots of block entities, one after the other.
+    async fn container_slots(&self, positions: &[BlockPosition]) -> Option<Vec<Slot>> {
+        let mut slots = Vec::new();
+        for position in positions {
+            slots.extend(self.world.get_container_slots(position.clone()).await?);
+        }
+        Some(slots)
+    }
+
+    /// Sends the whole content of the window opened on a block.
+    pub(super) async fn resync_block_window(&self, window_id: u8) {
+        let Some(positions) = self.observe(|player| player.window.as_ref().map(|window| window.source.positions()).unwrap_or_default()).await else { return };
+        let Some(world_slots) = self.container_slots(&positions).await else { return };
+        let Some(Some(packet)) = self.mutate(|player| {
+            let Some(window) = player.window.as_mut().filter(|window| window.id == window_id) else { return (None, EntityChanges::nothing()) };
+            let container_slots = match &window.source {
+                WindowSource::BlockEntities { .. } => world_slots,
+                WindowSource::CraftingTable { grid, .. } => grid.clone(),
+            };
+            let slots = player.inventory.window_slots(&container_slots);
+            (Some(content_packet(window.id, &mut window.state_id, &slots, player.inventory.cursor())), EntityChanges::other())
+        }).await else { return };
+        self.send_packet(packet).await;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn on_click_block_window(&self, window_id: u8, state_id: i32, slot: i16, button: i8, mode: i32, new_slot_values: Vec<(i16, Slot)>, carried_item: Slot) {
+        let Some(Some((layout, positions))) = self.observe(|player| {
+            player.window.as_ref().filter(|window| window.id == window_id).map(|window| (window.layout, window.source.positions()))
+        }).await else {
+            warn!("Click in unknown window {window_id}");
+            return;
+        };
+        let Some(action) = ClickAction::parse(slot, button, mode) else {
+            warn!("Invalid click: slot {slot}, button {button}, mode {mode}");
+            return self.resync_block_window(window_id).await;
+        };
+
+        // Block entities are only written back if nobody modified them since they were read
+        let mut expected = Vec::new();
+        for position in &positions {
+            match self.world.get_container_slots(position.clone()).await {
+                Some(slots) => expected.push(slots),
+                None => return self.close_block_window(true).await,
+            }
+        }
+
+        let Some(Some((inventory, container_slots, thrown, client_slots))) = self.observe(|player| {
+            let window = player.window.as_ref().filter(|window| window.id == window_id)?;
+            let mut container_slots = match &window.source {
+                WindowSource::BlockEntities { .. } => expected.concat(),
+                WindowSource::CraftingTable { grid, .. } => grid.clone(),
+            };
+
+            // What the client predicted
+            let mut client_slots = player.inventory.window_slots(&container_slots);
+            for (index, value) in new_slot_values {
+                if let Some(client_slot) = usize::try_from(index).ok().and_then(|index| client_slots.get_mut(index)) {
+                    *client_slot = value;
+                }
+            }
+
+            let mut inventory = player.inventory.clone();
+            let thrown = inventory.click_with(layout, &mut container_slots, action, player.game_mode == Gamemode::Creative);
+            Some((inventory, container_slots, thrown, client_slots))
+        }).await else { return };
+
+        let mut offset = 0;
+        for (position, expected_slots) in positions.into_iter().zip(&expected) {
+            let new_slots = &container_slots[offset..offset + expected_slots.len()];
+            offset += expected_slots.len();
+            if !self.world.set_container_slots(position, expected_slots, new_slots).await {
+                warn!("Container was modified by someone else during a click");
+                return self.resync_block_window(window_id).await;
+            }
+        }
+
+        let Some(Some(packets)) = self.mutate(|player| {
+            let Some(window) = player.window.as_mut().filter(|window| window.id == window_id) else { return (None, EntityChanges::nothing()) };
+            player.inventory = inventory;
+            if let WindowSource::CraftingTable { grid, .. } = &mut window.source {
+                *grid = container_slots.clone();
+            }
+            let slots = player.inventory.window_slots(&container_slots);
+            let packets = match state_id == window.state_id {
+                true => sync_packets(window.id as i8, &mut window.state_id, &slots, player.inventory.cursor(), &client_slots, &carried_item),
+                false => vec![content_packet(window.id, &mut window.state_id, &slots, player.inventory.cursor())],
+            };
+            (Some(packets), EntityChanges::other())
+        }).await else { return };
+
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+        self.throw_items(thrown).await;
+    }
+
+    /// Shows the changes made to a container by other players or by a furnace.
+    pub(super) async fn on_container_slots_change(&self, position: BlockPosition, changed_slots: Vec<(usize, Slot)>) {
+        let Some(packets) = self.mutate(|player| {
+            let Some(window) = &mut player.window else { return (Vec::new(), EntityChanges::nothing()) };
+            let WindowSource::BlockEntities { positions, size } = &window.source else { return (Vec::new(), EntityChanges::nothing()) };
+            let Some(index) = positions.iter().position(|window_position| *window_position == position) else { return (Vec::new(), EntityChanges::nothing()) };
+            let offset = index * size;
+            let packets = changed_slots.into_iter().map(|(slot_index, slot_value)| PlayClientbound::SetContainerSlot {
+                window_id: window.id as i8,
+                state_id: next_state_id(&mut window.state_id),
+                slot_index: (offset + slot_index) as i16,
+                slot_value,
+            }).collect();
+            (packets, EntityChanges::other())
+        }).await else { return };
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+    }
+
+    /// Shows the progress of a furnace.
+    pub(super) async fn on_container_property_change(&self, position: BlockPosition, property: i16, value: i16) {
+        let Some(Some(window_id)) = self.observe(|player| {
+            let window = player.window.as_ref()?;
+            (window.layout == WindowLayout::Furnace && window.source.shows(&position)).then_some(window.id)
+        }).await else { return };
+        self.send_packet(PlayClientbound::SetContainerProperty { window_id, property, value }).await;
+    }
+
+    /// Closes the window opened on a block when that block is destroyed.
+    pub(super) async fn on_window_block_change(&self, position: &BlockPosition, block: &BlockWithState) {
+        let closes = self.observe(|player| {
+            player.window.as_ref().is_some_and(|window| window.source.shows(position) && !window.source.allows(block))
+        }).await;
+        if closes == Some(true) {
+            self.close_block_window(true).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::ids::items::Item;
+    use crate::player_handler::test_client::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_leave_with_window_open() {
+        let directory = std::env::temp_dir().join(format!("minecraft-server-window-test-{}", std::process::id()));
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .storage(directory.clone())
+            .tick_source(TickSource::Manual)
+            .start().await.unwrap();
+        let world = server.world();
+        let client = connect(server.local_addr(), &join_packets("leaver", 1)).await;
+        let (uuid, entry) = wait_for_player(world, "leaver").await;
+
+        let spawn = world.config().spawn_position();
+        let table = BlockPosition { x: spawn.x as i32 + 1, y: spawn.y as i32, z: spawn.z as i32 };
+        world.set_block(table.clone(), BlockWithState::from(Block::CraftingTable)).await;
+        let handler = Handler::<Player>::assume(entry.eid, world);
+        assert!(handler.open_block_window(table).await);
+        handler.mutate(|player| {
+            if let Some(OpenWindow { source: WindowSource::CraftingTable { grid, .. }, .. }) = &mut player.window {
+                grid[1].item = Some(SlotItem { item_id: Item::Diamond, item_count: 3, nbt_data: NbtTag::Null });
+            }
+            ((), EntityChanges::other())
+        }).await;
+
+        // The player leaves cleanly, keeping the items of the crafting grid
+        drop(client);
+        wait_for_no_players(world).await;
+        assert!(world.observe_entity(entry.eid, |_| ()).await.is_none());
+        let data = world.load_player_data(uuid).await.unwrap();
+        assert!(data.inventory.iter().any(|slot| slot.item.as_ref().is_some_and(|item| item.item_id == Item::Diamond && item.item_count == 3)));
+
+        server.stop().await;
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
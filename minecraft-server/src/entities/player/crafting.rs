@@ -0,0 +1,39 @@
+use minecraft_protocol::crafting;
+use super::*;
+
+/// Returns the item crafted from a square crafting grid, if the grid matches a recipe.
+pub fn craft(grid: &[Slot], width: usize) -> Option<SlotItem> {
+    let items: Vec<Option<Item>> = grid.iter().map(|slot| slot.item.as_ref().map(|item| item.item_id)).collect();
+    let result = crafting::craft(&items, width)?.result;
+    Some(SlotItem { item_id: result.item, item_count: result.count as i8, nbt_data: NbtTag::Null })
+}
+
+/// Removes one item from every slot of a crafting grid, as when the result is taken.
+/// Buckets and bottles are left behind.
+pub fn consume_ingredients(grid: &mut [Slot]) {
+    for slot in grid {
+        let Some(item) = &mut slot.item else { continue };
+        let remainder = crafting::crafting_remainder(item.item_id);
+        item.item_count -= 1;
+        if item.item_count <= 0 {
+            slot.item = remainder.map(|item_id| SlotItem { item_id, item_count: 1, nbt_data: NbtTag::Null });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(items: &[Option<Item>]) -> Vec<Slot> {
+        items.iter().map(|item| Slot { item: item.map(|item_id| SlotItem { item_id, item_count: 1, nbt_data: NbtTag::Null }) }).collect()
+    }
+
+    #[test]
+    fn test_consume_ingredients() {
+        let mut slots = grid(&[Some(Item::MilkBucket), Some(Item::Sugar), None, Some(Item::Wheat)]);
+        slots[1].item.as_mut().unwrap().item_count = 2;
+        consume_ingredients(&mut slots);
+        assert_eq!(slots, grid(&[Some(Item::Bucket), Some(Item::Sugar), None, None]));
+    }
+}
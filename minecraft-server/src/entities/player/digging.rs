@@ -0,0 +1,173 @@
+use std::time::Instant;
+use minecraft_protocol::{components::blocks::DiggingState, ids::blocks::Block};
+use super::*;
+
+/// A block the player started to break in survival
+pub(super) struct Digging {
+    position: BlockPosition,
+    block: BlockWithState,
+    started_at: Instant,
+    /// The number of ticks it takes to break the block with the tool used when digging started
+    ticks: u32,
+    can_harvest: bool,
+    /// The item held when digging started, which decides what the block drops
+    tool: Option<SlotItem>,
+    stage: Option<u8>,
+}
+
+impl Digging {
+    fn elapsed_ticks(&self) -> u32 {
+        (self.started_at.elapsed().as_millis() / 50) as u32
+    }
+}
+
+impl Handler<Player> {
+    pub(super) async fn on_dig_block(&self, status: DiggingState, location: NetworkPosition, sequence: VarInt) {
+        let position: BlockPosition = location.into();
+        match status {
+            DiggingState::Started => self.start_digging(position).await,
+            DiggingState::Cancelled => self.stop_digging().await,
+            DiggingState::Finished => self.finish_digging(position).await,
+            DiggingState::DropItem => return self.drop_held_item(false).await,
+            DiggingState::DropItemStack => return self.drop_held_item(true).await,
+            DiggingState::SwapItemInHand => return self.swap_hands().await,
+            status => {
+                warn!("Unsupported digging state: {status:?}");
+                return;
+            }
+        }
+        self.send_packet(PlayClientbound::AcknowledgeBlockChange { id: sequence }).await;
+    }
+
+    async fn start_digging(&self, position: BlockPosition) {
+        let Some((game_mode, held_item, on_ground, player_position)) = self.observe(|player| {
+            let held_item = player.inventory.held_item(Hand::MainHand).item.clone();
+            (player.game_mode.clone(), held_item, player.on_ground, player.get_entity().position.clone())
+        }).await else { return };
+        let Some(block) = self.world.get_block(position.clone()).await else { return };
+
+        let dx = position.x as f64 + 0.5 - player_position.x;
+        let dy = position.y as f64 + 0.5 - (player_position.y + 1.62);
+        let dz = position.z as f64 + 0.5 - player_position.z;
+        if game_mode == Gamemode::Adventure || game_mode == Gamemode::Spectator {
+            warn!("Player tried to break a block in {game_mode:?} mode");
+            self.send_block(position).await;
+            return;
+        }
+        if dx * dx + dy * dy + dz * dz > 8.0 * 8.0 {
+            warn!("Player tried to break a block they can't reach");
+            self.send_block(position).await;
+            return;
+        }
+        if game_mode == Gamemode::Creative {
+            self.break_block(position, false, None).await;
+            return;
+        }
+
+        let block_type = Block::from(block.clone());
+        let can_harvest = can_harvest(held_item.as_ref(), block_type);
+        match mining_ticks(held_item.as_ref(), block_type, on_ground) {
+            None => self.send_block(position).await,
+            Some(0) => self.break_block(position, can_harvest, held_item.as_ref()).await,
+            Some(ticks) => {
+                self.stop_digging().await;
+                self.mutate(|player| {
+                    player.digging = Some(Digging { position, block, started_at: Instant::now(), ticks, can_harvest, tool: held_item, stage: None });
+                    ((), EntityChanges::other())
+                }).await;
+            }
+        }
+    }
+
+    async fn stop_digging(&self) {
+        let Some(Some(digging)) = self.mutate(|player| (player.digging.take(), EntityChanges::other())).await else { return };
+        if digging.stage.is_some() {
+            self.world.set_block_destroy_stage(self.eid, digging.position, None).await;
+        }
+    }
+
+    async fn finish_digging(&self, position: BlockPosition) {
+        let Some(Some(digging)) = self.mutate(|player| (player.digging.take(), EntityChanges::other())).await else {
+            self.send_block(position).await;
+            return;
+        };
+        if digging.stage.is_some() {
+            self.world.set_block_destroy_stage(self.eid, digging.position.clone(), None).await;
+        }
+
+        // Clients are allowed to be a bit faster than the server, as in vanilla
+        let elapsed_ticks = digging.elapsed_ticks();
+        if digging.position != position || (elapsed_ticks as f32) < digging.ticks as f32 * 0.7 {
+            warn!("Player finished breaking a block too early ({elapsed_ticks} ticks instead of {})", digging.ticks);
+            self.send_block(position).await;
+            return;
+        }
+        let Some(block) = self.world.get_block(position.clone()).await else { return };
+        if block.block_state_id() != digging.block.block_state_id() {
+            self.send_block(position).await;
+            return;
+        }
+        self.break_block(position, digging.can_harvest, digging.tool.as_ref()).await;
+    }
+
+    /// Shows the break progress to other players
+    pub(super) async fn tick_digging(&self) {
+        let Some(Some((position, stage))) = self.mutate(|player| {
+            let Some(digging) = &mut player.digging else { return (None, EntityChanges::nothing()) };
+            let stage = (digging.elapsed_ticks() * 10 / digging.ticks).min(9) as u8;
+            if digging.stage == Some(stage) {
+                return (None, EntityChanges::nothing());
+            }
+            digging.stage = Some(stage);
+            (Some((digging.position.clone(), stage)), EntityChanges::other())
+        }).await else { return };
+        self.world.set_block_destroy_stage(self.eid, position, Some(stage)).await;
+    }
+
+    /// Breaks a block, dropping its items if `can_harvest` and the content of containers in any case.
+    /// The other half of a door is broken too, without dropping anything more.
+    async fn break_block(&self, position: BlockPosition, can_harvest: bool, tool: Option<&SlotItem>) {
+        let Some(block) = self.world.get_block(position.clone()).await else { return };
+        let content = self.world.get_container_slots(position.clone()).await.unwrap_or_default();
+        self.world.set_block(position.clone(), BlockWithState::Air).await;
+
+        // The other half of a double chest becomes a single chest
+        if let Some(partner_position) = chest_partner(&position, &block) {
+            if let Some(partner) = self.world.get_block(partner_position.clone()).await {
+                if chest_partner(&partner_position, &partner).as_ref() == Some(&position) {
+                    if let Some(partner) = partner.with_property("type", "single") {
+                        self.world.set_block(partner_position, partner).await;
+                    }
+                }
+            }
+        }
+
+        if let Some(partner_position) = double_block_partner(&position, &block) {
+            if let Some(partner) = self.world.get_block(partner_position.clone()).await {
+                if Block::from(partner.clone()) == Block::from(block.clone()) && double_block_partner(&partner_position, &partner).as_ref() == Some(&position) {
+                    self.world.set_block(partner_position, BlockWithState::Air).await;
+                }
+            }
+        }
+
+        let center = Position { x: position.x as f64 + 0.5, y: position.y as f64 + 0.25, z: position.z as f64 + 0.5 };
+        let mut items: Vec<SlotItem> = content.into_iter().filter_map(|slot| slot.item).collect();
+        if can_harvest {
+            items.extend(block_drops(Block::from(block), tool));
+        }
+        for item in items {
+            let velocity = Translation {
+                x: rand::random::<f64>() * 0.2 - 0.1,
+                y: 0.2,
+                z: rand::random::<f64>() * 0.2 - 0.1,
+            };
+            self.world.spawn_item(center.clone(), velocity, item, 10).await;
+        }
+    }
+
+    /// Sends the real state of a block, to revert a client-side prediction
+    async fn send_block(&self, position: BlockPosition) {
+        let Some(block) = self.world.get_block(position.clone()).await else { return };
+        self.send_packet(PlayClientbound::BlockUpdate { location: position.into(), block_state: block }).await;
+    }
+}
@@ -0,0 +1,366 @@
+use minecraft_protocol::components::slots::EquipmentSlot;
+use super::*;
+
+/// The slots of the player inventory window, numbered as in the protocol.
+///
+/// 0 is the crafting output, 1-4 the crafting grid, 5-8 the armor, 9-35 the main inventory, 36-44 the hotbar and 45 the offhand.
+#[derive(Clone)]
+pub struct PlayerInventory {
+    slots: Vec<Slot>,
+    selected_hotbar_slot: usize,
+    /// The item held by the mouse cursor while a window is open
+    cursor: Slot,
+    /// Increases every time the server sends the content of the inventory
+    state_id: i32,
+    drag: Option<Drag>,
+}
+
+impl PlayerInventory {
+    pub const SLOT_COUNT: usize = 46;
+    pub const CRAFTING_GRID: std::ops::RangeInclusive<usize> = 1..=4;
+    pub const MAIN_START: usize = 9;
+    pub const HOTBAR_START: usize = 36;
+    pub const OFFHAND: usize = 45;
+
+    pub fn new() -> PlayerInventory {
+        PlayerInventory {
+            slots: vec![Slot::default(); Self::SLOT_COUNT],
+            selected_hotbar_slot: 0,
+            cursor: Slot::default(),
+            state_id: 1,
+            drag: None,
+        }
+    }
+
+    /// Restores a saved inventory, ignoring the slots that don't exist.
+    pub fn restore(slots: &[Slot], selected_hotbar_slot: u8) -> PlayerInventory {
+        let mut inventory = PlayerInventory::new();
+        for (slot, saved) in inventory.slots.iter_mut().zip(slots) {
+            *slot = saved.clone();
+        }
+        inventory.set_selected_hotbar_slot(selected_hotbar_slot as usize);
+        inventory
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn get_slot(&self, index: usize) -> Option<&Slot> {
+        self.slots.get(index)
+    }
+
+    /// Returns false if the index is out of bounds.
+    pub fn set_slot(&mut self, index: usize, slot: Slot) -> bool {
+        match self.slots.get_mut(index) {
+            Some(old_slot) => {
+                *old_slot = slot;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cursor(&self) -> &Slot {
+        &self.cursor
+    }
+
+    pub fn state_id(&self) -> i32 {
+        self.state_id
+    }
+
+    /// The selected hotbar slot, from 0 to 8.
+    pub fn selected_hotbar_slot(&self) -> usize {
+        self.selected_hotbar_slot
+    }
+
+    /// Returns false if the hotbar slot is not between 0 and 8.
+    pub fn set_selected_hotbar_slot(&mut self, hotbar_slot: usize) -> bool {
+        if hotbar_slot >= 9 {
+            return false;
+        }
+        self.selected_hotbar_slot = hotbar_slot;
+        true
+    }
+
+    /// The index of the slot held in a hand
+    pub fn held_slot_index(&self, hand: Hand) -> usize {
+        match hand {
+            Hand::MainHand => Self::HOTBAR_START + self.selected_hotbar_slot,
+            Hand::OffHand => Self::OFFHAND,
+        }
+    }
+
+    pub fn held_item(&self, hand: Hand) -> &Slot {
+        &self.slots[self.held_slot_index(hand)]
+    }
+
+    /// Removes one item from the stack held in a hand.
+    pub fn consume_held_item(&mut self, hand: Hand) {
+        self.take_held_item(hand, false);
+    }
+
+    /// Removes one item or the whole stack from a hand, and returns what was removed.
+    pub fn take_held_item(&mut self, hand: Hand, whole_stack: bool) -> Option<SlotItem> {
+        let index = self.held_slot_index(hand);
+        let slot = &mut self.slots[index];
+        let item = slot.item.as_mut()?;
+        let taken = if whole_stack { item.item_count } else { 1 };
+        item.item_count -= taken;
+        let taken = SlotItem { item_count: taken, ..item.clone() };
+        if item.item_count <= 0 {
+            slot.item = None;
+        }
+        Some(taken)
+    }
+
+    /// Returns what the player holds and wears, as seen by other players.
+    pub fn equipment(&self) -> Vec<(EquipmentSlot, Slot)> {
+        vec![
+            (EquipmentSlot::MainHand, self.held_item(Hand::MainHand).clone()),
+            (EquipmentSlot::OffHand, self.held_item(Hand::OffHand).clone()),
+            (EquipmentSlot::Helmet, self.slots[5].clone()),
+            (EquipmentSlot::Chestplate, self.slots[6].clone()),
+            (EquipmentSlot::Leggings, self.slots[7].clone()),
+            (EquipmentSlot::Boots, self.slots[8].clone()),
+        ]
+    }
+
+    pub fn swap_hands(&mut self) {
+        let main_hand = self.held_slot_index(Hand::MainHand);
+        self.slots.swap(main_hand, Self::OFFHAND);
+    }
+
+    /// Adds items to the hotbar, then to the main inventory.
+    /// Returns the items that didn't fit.
+    pub fn add_item(&mut self, mut item: SlotItem) -> Option<SlotItem> {
+        let order: Vec<usize> = (Self::HOTBAR_START..Self::OFFHAND).chain(Self::MAIN_START..Self::HOTBAR_START).collect();
+        let max = item.item_id.max_stack_size() as i8;
+        for fill_empty in [false, true] {
+            for &index in &order {
+                let slot = &mut self.slots[index];
+                let moved = match slot.item.as_mut() {
+                    Some(present) if !fill_empty && can_stack(present, &item) => {
+                        let moved = item.item_count.min(max - present.item_count).max(0);
+                        present.item_count += moved;
+                        moved
+                    }
+                    None if fill_empty => {
+                        slot.item = Some(item.clone());
+                        item.item_count
+                    }
+                    _ => 0,
+                };
+                item.item_count -= moved;
+                if item.item_count <= 0 {
+                    return None;
+                }
+            }
+        }
+        Some(item)
+    }
+
+    /// Puts the items of the crafting grid and of the cursor back into the inventory, as when it is closed.
+    /// Returns the items that didn't fit.
+    pub fn close(&mut self) -> Vec<SlotItem> {
+        let mut items: Vec<SlotItem> = self.take_cursor().into_iter().collect();
+        self.slots[0] = Slot::default();
+        for index in Self::CRAFTING_GRID {
+            items.extend(self.slots[index].item.take());
+        }
+        items.into_iter().filter_map(|item| self.add_item(item)).collect()
+    }
+
+    /// Empties the cursor, as when a window is closed.
+    pub fn take_cursor(&mut self) -> Option<SlotItem> {
+        self.drag = None;
+        self.cursor.item.take()
+    }
+
+    /// Returns the slots of a window that shows other slots before the main inventory and the hotbar, such as a chest.
+    pub fn window_slots(&self, container_slots: &[Slot]) -> Vec<Slot> {
+        container_slots.iter().chain(&self.slots[Self::MAIN_START..Self::OFFHAND]).cloned().collect()
+    }
+
+    /// Applies a click in a window that shows other slots before the main inventory and the hotbar.
+    /// Returns the items thrown out of the window.
+    pub fn click_with(&mut self, layout: WindowLayout, container_slots: &mut [Slot], action: ClickAction, creative: bool) -> Vec<SlotItem> {
+        let mut slots = self.window_slots(container_slots);
+        let thrown = WindowState { layout, slots: &mut slots, cursor: &mut self.cursor, drag: &mut self.drag, creative }.click(action);
+        let (new_container_slots, new_player_slots) = slots.split_at(container_slots.len());
+        container_slots.clone_from_slice(new_container_slots);
+        self.slots[Self::MAIN_START..Self::OFFHAND].clone_from_slice(new_player_slots);
+        thrown
+    }
+
+    /// Gives access to the inventory as a window, to apply clicks.
+    pub fn window(&mut self, creative: bool) -> WindowState<'_> {
+        WindowState {
+            layout: WindowLayout::PlayerInventory,
+            slots: &mut self.slots,
+            cursor: &mut self.cursor,
+            drag: &mut self.drag,
+            creative,
+        }
+    }
+
+    /// Returns the packet sending the whole inventory
+    pub fn content_packet(&mut self) -> PlayClientbound<'static> {
+        content_packet(0, &mut self.state_id, &self.slots, &self.cursor)
+    }
+
+    /// Returns the packets updating the slots of the inventory that differ from what the client thinks
+    pub fn sync_packets(&mut self, client_slots: &[Slot], client_cursor: &Slot) -> Vec<PlayClientbound<'static>> {
+        sync_packets(0, &mut self.state_id, &self.slots, &self.cursor, client_slots, client_cursor)
+    }
+}
+
+impl Default for PlayerInventory {
+    fn default() -> Self {
+        PlayerInventory::new()
+    }
+}
+
+impl Handler<Player> {
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn on_click_window(&self, window_id: i8, state_id: i32, slot: i16, button: i8, mode: i32, new_slot_values: Vec<(i16, Slot)>, carried_item: Slot) {
+        if window_id != 0 {
+            return self.on_click_block_window(window_id as u8, state_id, slot, button, mode, new_slot_values, carried_item).await;
+        }
+        let action = ClickAction::parse(slot, button, mode);
+        let Some((thrown, packets)) = self.mutate(|player| {
+            let creative = player.game_mode == Gamemode::Creative;
+            let inventory = &mut player.inventory;
+            let Some(action) = action else {
+                warn!("Invalid click: slot {slot}, button {button}, mode {mode}");
+                return ((Vec::new(), vec![inventory.content_packet()]), EntityChanges::other());
+            };
+
+            // What the client predicted
+            let mut client_slots = inventory.slots.clone();
+            for (index, value) in new_slot_values {
+                if let Some(client_slot) = usize::try_from(index).ok().and_then(|index| client_slots.get_mut(index)) {
+                    *client_slot = value;
+                }
+            }
+
+            let thrown = inventory.window(creative).click(action);
+            let packets = match state_id == inventory.state_id {
+                true => inventory.sync_packets(&client_slots, &carried_item),
+                false => vec![inventory.content_packet()],
+            };
+            ((thrown, packets), EntityChanges::other())
+        }).await else { return };
+
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+        self.throw_items(thrown).await;
+    }
+
+    pub(super) async fn on_close_window(&self, window_id: i8) {
+        if window_id != 0 {
+            return self.close_block_window(false).await;
+        }
+        let Some((leftovers, packet)) = self.mutate(|player| {
+            let leftovers = player.inventory.close();
+            ((leftovers, player.inventory.content_packet()), EntityChanges::other())
+        }).await else { return };
+        self.send_packet(packet).await;
+        self.throw_items(leftovers).await;
+    }
+
+    pub(super) async fn on_set_creative_slot(&self, id: i16, clicked_item: Slot) {
+        let Some(thrown) = self.mutate(|player| {
+            if player.game_mode != Gamemode::Creative {
+                warn!("Player tried to set a creative slot outside of creative mode");
+                return (None, EntityChanges::nothing());
+            }
+            if id == -1 {
+                return (clicked_item.item, EntityChanges::nothing());
+            }
+            if id < 0 || !player.inventory.set_slot(id as usize, clicked_item) {
+                warn!("Invalid creative mode slot update: {id}");
+            }
+            (None, EntityChanges::other())
+        }).await else { return };
+        self.throw_items(thrown.into_iter().collect()).await;
+    }
+
+    pub(super) async fn drop_held_item(&self, whole_stack: bool) {
+        let Some((thrown, packets)) = self.mutate(|player| {
+            let inventory = &mut player.inventory;
+            let client_slots = inventory.slots.clone();
+            let thrown = inventory.take_held_item(Hand::MainHand, whole_stack);
+            let packets = inventory.sync_packets(&client_slots, &Slot::default());
+            ((thrown, packets), EntityChanges::other())
+        }).await else { return };
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+        self.throw_items(thrown.into_iter().collect()).await;
+    }
+
+    pub(super) async fn swap_hands(&self) {
+        let Some(packets) = self.mutate(|player| {
+            let inventory = &mut player.inventory;
+            let client_slots = inventory.slots.clone();
+            inventory.swap_hands();
+            let cursor = inventory.cursor.clone();
+            (inventory.sync_packets(&client_slots, &cursor), EntityChanges::other())
+        }).await else { return };
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+    }
+
+    /// Puts an item lying on the ground into the inventory.
+    /// Returns the items that didn't fit.
+    pub async fn pick_up_item(&self, item: SlotItem) -> Option<SlotItem> {
+        let picked_up = item.clone();
+        let Some((leftover, packets, window_id)) = self.mutate(|player| {
+            if matches!(player.game_mode, Gamemode::Spectator) {
+                return ((Some(picked_up), Vec::new(), None), EntityChanges::nothing());
+            }
+            let inventory = &mut player.inventory;
+            let client_slots = inventory.slots.clone();
+            let cursor = inventory.cursor.clone();
+            let leftover = inventory.add_item(picked_up);
+            let window_id = player.window.as_ref().map(|window| window.id);
+            let packets = match window_id {
+                Some(_) => Vec::new(),
+                None => player.inventory.sync_packets(&client_slots, &cursor),
+            };
+            ((leftover, packets, window_id), EntityChanges::other())
+        }).await else { return Some(item) };
+        for packet in packets {
+            self.send_packet(packet).await;
+        }
+        if let Some(window_id) = window_id {
+            self.resync_block_window(window_id).await;
+        }
+        self.update_equipment().await;
+        leftover
+    }
+
+    /// Throws items in front of the player
+    pub(super) async fn throw_items(&self, items: Vec<SlotItem>) {
+        if items.is_empty() {
+            return;
+        }
+        let Some((mut position, yaw, pitch)) = self.observe(|player| {
+            let entity = player.get_entity();
+            (entity.position.clone(), entity.yaw, entity.pitch)
+        }).await else { return };
+        position.y += 1.3;
+        let (yaw, pitch) = ((yaw as f64).to_radians(), (pitch as f64).to_radians());
+        let velocity = Translation {
+            x: -yaw.sin() * pitch.cos() * 0.3,
+            y: -pitch.sin() * 0.3 + 0.1,
+            z: yaw.cos() * pitch.cos() * 0.3,
+        };
+        for item in items {
+            self.world.spawn_item(position.clone(), velocity.clone(), item, 40).await;
+        }
+    }
+}
@@ -0,0 +1,883 @@
+use std::time::Instant;
+use super::*;
+use minecraft_protocol::{components::{blocks::{BlockEntityType, BlockFace}, slots::EquipmentSlot}, ids::blocks::Block};
+
+mod inventory;
+pub use inventory::*;
+mod window;
+pub use window::*;
+mod digging;
+use digging::*;
+mod crafting;
+pub use crafting::*;
+mod container;
+use container::*;
+mod visibility;
+mod chat;
+mod commands;
+
+#[MinecraftEntity(
+    ancestors { LivingEntity, Entity },
+    defines {
+        Entity.init(self, ticks: TickReceiver);
+    }
+)]
+pub struct Player {
+    pub living_entity: LivingEntity,
+    pub additional_hearts: usize,
+    pub score: usize,
+    pub is_cape_enabled: bool,
+    pub is_jacket_enabled: bool,
+    pub is_left_sleeve_enabled: bool,
+    pub is_right_sleeve_enabled: bool,
+    pub is_left_pants_leg_enabled: bool,
+    pub is_right_pants_leg_enabled: bool,
+    pub is_hat_enabled: bool,
+    pub left_shoulder_entity: NbtTag,
+    pub right_shoulder_entity: NbtTag,
+
+    game_mode: Gamemode,
+    info: PlayerInfo,
+    on_ground: bool,
+    packet_sender: MpscSender<Vec<u8>>,
+    entity_prev_positions: HashMap<Eid, Position>,
+    render_distance: i32,
+    loaded_chunks: HashSet<ChunkColumnPosition>,
+    center_chunk: ChunkPosition,
+    packets_sent: usize,
+    inventory: PlayerInventory,
+    digging: Option<Digging>,
+    sneaking: bool,
+    /// The window opened on a block, such as a chest
+    window: Option<OpenWindow>,
+    window_counter: u8,
+    /// What other players were last told the player holds and wears
+    equipment: Vec<(EquipmentSlot, Slot)>,
+    chat_limiter: ChatRateLimiter,
+    keep_alive: KeepAliveTracker,
+    /// Set by [Handler::kick], the player is disconnected with this reason on the next tick
+    kick_reason: Option<String>,
+}
+
+impl Player {
+    pub async fn spawn_player(
+        world: &'static World,
+        stream: TcpStream,
+        player_info: PlayerInfo,
+        permit: ConnectionPermit,
+        server_msg_rcvr: BroadcastReceiver<ServerMessage>,
+        change_receiver: MpscReceiver<WorldChange>
+    ) -> Eid {
+        let (packet_sender, packet_receiver) = mpsc_channel(1000);
+        let uuid = player_info.uuid;
+
+        let config = world.config();
+        let inventory = PlayerInventory::restore(&player_info.data.inventory, player_info.data.selected_hotbar_slot);
+        let equipment = inventory.equipment();
+        let mut player = Player {
+            living_entity: LivingEntity::default(),
+            additional_hearts: 0,
+            score: 0,
+            is_cape_enabled: false,
+            is_jacket_enabled: false,
+            is_left_sleeve_enabled: false,
+            is_right_sleeve_enabled: false,
+            is_left_pants_leg_enabled: false,
+            is_right_pants_leg_enabled: false,
+            is_hat_enabled: false,
+            left_shoulder_entity: NbtTag::Null,
+            right_shoulder_entity: NbtTag::Null,
+
+            game_mode: player_info.data.gamemode.clone(),
+            on_ground: false,
+            packet_sender,
+    
+            entity_prev_positions: HashMap::new(),
+    
+            center_chunk: player_info.data.position.chunk(),
+            render_distance: player_info.render_distance.clamp(2, config.view_distance as usize) as i32,
+            loaded_chunks: HashSet::new(),
+    
+            info: player_info,
+            packets_sent: 0,
+            inventory,
+            digging: None,
+            sneaking: false,
+            window: None,
+            window_counter: 0,
+            equipment,
+            chat_limiter: ChatRateLimiter::new(),
+            keep_alive: KeepAliveTracker::new(),
+            kick_reason: None,
+        };
+        
+        let (position, yaw, pitch) = (player.info.data.position.clone(), player.info.data.yaw, player.info.data.pitch);
+        let entity = player.get_entity_mut();
+        entity.position = position;
+        entity.yaw = yaw;
+        entity.pitch = pitch;
+
+        // TODO: player should load existing entities
+        
+        let center = player.info.data.position.chunk_column();
+        for cx in center.cx-3..=center.cx+3 {
+            for cz in center.cz-3..=center.cz+3 {
+                player.loaded_chunks.insert(ChunkColumnPosition { cx, cz });
+            }
+        }
+        
+        // Other players must know the player before its entity is spawned
+        let other_players = world.players().await;
+        let loaded_chunks: Vec<ChunkColumnPosition> = player.loaded_chunks.iter().cloned().collect();
+        world.add_player(uuid, PlayerListEntry {
+            eid: player.info.eid,
+            name: player.info.username.clone(),
+            properties: Vec::new(),
+            gamemode: player.game_mode.clone(),
+            latency: player.info.latency.map(|latency| latency.as_millis() as i32).unwrap_or(0),
+            listed: true,
+        }).await;
+
+        let eid = world.spawn_entity_with_ids::<Player>(player.info.eid, uuid, AnyEntity::Player(player)).await;
+        let handler = Handler::assume(eid, world);
+        handler.send_player_list(&other_players).await;
+        handler.spawn_entities_in(&loaded_chunks).await;
+        handler.clone().insert_task("player", tokio::spawn(async move {
+            handle_player(handler, uuid, stream, packet_receiver, server_msg_rcvr, change_receiver).await;
+            // The connection counts towards the limits until the player leaves
+            drop(permit);
+        })).await;
+
+        eid
+    }
+
+    pub fn inventory(&self) -> &PlayerInventory {
+        &self.inventory
+    }
+}
+
+impl Handler<Player> {
+    async fn update_center_chunk(self) {
+        let Some((old_center_chunk, new_center_chunk, render_distance)) = self.mutate(|player| {
+            let old_center_chunk = player.center_chunk.clone();
+            let new_center_chunk = player.get_entity().position.chunk();
+            player.center_chunk = new_center_chunk.clone();
+            ((old_center_chunk, new_center_chunk, player.render_distance), EntityChanges::other())
+        }).await else {return};
+
+        // Tell the client which chunk he is in
+        if new_center_chunk == old_center_chunk { return };
+        self.send_packet(PlayClientbound::SetCenterChunk { chunk_x: VarInt(new_center_chunk.cx), chunk_z: VarInt(new_center_chunk.cz) }).await;
+
+        // Find out which chunks should be loaded
+        if new_center_chunk.chunk_column() == old_center_chunk.chunk_column() { return };
+        let mut loaded_chunks_after = HashSet::new();
+        for cx in (new_center_chunk.cx - render_distance)..=(new_center_chunk.cx + render_distance) {
+            for cz in (new_center_chunk.cz - render_distance)..=(new_center_chunk.cz + render_distance) {
+                let dist = (((cx - new_center_chunk.cx).pow(2) + (cz - new_center_chunk.cz).pow(2)) as f32).sqrt();
+                if dist > render_distance as f32 { continue };
+                loaded_chunks_after.insert(ChunkColumnPosition { cx, cz });
+            }
+        }
+
+        // Select chunks to load (max 50) and unload
+        let Some((loaded_chunks_after, newly_loaded_chunks, unloaded_chunks, uuid)) = self.mutate(|player| {
+            if loaded_chunks_after == player.loaded_chunks { return (None, EntityChanges::nothing()) };
+            let mut newly_loaded_chunks: Vec<_> = loaded_chunks_after.difference(&player.loaded_chunks).cloned().collect();
+            let unloaded_chunks: Vec<_> = player.loaded_chunks.difference(&loaded_chunks_after).cloned().collect();
+            for skipped in newly_loaded_chunks.iter().skip(50) {
+                loaded_chunks_after.remove(skipped);
+            }
+            newly_loaded_chunks.truncate(50);
+            let uuid = player.info.uuid;
+            player.loaded_chunks = loaded_chunks_after.clone();
+            (Some((loaded_chunks_after, newly_loaded_chunks, unloaded_chunks, uuid)), EntityChanges::other())
+        }).await.flatten() else { return };
+
+        // Tell the world about the changes
+        self.world.update_loaded_chunks(uuid, loaded_chunks_after).await;
+        self.despawn_entities_in(&unloaded_chunks).await;
+
+        // Send the chunks to the client
+        let mut heightmaps = HashMap::new();
+        heightmaps.insert(String::from("MOTION_BLOCKING"), NbtTag::LongArray(vec![0; 37]));
+        let heightmaps = NbtTag::Compound(heightmaps);
+        for newly_loaded_chunk in newly_loaded_chunks.iter().cloned() {
+            let mut column = Vec::new();
+            for cy in -4..20 {
+                let chunk = self.world.get_network_chunk(newly_loaded_chunk.chunk(cy)).await.unwrap_or_else(|| {
+                    error!("Chunk not loaded: {newly_loaded_chunk:?}");
+                    NetworkChunk { // TODO hard error
+                        block_count: 0,
+                        blocks: PalettedData::Single { value: 0 },
+                        biomes: PalettedData::Single { value: 4 },
+                    }
+                });
+                column.push(chunk);
+            }
+            let serialized: Vec<u8> = NetworkChunk::into_data(column).unwrap();
+            let block_entities = self.world.get_network_block_entities(newly_loaded_chunk.clone()).await;
+            let chunk_data = PlayClientbound::ChunkData {
+                value: ChunkData {
+                    chunk_x: newly_loaded_chunk.cx,
+                    chunk_z: newly_loaded_chunk.cz,
+                    heightmaps: heightmaps.clone(),
+                    data: Array::from(serialized.clone()),
+                    block_entities: Array::from(block_entities),
+                    sky_light_mask: Array::default(),
+                    block_light_mask: Array::default(),
+                    empty_sky_light_mask: Array::default(),
+                    empty_block_light_mask: Array::default(),
+                    sky_light: Array::default(),
+                    block_light: Array::default(),
+                }
+            };
+            self.send_packet(chunk_data).await;
+        }
+        self.spawn_entities_in(&newly_loaded_chunks).await;
+
+        // Tell the client to unload chunks
+        for unloaded_chunk in unloaded_chunks {
+            self.send_packet(PlayClientbound::UnloadChunk {
+                chunk_x: unloaded_chunk.cx,
+                chunk_z: unloaded_chunk.cz,
+            }).await;
+        }
+    }
+
+    async fn on_place_block(&self, hand: Hand, location: NetworkPosition, face: BlockFace, cursor_y: f32, sequence: VarInt) {
+        let clicked_position: BlockPosition = location.into();
+
+        // Containers open instead, unless the player sneaks with an item in hand
+        let Some(keeps_closed) = self.observe(|player| {
+            player.sneaking && [Hand::MainHand, Hand::OffHand].iter().any(|hand| player.inventory.held_item(*hand).item.is_some())
+        }).await else { return };
+        if !keeps_closed && hand == Hand::MainHand && self.open_block_window(clicked_position.clone()).await {
+            self.send_packet(PlayClientbound::AcknowledgeBlockChange { id: sequence }).await;
+            return;
+        }
+
+        let placed = self.place_block(hand, clicked_position.clone(), face, cursor_y).await;
+
+        // Tell the client the real blocks so that it can revert its prediction
+        if placed.is_none() {
+            for position in [offset_by_face(&clicked_position, face), clicked_position] {
+                if let Some(block) = self.world.get_block(position.clone()).await {
+                    self.send_packet(PlayClientbound::BlockUpdate { location: position.into(), block_state: block }).await;
+                }
+            }
+        }
+        self.send_packet(PlayClientbound::AcknowledgeBlockChange { id: sequence }).await;
+
+        if let Some((position, block)) = placed {
+            if matches!(BlockEntityType::from_block(Block::from(block)), Some(BlockEntityType::Sign | BlockEntityType::HangingSign)) {
+                self.send_packet(PlayClientbound::OpenSignEditor { location: position.into(), is_front_text: true }).await;
+            }
+        }
+    }
+
+    /// Places the block held in a hand against a face of the clicked block.
+    /// Returns the position and state of the placed block, or `None` if the placement was rejected.
+    async fn place_block(&self, hand: Hand, clicked_position: BlockPosition, face: BlockFace, cursor_y: f32) -> Option<(BlockPosition, BlockWithState)> {
+        let (held_item, game_mode, player_position, yaw, pitch, sneaking) = self.observe(|player| {
+            let entity = player.get_entity();
+            let held_item = player.inventory.held_item(hand).item.as_ref().map(|item| item.item_id);
+            (held_item, player.game_mode.clone(), entity.position.clone(), entity.yaw, entity.pitch, player.sneaking)
+        }).await?;
+        if game_mode == Gamemode::Spectator || game_mode == Gamemode::Adventure {
+            return None;
+        }
+        let block = block_for_item(held_item?).filter(|block| !block.is_air_block())?;
+
+        // Blocks are placed against the clicked face, unless the clicked block can be replaced
+        let clicked_block = self.world.get_block(clicked_position.clone()).await?;
+        let clicked_top_or_bottom = (face == BlockFace::Top && clicked_block.property("type") == Some("bottom"))
+            || (face == BlockFace::Bottom && clicked_block.property("type") == Some("top"));
+        let position = if is_replaceable(&clicked_block) || (clicked_top_or_bottom && merges_with(block, &clicked_block)) {
+            clicked_position
+        } else {
+            offset_by_face(&clicked_position, face)
+        };
+        let replaced = self.world.get_block(position.clone()).await?;
+        if !is_replaceable(&replaced) && !merges_with(block, &replaced) {
+            return None;
+        }
+
+        let dx = position.x as f64 + 0.5 - player_position.x;
+        let dy = position.y as f64 + 0.5 - (player_position.y + 1.62);
+        let dz = position.z as f64 + 0.5 - player_position.z;
+        if dx * dx + dy * dy + dz * dz > 8.0 * 8.0 {
+            warn!("Player tried to place a block that is too far away");
+            return None;
+        }
+
+        let context = PlacementContext { face, cursor_y, yaw, pitch, replaced };
+        let mut state = placement_state(block, &context);
+
+        // Chests join a single chest facing the same way next to them
+        let mut chest_partner_state = None;
+        if state.property("type") == Some("single") && !sneaking {
+            for (chest_type, partner_type) in [("left", "right"), ("right", "left")] {
+                let Some(candidate) = state.with_property("type", chest_type) else { break };
+                let Some(partner_position) = chest_partner(&position, &candidate) else { continue };
+                let Some(partner) = self.world.get_block(partner_position.clone()).await else { continue };
+                if Block::from(partner.clone()) == Block::from(state.clone())
+                    && partner.property("facing") == state.property("facing")
+                    && partner.property("type") == Some("single")
+                {
+                    chest_partner_state = partner.with_property("type", partner_type).map(|partner| (partner_position, partner));
+                    state = candidate;
+                    break;
+                }
+            }
+        }
+
+        // Doors are two blocks high
+        let mut blocks = vec![(position.clone(), state.clone())];
+        if state.property("half") == Some("lower") {
+            let upper_position = BlockPosition { x: position.x, y: position.y + 1, z: position.z };
+            let upper_block = self.world.get_block(upper_position.clone()).await?;
+            if !is_replaceable(&upper_block) {
+                return None;
+            }
+            blocks.push((upper_position, state.with_property("half", "upper")?));
+        }
+
+        // Blocks can't be placed where entities are
+        if Block::from(state.clone()).is_blocking() {
+            for (position, _) in &blocks {
+                if self.intersects_entities(CollisionShape::full_block(position)).await {
+                    return None;
+                }
+            }
+        }
+
+        blocks.extend(chest_partner_state);
+        for (position, block) in blocks {
+            self.world.set_block(position, block).await;
+        }
+        if game_mode == Gamemode::Survival {
+            self.mutate(|player| {
+                player.inventory.consume_held_item(hand);
+                ((), EntityChanges::other())
+            }).await;
+        }
+
+        Some((position, state))
+    }
+
+    async fn intersects_entities(&self, shape: CollisionShape) -> bool {
+        // Entities are at most a few blocks wide, so their position is in a neighbouring chunk column
+        let mut chunk_columns = HashSet::new();
+        for dx in [-4.0, 0.0, 4.0] {
+            for dz in [-4.0, 0.0, 4.0] {
+                chunk_columns.insert(Position { x: shape.x1 + dx, y: shape.y1, z: shape.z1 + dz }.chunk_column());
+            }
+        }
+        for chunk_column in chunk_columns {
+            let intersecting = self.world.observe_entities(chunk_column, |entity| {
+                let ty = entity.to_network()?;
+                if matches!(ty, NetworkEntity::Item | NetworkEntity::ExperienceOrb | NetworkEntity::Arrow) {
+                    return None;
+                }
+                let position = &entity.as_entity().position;
+                let (half_width, height) = (ty.width() as f64 / 2.0, ty.height() as f64);
+                let entity_shape = CollisionShape {
+                    x1: position.x - half_width,
+                    y1: position.y,
+                    z1: position.z - half_width,
+                    x2: position.x + half_width,
+                    y2: position.y + height,
+                    z2: position.z + half_width,
+                };
+                entity_shape.intersects(&shape).then_some(())
+            }).await;
+            if !intersecting.is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn send_packet<'a>(&self, packet: PlayClientbound<'a>) {
+        let packet = packet.serialize_minecraft_packet().unwrap();
+        let packets_sent = self.mutate(|player| {
+            player.packets_sent += 1;
+            (player.packets_sent, EntityChanges::other())
+        }).await.unwrap_or(0);
+        if packets_sent > 500 {
+            warn!("Many packets sent ({packets_sent})");
+        }
+        let Some(packet_sender) = self.observe(|player| player.packet_sender.clone()).await else {return};
+        if packet_sender.send(packet).await.is_err() {
+            debug!("Packet not sent, as the connection is closed");
+        }
+    }
+
+    /// Returns the round-trip time measured with keep-alives.
+    pub async fn latency(&self) -> Option<Duration> {
+        self.observe(|player| player.keep_alive.latency()).await.flatten()
+    }
+
+    /// Disconnects the player on the next tick.
+    pub async fn kick(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.mutate(|player| {
+            player.kick_reason = Some(reason);
+            ((), EntityChanges::other())
+        }).await;
+    }
+
+    /// Saves the position, game mode and inventory of the player, so that they are restored when they join again.
+    pub async fn save_data(&self) {
+        let data = self.observe(|player| {
+            let entity = player.get_entity();
+            (player.info.uuid, PlayerData {
+                position: entity.position.clone(),
+                yaw: entity.yaw,
+                pitch: entity.pitch,
+                gamemode: player.game_mode.clone(),
+                inventory: player.inventory.slots().to_vec(),
+                selected_hotbar_slot: player.inventory.selected_hotbar_slot() as u8,
+            })
+        }).await;
+        if let Some((uuid, data)) = data {
+            self.world.save_player_data(uuid, &data).await;
+        }
+    }
+
+    /// Returns the reason to disconnect the player if the connection should be closed.
+    async fn on_server_message(self, message: ServerMessage) -> Result<(), String> {
+        use ServerMessage::*;
+        match message {
+            Tick(tick_id) => {
+                let now = Instant::now();
+                let send_keep_alive = tick_id % KeepAliveTracker::INTERVAL_TICKS == 0;
+                let (timed_out, kick_reason) = self.mutate(|player| {
+                    player.packets_sent = 0;
+                    if send_keep_alive {
+                        player.keep_alive.sent(tick_id as u64, now);
+                    }
+                    ((player.keep_alive.timed_out(now), player.kick_reason.take()), EntityChanges::other())
+                }).await.unwrap_or((false, None));
+                if let Some(reason) = kick_reason {
+                    return Err(reason);
+                }
+                if timed_out {
+                    return Err(String::from("Timed out"));
+                }
+                if send_keep_alive {
+                    self.send_packet(PlayClientbound::KeepAlive { keep_alive_id: tick_id as u64 }).await;
+                }
+                self.tick_digging().await;
+                self.send_packet(PlayClientbound::BundleDelimiter).await;
+            }
+            Shutdown(reason) => return Err(reason),
+        }
+        Ok(())
+    }
+
+    async fn on_world_change(self, change: WorldChange) {
+        // The client handles its own entity
+        if let WorldChange::EntitySpawned { eid, .. }
+            | WorldChange::EntityDispawned { eid }
+            | WorldChange::EntityPosition { eid, .. }
+            | WorldChange::EntityPitch { eid, .. }
+            | WorldChange::EntityEquipment { eid, .. } = &change
+        {
+            if *eid == self.eid {
+                return;
+            }
+        }
+
+        match change {
+            WorldChange::Block(position, block) => {
+                self.on_window_block_change(&position, &block).await;
+                self.send_packet(PlayClientbound::BlockUpdate {
+                    location: position.into(),
+                    block_state: block,
+                }).await;
+            },
+            WorldChange::BlockAction { position, action_id, action_param, block } => {
+                self.send_packet(PlayClientbound::BlockAction {
+                    location: position.into(),
+                    action_id,
+                    action_param,
+                    block,
+                }).await;
+            },
+            WorldChange::ContainerSlots { position, slots } => {
+                self.on_container_slots_change(position, slots).await;
+            },
+            WorldChange::ContainerProperty { position, property, value } => {
+                self.on_container_property_change(position, property, value).await;
+            },
+            WorldChange::SectionBlocks { section, blocks } => {
+                use minecraft_protocol::components::blocks::MultiBlockChange;
+                let Ok(chunk_section_position) = MultiBlockChange::encode_chunk_section_position(section.cx, section.cy, section.cz) else {return};
+                let blocks: Vec<VarLong> = blocks.into_iter().filter_map(|(position, block)| {
+                    let block = MultiBlockChange::encode_block(block.block_state_id()?, position.bx, position.by, position.bz).ok()?;
+                    Some(VarLong(block as i64))
+                }).collect();
+                self.send_packet(PlayClientbound::UpdateSectionBlocks {
+                    value: MultiBlockChange {
+                        chunk_section_position,
+                        blocks: Array::from(blocks),
+                    }
+                }).await;
+            },
+            WorldChange::BlockEntity { position, ty, data } => {
+                self.send_packet(PlayClientbound::BlockEntityData {
+                    location: position.into(),
+                    block_entity: VarInt(ty as i32),
+                    data,
+                }).await;
+            },
+            WorldChange::BlockDestroyStage { eid, position, stage } => {
+                // Players see their own progress without being told
+                if eid == self.eid {
+                    return;
+                }
+                self.send_packet(PlayClientbound::SetBlockDestroyStage {
+                    id: VarInt(eid as i32),
+                    location: position.into(),
+                    destroy_stage: stage.unwrap_or(u8::MAX),
+                }).await;
+            },
+            WorldChange::EntitySpawned { eid, uuid, ty, position, pitch, yaw, head_yaw, data, velocity, metadata } => {
+                self.mutate(|player| {player.entity_prev_positions.insert(eid, position.clone()); ((), EntityChanges::other())}).await;
+                self.send_packet(PlayClientbound::SpawnEntity {
+                    id: VarInt(eid as i32),
+                    uuid,
+                    entity_type: ty,
+                    x: position.x,
+                    y: position.y,
+                    z: position.z,
+                    pitch: (pitch * (256.0 / 360.0)) as u8,
+                    yaw: (yaw * (256.0 / 360.0)) as u8,
+                    head_yaw: (head_yaw * (256.0 / 360.0)) as u8,
+                    data: VarInt(data as i32),
+                    velocity_x: (velocity.x * 8000.0) as i16,
+                    velocity_y: (velocity.y * 8000.0) as i16,
+                    velocity_z: (velocity.z * 8000.0) as i16,
+                }).await;
+                if !metadata.is_empty() {
+                    self.send_packet(PlayClientbound::SetEntityMetadata {
+                        entity_id: VarInt(eid as i32),
+                        metadata: EntityMetadata { items: metadata },
+                    }).await;
+                }
+            },
+            WorldChange::EntityDispawned { eid } => {
+                self.mutate(|player| {player.entity_prev_positions.remove(&eid); ((), EntityChanges::other())}).await;
+                self.send_packet(PlayClientbound::RemoveEntities { entity_ids: Array::from(vec![VarInt(eid as i32)]) }).await;
+            },
+            WorldChange::EntityMetadata { eid, metadata } => {
+                self.send_packet(PlayClientbound::SetEntityMetadata {
+                    entity_id: VarInt(eid as i32),
+                    metadata: EntityMetadata { items: metadata },
+                }).await;
+            },
+            WorldChange::EntityPosition { eid, position } => {
+                let Some(prev_position) = self.mutate(|player| ((player.entity_prev_positions.insert(eid, position.clone())), EntityChanges::other())).await else {return};
+                match prev_position {
+                    Some(prev_position) => {
+                        self.send_packet(PlayClientbound::UpdateEntityPosition {
+                            entity_id: VarInt(eid as i32),
+                            delta_x: (position.x * 4096.0 - prev_position.x * 4096.0) as i16,
+                            delta_y: (position.y * 4096.0 - prev_position.y * 4096.0) as i16,
+                            delta_z: (position.z * 4096.0 - prev_position.z * 4096.0) as i16,
+                            on_ground: true, // TODO add on_ground in entity position
+                        }).await;
+                    }
+                    None => {
+                        self.spawn_entity_for_client(eid).await;
+                    }
+                }
+            },
+            WorldChange::EntityVelocity { eid, velocity } => {
+                self.send_packet(PlayClientbound::SetEntityVelocity {
+                    entity_id: VarInt(eid as i32),
+                    velocity_x: (velocity.x * 8000.0) as i16,
+                    velocity_y: (velocity.y * 8000.0) as i16,
+                    velocity_z: (velocity.z * 8000.0) as i16,
+                }).await;
+            },
+            WorldChange::EntityPitch { eid, pitch, yaw, head_yaw } => {
+                self.send_packet(PlayClientbound::UpdateEntityRotation {
+                    entity_id: VarInt(eid as i32),
+                    yaw: (yaw * (256.0 / 360.0)) as u8,
+                    pitch: (pitch * (256.0 / 360.0)) as u8,
+                    on_ground: true, // TODO add on_ground in entity position
+                }).await;
+            },
+            WorldChange::EntityEquipment { eid, equipment } => {
+                self.on_equipment_change(eid, equipment).await;
+            },
+            WorldChange::PlayerInfoAdded { uuid, entry } => {
+                self.send_player_list(&[(uuid, entry)]).await;
+            },
+            WorldChange::PlayerInfoUpdated { uuid, update } => {
+                self.on_player_info_update(uuid, update).await;
+            },
+            WorldChange::PlayerInfoRemoved { uuid } => {
+                self.on_player_info_removed(uuid).await;
+            },
+            WorldChange::Chat { kind, text, .. } => {
+                self.on_chat(kind, text).await;
+            },
+            WorldChange::ItemPickedUp { collected, collector, count } => {
+                self.send_packet(PlayClientbound::PickupItem {
+                    collected_entity_id: VarInt(collected as i32),
+                    collector_entity_id: VarInt(collector as i32),
+                    pickup_item_count: VarInt(count as i32),
+                }).await;
+            },
+        }
+    }
+
+    async fn on_packet<'a>(mut self, packet: PlayServerbound<'a>) {
+        use PlayServerbound::*;
+        match packet {
+            SetPlayerPosition { x, y, z, on_ground } => {
+                self.mutate(|player| {
+                    let entity = player.get_entity_mut();
+                    entity.position.x = x;
+                    entity.position.y = y;
+                    entity.position.z = z;
+                    player.on_ground = on_ground;
+                    ((), EntityChanges::position())
+                }).await;
+                self.update_center_chunk().await;
+                // TODO: make sure the movement is allowed
+            },
+            SetPlayerRotation { yaw, pitch, on_ground } => {
+                self.mutate(|player| {
+                    let entity = player.get_entity_mut();
+                    entity.yaw = yaw;
+                    entity.pitch = pitch;
+                    player.on_ground = on_ground;
+                    ((), EntityChanges::pitch())
+                }).await;
+            }
+            SetPlayerPositionAndRotation { x, y, z, yaw, pitch, on_ground } => {
+                self.mutate(|player| {
+                    let entity = player.get_entity_mut();
+                    entity.position.x = x;
+                    entity.position.y = y;
+                    entity.position.z = z;
+                    entity.yaw = yaw;
+                    entity.pitch = pitch;
+                    player.on_ground = on_ground;
+                    ((), EntityChanges::position()+EntityChanges::pitch())
+                }).await;
+                self.update_center_chunk().await;
+                // TODO: make sure the movement is allowed
+            },
+            DigBlock { status, location, face: _, sequence } => {
+                self.on_dig_block(status, location, sequence).await;
+            }
+            ChatMessage { message, .. } => {
+                self.on_chat_message(message).await;
+            }
+            ChatCommand { command, .. } => {
+                self.on_chat_command(command).await;
+            }
+            CommandSuggestionsRequest { transaction_id, text } => {
+                self.on_command_suggestions_request(transaction_id, text).await;
+            }
+            ClientSettings { chat_mode, chat_colors_enabled, .. } => {
+                self.mutate(|player| {
+                    player.info.chat_mode = chat_mode;
+                    player.info.chat_colors = chat_colors_enabled;
+                    ((), EntityChanges::other())
+                }).await;
+            }
+            UpdateSign { location, is_front_text, line1, line2, line3, line4 } => {
+                let position: BlockPosition = location.into();
+                let Some(player_position) = self.observe(|player| player.get_entity().position.clone()).await else {return};
+                let dx = position.x as f64 + 0.5 - player_position.x;
+                let dy = position.y as f64 + 0.5 - player_position.y;
+                let dz = position.z as f64 + 0.5 - player_position.z;
+                if dx * dx + dy * dy + dz * dz > 8.0 * 8.0 {
+                    warn!("Player tried to edit a sign that is too far away");
+                    return;
+                }
+                let lines = [line1, line2, line3, line4].map(|line| line.chars().filter(|c| *c != '§' && !c.is_control()).take(384).collect::<String>());
+                self.world.mutate_block_entity(position, |block_entity| {
+                    let BlockEntityData::Sign { front_text, back_text, is_waxed: false } = &mut block_entity.data else {return};
+                    let text = if is_front_text { front_text } else { back_text };
+                    text.set_lines([lines[0].as_str(), lines[1].as_str(), lines[2].as_str(), lines[3].as_str()]);
+                }).await;
+            }
+            QueryBlockNbt { transaction_id, position } => {
+                // Block entities can hold private data such as container contents
+                let Some(uuid) = self.world.entity_uuid(self.eid).await else { return };
+                if self.world.permission_level(uuid).await < 2 {
+                    warn!("Player without permission tried to query block NBT");
+                    return;
+                }
+                let position: BlockPosition = position.into();
+                let nbt_data = match self.world.get_block_entity(position.clone()).await {
+                    Some(block_entity) => block_entity.to_nbt(&position),
+                    None => NbtTag::Null,
+                };
+                self.send_packet(PlayClientbound::TagQueryResponse { query_id: transaction_id, nbt_data }).await;
+            }
+            SetHeldItem { slot } => {
+                self.mutate(|player| {
+                    if !player.inventory.set_selected_hotbar_slot(slot as usize) {
+                        warn!("Invalid hotbar slot selected: {slot}");
+                    }
+                    ((), EntityChanges::other())
+                }).await;
+            }
+            SetCreativeModeSlot { id, clicked_item } => {
+                self.on_set_creative_slot(id, clicked_item).await;
+            }
+            ClickWindowSlot { window_id, state_id, slot, button, mode, new_slot_values, carried_item } => {
+                let new_slot_values = new_slot_values.items.into_iter().collect();
+                self.on_click_window(window_id, state_id.0, slot, button, mode.0, new_slot_values, carried_item).await;
+            }
+            CloseWindow { window_id } => {
+                self.on_close_window(window_id).await;
+            }
+            PlayerAction { player_id: _, action_id, jump_boost: _ } => {
+                use minecraft_protocol::components::entity::PlayerAction as Action;
+                self.mutate(|player| {
+                    match action_id {
+                        Action::StartSneaking => player.sneaking = true,
+                        Action::StopSneaking => player.sneaking = false,
+                        _ => (),
+                    }
+                    ((), EntityChanges::other())
+                }).await;
+            }
+            PlaceBlock { hand, location, face, cursor_position_x: _, cursor_position_y, cursor_position_z: _, inside_block: _, sequence } => {
+                self.on_place_block(hand, location, face, cursor_position_y, sequence).await;
+            }
+            UseItem { hand: _, sequence } => {
+                // TODO: use items
+                self.send_packet(PlayClientbound::AcknowledgeBlockChange { id: sequence }).await;
+            }
+            KeepAlive { keep_alive_id } => {
+                let answered = self.mutate(|player| {
+                    let latency = player.keep_alive.received(keep_alive_id, Instant::now());
+                    ((player.info.uuid, latency), EntityChanges::other())
+                }).await;
+                match answered {
+                    Some((uuid, Some(latency))) => self.world.update_player(uuid, PlayerListUpdate::Latency(latency.as_millis() as i32)).await,
+                    _ => warn!("Received an unexpected keep-alive ({keep_alive_id})"),
+                }
+            }
+            RequestPing { payload } => {
+                self.send_packet(PlayClientbound::Ping { id: payload as i32 }).await;
+            }
+            packet => warn!("Unsupported packet received: {packet:?}"),
+        }
+    }
+}
+
+async fn handle_player(h: Handler<Player>, uuid: UUID, stream: TcpStream, packet_receiver: MpscReceiver<Vec<u8>>, server_msg_rcvr: BroadcastReceiver<ServerMessage>, change_receiver: MpscReceiver<WorldChange>) {
+    let r = handle_player_inner(h.clone(), stream, packet_receiver, server_msg_rcvr, change_receiver).await;
+    match r {
+        Ok(()) => info!("Player handler shut down gracefully"),
+        Err(()) => error!("Player handler crashed")
+    }
+    // The connection is closed, so nothing can be sent anymore
+    h.release_block_window().await;
+    h.save_data().await;
+    h.world.remove_loader(uuid).await;
+    h.world.remove_entity(h.eid).await;
+    h.world.remove_player(uuid).await;
+}
+
+async fn handle_player_inner(h: Handler<Player>, stream: TcpStream, mut packet_receiver: MpscReceiver<Vec<u8>>, mut server_msg_rcvr: BroadcastReceiver<ServerMessage>, mut change_receiver: MpscReceiver<WorldChange>) -> Result<(), ()> {
+    let (mut reader_stream, mut writer_stream) = stream.into_split();
+    
+    let mut receive_packet_fut = Box::pin(receive_packet_split(&mut reader_stream).fuse());
+    let mut receive_clientbound_fut = Box::pin(packet_receiver.recv().fuse());
+    let mut receive_server_message_fut = Box::pin(server_msg_rcvr.recv().fuse());
+    let mut receive_change_fut = Box::pin(change_receiver.recv().fuse());
+    loop {
+        // Select the first event that happens
+        enum Event {
+            PacketServerbound(Result<Vec<u8>, ()>),
+            PacketClientbound(Option<Vec<u8>>),
+            Message(Result<ServerMessage, BroadcastRecvError>),
+            WorldChange(Option<WorldChange>),
+        }
+        let event = futures::select! {
+            packet_serverbound = receive_packet_fut => Event::PacketServerbound(packet_serverbound),
+            packet_clientbound = receive_clientbound_fut => Event::PacketClientbound(packet_clientbound),
+            message = receive_server_message_fut => Event::Message(message),
+            change = receive_change_fut => Event::WorldChange(change),
+        };
+        match event {
+            Event::PacketServerbound(Ok(packet)) => {
+                drop(receive_packet_fut);
+                receive_packet_fut = Box::pin(receive_packet_split(&mut reader_stream).fuse());
+                h.world.metrics().packet_received(&packet);
+
+                let packet = match PlayServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()) {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Received a malformed packet: {e}");
+                        disconnect_split(&mut writer_stream, &format!("Invalid packet: {e}")).await;
+                        return Err(());
+                    }
+                };
+                h.clone().on_packet(packet).await;
+                h.update_equipment().await;
+            },
+            Event::PacketClientbound(Some(packet)) => {
+                drop(receive_clientbound_fut);
+                receive_clientbound_fut = Box::pin(packet_receiver.recv().fuse());
+
+                h.world.metrics().packet_sent(&packet);
+                if send_packet_raw_split(&mut writer_stream, packet.as_slice()).await.is_err() {
+                    error!("Failed to send clientbound packet");
+                    return Err(());
+                }
+            },
+            Event::Message(Ok(message)) => {
+                drop(receive_server_message_fut);
+                receive_server_message_fut = Box::pin(server_msg_rcvr.recv().fuse());
+
+                if let Err(reason) = h.clone().on_server_message(message).await {
+                    info!("Disconnecting player: {reason}");
+                    disconnect_split(&mut writer_stream, &reason).await;
+                    return Ok(());
+                }
+            },
+            Event::WorldChange(Some(change)) => {
+                drop(receive_change_fut);
+                receive_change_fut = Box::pin(change_receiver.recv().fuse());
+
+                h.clone().on_world_change(change).await;
+            },
+            Event::Message(Err(recv_error)) => {
+                error!("Failed to receive message: {recv_error:?}");
+                return Err(());
+            }
+            Event::PacketClientbound(None) => {
+                error!("Failed to receive clientbound packet");
+                return Err(());
+            }
+            Event::PacketServerbound(Err(e)) => {
+                error!("Failed to receive serverbound packet: {e:?}");
+                return Err(());
+            }
+            Event::WorldChange(None) => {
+                error!("Failed to receive world change");
+                return Err(());
+            }
+        }
+    }
+}
+
+impl Handler<Player> {
+    pub async fn init(self, ticks: TickReceiver) {
+        //self.insert_task("newton", tokio::spawn(newton_task(self.clone(), server_msg_rcvr))).await;
+    }
+}
@@ -0,0 +1,138 @@
+use minecraft_protocol::components::{
+    players::{AddPlayersAction, PingAction, PlayerActions, PlayerInfos, PlayersInfos, Property, UpdateGamemodesAction, UpdateListedAction},
+    slots::{EquipmentSlot, EquipmentSlotArray},
+};
+use super::*;
+
+/// Returns the packet adding players to the player list of a client.
+fn add_players_packet(players: &[(UUID, PlayerListEntry)]) -> PlayClientbound<'_> {
+    let players_infos = players.iter().map(|(uuid, entry)| PlayerInfos {
+        player_uuid: *uuid,
+        actions: vec![
+            PlayerActions::AddPlayer(AddPlayersAction {
+                name: &entry.name,
+                properties: Array::from(entry.properties.iter().map(|property| Property {
+                    name: &property.name,
+                    value: &property.value,
+                    signature: property.signature.as_deref(),
+                }).collect::<Vec<_>>()),
+            }),
+            PlayerActions::UpdateGamemodes(UpdateGamemodesAction { gamemode: VarInt(entry.gamemode.clone() as u8 as i32) }),
+            PlayerActions::UpdateListed(UpdateListedAction { listed: entry.listed }),
+            PlayerActions::Ping(PingAction { ping: VarInt(entry.latency) }),
+        ],
+    }).collect();
+    PlayClientbound::UpdatePlayersInfo { players_info: PlayersInfos { players_infos } }
+}
+
+fn equipment_packet(eid: Eid, equipment: Vec<(EquipmentSlot, Slot)>) -> PlayClientbound<'static> {
+    PlayClientbound::SetEquipment {
+        entity_id: VarInt(eid as i32),
+        equipment: EquipmentSlotArray { slots: equipment.into_iter().collect() },
+    }
+}
+
+impl Handler<Player> {
+    /// Adds players to the player list of the client.
+    pub(super) async fn send_player_list(&self, players: &[(UUID, PlayerListEntry)]) {
+        if players.is_empty() {
+            return;
+        }
+        self.send_packet(add_players_packet(players)).await;
+    }
+
+    pub(super) async fn on_player_info_update(&self, uuid: UUID, update: PlayerListUpdate) {
+        let action = match update {
+            PlayerListUpdate::Gamemode(gamemode) => PlayerActions::UpdateGamemodes(UpdateGamemodesAction { gamemode: VarInt(gamemode as u8 as i32) }),
+            PlayerListUpdate::Latency(latency) => PlayerActions::Ping(PingAction { ping: VarInt(latency) }),
+            PlayerListUpdate::Listed(listed) => PlayerActions::UpdateListed(UpdateListedAction { listed }),
+        };
+        self.send_packet(PlayClientbound::UpdatePlayersInfo {
+            players_info: PlayersInfos { players_infos: vec![PlayerInfos { player_uuid: uuid, actions: vec![action] }] },
+        }).await;
+    }
+
+    pub(super) async fn on_player_info_removed(&self, uuid: UUID) {
+        self.send_packet(PlayClientbound::RemovePlayerInfo { players_to_remove: Array::from(vec![uuid]) }).await;
+    }
+
+    /// Shows an existing entity to the client.
+    pub(super) async fn spawn_entity_for_client(&self, eid: Eid) {
+        let Some(Some((ty, position, velocity, pitch, yaw, head_yaw, metadata, equipment))) = self.world.observe_entity(eid, |any_entity| {
+            let ty = any_entity.to_network()?;
+            let entity = any_entity.as_entity();
+            let head_yaw = any_entity.as_other::<LivingEntity>().map(|living| living.head_yaw).unwrap_or(0.0);
+            let equipment = any_entity.as_other::<Player>().map(|player| player.inventory.equipment());
+            Some((ty, entity.position.clone(), entity.velocity.clone(), entity.pitch, entity.yaw, head_yaw, any_entity.network_metadata(), equipment))
+        }).await else { return };
+        let Some(uuid) = self.world.entity_uuid(eid).await else { return };
+
+        self.mutate(|player| {player.entity_prev_positions.insert(eid, position.clone()); ((), EntityChanges::other())}).await;
+        self.send_packet(PlayClientbound::SpawnEntity {
+            id: VarInt(eid as i32),
+            uuid,
+            entity_type: ty,
+            x: position.x,
+            y: position.y,
+            z: position.z,
+            pitch: (pitch * (256.0 / 360.0)) as u8,
+            yaw: (yaw * (256.0 / 360.0)) as u8,
+            head_yaw: (head_yaw * (256.0 / 360.0)) as u8,
+            data: VarInt(0), // TODO set data on entities
+            velocity_x: (velocity.x * 8000.0) as i16,
+            velocity_y: (velocity.y * 8000.0) as i16,
+            velocity_z: (velocity.z * 8000.0) as i16,
+        }).await;
+        if !metadata.is_empty() {
+            self.send_packet(PlayClientbound::SetEntityMetadata {
+                entity_id: VarInt(eid as i32),
+                metadata: EntityMetadata { items: metadata },
+            }).await;
+        }
+        if let Some(equipment) = equipment {
+            self.send_packet(equipment_packet(eid, equipment)).await;
+        }
+    }
+
+    /// Shows the client the entities that are in newly loaded chunks.
+    pub(super) async fn spawn_entities_in(&self, chunks: &[ChunkColumnPosition]) {
+        for chunk in chunks {
+            let eids = self.world.observe_entities_with_eids(chunk.clone(), |eid, _| (eid != self.eid).then_some(eid)).await;
+            for eid in eids {
+                self.spawn_entity_for_client(eid).await;
+            }
+        }
+    }
+
+    /// Removes the entities that are in unloaded chunks from the client.
+    pub(super) async fn despawn_entities_in(&self, chunks: &[ChunkColumnPosition]) {
+        let Some(removed) = self.mutate(|player| {
+            let removed: Vec<Eid> = player.entity_prev_positions.iter().filter(|(_, position)| chunks.contains(&position.chunk_column())).map(|(eid, _)| *eid).collect();
+            for eid in &removed {
+                player.entity_prev_positions.remove(eid);
+            }
+            (removed, EntityChanges::other())
+        }).await else { return };
+        if removed.is_empty() {
+            return;
+        }
+        self.send_packet(PlayClientbound::RemoveEntities { entity_ids: Array::from(removed.into_iter().map(|eid| VarInt(eid as i32)).collect::<Vec<_>>()) }).await;
+    }
+
+    pub(super) async fn on_equipment_change(&self, eid: Eid, equipment: Vec<(EquipmentSlot, Slot)>) {
+        self.send_packet(equipment_packet(eid, equipment)).await;
+    }
+
+    /// Tells other players about changes to what the player holds and wears.
+    pub(super) async fn update_equipment(&self) {
+        let Some(changed) = self.mutate(|player| {
+            let equipment = player.inventory.equipment();
+            let changed: Vec<(EquipmentSlot, Slot)> = equipment.iter().zip(&player.equipment).filter(|((_, new), (_, old))| new != old).map(|(change, _)| change.clone()).collect();
+            player.equipment = equipment;
+            (changed, EntityChanges::other())
+        }).await else { return };
+        if !changed.is_empty() {
+            self.world.notify_equipment(self.eid, changed).await;
+        }
+    }
+}
@@ -0,0 +1,653 @@
+use super::*;
+
+/// What a click in a window does, decoded from the `mode` and `button` of [PlayServerbound::ClickWindowSlot].
+/// See [the wiki](https://wiki.vg/Protocol#Click_Container) for the meaning of each mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickAction {
+    /// Left or right click on a slot
+    Pickup { slot: usize, right: bool },
+    /// Left or right click outside of the window
+    PickupOutside { right: bool },
+    /// Shift click
+    QuickMove { slot: usize },
+    /// Number keys (0 to 8) or the offhand key (40) while hovering a slot
+    Swap { slot: usize, button: u8 },
+    /// Middle click, only in creative mode
+    Clone { slot: usize },
+    /// Drop key while hovering a slot
+    Throw { slot: usize, whole_stack: bool },
+    StartDrag { kind: DragKind },
+    AddDragSlot { kind: DragKind, slot: usize },
+    EndDrag { kind: DragKind },
+    /// Double click
+    PickupAll { slot: usize },
+}
+
+/// How a drag distributes the cursor stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragKind {
+    /// Splits the stack evenly
+    Left,
+    /// Places one item per slot
+    Right,
+    /// Places a full stack per slot (creative only)
+    Middle,
+}
+
+/// A drag that has started but hasn't ended yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drag {
+    kind: DragKind,
+    slots: Vec<usize>,
+}
+
+impl ClickAction {
+    pub fn parse(slot: i16, button: i8, mode: i32) -> Option<ClickAction> {
+        let slot_index = usize::try_from(slot).ok();
+        let drag_kind = |button: i8| match button / 4 {
+            0 => Some(DragKind::Left),
+            1 => Some(DragKind::Right),
+            2 => Some(DragKind::Middle),
+            _ => None,
+        };
+        Some(match (mode, button) {
+            (0, 0 | 1) if slot == -999 => ClickAction::PickupOutside { right: button == 1 },
+            (0, 0 | 1) => ClickAction::Pickup { slot: slot_index?, right: button == 1 },
+            (1, 0 | 1) => ClickAction::QuickMove { slot: slot_index? },
+            (2, 0..=8 | 40) => ClickAction::Swap { slot: slot_index?, button: button as u8 },
+            (3, 2) => ClickAction::Clone { slot: slot_index? },
+            (4, 0 | 1) => ClickAction::Throw { slot: slot_index?, whole_stack: button == 1 },
+            (5, 0 | 4 | 8) => ClickAction::StartDrag { kind: drag_kind(button)? },
+            (5, 1 | 5 | 9) => ClickAction::AddDragSlot { kind: drag_kind(button)?, slot: slot_index? },
+            (5, 2 | 6 | 10) => ClickAction::EndDrag { kind: drag_kind(button)? },
+            (6, 0) => ClickAction::PickupAll { slot: slot_index? },
+            _ => return None,
+        })
+    }
+}
+
+/// Describes the slots of a window, numbered as in the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowLayout {
+    /// The inventory of the player (window 0)
+    PlayerInventory,
+    /// A chest, barrel, shulker box, dispenser, dropper or hopper with that number of slots
+    Container { size: usize },
+    /// A furnace, smoker or blast furnace: input, fuel and output
+    Furnace,
+    /// The output and the 3x3 grid of a crafting table
+    CraftingTable,
+}
+
+impl WindowLayout {
+    /// The number of slots that come before the main inventory and the hotbar of the player.
+    /// The player inventory window doesn't follow this layout.
+    pub fn container_size(&self) -> usize {
+        match self {
+            WindowLayout::PlayerInventory => 0,
+            WindowLayout::Container { size } => *size,
+            WindowLayout::Furnace => 3,
+            WindowLayout::CraftingTable => 10,
+        }
+    }
+
+    pub fn slot_count(&self) -> usize {
+        match self {
+            WindowLayout::PlayerInventory => PlayerInventory::SLOT_COUNT,
+            _ => self.container_size() + 36,
+        }
+    }
+
+    /// Output slots can only be emptied
+    pub fn is_output(&self, slot: usize) -> bool {
+        match self {
+            WindowLayout::PlayerInventory | WindowLayout::CraftingTable => slot == 0,
+            WindowLayout::Furnace => slot == 2,
+            WindowLayout::Container { .. } => false,
+        }
+    }
+
+    /// The first slot and the width of the crafting grid, whose output is slot 0
+    pub fn crafting_grid(&self) -> Option<(usize, usize)> {
+        match self {
+            WindowLayout::PlayerInventory => Some((1, 2)),
+            WindowLayout::CraftingTable => Some((1, 3)),
+            _ => None,
+        }
+    }
+
+    fn is_crafting_output(&self, slot: usize) -> bool {
+        slot == 0 && self.crafting_grid().is_some()
+    }
+
+    /// Returns the window slot of a hotbar slot (0 to 8), or of the offhand (40)
+    pub fn hotbar_slot(&self, button: u8) -> Option<usize> {
+        match (self, button) {
+            (WindowLayout::PlayerInventory, 0..=8) => Some(PlayerInventory::HOTBAR_START + button as usize),
+            (WindowLayout::PlayerInventory, 40) => Some(PlayerInventory::OFFHAND),
+            (WindowLayout::PlayerInventory, _) => None,
+            (_, 0..=8) => Some(self.container_size() + 27 + button as usize),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the item can be put in the slot
+    pub fn accepts(&self, slot: usize, item: &SlotItem) -> bool {
+        if self.is_output(slot) {
+            return false;
+        }
+        match self {
+            WindowLayout::PlayerInventory => match armor_slot(item.item_id) {
+                Some(armor_slot) => !(5..=8).contains(&slot) || slot == armor_slot,
+                None => !(5..=8).contains(&slot),
+            }
+            WindowLayout::Furnace if slot == 1 => fuel_ticks(item.item_id).is_some() || item.item_id == Item::Bucket,
+            _ => true,
+        }
+    }
+
+    /// Returns the maximum number of items the slot can hold
+    pub fn slot_capacity(&self, slot: usize, item: &SlotItem) -> i8 {
+        match self {
+            WindowLayout::PlayerInventory if (5..=8).contains(&slot) => 1,
+            _ => item.item_id.max_stack_size() as i8,
+        }
+    }
+
+    /// Returns the slots where a shift click puts an item, by order of preference
+    pub fn quick_move_targets(&self, slot: usize, item: &SlotItem) -> Vec<usize> {
+        match self {
+            WindowLayout::PlayerInventory => {
+                let mut targets = Vec::new();
+                if let Some(armor_slot) = armor_slot(item.item_id) {
+                    if !(5..=8).contains(&slot) {
+                        targets.push(armor_slot);
+                    }
+                }
+                match slot {
+                    9..=35 => targets.extend(36..45),
+                    36..=44 => targets.extend(9..36),
+                    _ => targets.extend(9..45),
+                }
+                targets
+            }
+            // Items leaving the container fill the hotbar first, from its end
+            _ if slot < self.container_size() => (self.container_size()..self.slot_count()).rev().collect(),
+            WindowLayout::Container { size } => (0..*size).collect(),
+            WindowLayout::Furnace if smelting_result(BlockEntityType::Furnace, item.item_id).is_some() => vec![0],
+            WindowLayout::Furnace if fuel_ticks(item.item_id).is_some() => vec![1],
+            _ => {
+                let main_end = self.container_size() + 27;
+                match slot < main_end {
+                    true => (main_end..self.slot_count()).collect(),
+                    false => (self.container_size()..main_end).collect(),
+                }
+            }
+        }
+    }
+}
+
+/// The armor slot of the player inventory where an item can be equipped
+fn armor_slot(item: Item) -> Option<usize> {
+    let text_id = item.text_id();
+    if text_id.ends_with("_helmet") || text_id.ends_with("_head") || text_id.ends_with("_skull") || text_id == "carved_pumpkin" {
+        Some(5)
+    } else if text_id.ends_with("_chestplate") || text_id == "elytra" {
+        Some(6)
+    } else if text_id.ends_with("_leggings") {
+        Some(7)
+    } else if text_id.ends_with("_boots") {
+        Some(8)
+    } else {
+        None
+    }
+}
+
+/// Returns true if two items can be in the same stack
+pub fn can_stack(a: &SlotItem, b: &SlotItem) -> bool {
+    a.item_id == b.item_id && a.nbt_data == b.nbt_data
+}
+
+fn max_stack_size(item: &SlotItem) -> i8 {
+    item.item_id.max_stack_size() as i8
+}
+
+fn with_count(item: &SlotItem, item_count: i8) -> Slot {
+    match item_count > 0 {
+        true => Slot { item: Some(SlotItem { item_count, ..item.clone() }) },
+        false => Slot { item: None },
+    }
+}
+
+/// Moves up to `count` items from a stack to a slot, if they can be stacked there.
+/// Returns the number of items moved.
+fn merge_into(layout: &WindowLayout, target_index: usize, target: &mut Slot, item: &SlotItem, count: i8) -> i8 {
+    if !layout.accepts(target_index, item) {
+        return 0;
+    }
+    let max = layout.slot_capacity(target_index, item);
+    let present = match &target.item {
+        Some(target_item) if can_stack(target_item, item) => target_item.item_count,
+        Some(_) => return 0,
+        None => 0,
+    };
+    let moved = count.min(max - present).max(0);
+    if moved > 0 {
+        *target = with_count(item, present + moved);
+    }
+    moved
+}
+
+/// The slots of a window, the item held by the cursor and an ongoing drag.
+pub struct WindowState<'a> {
+    pub layout: WindowLayout,
+    pub slots: &'a mut [Slot],
+    pub cursor: &'a mut Slot,
+    pub drag: &'a mut Option<Drag>,
+    pub creative: bool,
+}
+
+impl<'a> WindowState<'a> {
+    /// Applies a click and returns the items thrown out of the window.
+    pub fn click(&mut self, action: ClickAction) -> Vec<SlotItem> {
+        let mut thrown = Vec::new();
+        if self.drag.is_some() && !matches!(action, ClickAction::AddDragSlot { .. } | ClickAction::EndDrag { .. }) {
+            *self.drag = None;
+        }
+        if let ClickAction::Pickup { slot, .. } | ClickAction::QuickMove { slot } | ClickAction::Swap { slot, .. } | ClickAction::Clone { slot }
+            | ClickAction::Throw { slot, .. } | ClickAction::AddDragSlot { slot, .. } | ClickAction::PickupAll { slot } = action
+        {
+            if slot >= self.slots.len() {
+                return thrown;
+            }
+        }
+        let output_before = self.layout.crafting_grid().map(|_| self.slots[0].clone());
+
+        match action {
+            ClickAction::Pickup { slot, right } => self.pickup(slot, right),
+            ClickAction::PickupOutside { right } => {
+                if let Some(cursor_item) = self.cursor.item.clone() {
+                    let count = if right { 1 } else { cursor_item.item_count };
+                    *self.cursor = with_count(&cursor_item, cursor_item.item_count - count);
+                    thrown.push(SlotItem { item_count: count, ..cursor_item });
+                }
+            }
+            ClickAction::QuickMove { slot } => self.quick_move(slot),
+            ClickAction::Swap { slot, button } => {
+                let Some(hotbar_slot) = self.layout.hotbar_slot(button) else { return thrown };
+                if hotbar_slot >= self.slots.len() || hotbar_slot == slot {
+                    return thrown;
+                }
+                let allowed = |index: usize, item: &Option<SlotItem>| item.as_ref().is_none_or(|item| self.layout.accepts(index, item));
+                if allowed(slot, &self.slots[hotbar_slot].item) && allowed(hotbar_slot, &self.slots[slot].item) {
+                    self.slots.swap(slot, hotbar_slot);
+                }
+            }
+            ClickAction::Clone { slot } => {
+                if self.creative && self.cursor.item.is_none() {
+                    if let Some(item) = &self.slots[slot].item {
+                        *self.cursor = with_count(item, max_stack_size(item));
+                    }
+                }
+            }
+            ClickAction::Throw { slot, whole_stack } => {
+                if self.cursor.item.is_some() {
+                    return thrown;
+                }
+                if let Some(item) = self.slots[slot].item.clone() {
+                    let count = if whole_stack || self.layout.is_crafting_output(slot) { item.item_count } else { 1 };
+                    self.slots[slot] = with_count(&item, item.item_count - count);
+                    thrown.push(SlotItem { item_count: count, ..item });
+                }
+            }
+            ClickAction::StartDrag { kind } => {
+                if self.cursor.item.is_some() && (kind != DragKind::Middle || self.creative) {
+                    *self.drag = Some(Drag { kind, slots: Vec::new() });
+                }
+            }
+            ClickAction::AddDragSlot { kind, slot } => {
+                let Some(cursor_item) = &self.cursor.item else { return thrown };
+                let Some(drag) = self.drag.as_mut().filter(|drag| drag.kind == kind) else { return thrown };
+                let compatible = self.slots[slot].item.as_ref().is_none_or(|item| can_stack(item, cursor_item));
+                if compatible && self.layout.accepts(slot, cursor_item) && !drag.slots.contains(&slot) {
+                    drag.slots.push(slot);
+                }
+            }
+            ClickAction::EndDrag { kind } => {
+                let Some(drag) = self.drag.take().filter(|drag| drag.kind == kind) else { return thrown };
+                self.end_drag(drag);
+            }
+            ClickAction::PickupAll { slot } => self.pickup_all(slot),
+        }
+        if let Some(output_before) = output_before {
+            self.update_crafting(output_before, action);
+        }
+        thrown
+    }
+
+    /// Consumes the ingredients when the crafting output was taken, and shows the new output.
+    fn update_crafting(&mut self, output_before: Slot, action: ClickAction) {
+        let Some((grid_start, width)) = self.layout.crafting_grid() else { return };
+        let grid = grid_start..grid_start + width * width;
+        if output_before.item.is_some() && self.slots[0] != output_before {
+            consume_ingredients(&mut self.slots[grid.clone()]);
+
+            // Shift clicking crafts as many times as possible
+            if action == (ClickAction::QuickMove { slot: 0 }) {
+                while let Some(result) = craft(&self.slots[grid.clone()], width).filter(|result| output_before.item.as_ref() == Some(result)) {
+                    self.slots[0] = Slot { item: Some(result) };
+                    self.quick_move(0);
+                    if self.slots[0].item.is_some() {
+                        break;
+                    }
+                    consume_ingredients(&mut self.slots[grid.clone()]);
+                }
+            }
+        }
+        self.slots[0] = Slot { item: craft(&self.slots[grid], width) };
+    }
+
+    fn pickup(&mut self, slot: usize, right: bool) {
+        let slot_item = self.slots[slot].item.clone();
+        let cursor_item = self.cursor.item.clone();
+        match (slot_item, cursor_item) {
+            (None, None) => (),
+            // Take items
+            (Some(slot_item), None) => {
+                let taken = if right && !self.layout.is_output(slot) { (slot_item.item_count + 1) / 2 } else { slot_item.item_count };
+                *self.cursor = with_count(&slot_item, taken);
+                self.slots[slot] = with_count(&slot_item, slot_item.item_count - taken);
+            }
+            // Take the output of a crafting slot while holding the same item
+            (Some(slot_item), Some(cursor_item)) if self.layout.is_output(slot) => {
+                if can_stack(&slot_item, &cursor_item) && cursor_item.item_count + slot_item.item_count <= max_stack_size(&cursor_item) {
+                    *self.cursor = with_count(&cursor_item, cursor_item.item_count + slot_item.item_count);
+                    self.slots[slot] = Slot::default();
+                }
+            }
+            // Put items
+            (slot_item, Some(cursor_item)) if slot_item.as_ref().is_none_or(|slot_item| can_stack(slot_item, &cursor_item)) => {
+                let count = if right { 1 } else { cursor_item.item_count };
+                let moved = merge_into(&self.layout, slot, &mut self.slots[slot], &cursor_item, count);
+                *self.cursor = with_count(&cursor_item, cursor_item.item_count - moved);
+            }
+            // Swap different items
+            (Some(slot_item), Some(cursor_item)) => {
+                if self.layout.accepts(slot, &cursor_item) && cursor_item.item_count <= self.layout.slot_capacity(slot, &cursor_item) {
+                    self.slots[slot] = Slot { item: Some(cursor_item) };
+                    *self.cursor = Slot { item: Some(slot_item) };
+                }
+            }
+            (None, Some(_)) => unreachable!(),
+        }
+    }
+
+    fn quick_move(&mut self, slot: usize) {
+        let Some(item) = self.slots[slot].item.clone() else { return };
+        let targets = self.layout.quick_move_targets(slot, &item);
+        let mut remaining = item.item_count;
+        // Crafting results are moved entirely or not at all
+        let before = self.layout.is_crafting_output(slot).then(|| self.slots.to_vec());
+
+        // Complete existing stacks first, then fill empty slots
+        for fill_empty in [false, true] {
+            for &target in &targets {
+                if remaining == 0 || target >= self.slots.len() || target == slot {
+                    continue;
+                }
+                if self.slots[target].item.is_none() != fill_empty {
+                    continue;
+                }
+                remaining -= merge_into(&self.layout, target, &mut self.slots[target], &item, remaining);
+            }
+        }
+        if let (Some(before), true) = (before, remaining > 0) {
+            self.slots.clone_from_slice(&before);
+            return;
+        }
+        self.slots[slot] = with_count(&item, remaining);
+    }
+
+    fn end_drag(&mut self, drag: Drag) {
+        let Some(cursor_item) = self.cursor.item.clone() else { return };
+        if drag.slots.is_empty() {
+            return;
+        }
+        // Dragging over a single slot is a normal click
+        if drag.slots.len() == 1 && drag.kind != DragKind::Middle {
+            self.pickup(drag.slots[0], drag.kind == DragKind::Right);
+            return;
+        }
+
+        let per_slot = match drag.kind {
+            DragKind::Left => cursor_item.item_count / drag.slots.len() as i8,
+            DragKind::Right => 1,
+            DragKind::Middle => max_stack_size(&cursor_item),
+        };
+        let mut remaining = cursor_item.item_count;
+        for slot in drag.slots {
+            let count = match drag.kind {
+                DragKind::Middle => per_slot,
+                _ => per_slot.min(remaining),
+            };
+            let moved = merge_into(&self.layout, slot, &mut self.slots[slot], &cursor_item, count);
+            if drag.kind != DragKind::Middle {
+                remaining -= moved;
+            }
+        }
+        *self.cursor = with_count(&cursor_item, remaining);
+    }
+
+    fn pickup_all(&mut self, slot: usize) {
+        let Some(cursor_item) = self.cursor.item.clone().or_else(|| self.slots[slot].item.clone()) else { return };
+        let max = max_stack_size(&cursor_item);
+        let mut count = if self.cursor.item.is_some() { cursor_item.item_count } else { 0 };
+
+        // Incomplete stacks are taken before full ones
+        for take_full_stacks in [false, true] {
+            for index in 0..self.slots.len() {
+                if count >= max || self.layout.is_output(index) {
+                    continue;
+                }
+                let Some(item) = self.slots[index].item.clone() else { continue };
+                if !can_stack(&item, &cursor_item) || (item.item_count >= max_stack_size(&item)) != take_full_stacks {
+                    continue;
+                }
+                let taken = item.item_count.min(max - count);
+                count += taken;
+                self.slots[index] = with_count(&item, item.item_count - taken);
+            }
+        }
+        *self.cursor = with_count(&cursor_item, count);
+    }
+}
+
+pub(super) fn next_state_id(state_id: &mut i32) -> VarInt {
+    *state_id = (*state_id + 1) & 0x7fff;
+    VarInt(*state_id)
+}
+
+/// Returns the packet sending all the slots of a window
+pub fn content_packet(window_id: u8, state_id: &mut i32, slots: &[Slot], cursor: &Slot) -> PlayClientbound<'static> {
+    PlayClientbound::SetContainerContent {
+        window_id,
+        state_id: next_state_id(state_id),
+        slots: Array::from(slots.to_vec()),
+        carried_item: cursor.clone(),
+    }
+}
+
+/// Returns the packets updating the slots where the client is wrong
+pub fn sync_packets(window_id: i8, state_id: &mut i32, slots: &[Slot], cursor: &Slot, client_slots: &[Slot], client_cursor: &Slot) -> Vec<PlayClientbound<'static>> {
+    let mut packets = Vec::new();
+    for (index, (slot, client_slot)) in slots.iter().zip(client_slots).enumerate() {
+        if slot != client_slot {
+            packets.push(PlayClientbound::SetContainerSlot {
+                window_id,
+                state_id: next_state_id(state_id),
+                slot_index: index as i16,
+                slot_value: slot.clone(),
+            });
+        }
+    }
+    if cursor != client_cursor {
+        packets.push(PlayClientbound::SetContainerSlot {
+            window_id: -1,
+            state_id: next_state_id(state_id),
+            slot_index: -1,
+            slot_value: cursor.clone(),
+        });
+    }
+    packets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(item_id: Item, item_count: i8) -> Slot {
+        Slot { item: Some(SlotItem { item_id, item_count, nbt_data: NbtTag::Null }) }
+    }
+
+    fn click_in(layout: WindowLayout, slots: &mut [Slot], cursor: &mut Slot, drag: &mut Option<Drag>, slot: i16, button: i8, mode: i32) -> Vec<SlotItem> {
+        let action = ClickAction::parse(slot, button, mode).unwrap();
+        WindowState { layout, slots, cursor, drag, creative: false }.click(action)
+    }
+
+    fn click(slots: &mut [Slot], cursor: &mut Slot, drag: &mut Option<Drag>, slot: i16, button: i8, mode: i32) -> Vec<SlotItem> {
+        click_in(WindowLayout::PlayerInventory, slots, cursor, drag, slot, button, mode)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ClickAction::parse(-999, 0, 0), Some(ClickAction::PickupOutside { right: false }));
+        assert_eq!(ClickAction::parse(36, 1, 0), Some(ClickAction::Pickup { slot: 36, right: true }));
+        assert_eq!(ClickAction::parse(10, 40, 2), Some(ClickAction::Swap { slot: 10, button: 40 }));
+        assert_eq!(ClickAction::parse(-999, 4, 5), Some(ClickAction::StartDrag { kind: DragKind::Right }));
+        assert_eq!(ClickAction::parse(12, 3, 0), None);
+        assert_eq!(ClickAction::parse(-1, 0, 1), None);
+    }
+
+    #[test]
+    fn test_pickup() {
+        let mut slots = vec![Slot::default(); 46];
+        let (mut cursor, mut drag) = (Slot::default(), None);
+        slots[36] = stack(Item::Stone, 33);
+
+        // Right click takes half, rounded up
+        click(&mut slots, &mut cursor, &mut drag, 36, 1, 0);
+        assert_eq!((cursor.clone(), slots[36].clone()), (stack(Item::Stone, 17), stack(Item::Stone, 16)));
+
+        // Right click places one item
+        click(&mut slots, &mut cursor, &mut drag, 37, 1, 0);
+        assert_eq!((cursor.clone(), slots[37].clone()), (stack(Item::Stone, 16), stack(Item::Stone, 1)));
+
+        // Left click merges everything
+        click(&mut slots, &mut cursor, &mut drag, 36, 0, 0);
+        assert_eq!((cursor.clone(), slots[36].clone()), (Slot::default(), stack(Item::Stone, 32)));
+
+        // Different items are swapped
+        slots[9] = stack(Item::Dirt, 5);
+        click(&mut slots, &mut cursor, &mut drag, 9, 0, 0);
+        click(&mut slots, &mut cursor, &mut drag, 36, 0, 0);
+        assert_eq!((cursor.clone(), slots[36].clone()), (stack(Item::Stone, 32), stack(Item::Dirt, 5)));
+
+        // Items can't be put in the crafting output or in the wrong armor slot
+        click(&mut slots, &mut cursor, &mut drag, 0, 0, 0);
+        click(&mut slots, &mut cursor, &mut drag, 5, 0, 0);
+        assert_eq!((cursor.clone(), slots[0].clone(), slots[5].clone()), (stack(Item::Stone, 32), Slot::default(), Slot::default()));
+
+        // Throwing the cursor outside of the window
+        let thrown = click(&mut slots, &mut cursor, &mut drag, -999, 1, 0);
+        assert_eq!((thrown[0].item_count, cursor.clone()), (1, stack(Item::Stone, 31)));
+    }
+
+    #[test]
+    fn test_quick_move() {
+        let mut slots = vec![Slot::default(); 46];
+        let (mut cursor, mut drag) = (Slot::default(), None);
+        slots[38] = stack(Item::Stone, 60);
+        slots[12] = stack(Item::Stone, 50);
+        click(&mut slots, &mut cursor, &mut drag, 12, 0, 1);
+        assert_eq!((slots[38].clone(), slots[36].clone(), slots[12].clone()), (stack(Item::Stone, 64), stack(Item::Stone, 46), Slot::default()));
+
+        // Armor goes to its slot
+        slots[20] = stack(Item::IronHelmet, 1);
+        click(&mut slots, &mut cursor, &mut drag, 20, 0, 1);
+        assert_eq!(slots[5], stack(Item::IronHelmet, 1));
+    }
+
+    #[test]
+    fn test_swap_and_throw() {
+        let mut slots = vec![Slot::default(); 46];
+        let (mut cursor, mut drag) = (Slot::default(), None);
+        slots[9] = stack(Item::Dirt, 10);
+        click(&mut slots, &mut cursor, &mut drag, 9, 2, 2);
+        assert_eq!((slots[9].clone(), slots[38].clone()), (Slot::default(), stack(Item::Dirt, 10)));
+        click(&mut slots, &mut cursor, &mut drag, 38, 40, 2);
+        assert_eq!(slots[45], stack(Item::Dirt, 10));
+
+        let thrown = click(&mut slots, &mut cursor, &mut drag, 45, 0, 4);
+        assert_eq!((thrown[0].item_count, slots[45].clone()), (1, stack(Item::Dirt, 9)));
+        let thrown = click(&mut slots, &mut cursor, &mut drag, 45, 1, 4);
+        assert_eq!((thrown[0].item_count, slots[45].clone()), (9, Slot::default()));
+    }
+
+    #[test]
+    fn test_drag() {
+        let mut slots = vec![Slot::default(); 46];
+        let (mut cursor, mut drag) = (stack(Item::Stone, 10), None);
+        slots[11] = stack(Item::Stone, 62);
+        slots[12] = stack(Item::Dirt, 1);
+        click(&mut slots, &mut cursor, &mut drag, -999, 0, 5);
+        for slot in [9, 10, 11, 12] {
+            click(&mut slots, &mut cursor, &mut drag, slot, 1, 5);
+        }
+        click(&mut slots, &mut cursor, &mut drag, -999, 2, 5);
+        // The dirt slot is ignored and the stack is split in 3
+        assert_eq!((slots[9].clone(), slots[10].clone(), slots[11].clone()), (stack(Item::Stone, 3), stack(Item::Stone, 3), stack(Item::Stone, 64)));
+        assert_eq!(cursor, stack(Item::Stone, 2));
+
+        click(&mut slots, &mut cursor, &mut drag, -999, 4, 5);
+        click(&mut slots, &mut cursor, &mut drag, 13, 5, 5);
+        click(&mut slots, &mut cursor, &mut drag, 14, 5, 5);
+        click(&mut slots, &mut cursor, &mut drag, -999, 6, 5);
+        assert_eq!((slots[13].clone(), slots[14].clone(), cursor.clone()), (stack(Item::Stone, 1), stack(Item::Stone, 1), Slot::default()));
+    }
+
+    #[test]
+    fn test_pickup_all() {
+        let mut slots = vec![Slot::default(); 46];
+        let (mut cursor, mut drag) = (stack(Item::Stone, 10), None);
+        slots[9] = stack(Item::Stone, 64);
+        slots[20] = stack(Item::Stone, 30);
+        slots[36] = stack(Item::Stone, 30);
+        click(&mut slots, &mut cursor, &mut drag, 40, 0, 6);
+        assert_eq!(cursor, stack(Item::Stone, 64));
+        assert_eq!((slots[9].clone(), slots[20].clone(), slots[36].clone()), (stack(Item::Stone, 64), Slot::default(), stack(Item::Stone, 6)));
+    }
+
+    #[test]
+    fn test_crafting() {
+        let layout = WindowLayout::CraftingTable;
+        let mut slots = vec![Slot::default(); layout.slot_count()];
+        let (mut cursor, mut drag) = (stack(Item::OakPlanks, 3), None);
+
+        // The output shows up when the grid matches a recipe
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 2, 1, 0);
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 5, 1, 0);
+        assert_eq!(slots[0], stack(Item::Stick, 4));
+
+        // Taking it consumes the ingredients
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 20, 0, 0);
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 0, 0, 0);
+        assert_eq!((cursor.clone(), slots[0].clone(), slots[2].clone()), (stack(Item::Stick, 4), Slot::default(), Slot::default()));
+
+        // Shift clicking crafts everything
+        slots[2] = stack(Item::OakPlanks, 10);
+        slots[5] = stack(Item::OakPlanks, 10);
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 30, 0, 0);
+        click_in(layout, &mut slots, &mut cursor, &mut drag, 0, 0, 1);
+        assert_eq!((slots[30].clone(), slots[2].clone(), slots[5].clone()), (stack(Item::Stick, 44), Slot::default(), Slot::default()));
+    }
+}
@@ -2,7 +2,29 @@ use crate::CollisionShape;
 
 use super::*;
 
-pub async fn newton_task<T: EntityDescendant>(h: Handler<T>, mut server_msg_rcvr: BroadcastReceiver<ServerMessage>) where AnyEntity: TryAsEntityRef<T> {
+/// How much of their velocity entities keep every tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Friction {
+    /// Kept on every axis in the air, and vertically on the ground
+    pub air: f64,
+    /// Kept horizontally on the ground
+    pub ground: f64,
+}
+
+impl Friction {
+    /// The friction of item entities, on blocks that aren't slippery
+    pub const ITEM: Friction = Friction { air: 0.98, ground: 0.6 * 0.98 };
+}
+
+/// Velocities smaller than this are rounded to zero when slowed down, like on vanilla servers
+const MIN_VELOCITY: f64 = 0.003;
+
+pub async fn newton_task<T: EntityDescendant>(h: Handler<T>, ticks: TickReceiver) where AnyEntity: TryAsEntityRef<T> {
+    newton_task_with_friction(h, ticks, None).await
+}
+
+/// Moves the entity like [newton_task], also slowing it down with `friction`.
+pub async fn newton_task_with_friction<T: EntityDescendant>(h: Handler<T>, mut ticks: TickReceiver, friction: Option<Friction>) where AnyEntity: TryAsEntityRef<T> {
     let Some(network_entity) = h.observe_any(|any_entity| any_entity.to_network()).await else { return; };
     
     let (width, height) = match network_entity {
@@ -13,13 +35,7 @@ pub async fn newton_task<T: EntityDescendant>(h: Handler<T>, mut server_msg_rcvr
         }
     };
 
-    loop {
-        let Ok(msg) = server_msg_rcvr.recv().await else {continue};
-
-        if !matches!(&msg, &ServerMessage::Tick(_)) {
-            continue;
-        }
-
+    while ticks.next().await.is_some() {
         // Get data from entity
         let Some((mut position, mut velocity)) = h.observe_any(|any_entity| {
             let entity = any_entity.as_entity();
@@ -38,7 +54,10 @@ pub async fn newton_task<T: EntityDescendant>(h: Handler<T>, mut server_msg_rcvr
             y2: position.y + height,
             z2: position.z + width/2.0,
         };
+        let falling = new_velocity.y < 0.0;
+        let requested_y = new_velocity.y;
         let new_velocity = h.world.try_move(&bounding_box, &new_velocity).await;
+        let on_ground = falling && new_velocity.y != requested_y;
         if velocity.x != new_velocity.x {
             velocity.x = 0.0;
             changes += EntityChanges::velocity();
@@ -56,8 +75,16 @@ pub async fn newton_task<T: EntityDescendant>(h: Handler<T>, mut server_msg_rcvr
             position += new_velocity;
         }
 
-        // TODO(feat): Apply air resistance to x and z velocity
         // Keep in mind that velocity shouldn't flicker when constantly kept up by another task but slowed down in this task
+        if let Some(friction) = friction {
+            let horizontal = if on_ground { friction.ground } else { friction.air };
+            let slow_down = |value: f64, factor: f64| if (value * factor).abs() < MIN_VELOCITY { 0.0 } else { value * factor };
+            let slowed = Translation { x: slow_down(velocity.x, horizontal), y: slow_down(velocity.y, friction.air), z: slow_down(velocity.z, horizontal) };
+            if slowed != velocity {
+                velocity = slowed;
+                changes += EntityChanges::velocity();
+            }
+        }
 
         // Mutate entity
         // TODO(correctness): Before modifying entity values, we should ensure the original values we based the changes on are still the same
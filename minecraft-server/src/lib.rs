@@ -0,0 +1,16 @@
+//! A Minecraft server that can run as a binary or be embedded, in integration tests for instance.
+//! See [prelude::ServerBuilder].
+
+mod player_handler;
+mod server_behavior;
+pub mod prelude;
+mod world;
+mod entities;
+mod commands;
+mod config;
+mod console;
+mod metrics_server;
+mod rcon;
+mod query;
+
+use crate::prelude::*;
@@ -1,12 +1,4 @@
-#![allow(clippy::uninit_vec)]
-
-mod player_handler;
-mod server_behavior;
-mod prelude;
-mod world;
-mod entities;
-
-use crate::prelude::*;
+use minecraft_server::prelude::*;
 
 
 struct ServerFuture {
@@ -21,12 +13,41 @@ impl std::future::Future for ServerFuture {
     }
 }
 
+/// Waits for Ctrl-C, or for SIGTERM on unix.
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    let console = Console::init();
+    if let Err(e) = &console {
+        env_logger::init();
+        warn!("Commands can't be typed in the terminal: {e}");
+    }
 
     let server = ServerBehavior::init().await;
+    let world = server.world();
+    let console_thread = console.ok().and_then(|console| console.start(world));
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        world.shutdown("Server closed");
+    });
     let fut = ServerFuture { server };
 
     fut.await;
+    if let Some(console_thread) = console_thread {
+        let _ = console_thread.join();
+    }
 }
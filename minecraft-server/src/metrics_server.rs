@@ -0,0 +1,57 @@
+use tokio::net::TcpListener;
+use crate::prelude::*;
+
+/// The largest request head accepted by the metrics endpoint
+const MAX_REQUEST_SIZE: usize = 8192;
+/// How long clients have to send their request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads the head of an HTTP request, up to the empty line.
+async fn read_request(stream: &mut TcpStream) -> Option<String> {
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") && !request.ends_with(b"\n\n") {
+        if request.len() >= MAX_REQUEST_SIZE {
+            return None;
+        }
+        let mut buffer = [0; 1024];
+        let read = stream.read(&mut buffer[..(MAX_REQUEST_SIZE - request.len()).min(1024)]).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    String::from_utf8(request).ok()
+}
+
+async fn handle_request(mut stream: TcpStream, world: &'static World) {
+    let Ok(Some(request)) = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await else { return };
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (request_line.next(), request_line.next());
+
+    let response = match (method, path) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = world.render_metrics().await;
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+        }
+        _ => String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    };
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Serves the metrics of the world at `GET /metrics` until the server shuts down.
+pub async fn serve_metrics(listener: TcpListener, world: &'static World) {
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Metrics listener couldn't listen anymore: {e}");
+                    return;
+                }
+            },
+            _ = world.shutdown_requested() => return,
+        };
+        tokio::spawn(handle_request(stream, world));
+    }
+}
@@ -1,25 +1,46 @@
 use super::*;
 
+/// How long a client has to log in and download the world around it
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub async fn handle_connection(
     mut stream: TcpStream,
     addr: SocketAddr,
+    permit: ConnectionPermit,
     server_msg_rcvr: BroadcastReceiver<ServerMessage>,
     world: &'static World,
 ) -> Result<(), ()> {
+    // Clients older than 1.7 start with a 0xFE byte instead of a handshake packet
+    let mut first_byte = [0];
+    match tokio::time::timeout(READ_TIMEOUT, stream.peek(&mut first_byte)).await {
+        Ok(Ok(1..)) => (),
+        _ => return Err(()),
+    }
+    if first_byte[0] == 0xFE {
+        return legacy_status(&mut stream, world).await;
+    }
+
     // Receive handshake
     let packet = receive_packet(&mut stream).await?;
-    let HandshakeServerbound::Hello { protocol_version, server_address, server_port, next_state } = HandshakeServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let HandshakeServerbound::Hello { protocol_version, server_address, server_port, next_state } = parse_packet(&mut stream, ConnectionPhase::Handshake, &packet).await?;
     match next_state {
         ConnectionState::Login => {
-            let player_info = login(&mut stream, addr).await?;
-            let (player_info, change_receiver) = handshake(&mut stream, player_info, world).await?;
-            let uuid = player_info.uuid;
-            let eid = Player::spawn_player(world, stream, player_info, server_msg_rcvr, change_receiver).await;
+            let joined = tokio::time::timeout(LOGIN_TIMEOUT, async {
+                let player_info = login(&mut stream, addr, world).await?;
+                handshake(&mut stream, player_info, world).await
+            }).await;
+            let (player_info, change_receiver) = match joined {
+                Ok(result) => result?,
+                Err(_) => {
+                    debug!("{addr} took too long to log in");
+                    return Err(());
+                }
+            };
+            Player::spawn_player(world, stream, player_info, permit, server_msg_rcvr, change_receiver).await;
             Ok(())
         },
         ConnectionState::Status => {
-            status(&mut stream).await;
-            Ok(())
+            status(&mut stream, world).await
         },
         _ => {
             error!("Unexpected next state: {next_state:?}");
@@ -0,0 +1,137 @@
+//! Feeds random bytes to the server in every connection state, checking that nothing panics.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::net::TcpListener;
+use minecraft_protocol::components::blocks::{BlockFace, DiggingState};
+use super::{*, test_client::*};
+
+const ITERATIONS: usize = 20_000;
+const CONNECTIONS: usize = 100;
+
+fn random_bytes(rng: &mut StdRng, max_len: usize) -> Vec<u8> {
+    let len = rng.gen_range(0..=max_len);
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn fuzz_deserialization() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for i in 0..ITERATIONS {
+        let mut payload = random_bytes(&mut rng, 64);
+        // Starting with a valid packet id makes sure every variant gets exercised
+        if i % 2 == 0 {
+            payload.insert(0, (i / 2 % 0x40) as u8);
+        }
+        let _ = HandshakeServerbound::deserialize_uncompressed_minecraft_packet(&payload);
+        let _ = StatusServerbound::deserialize_uncompressed_minecraft_packet(&payload);
+        let _ = LoginServerbound::deserialize_uncompressed_minecraft_packet(&payload);
+        let _ = ConfigServerbound::deserialize_uncompressed_minecraft_packet(&payload);
+        let _ = PlayServerbound::deserialize_uncompressed_minecraft_packet(&payload);
+    }
+}
+
+/// Opens connections that send `prefix` followed by random bytes, and checks the connection handler never panics.
+async fn fuzz_connection(prefix: Vec<u8>, seed: u64) {
+    let world: &'static World = Box::leak(Box::new(World::new()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let limiter = ConnectionLimiter::new(CONNECTIONS, CONNECTIONS);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..CONNECTIONS {
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (stream, peer) = listener.accept().await.unwrap();
+        let permit = limiter.try_acquire(peer.ip()).unwrap();
+        let server = tokio::spawn(handle_connection(stream, peer, permit, broadcast_channel(100).1, world));
+
+        let mut data = prefix.clone();
+        data.extend(random_bytes(&mut rng, 256));
+        let _ = client.write_all(&data).await;
+        // Closing the connection makes incomplete frames fail immediately instead of timing out
+        let _ = client.shutdown().await;
+
+        assert!(server.await.is_ok(), "The connection handler panicked");
+    }
+}
+
+#[tokio::test]
+async fn fuzz_handshake() {
+    fuzz_connection(Vec::new(), 1).await;
+}
+
+#[tokio::test]
+async fn fuzz_status() {
+    fuzz_connection(hello(ConnectionState::Status), 2).await;
+}
+
+#[tokio::test]
+async fn fuzz_login() {
+    fuzz_connection(hello(ConnectionState::Login), 3).await;
+}
+
+#[tokio::test]
+async fn fuzz_configuration() {
+    let mut prefix = hello(ConnectionState::Login);
+    prefix.extend(frame(LoginServerbound::LoginStart { username: "fuzzer", player_uuid: 42 }));
+    prefix.extend(frame(LoginServerbound::LoginAcknowledged));
+    fuzz_connection(prefix, 4).await;
+}
+
+/// Valid play packets, that are mutated to reach deeper than random bytes would
+fn play_packets() -> Vec<Vec<u8>> {
+    let position = NetworkPosition { x: 1, y: 60, z: 1 };
+    let packets = [
+        PlayServerbound::KeepAlive { keep_alive_id: 0 },
+        PlayServerbound::SetPlayerPosition { x: 0.5, y: 61.0, z: 0.5, on_ground: true },
+        PlayServerbound::SetHeldItem { slot: 3 },
+        PlayServerbound::CloseWindow { window_id: 0 },
+        PlayServerbound::ChatCommand { command: "help", timestamp: 0, salt: 0, signatures: RawBytes { data: &[0, 0, 0, 0, 0] } },
+        PlayServerbound::CommandSuggestionsRequest { transaction_id: VarInt(1), text: "/ga" },
+        PlayServerbound::QueryBlockNbt { transaction_id: VarInt(2), position: position.clone() },
+        PlayServerbound::DigBlock { status: DiggingState::Started, location: position, face: BlockFace::Top, sequence: VarInt(3) },
+    ];
+    packets.into_iter().map(|packet| packet.serialize_minecraft_packet().unwrap()).collect()
+}
+
+/// Lets players join and send random, corrupted or truncated play packets, and checks their handler cleans up after them.
+/// Handlers that panic never remove their player from the list.
+#[tokio::test]
+async fn fuzz_play() {
+    let server = ServerBuilder::new().address(SocketAddr::from(([127, 0, 0, 1], 0))).start().await.unwrap();
+    let world = server.world();
+    let packets = play_packets();
+    let mut rng = StdRng::seed_from_u64(5);
+
+    for i in 0..CONNECTIONS / 4 {
+        let username = format!("fuzzer{i}");
+        let mut client = connect(server.local_addr(), &join_packets(&username, i as UUID)).await;
+        wait_for_player(world, &username).await;
+
+        let mut data = Vec::new();
+        for _ in 0..rng.gen_range(1..=20) {
+            let mut packet = packets[rng.gen_range(0..packets.len())].clone();
+            match rng.gen_range(0..3) {
+                0 => packet = random_bytes(&mut rng, 64),
+                1 => packet.truncate(rng.gen_range(0..packet.len())),
+                _ => {
+                    let index = rng.gen_range(0..packet.len());
+                    packet[index] = rng.gen();
+                }
+            }
+            data.extend(VarInt::from(packet.len()).serialize_minecraft_packet().unwrap());
+            data.extend(packet);
+        }
+        // A frame announcing more bytes than it has
+        if i % 2 == 0 {
+            let packet = random_bytes(&mut rng, 64);
+            data.extend(VarInt::from(packet.len() + 1).serialize_minecraft_packet().unwrap());
+            data.extend(packet);
+        }
+        let _ = client.write_all(&data).await;
+        let _ = client.shutdown().await;
+
+        wait_for_no_players(world).await;
+    }
+
+    server.stop().await;
+}
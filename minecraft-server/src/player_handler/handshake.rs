@@ -1,6 +1,8 @@
+use std::time::Instant;
 use super::*;
 
 pub struct PlayerInfo {
+    pub eid: Eid,
     pub addr: SocketAddr,
     pub username: String,
     pub uuid: u128,
@@ -12,19 +14,45 @@ pub struct PlayerInfo {
     pub main_hand: MainHand,
     pub enable_text_filtering: bool,
     pub allow_server_listing: bool,
+    /// The round-trip time measured during configuration
+    pub latency: Option<Duration>,
+    /// Where the player left off, or the defaults for new players
+    pub data: PlayerData,
+}
+
+/// Receives the next configuration packet, recording the keep-alives answered in the meantime.
+async fn receive_config_packet(stream: &mut TcpStream, keep_alive: &mut KeepAliveTracker) -> Result<Vec<u8>, ()> {
+    loop {
+        let packet = receive_packet(stream).await?;
+        match ConfigServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()) {
+            Ok(ConfigServerbound::KeepAlive { id }) => {
+                if keep_alive.received(id as u64, Instant::now()).is_none() {
+                    warn!("Received an unexpected keep-alive during configuration");
+                }
+            },
+            _ => return Ok(packet),
+        }
+    }
 }
 
 pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPlayerInfo, world: &'static World) -> Result<(PlayerInfo, MpscReceiver<WorldChange>), ()> {
     // Receive client informations
     let packet = receive_packet(stream).await?;
     debug!("Packet received");
-    let packet = ConfigServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let packet = parse_packet::<ConfigServerbound>(stream, ConnectionPhase::Configuration, &packet).await?;
     let ConfigServerbound::ClientInformations { locale, render_distance, chat_mode, chat_colors, displayed_skin_parts, main_hand, enable_text_filtering, allow_server_listing } = packet else {
         error!("Expected ClientInformation packet, got: {packet:?}");
         return Err(());
     };
     debug!("ClientInformation received");
 
+    // Send keep alive
+    let mut keep_alive = KeepAliveTracker::new();
+    let keep_alive_id: i64 = rand::random();
+    send_packet(stream, ConfigClientbound::KeepAlive { keep_alive_id }).await;
+    keep_alive.sent(keep_alive_id as u64, Instant::now());
+    debug!("KeepAlive sent");
+
     // Send server agent
     let server_agent = ConfigClientbound::PluginMessage {
         channel: "minecraft:brand",
@@ -59,8 +87,8 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     debug!("FinishConfiguration sent");
 
     // Receive finish configuration
-    let packet = receive_packet(stream).await?;
-    let packet = ConfigServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let packet = receive_config_packet(stream, &mut keep_alive).await?;
+    let packet = parse_packet::<ConfigServerbound>(stream, ConnectionPhase::Configuration, &packet).await?;
     let ConfigServerbound::FinishConfiguration = packet else {
         error!("Expected FinishConfiguration packet, got: {packet:?}");
         return Err(());
@@ -68,22 +96,24 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     debug!("FinishConfiguration received");
 
     // Send join game
-    let player_id: usize = 3429; // TODO dynamic attribution
+    let config = world.config();
+    let data = world.load_player_data(logged_in_player_info.uuid).await.unwrap_or_else(|| PlayerData::new(config));
+    let player_id = world.reserve_eid() as usize;
     let join_game = PlayClientbound::JoinGame {
         player_id: player_id as i32,
-        is_hardcore: false,
+        is_hardcore: config.hardcore,
         dimensions_names: Array::from(vec!["minecraft:overworld"]),
-        max_players: VarInt::from(1000),
-        render_distance: VarInt::from(12),
-        simulation_distance: VarInt::from(8),
+        max_players: VarInt::from(config.max_players as i32),
+        render_distance: VarInt::from(config.view_distance as i32),
+        simulation_distance: VarInt::from(config.simulation_distance as i32),
         reduced_debug_info: false,
         enable_respawn_screen: true,
         do_limited_crafting: false,
         dimension_type: "minecraft:overworld",
         dimension_name: "minecraft:overworld",
-        hashed_seed: 42,
-        gamemode: Gamemode::Creative,
-        previous_gamemode: PreviousGamemode::Creative,
+        hashed_seed: config.hashed_seed,
+        gamemode: data.gamemode.clone(),
+        previous_gamemode: PreviousGamemode::None,
         is_debug: false,
         is_flat: true,
         death_location: None,
@@ -94,7 +124,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     // Set difficulty
     let change_difficulty = PlayClientbound::ChangeDifficulty {
-        difficulty: Difficulty::Normal,
+        difficulty: config.difficulty,
         difficulty_locked: false
     };
     send_packet(stream, change_difficulty).await;
@@ -111,7 +141,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     // Set held item
     let held_item_change = PlayClientbound::SetHeldItem {
-        slot: 0 // TODO should be the same as when disconnected
+        slot: data.selected_hotbar_slot,
     };
     send_packet(stream, held_item_change).await;
     debug!("SetHeldItem sent");
@@ -126,20 +156,17 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     debug!("UpdateRecipes sent");
 
     // Entity event
+    // Tells the client its permission level, with statuses 24 to 28
+    let permission_level = world.permission_level(logged_in_player_info.uuid).await;
     let entity_event = PlayClientbound::EntityEvent {
         entity_id: player_id as i32,
-        entity_status: 28
+        entity_status: 24 + permission_level
     };
     send_packet(stream, entity_event).await;
     debug!("EntityEvent sent");
 
     // Declare commands
-    let declare_commands = PlayClientbound::DeclareCommands {
-        count: VarInt(0),
-        data: RawBytes {
-            data: &[0]
-        }
-    };
+    let declare_commands = world.commands().declare_commands(permission_level);
     send_packet(stream, declare_commands).await;
     debug!("DeclareCommands sent");
 
@@ -163,11 +190,11 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     // Spawn player
     let player_position = PlayClientbound::PlayerPositionAndLook {
-        x: 0.0,
-        y: 60.0,
-        z: 0.0,
-        yaw: 0.0,
-        pitch: 0.0,
+        x: data.position.x,
+        y: data.position.y,
+        z: data.position.z,
+        yaw: data.yaw,
+        pitch: data.pitch,
         flags: 0,
         teleport_id: VarInt(1),
     };
@@ -175,9 +202,10 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     debug!("PlayerPositionAndLook sent");
 
     // Send server metadata
+    let motd = ChatText::plain(config.motd.as_str()).to_json(false);
     let server_data = PlayClientbound::ServerData {
-        motd: "{\"text\":\"A Minecraft Server\"}",
-        icon: None,
+        motd: &motd,
+        icon: world.favicon().map(|favicon| Array::from(favicon.to_vec())),
         enforces_secure_chat: false,
     };
     send_packet(stream, server_data).await;
@@ -191,7 +219,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     send_packet(stream, spawn_message).await;
     debug!("SystemChatMessage sent");
 
-    // TODO: update players info (x2)
+    // The player list is sent once the player is spawned
 
     // Set entity metadata
     let mut entity_metadata = BTreeMap::new();
@@ -229,26 +257,28 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     // Set spawn position
     let set_spawn_position = PlayClientbound::SetSpawnPosition {
-        location: minecraft_protocol::packets::Position { x: 0, y: 70, z: 0 },
+        location: minecraft_protocol::packets::Position { x: config.spawn_x, y: config.spawn_y as i16, z: config.spawn_z },
         angle: 0.0,
     };
     send_packet(stream, set_spawn_position).await;
     debug!("SetSpawnPosition sent");
 
     // Set center chunk
+    let center = data.position.chunk_column();
     let set_center_chunk = PlayClientbound::SetCenterChunk {
-        chunk_x: VarInt(0), // TODO: should be the same as when disconnected
-        chunk_z: VarInt(0), // TODO: should be the same as when disconnected
+        chunk_x: VarInt(center.cx),
+        chunk_z: VarInt(center.cz),
     };
     send_packet(stream, set_center_chunk).await;
     debug!("SetCenterChunk sent");
 
     // Set inventory
+    let inventory = PlayerInventory::restore(&data.inventory, data.selected_hotbar_slot);
     let set_container_content = PlayClientbound::SetContainerContent {
         window_id: 0,
-        state_id: VarInt(1),
-        slots: Array::default(),
-        carried_item: Slot { item: None }
+        state_id: VarInt(inventory.state_id()),
+        slots: Array::from(inventory.slots().to_vec()),
+        carried_item: inventory.cursor().clone(),
     };
     send_packet(stream, set_container_content).await;
     debug!("SetContainerContent sent");
@@ -308,8 +338,8 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     let change_receiver = world.add_loader(logged_in_player_info.uuid).await;
     let mut loaded_chunks = HashSet::new();
-    for cx in -3..=3 {
-        for cz in -3..=3 {
+    for cx in center.cx-3..=center.cx+3 {
+        for cz in center.cz-3..=center.cz+3 {
             loaded_chunks.insert(ChunkColumnPosition { cx, cz });
         }
     }
@@ -319,8 +349,8 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     heightmaps.insert(String::from("MOTION_BLOCKING"), NbtTag::LongArray(vec![0; 37]));
     let heightmaps = NbtTag::Compound(heightmaps);
     
-    for cx in -3..=3 {
-        for cz in -3..=3 {
+    for cx in center.cx-3..=center.cx+3 {
+        for cz in center.cz-3..=center.cz+3 {
             let mut column = Vec::new();
             for cy in -4..20 {
                 let chunk = world.get_network_chunk(ChunkPosition { cx, cy, cz }).await.unwrap_or_else(|| {
@@ -340,7 +370,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
                     chunk_z: cz,
                     heightmaps: heightmaps.clone(),
                     data: Array::from(serialized.clone()),
-                    block_entities: Array::default(),
+                    block_entities: Array::from(world.get_network_block_entities(ChunkColumnPosition { cx, cz }).await),
                     sky_light_mask: Array::default(),
                     block_light_mask: Array::default(),
                     empty_sky_light_mask: Array::default(),
@@ -361,7 +391,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
 
     // Get chunk batch acknoledgement
     let packet = receive_packet(stream).await?;
-    let packet = PlayServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let packet = parse_packet::<PlayServerbound>(stream, ConnectionPhase::Play, &packet).await?;
     let PlayServerbound::ChunkBatchReceived { chunks_per_tick } = packet else {
         error!("Expected ChunkBatchAcknoledgement packet, got: {packet:?}");
         return Err(());
@@ -369,6 +399,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
     debug!("ChunkBatchAcknoledgement received");
 
     Ok((PlayerInfo {
+        eid: player_id as Eid,
         addr: logged_in_player_info.addr,
         username: logged_in_player_info.username,
         uuid: logged_in_player_info.uuid,
@@ -380,5 +411,7 @@ pub async fn handshake(stream: &mut TcpStream, logged_in_player_info: LoggedInPl
         main_hand,
         enable_text_filtering,
         allow_server_listing,
+        latency: keep_alive.latency(),
+        data,
     }, change_receiver))
 }
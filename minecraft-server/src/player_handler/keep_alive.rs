@@ -0,0 +1,89 @@
+use std::{collections::VecDeque, time::Instant};
+use super::*;
+
+/// Tracks the keep-alives sent to a client, to notice dead connections and measure latency.
+#[derive(Debug, Clone)]
+pub struct KeepAliveTracker {
+    /// The keep-alives the client hasn't answered yet, oldest first
+    outstanding: VecDeque<(u64, Instant)>,
+    latency: Option<Duration>,
+}
+
+impl KeepAliveTracker {
+    /// How long a client has to answer a keep-alive
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+    /// Keep-alives are sent every this many ticks
+    pub const INTERVAL_TICKS: usize = 20 * 10;
+
+    pub fn new() -> KeepAliveTracker {
+        KeepAliveTracker { outstanding: VecDeque::new(), latency: None }
+    }
+
+    /// Records that a keep-alive with this id was sent.
+    pub fn sent(&mut self, id: u64, now: Instant) {
+        self.outstanding.push_back((id, now));
+    }
+
+    /// Records the answer of the client, returning the updated latency.
+    /// Returns `None` if no keep-alive with this id is waiting for an answer.
+    pub fn received(&mut self, id: u64, now: Instant) -> Option<Duration> {
+        let index = self.outstanding.iter().position(|(sent_id, _)| *sent_id == id)?;
+        let (_, sent_at) = self.outstanding[index];
+        // Older keep-alives were answered out of order or lost, but the connection is alive
+        self.outstanding.drain(..=index);
+
+        let sample = now.saturating_duration_since(sent_at);
+        let latency = match self.latency {
+            Some(latency) => (latency * 3 + sample) / 4,
+            None => sample,
+        };
+        self.latency = Some(latency);
+        Some(latency)
+    }
+
+    /// Returns true if the client has left a keep-alive unanswered for longer than [KeepAliveTracker::TIMEOUT].
+    pub fn timed_out(&self, now: Instant) -> bool {
+        match self.outstanding.front() {
+            Some((_, sent_at)) => now.saturating_duration_since(*sent_at) > Self::TIMEOUT,
+            None => false,
+        }
+    }
+
+    /// Returns the rolling average of the round-trip time, once the client answered a keep-alive.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
+
+impl Default for KeepAliveTracker {
+    fn default() -> Self {
+        KeepAliveTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_alive_tracker() {
+        let start = Instant::now();
+        let mut tracker = KeepAliveTracker::new();
+        assert!(!tracker.timed_out(start + Duration::from_secs(60)));
+
+        tracker.sent(1, start);
+        assert_eq!(tracker.received(1, start + Duration::from_millis(100)), Some(Duration::from_millis(100)));
+        assert_eq!(tracker.received(1, start + Duration::from_millis(100)), None);
+        assert_eq!(tracker.received(2, start + Duration::from_millis(100)), None);
+
+        tracker.sent(2, start + Duration::from_secs(10));
+        tracker.sent(3, start + Duration::from_secs(20));
+        assert_eq!(tracker.received(3, start + Duration::from_millis(20_300)), Some(Duration::from_millis(150)));
+        assert!(!tracker.timed_out(start + Duration::from_secs(60)));
+
+        tracker.sent(4, start + Duration::from_secs(30));
+        assert!(!tracker.timed_out(start + Duration::from_secs(60)));
+        assert!(tracker.timed_out(start + Duration::from_millis(60_001)));
+        assert_eq!(tracker.latency(), Some(Duration::from_millis(150)));
+    }
+}
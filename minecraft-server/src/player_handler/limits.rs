@@ -0,0 +1,78 @@
+use std::{net::IpAddr, sync::Mutex};
+use super::*;
+
+/// Limits the number of simultaneous connections, in total and from each IP address.
+pub struct ConnectionLimiter {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    connections: Mutex<HashMap<IpAddr, usize>>,
+}
+
+/// Counts a connection towards the limits until it is dropped
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections: usize, max_connections_per_ip: usize) -> Arc<ConnectionLimiter> {
+        Arc::new(ConnectionLimiter {
+            max_connections,
+            max_connections_per_ip,
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns `None` if accepting a connection from this address would exceed a limit.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Option<ConnectionPermit> {
+        let mut connections = self.connections.lock().unwrap();
+        let total: usize = connections.values().sum();
+        let from_ip = connections.get(&ip).copied().unwrap_or(0);
+        if total >= self.max_connections || from_ip >= self.max_connections_per_ip {
+            return None;
+        }
+        connections.insert(ip, from_ip + 1);
+        Some(ConnectionPermit { limiter: Arc::clone(self), ip })
+    }
+
+    /// Returns the number of open connections.
+    pub fn connection_count(&self) -> usize {
+        self.connections.lock().unwrap().values().sum()
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut connections = self.limiter.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limiter() {
+        let limiter = ConnectionLimiter::new(3, 2);
+        let a = IpAddr::from([10, 0, 0, 1]);
+        let b = IpAddr::from([10, 0, 0, 2]);
+
+        let first = limiter.try_acquire(a).unwrap();
+        let second = limiter.try_acquire(a).unwrap();
+        assert!(limiter.try_acquire(a).is_none());
+        let third = limiter.try_acquire(b).unwrap();
+        assert!(limiter.try_acquire(b).is_none());
+        assert_eq!(limiter.connection_count(), 3);
+
+        drop(first);
+        assert!(limiter.try_acquire(b).is_some());
+        drop((second, third));
+        assert_eq!(limiter.connection_count(), 0);
+    }
+}
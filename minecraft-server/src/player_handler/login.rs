@@ -6,16 +6,21 @@ pub struct LoggedInPlayerInfo {
     pub(super) uuid: u128,
 }
 
-pub async fn login(stream: &mut TcpStream, addr: SocketAddr) -> Result<LoggedInPlayerInfo, ()> {
+pub async fn login(stream: &mut TcpStream, addr: SocketAddr, world: &'static World) -> Result<LoggedInPlayerInfo, ()> {
     // Receive login start
     let packet = receive_packet(stream).await?;
-    let packet = LoginServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let packet = parse_packet::<LoginServerbound>(stream, ConnectionPhase::Login, &packet).await?;
     let LoginServerbound::LoginStart{ username, player_uuid } = packet else {
         error!("Expected LoginStart packet, got: {packet:?}");
         return Err(());
     };
     debug!("LoginStart: {username}");
 
+    if world.players().await.len() >= world.config().max_players {
+        disconnect(stream, ConnectionPhase::Login, "The server is full").await;
+        return Err(());
+    }
+
     // TODO encryption
 
     // TODO compression
@@ -31,7 +36,7 @@ pub async fn login(stream: &mut TcpStream, addr: SocketAddr) -> Result<LoggedInP
 
     // Receive login acknowledged
     let packet = receive_packet(stream).await?;
-    let packet = LoginServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap();
+    let packet = parse_packet::<LoginServerbound>(stream, ConnectionPhase::Login, &packet).await?;
     let LoginServerbound::LoginAcknowledged = packet else {
         error!("Expected LoginAcknowledged packet, got: {packet:?}");
         return Err(());
@@ -4,11 +4,19 @@ mod connect;
 pub use connect::*;
 mod handshake;
 pub use handshake::*;
+mod keep_alive;
+pub use keep_alive::*;
+mod limits;
+pub use limits::*;
 mod login;
 pub use login::*;
 mod network;
 pub use network::*;
 mod status;
 pub use status::*;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+pub mod test_client;
 
 pub type Task = Pin<Box<dyn Future<Output = Result<(), ()>> + Send + Sync + 'static>>;
@@ -1,11 +1,29 @@
+use tokio::io::{AsyncRead, AsyncWrite};
 use super::*;
 
-pub async fn receive_packet(stream: &mut TcpStream) -> Result<Vec<u8>, ()> {
-    let mut length: Vec<u8> = Vec::with_capacity(2);
+/// The largest frame a client may send, which is the largest length that fits in a 3-byte VarInt
+pub const MAX_FRAME_SIZE: usize = 2_097_151;
+/// How long to wait for each packet before the player starts playing
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The state of a connection, which decides how a client is told it's disconnected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionPhase {
+    Handshake,
+    Status,
+    Login,
+    Configuration,
+    Play,
+}
+
+/// Reads a length-prefixed frame, rejecting frames larger than [MAX_FRAME_SIZE].
+async fn read_frame<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Vec<u8>, ()> {
+    let mut length: Vec<u8> = Vec::with_capacity(3);
 
     loop {
-        if length.len() >= 5 {
-            //return Err("length too long".into());
+        if length.len() >= 3 {
+            debug!("Frame length doesn't fit in 3 bytes");
+            return Err(());
         }
         let mut byte = [0];
         stream.read_exact(&mut byte).await.map_err(|_| ())?;
@@ -15,54 +33,109 @@ pub async fn receive_packet(stream: &mut TcpStream) -> Result<Vec<u8>, ()> {
         }
     }
 
-    let length = VarInt::deserialize_uncompressed_minecraft_packet(length.as_mut_slice()).map_err(|_| ())?;
+    let length = VarInt::deserialize_uncompressed_minecraft_packet(length.as_slice()).map_err(|_| ())?;
+    let length = usize::try_from(length.0).map_err(|_| ())?;
+    if length > MAX_FRAME_SIZE {
+        debug!("Frame is too large ({length} bytes)");
+        return Err(());
+    }
 
-    let mut data = Vec::with_capacity(length.0 as usize);
-    unsafe { data.set_len(length.0 as usize); }
+    let mut data = vec![0; length];
     stream.read_exact(&mut data).await.map_err(|_| ())?;
 
     Ok(data)
 }
 
-pub async fn receive_packet_split(stream: &mut OwnedReadHalf) -> Result<Vec<u8>, ()> {
-    let mut length: Vec<u8> = Vec::with_capacity(2);
+async fn write_frame<W: AsyncWrite + Unpin>(stream: &mut W, packet: &[u8]) -> Result<(), ()> {
+    let length = VarInt::from(packet.len());
+    stream.write_all(length.serialize_minecraft_packet().map_err(|_| ())?.as_slice()).await.map_err(|_| ())?;
+    stream.write_all(packet).await.map_err(|_| ())?;
+    stream.flush().await.map_err(|_| ())
+}
 
-    loop {
-        if length.len() >= 5 {
-            //return Err("length too long".into());
-        }
-        let mut byte = [0];
-        stream.read_exact(&mut byte).await.map_err(|_| ())?;
-        length.push(byte[0]);
-        if byte[0] < 0b1000_0000 {
-            break;
+/// Receives a packet before the player starts playing, giving up after [READ_TIMEOUT].
+pub async fn receive_packet(stream: &mut TcpStream) -> Result<Vec<u8>, ()> {
+    match tokio::time::timeout(READ_TIMEOUT, read_frame(stream)).await {
+        Ok(result) => result,
+        Err(_) => {
+            debug!("Timed out waiting for a packet");
+            Err(())
         }
     }
+}
 
-    let length = VarInt::deserialize_uncompressed_minecraft_packet(length.as_mut_slice()).map_err(|_| ())?;
-
-    let mut data = Vec::with_capacity(length.0 as usize);
-    unsafe { data.set_len(length.0 as usize); }
-    stream.read_exact(&mut data).await.map_err(|_| ())?;
-
-    Ok(data)
+pub async fn receive_packet_split(stream: &mut OwnedReadHalf) -> Result<Vec<u8>, ()> {
+    read_frame(stream).await
 }
 
 pub async fn send_packet_raw(stream: &mut TcpStream, packet: &[u8]) {
-    let length = VarInt::from(packet.len());
-    stream.write_all(length.serialize_minecraft_packet().unwrap().as_slice()).await.unwrap();
-    stream.write_all(packet).await.unwrap();
-    stream.flush().await.unwrap();
+    if write_frame(stream, packet).await.is_err() {
+        debug!("Failed to send packet");
+    }
 }
 
-pub async fn send_packet_raw_split(stream: &mut OwnedWriteHalf, packet: &[u8]) {
-    let length = VarInt::from(packet.len());
-    stream.write_all(length.serialize_minecraft_packet().unwrap().as_slice()).await.unwrap();
-    stream.write_all(packet).await.unwrap();
-    stream.flush().await.unwrap();
+pub async fn send_packet_raw_split(stream: &mut OwnedWriteHalf, packet: &[u8]) -> Result<(), ()> {
+    write_frame(stream, packet).await
 }
 
 pub async fn send_packet<'a, P: MinecraftPacketPart<'a>>(stream: &mut TcpStream, packet: P) {
     let packet = packet.serialize_minecraft_packet().unwrap();
     send_packet_raw(stream, packet.as_slice()).await;
 }
+
+/// Tells the client why it is being disconnected.
+/// Nothing is sent during the handshake and status phases, where clients don't expect it.
+pub async fn disconnect(stream: &mut TcpStream, phase: ConnectionPhase, reason: &str) {
+    let reason = ChatText::plain(reason).to_json(false);
+    match phase {
+        ConnectionPhase::Handshake | ConnectionPhase::Status => (),
+        ConnectionPhase::Login => send_packet(stream, LoginClientbound::Disconnect { reason: &reason }).await,
+        ConnectionPhase::Configuration => send_packet(stream, ConfigClientbound::Disconnect { reason: &reason }).await,
+        ConnectionPhase::Play => send_packet(stream, PlayClientbound::Disconnect { reason: &reason }).await,
+    }
+}
+
+/// Tells a playing client why it is being disconnected.
+pub async fn disconnect_split(stream: &mut OwnedWriteHalf, reason: &str) {
+    let reason = ChatText::plain(reason).to_json(false);
+    let packet = PlayClientbound::Disconnect { reason: &reason }.serialize_minecraft_packet().unwrap();
+    let _ = send_packet_raw_split(stream, &packet).await;
+}
+
+/// Deserializes a packet, disconnecting the client if it is malformed.
+pub async fn parse_packet<'a, P: MinecraftPacketPart<'a>>(stream: &mut TcpStream, phase: ConnectionPhase, data: &'a [u8]) -> Result<P, ()> {
+    match P::deserialize_uncompressed_minecraft_packet(data) {
+        Ok(packet) => Ok(packet),
+        Err(e) => {
+            warn!("Received a malformed packet during {phase:?}: {e}");
+            disconnect(stream, phase, &format!("Invalid packet: {e}")).await;
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let mut input: &[u8] = &[3, 1, 2, 3, 0];
+        assert_eq!(read_frame(&mut input).await, Ok(vec![1, 2, 3]));
+        assert_eq!(read_frame(&mut input).await, Ok(vec![]));
+        assert_eq!(read_frame(&mut input).await, Err(()));
+
+        // Truncated frames
+        let mut input: &[u8] = &[5, 1, 2];
+        assert_eq!(read_frame(&mut input).await, Err(()));
+
+        // Lengths that don't fit in 3 bytes are rejected before anything is allocated
+        let mut input: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0x07];
+        assert_eq!(read_frame(&mut input).await, Err(()));
+
+        let mut output = Vec::new();
+        write_frame(&mut output, &[0; 300]).await.unwrap();
+        let mut input = output.as_slice();
+        assert_eq!(read_frame(&mut input).await, Ok(vec![0; 300]));
+    }
+}
@@ -1,15 +1,95 @@
 use super::*;
 
-pub async fn status(stream: &mut TcpStream) -> Result<(), ()> {
+/// The maximum number of players listed when hovering the player count
+const MAX_SAMPLE_SIZE: usize = 12;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Reads the icon shown in the server list.
+/// It must be a 64x64 PNG image.
+pub fn load_favicon(path: impl AsRef<std::path::Path>) -> Result<Vec<u8>, String> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+    if data.len() < 24 || !data.starts_with(PNG_SIGNATURE) || data[12..16] != *b"IHDR" {
+        return Err(format!("{} is not a PNG image", path.display()));
+    }
+    let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+    let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+    if (width, height) != (64, 64) {
+        return Err(format!("{} must be 64x64 pixels, found {width}x{height}", path.display()));
+    }
+    Ok(data)
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// Formats a UUID the way it is written in JSON, with hyphens.
+fn format_uuid(uuid: UUID) -> String {
+    let hex = format!("{uuid:032x}");
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+/// Builds the JSON shown in the server list.
+fn status_response(config: &ServerConfig, online: usize, sample: &[(UUID, String)], favicon: Option<&[u8]>) -> String {
+    let mut json = String::from("{\"version\":{\"name\":\"");
+    push_json_escaped(&mut json, &config.version_name);
+    json.push_str(&format!("\",\"protocol\":{PROTOCOL_VERSION}}},\"players\":{{\"max\":{},\"online\":{online},\"sample\":[", config.max_players));
+    for (i, (uuid, name)) in sample.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str("{\"name\":\"");
+        push_json_escaped(&mut json, name);
+        json.push_str(&format!("\",\"id\":\"{}\"}}", format_uuid(*uuid)));
+    }
+    json.push_str("]},\"description\":");
+    json.push_str(&ChatText::plain(config.motd.as_str()).to_json(false));
+    if let Some(favicon) = favicon {
+        json.push_str(",\"favicon\":\"data:image/png;base64,");
+        json.push_str(&encode_base64(favicon));
+        json.push('"');
+    }
+    json.push_str(",\"enforcesSecureChat\":false}");
+    json
+}
+
+/// Returns the number of online players and a few of them to show in the server list.
+async fn online_players(world: &World) -> (usize, Vec<(UUID, String)>) {
+    let players = world.players().await;
+    let sample = players.iter()
+        .filter(|(_, entry)| entry.listed)
+        .take(MAX_SAMPLE_SIZE)
+        .map(|(uuid, entry)| (*uuid, entry.name.clone()))
+        .collect();
+    (players.len(), sample)
+}
+
+pub async fn status(stream: &mut TcpStream, world: &'static World) -> Result<(), ()> {
     loop {
         let packet = receive_packet(stream).await?;
-        match StatusServerbound::deserialize_uncompressed_minecraft_packet(packet.as_slice()).unwrap() {
+        match parse_packet::<StatusServerbound>(stream, ConnectionPhase::Status, &packet).await? {
             StatusServerbound::Request => {
+                let (online, sample) = online_players(world).await;
+                let json_response = status_response(world.config(), online, &sample, world.favicon());
                 let response = StatusClientbound::Response {
-                    json_response: include_str!("../raw/status_response.json")
+                    json_response: &json_response
                 };
-                send_packet(stream, response).await;    
-                debug!("StatusResponse sent");                
+                send_packet(stream, response).await;
+                debug!("StatusResponse sent");
             },
             StatusServerbound::Ping { payload } => {
                 warn!("Ping received");
@@ -26,3 +106,68 @@ pub async fn status(stream: &mut TcpStream) -> Result<(), ()> {
         };
     }
 }
+
+/// Builds the kick packet legacy clients expect in response to their ping.
+/// Clients from 1.4 to 1.6 send `0xFE 0x01` and understand a more detailed response than older ones.
+fn legacy_status_response(config: &ServerConfig, online: usize, detailed: bool) -> Vec<u8> {
+    let motd: String = config.motd.chars().filter(|c| *c != '§' && *c != '\0').collect();
+    let text = match detailed {
+        true => format!("§1\0{PROTOCOL_VERSION}\0{}\0{motd}\0{online}\0{}", config.version_name, config.max_players),
+        false => format!("{motd}§{online}§{}", config.max_players),
+    };
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let mut response = Vec::with_capacity(3 + text.len() * 2);
+    response.push(0xFF);
+    response.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    for c in text {
+        response.extend_from_slice(&c.to_be_bytes());
+    }
+    response
+}
+
+/// Answers a ping from a client older than 1.7, which starts with a `0xFE` byte instead of a handshake packet.
+pub async fn legacy_status(stream: &mut TcpStream, world: &'static World) -> Result<(), ()> {
+    let mut buf = [0; 256];
+    let read = stream.read(&mut buf).await.map_err(|_| ())?;
+    let detailed = read >= 2 && buf[1] == 0x01;
+    let (online, _) = online_players(world).await;
+    let response = legacy_status_response(world.config(), online, detailed);
+    stream.write_all(&response).await.map_err(|_| ())?;
+    stream.flush().await.map_err(|_| ())?;
+    debug!("Legacy status sent");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_status_response() {
+        let config = ServerConfig { motd: String::from("Hello \"world\""), max_players: 20, ..ServerConfig::default() };
+        let sample = vec![(0x0123456789abcdef0123456789abcdef, String::from("Steve"))];
+        let json = status_response(&config, 1, &sample, Some(&b"foo"[..]));
+        assert!(json.contains("\"max\":20,\"online\":1"));
+        assert!(json.contains("{\"name\":\"Steve\",\"id\":\"01234567-89ab-cdef-0123-456789abcdef\"}"));
+        assert!(json.contains("Hello \\\"world\\\""));
+        assert!(json.contains("\"favicon\":\"data:image/png;base64,Zm9v\""));
+    }
+
+    #[test]
+    fn test_legacy_status_response() {
+        let config = ServerConfig { motd: String::from("A"), max_players: 20, ..ServerConfig::default() };
+        assert_eq!(legacy_status_response(&config, 3, false), vec![0xFF, 0, 6, 0, b'A', 0, 0xA7, 0, b'3', 0, 0xA7, 0, b'2', 0, b'0']);
+        let response = legacy_status_response(&config, 3, true);
+        let text: Vec<u16> = response[3..].chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        assert_eq!(String::from_utf16(&text).unwrap(), format!("§1\0{PROTOCOL_VERSION}\0{VERSION_NAME}\0A\03\020"));
+    }
+}
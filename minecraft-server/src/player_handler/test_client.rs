@@ -0,0 +1,79 @@
+//! A minimal client for tests that need a player to join a server.
+
+use tokio::net::tcp::OwnedWriteHalf;
+use super::*;
+
+/// Prefixes a packet with its length.
+pub fn frame<'a>(packet: impl MinecraftPacketPart<'a>) -> Vec<u8> {
+    let packet = packet.serialize_minecraft_packet().unwrap();
+    let mut frame = VarInt::from(packet.len()).serialize_minecraft_packet().unwrap();
+    frame.extend(packet);
+    frame
+}
+
+pub fn hello(next_state: ConnectionState) -> Vec<u8> {
+    frame(HandshakeServerbound::Hello {
+        protocol_version: VarInt(PROTOCOL_VERSION),
+        server_address: "localhost",
+        server_port: 25565,
+        next_state,
+    })
+}
+
+/// Everything a client sends to join, up to the acknowledgement of the first chunk batch.
+/// The server doesn't need to be answered in between, so the packets can be sent at once.
+pub fn join_packets(username: &str, uuid: UUID) -> Vec<u8> {
+    let mut packets = hello(ConnectionState::Login);
+    packets.extend(frame(LoginServerbound::LoginStart { username, player_uuid: uuid }));
+    packets.extend(frame(LoginServerbound::LoginAcknowledged));
+    // Like vanilla clients, tell the server which client this is first, as the login skips a packet
+    packets.extend(frame(ConfigServerbound::PluginMessage { channel: "minecraft:brand", data: RawBytes { data: b"\x07vanilla" } }));
+    packets.extend(frame(ConfigServerbound::ClientInformations {
+        locale: "en_US",
+        render_distance: 2,
+        chat_mode: ChatMode::Enabled,
+        chat_colors: true,
+        displayed_skin_parts: 0,
+        main_hand: MainHand::Right,
+        enable_text_filtering: false,
+        allow_server_listing: true,
+    }));
+    packets.extend(frame(ConfigServerbound::FinishConfiguration));
+    packets.extend(frame(PlayServerbound::ChunkBatchReceived { chunks_per_tick: 10.0 }));
+    packets
+}
+
+/// Connects to the server and sends `data`, reading and discarding what the server sends in the background
+/// so that it never waits for the client. The connection is closed when the returned half is dropped.
+pub async fn connect(addr: SocketAddr, data: &[u8]) -> OwnedWriteHalf {
+    let (mut reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    tokio::spawn(async move {
+        let mut buffer = [0; 4096];
+        while matches!(reader.read(&mut buffer).await, Ok(1..)) {}
+    });
+    writer.write_all(data).await.unwrap();
+    writer
+}
+
+/// Waits until a player named `username` is in the player list and their entity spawned.
+pub async fn wait_for_player(world: &World, username: &str) -> (UUID, PlayerListEntry) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some(player) = world.players().await.into_iter().find(|(_, entry)| entry.name == username) {
+                if world.entity_uuid(player.1.eid).await.is_some() {
+                    return player;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }).await.expect("The player didn't join")
+}
+
+/// Waits until every player left, which only happens once their handler cleaned up after them.
+pub async fn wait_for_no_players(world: &World) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !world.players().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }).await.expect("A player handler didn't clean up");
+}
@@ -0,0 +1,251 @@
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use crate::prelude::*;
+
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const TYPE_HANDSHAKE: u8 = 9;
+const TYPE_STAT: u8 = 0;
+/// Challenge tokens expire after this duration, like on vanilla servers
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+/// Full stat responses start with this padding
+const FULL_STAT_PADDING: &[u8] = b"splitnum\0\x80\0";
+/// The player list of full stat responses starts with this padding
+const PLAYERS_PADDING: &[u8] = b"\x01player_\0\0";
+
+/// A request of the GameSpy4 query protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QueryRequest {
+    Handshake { session_id: i32 },
+    BasicStat { session_id: i32, challenge: i32 },
+    FullStat { session_id: i32, challenge: i32 },
+}
+
+impl QueryRequest {
+    fn parse(data: &[u8]) -> Option<QueryRequest> {
+        if data.len() < 7 || data[0..2] != MAGIC {
+            return None;
+        }
+        // Clients only use the lower 4 bits of each byte of the session id
+        let session_id = i32::from_be_bytes(data[3..7].try_into().ok()?) & 0x0F0F0F0F;
+        let challenge = || Some(i32::from_be_bytes(data.get(7..11)?.try_into().ok()?));
+        match (data[2], data.len()) {
+            (TYPE_HANDSHAKE, _) => Some(QueryRequest::Handshake { session_id }),
+            (TYPE_STAT, 11) => Some(QueryRequest::BasicStat { session_id, challenge: challenge()? }),
+            (TYPE_STAT, 15) => Some(QueryRequest::FullStat { session_id, challenge: challenge()? }),
+            _ => None,
+        }
+    }
+}
+
+/// What monitoring tools get to know about the server
+struct QueryStatus {
+    motd: String,
+    map: String,
+    version: String,
+    max_players: usize,
+    players: Vec<String>,
+    host: SocketAddr,
+}
+
+impl QueryStatus {
+    async fn new(world: &World, host: SocketAddr) -> QueryStatus {
+        let config = world.config();
+        let mut players: Vec<String> = world.players().await.into_iter().map(|(_, entry)| entry.name).collect();
+        players.sort_unstable();
+        QueryStatus {
+            motd: config.motd.clone(),
+            map: config.level_name.clone(),
+            version: config.version_name.clone(),
+            max_players: config.max_players,
+            players,
+            host,
+        }
+    }
+}
+
+/// Appends a null-terminated string, removing the null bytes it contains.
+fn push_string(response: &mut Vec<u8>, value: &str) {
+    response.extend(value.bytes().filter(|byte| *byte != 0));
+    response.push(0);
+}
+
+fn response_header(ty: u8, session_id: i32) -> Vec<u8> {
+    let mut response = vec![ty];
+    response.extend_from_slice(&session_id.to_be_bytes());
+    response
+}
+
+fn basic_stat(session_id: i32, status: &QueryStatus) -> Vec<u8> {
+    let mut response = response_header(TYPE_STAT, session_id);
+    push_string(&mut response, &status.motd);
+    push_string(&mut response, "SMP");
+    push_string(&mut response, &status.map);
+    push_string(&mut response, &status.players.len().to_string());
+    push_string(&mut response, &status.max_players.to_string());
+    response.extend_from_slice(&status.host.port().to_le_bytes());
+    push_string(&mut response, &status.host.ip().to_string());
+    response
+}
+
+fn full_stat(session_id: i32, status: &QueryStatus) -> Vec<u8> {
+    let mut response = response_header(TYPE_STAT, session_id);
+    response.extend_from_slice(FULL_STAT_PADDING);
+    for (key, value) in [
+        ("hostname", status.motd.clone()),
+        ("gametype", String::from("SMP")),
+        ("game_id", String::from("MINECRAFT")),
+        ("version", status.version.clone()),
+        ("plugins", String::new()),
+        ("map", status.map.clone()),
+        ("numplayers", status.players.len().to_string()),
+        ("maxplayers", status.max_players.to_string()),
+        ("hostport", status.host.port().to_string()),
+        ("hostip", status.host.ip().to_string()),
+    ] {
+        push_string(&mut response, key);
+        push_string(&mut response, &value);
+    }
+    response.push(0);
+    response.extend_from_slice(PLAYERS_PADDING);
+    for player in &status.players {
+        push_string(&mut response, player);
+    }
+    response.push(0);
+    response
+}
+
+/// The challenge tokens handed out to each client address
+#[derive(Default)]
+struct Challenges {
+    tokens: HashMap<SocketAddr, (i32, Instant)>,
+}
+
+impl Challenges {
+    fn issue(&mut self, addr: SocketAddr, now: Instant) -> i32 {
+        self.tokens.retain(|_, (_, issued)| now.duration_since(*issued) < CHALLENGE_LIFETIME);
+        let token = rand::random::<i32>() & 0x7FFFFFFF;
+        self.tokens.insert(addr, (token, now));
+        token
+    }
+
+    fn check(&self, addr: SocketAddr, token: i32, now: Instant) -> bool {
+        matches!(self.tokens.get(&addr), Some((expected, issued)) if *expected == token && now.duration_since(*issued) < CHALLENGE_LIFETIME)
+    }
+}
+
+/// Answers query requests until the server shuts down.
+/// `host` is the address players connect to, which is reported to clients.
+pub async fn serve_query(socket: UdpSocket, host: SocketAddr, world: &'static World) {
+    let mut challenges = Challenges::default();
+    let mut buffer = [0; 1460];
+    loop {
+        let (length, addr) = tokio::select! {
+            received = socket.recv_from(&mut buffer) => match received {
+                Ok(received) => received,
+                Err(e) => {
+                    debug!("Couldn't receive query request: {e}");
+                    continue;
+                }
+            },
+            _ = world.shutdown_requested() => return,
+        };
+        let Some(request) = QueryRequest::parse(&buffer[..length]) else {
+            debug!("Invalid query request from {addr}");
+            continue;
+        };
+        let now = Instant::now();
+        let response = match request {
+            QueryRequest::Handshake { session_id } => {
+                let token = challenges.issue(addr, now);
+                let mut response = response_header(TYPE_HANDSHAKE, session_id);
+                push_string(&mut response, &token.to_string());
+                response
+            }
+            QueryRequest::BasicStat { session_id, challenge } if challenges.check(addr, challenge, now) => {
+                basic_stat(session_id, &QueryStatus::new(world, host).await)
+            }
+            QueryRequest::FullStat { session_id, challenge } if challenges.check(addr, challenge, now) => {
+                full_stat(session_id, &QueryStatus::new(world, host).await)
+            }
+            _ => {
+                debug!("Query request from {addr} has an invalid challenge token");
+                continue;
+            }
+        };
+        if let Err(e) = socket.send_to(&response, addr).await {
+            debug!("Couldn't answer query request from {addr}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(socket: &UdpSocket, request: &[u8]) -> Vec<u8> {
+        socket.send(request).await.unwrap();
+        let mut buffer = [0; 1460];
+        let length = tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut buffer)).await.unwrap().unwrap();
+        buffer[..length].to_vec()
+    }
+
+    /// Splits null-terminated strings, some of which may be binary like the port of basic stat responses.
+    fn strings(data: &[u8]) -> Vec<String> {
+        data.split(|byte| *byte == 0).map(|string| String::from_utf8_lossy(string).into_owned()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .query(0)
+            .start().await.unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket.connect(server.query_addr().unwrap()).await.unwrap();
+
+        let response = request(&socket, &[0xFE, 0xFD, TYPE_HANDSHAKE, 0x01, 0x02, 0x03, 0x14]).await;
+        assert_eq!(response[0..5], [TYPE_HANDSHAKE, 0x01, 0x02, 0x03, 0x04]);
+        let token: i32 = strings(&response[5..])[0].parse().unwrap();
+
+        let mut stat_request = vec![0xFE, 0xFD, TYPE_STAT, 0x01, 0x02, 0x03, 0x04];
+        stat_request.extend_from_slice(&token.to_be_bytes());
+        let response = request(&socket, &stat_request).await;
+        assert_eq!(response[0..5], [TYPE_STAT, 0x01, 0x02, 0x03, 0x04]);
+        let fields = strings(&response[5..]);
+        assert_eq!(fields[0..5], ["Minecraft rust server", "SMP", "world", "0", "1000"]);
+        let port_start = 5 + fields[0..5].iter().map(|field| field.len() + 1).sum::<usize>();
+        assert_eq!(u16::from_le_bytes([response[port_start], response[port_start + 1]]), server.local_addr().port());
+
+        stat_request.extend_from_slice(&[0; 4]);
+        let response = request(&socket, &stat_request).await;
+        assert_eq!(&response[5..16], FULL_STAT_PADDING);
+        let fields = strings(&response[16..]);
+        assert_eq!(fields[0..4], ["hostname", "Minecraft rust server", "gametype", "SMP"]);
+        assert!(fields.windows(2).any(|pair| pair == ["numplayers", "0"]));
+        assert!(response.ends_with(&[0, 0]));
+
+        // Requests with a wrong token are ignored
+        stat_request[7..11].copy_from_slice(&token.wrapping_add(1).to_be_bytes());
+        socket.send(&stat_request).await.unwrap();
+        let mut buffer = [0; 1460];
+        assert!(tokio::time::timeout(Duration::from_millis(200), socket.recv(&mut buffer)).await.is_err());
+
+        server.stop().await;
+    }
+
+    #[test]
+    fn test_full_stat_players() {
+        let status = QueryStatus {
+            motd: String::from("A\0B"),
+            map: String::from("world"),
+            version: String::from("1.20.2"),
+            max_players: 20,
+            players: vec![String::from("Alice"), String::from("Bob")],
+            host: SocketAddr::from(([127, 0, 0, 1], 25565)),
+        };
+        let response = full_stat(1, &status);
+        let players_start = response.windows(PLAYERS_PADDING.len()).position(|window| window == PLAYERS_PADDING).unwrap() + PLAYERS_PADDING.len();
+        assert_eq!(&response[players_start..], b"Alice\0Bob\0\0");
+        assert_eq!(strings(&response[16..])[1], "AB");
+    }
+}
@@ -0,0 +1,247 @@
+use std::{net::IpAddr, sync::Mutex, time::Instant};
+use tokio::net::TcpListener;
+use crate::prelude::*;
+
+/// Answers a command, possibly in multiple packets
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+/// Runs a command once authenticated
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+/// Tells whether authentication succeeded, sharing its value with [SERVERDATA_EXECCOMMAND]
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_AUTH: i32 = 3;
+
+/// The largest packet clients may send, like the vanilla server
+const MAX_REQUEST_LENGTH: i32 = 1460;
+/// Longer outputs are split in multiple response packets
+const MAX_RESPONSE_BODY: usize = 4096;
+/// Addresses that sent this many wrong passwords are locked out
+const MAX_FAILED_LOGINS: u32 = 5;
+/// How long addresses stay locked out after their last wrong password
+const LOCKOUT_DURATION: Duration = Duration::from_secs(60);
+
+/// A packet of the RCON protocol, without its length prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RconPacket {
+    id: i32,
+    ty: i32,
+    body: String,
+}
+
+impl RconPacket {
+    /// Parses a packet, whose body ends with two null bytes.
+    fn parse(data: &[u8]) -> Option<RconPacket> {
+        if data.len() < 10 || !data.ends_with(&[0, 0]) {
+            return None;
+        }
+        let id = i32::from_le_bytes(data[0..4].try_into().ok()?);
+        let ty = i32::from_le_bytes(data[4..8].try_into().ok()?);
+        let body = String::from_utf8_lossy(&data[8..data.len() - 2]).into_owned();
+        Some(RconPacket { id, ty, body })
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let length = 4 + 4 + self.body.len() + 2;
+        let mut data = Vec::with_capacity(4 + length);
+        data.extend_from_slice(&(length as i32).to_le_bytes());
+        data.extend_from_slice(&self.id.to_le_bytes());
+        data.extend_from_slice(&self.ty.to_le_bytes());
+        data.extend_from_slice(self.body.as_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Option<RconPacket> {
+    let length = stream.read_i32_le().await.ok()?;
+    if !(10..=MAX_REQUEST_LENGTH).contains(&length) {
+        debug!("Invalid RCON packet length {length}");
+        return None;
+    }
+    let mut data = vec![0; length as usize];
+    stream.read_exact(&mut data).await.ok()?;
+    RconPacket::parse(&data)
+}
+
+async fn send_packet(stream: &mut TcpStream, id: i32, ty: i32, body: &str) -> Option<()> {
+    let packet = RconPacket { id, ty, body: body.to_string() };
+    stream.write_all(&packet.serialize()).await.ok()
+}
+
+/// Splits command output in bodies small enough for a packet, without splitting characters.
+fn split_response(output: &str) -> Vec<&str> {
+    let mut bodies = Vec::new();
+    let mut rest = output;
+    while rest.len() > MAX_RESPONSE_BODY {
+        let mut end = MAX_RESPONSE_BODY;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (body, remaining) = rest.split_at(end);
+        bodies.push(body);
+        rest = remaining;
+    }
+    bodies.push(rest);
+    bodies
+}
+
+/// The wrong passwords sent from each IP address, to slow down password guessing
+#[derive(Default)]
+struct FailedLogins {
+    failures: HashMap<IpAddr, (u32, Instant)>,
+}
+
+impl FailedLogins {
+    fn is_locked_out(&mut self, ip: IpAddr, now: Instant) -> bool {
+        self.failures.retain(|_, (_, last)| now.duration_since(*last) < LOCKOUT_DURATION);
+        matches!(self.failures.get(&ip), Some((count, _)) if *count >= MAX_FAILED_LOGINS)
+    }
+
+    fn record_failure(&mut self, ip: IpAddr, now: Instant) {
+        let (count, last) = self.failures.entry(ip).or_insert((0, now));
+        *count += 1;
+        *last = now;
+    }
+}
+
+/// Authenticates the client and runs its commands as the console.
+/// Returns `None` once the connection should be closed.
+async fn handle_client(stream: &mut TcpStream, addr: SocketAddr, password: &str, failed_logins: &Mutex<FailedLogins>, world: &'static World) -> Option<()> {
+    let mut authenticated = false;
+    loop {
+        let packet = read_packet(stream).await?;
+        match packet.ty {
+            SERVERDATA_AUTH => {
+                let ip = addr.ip();
+                let now = Instant::now();
+                let refusal = {
+                    let mut failed_logins = failed_logins.lock().unwrap();
+                    if failed_logins.is_locked_out(ip, now) {
+                        Some("too many wrong passwords")
+                    } else if packet.body != password {
+                        failed_logins.record_failure(ip, now);
+                        Some("wrong password")
+                    } else {
+                        failed_logins.failures.remove(&ip);
+                        None
+                    }
+                };
+                if let Some(refusal) = refusal {
+                    warn!("Refused RCON login from {addr}: {refusal}");
+                    send_packet(stream, -1, SERVERDATA_AUTH_RESPONSE, "").await;
+                    return None;
+                }
+                authenticated = true;
+                send_packet(stream, packet.id, SERVERDATA_AUTH_RESPONSE, "").await?;
+            }
+            _ if !authenticated => {
+                send_packet(stream, -1, SERVERDATA_AUTH_RESPONSE, "").await;
+                return None;
+            }
+            SERVERDATA_EXECCOMMAND => {
+                info!("RCON command from {addr}: {}", packet.body);
+                let output = match world.commands().execute(world, CommandSender::Console, &packet.body).await {
+                    Ok(feedback) => feedback.iter().map(ChatText::to_plain).collect::<Vec<_>>().join("\n"),
+                    Err(error) => error,
+                };
+                for body in split_response(&output) {
+                    send_packet(stream, packet.id, SERVERDATA_RESPONSE_VALUE, body).await?;
+                }
+            }
+            // Clients send an empty response packet after a command and wait for it to be echoed,
+            // which tells them when a response split in multiple packets is over
+            SERVERDATA_RESPONSE_VALUE => send_packet(stream, packet.id, SERVERDATA_RESPONSE_VALUE, "").await?,
+            ty => send_packet(stream, packet.id, SERVERDATA_RESPONSE_VALUE, &format!("Unknown request {ty:x}")).await?,
+        }
+    }
+}
+
+/// Accepts RCON clients until the server shuts down.
+pub async fn serve_rcon(listener: TcpListener, password: String, world: &'static World) {
+    let password: Arc<str> = password.into();
+    let failed_logins = Arc::new(Mutex::new(FailedLogins::default()));
+    loop {
+        let (mut stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("RCON listener couldn't listen anymore: {e}");
+                    return;
+                }
+            },
+            _ = world.shutdown_requested() => return,
+        };
+        debug!("RCON connection from {addr}");
+        let password = Arc::clone(&password);
+        let failed_logins = Arc::clone(&failed_logins);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = handle_client(&mut stream, addr, &password, &failed_logins, world) => (),
+                _ = world.shutdown_requested() => (),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rcon_packets() {
+        let packet = RconPacket { id: 7, ty: SERVERDATA_EXECCOMMAND, body: String::from("say hi") };
+        let data = packet.serialize();
+        assert_eq!(i32::from_le_bytes(data[0..4].try_into().unwrap()) as usize, data.len() - 4);
+        assert_eq!(RconPacket::parse(&data[4..]), Some(packet));
+        assert_eq!(RconPacket::parse(&[0; 9]), None);
+
+        let output = "é".repeat(MAX_RESPONSE_BODY);
+        let bodies = split_response(&output);
+        assert_eq!(bodies.len(), 2);
+        assert!(bodies.iter().all(|body| body.len() <= MAX_RESPONSE_BODY));
+        assert_eq!(bodies.concat(), output);
+        assert_eq!(split_response(""), vec![""]);
+    }
+
+    #[test]
+    fn test_failed_logins() {
+        let mut failed_logins = FailedLogins::default();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let now = Instant::now();
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert!(!failed_logins.is_locked_out(ip, now));
+            failed_logins.record_failure(ip, now);
+        }
+        assert!(failed_logins.is_locked_out(ip, now));
+        assert!(!failed_logins.is_locked_out(IpAddr::from([127, 0, 0, 2]), now));
+        assert!(!failed_logins.is_locked_out(ip, now + LOCKOUT_DURATION));
+    }
+
+    #[tokio::test]
+    async fn test_rcon() {
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .rcon(0, "secret")
+            .start().await.unwrap();
+        let rcon_addr = server.rcon_addr().unwrap();
+        async fn request(stream: &mut TcpStream, id: i32, ty: i32, body: &str) -> RconPacket {
+            send_packet(stream, id, ty, body).await.unwrap();
+            read_packet(stream).await.unwrap()
+        }
+
+        let mut stream = TcpStream::connect(rcon_addr).await.unwrap();
+        assert_eq!(request(&mut stream, 1, SERVERDATA_AUTH, "wrong").await.id, -1);
+
+        let mut stream = TcpStream::connect(rcon_addr).await.unwrap();
+        assert_eq!(request(&mut stream, 1, SERVERDATA_AUTH, "secret").await, RconPacket { id: 1, ty: SERVERDATA_AUTH_RESPONSE, body: String::new() });
+        let response = request(&mut stream, 2, SERVERDATA_EXECCOMMAND, "help").await;
+        assert_eq!(response.id, 2);
+        assert!(response.body.lines().any(|line| line == "/kick"));
+        assert_eq!(request(&mut stream, 3, SERVERDATA_RESPONSE_VALUE, "").await.id, 3);
+        assert_eq!(request(&mut stream, 4, SERVERDATA_EXECCOMMAND, "unknown").await.body, "Unknown command \"unknown\"");
+
+        server.stop().await;
+
+        // Servers with RCON need a password
+        assert!(ServerBuilder::new().address(SocketAddr::from(([127, 0, 0, 1], 0))).rcon(0, "").start().await.is_err());
+    }
+}
@@ -1,60 +1,478 @@
-use tokio::net::TcpListener;
+use std::{path::PathBuf, time::Instant};
+use tokio::{net::{TcpListener, UdpSocket}, task::JoinHandle};
 
-use crate::prelude::*;
+use crate::{metrics_server::serve_metrics, prelude::*, query::serve_query, rcon::serve_rcon};
+
+/// How long players have to leave once the server shuts down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Overrunning ticks are reported at most this often
+const LAG_WARNING_INTERVAL: Duration = Duration::from_secs(15);
 
 /// Message sent from the server to all player handlers
 #[derive(Clone, Debug)]
 pub enum ServerMessage {
     /// Message indicating a new tick has started
     Tick(usize),
+    /// The server is stopping, and players must be disconnected with this reason
+    Shutdown(String),
 }
 
-pub struct ServerBehavior {
-    world: &'static World,
-    message_receiver: BroadcastReceiver<ServerMessage>,
+/// Configures and starts a server.
+///
+/// ```no_run
+/// # use minecraft_server::prelude::*;
+/// # async fn run() -> std::io::Result<()> {
+/// let server = ServerBuilder::new().address(SocketAddr::from(([127, 0, 0, 1], 0))).start().await?;
+/// let addr = server.local_addr();
+/// server.stop().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ServerBuilder {
+    config: ServerConfig,
+    /// Settings that override the config, whenever it is set
+    address: Option<SocketAddr>,
+    metrics_address: Option<SocketAddr>,
+    rcon: Option<(u16, String)>,
+    query_port: Option<u16>,
+    storage: Option<PathBuf>,
+    favicon: Option<Vec<u8>>,
+    generator: Box<dyn WorldGenerator>,
+    tick_source: TickSource,
+    /// Permission levels given on top of the operators of the config
+    operators: Vec<(UUID, u8)>,
 }
 
-impl ServerBehavior {
-    pub async fn init() -> ServerBehavior {
-        let listener = TcpListener::bind("127.0.0.1:25567").await.expect("Failed to listen");
+impl ServerBuilder {
+    /// Returns a builder with the default settings, keeping the world in memory.
+    pub fn new() -> ServerBuilder {
+        ServerBuilder {
+            config: ServerConfig::default(),
+            address: None,
+            metrics_address: None,
+            rcon: None,
+            query_port: None,
+            storage: None,
+            favicon: None,
+            generator: Box::new(FlatGenerator),
+            tick_source: TickSource::default(),
+            operators: Vec::new(),
+        }
+    }
+
+    /// Replaces all settings.
+    /// Settings passed to the other methods take precedence, whether they are called before or after this one.
+    pub fn config(mut self, config: ServerConfig) -> ServerBuilder {
+        self.config = config;
+        self
+    }
+
+    /// Sets the address to listen on. Use port 0 to let the OS pick a free port.
+    pub fn address(mut self, addr: SocketAddr) -> ServerBuilder {
+        self.address = Some(addr);
+        self
+    }
+
+    /// Serves Prometheus metrics at `http://<addr>/metrics`.
+    pub fn metrics_address(mut self, addr: SocketAddr) -> ServerBuilder {
+        self.metrics_address = Some(addr);
+        self
+    }
+
+    /// Lets clients knowing the password run commands over RCON, on this port of the server address.
+    /// The server fails to start if the password is empty.
+    pub fn rcon(mut self, port: u16, password: impl Into<String>) -> ServerBuilder {
+        self.rcon = Some((port, password.into()));
+        self
+    }
+
+    /// Answers UDP queries from monitoring tools on this port of the server address.
+    pub fn query(mut self, port: u16) -> ServerBuilder {
+        self.query_port = Some(port);
+        self
+    }
+
+    /// Saves the world in `directory` instead of keeping it in memory.
+    pub fn storage(mut self, directory: impl Into<PathBuf>) -> ServerBuilder {
+        self.storage = Some(directory.into());
+        self
+    }
+
+    /// Sets the PNG icon shown in the server list.
+    pub fn favicon(mut self, favicon: Vec<u8>) -> ServerBuilder {
+        self.favicon = Some(favicon);
+        self
+    }
+
+    /// Sets how chunk columns that were never saved are created.
+    pub fn generator(mut self, generator: impl WorldGenerator + 'static) -> ServerBuilder {
+        self.generator = Box::new(generator);
+        self
+    }
+
+    /// Sets how many ticks run per second, instead of 20.
+    pub fn tick_rate(mut self, ticks_per_second: u32) -> ServerBuilder {
+        self.tick_source = TickSource::RealTime(Duration::from_secs(1) / ticks_per_second.max(1));
+        self
+    }
+
+    /// Sets what starts ticks. See [ServerBehavior::step] for manual ticks.
+    pub fn tick_source(mut self, tick_source: TickSource) -> ServerBuilder {
+        self.tick_source = tick_source;
+        self
+    }
+
+    /// Gives a permission level to a player, from 0 to 4, in addition to the ops of the config.
+    pub fn operator(mut self, uuid: UUID, level: u8) -> ServerBuilder {
+        self.operators.push((uuid, level));
+        self
+    }
+
+    /// Binds the listener and starts ticking.
+    pub async fn start(self) -> std::io::Result<ServerBehavior> {
+        let ServerBuilder { mut config, address, metrics_address, rcon, query_port, storage, favicon, generator, tick_source, operators } = self;
+        if let Some(addr) = address {
+            config.server_ip = addr.ip();
+            config.server_port = addr.port();
+        }
+        if let Some(metrics_address) = metrics_address {
+            config.metrics_address = Some(metrics_address);
+        }
+        if let Some((port, password)) = rcon {
+            config.enable_rcon = true;
+            config.rcon_port = port;
+            config.rcon_password = password;
+        }
+        if let Some(port) = query_port {
+            config.enable_query = true;
+            config.query_port = port;
+        }
+        config.validate().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let listener = TcpListener::bind(config.address()).await?;
+        let addr = listener.local_addr()?;
+        let metrics_listener = match config.metrics_address {
+            Some(metrics_address) => Some(TcpListener::bind(metrics_address).await?),
+            None => None,
+        };
+        let metrics_addr = metrics_listener.as_ref().map(|listener| listener.local_addr()).transpose()?;
+        let rcon_listener = match config.enable_rcon {
+            true => Some(TcpListener::bind(config.rcon_address()).await?),
+            false => None,
+        };
+        let rcon_addr = rcon_listener.as_ref().map(|listener| listener.local_addr()).transpose()?;
+        let rcon_password = config.rcon_password.clone();
+        let query_socket = match config.enable_query {
+            true => Some(UdpSocket::bind(config.query_address()).await?),
+            false => None,
+        };
+        let query_addr = query_socket.as_ref().map(|socket| socket.local_addr()).transpose()?;
         let (sender, receiver) = broadcast_channel(100);
-        let world = Box::leak(Box::new(World::new(receiver.resubscribe())));
+        let world = match storage {
+            Some(directory) => World::with_storage(directory),
+            None => World::new(),
+        };
+        let world = world.with_config(config).with_favicon(favicon).with_generator(generator);
+        let world: &'static World = Box::leak(Box::new(world));
+        for uuid in world.config().ops.clone() {
+            world.set_permission_level(uuid, 4).await;
+        }
+        for (uuid, level) in operators {
+            world.set_permission_level(uuid, level).await;
+        }
+        info!("Listening on {addr}");
+        let metrics_task = metrics_listener.map(|metrics_listener| {
+            info!("Serving metrics on http://{}/metrics", metrics_addr.unwrap_or(addr));
+            tokio::spawn(serve_metrics(metrics_listener, world))
+        });
+        let rcon_task = rcon_listener.map(|rcon_listener| {
+            info!("RCON listening on {}", rcon_addr.unwrap_or(addr));
+            tokio::spawn(serve_rcon(rcon_listener, rcon_password, world))
+        });
+        let query_task = query_socket.map(|query_socket| {
+            info!("Answering queries on {}", query_addr.unwrap_or(addr));
+            tokio::spawn(serve_query(query_socket, addr, world))
+        });
 
-        // Send ticks to player handlers
-        tokio::spawn(async move {
-            let mut tick_id = 0;
-            let mut tick = tokio::time::interval(Duration::from_millis(50));
+        // Send ticks to entities and player handlers
+        let sender2 = sender.clone();
+        let tick_task = tokio::spawn(async move {
+            let TickSource::RealTime(tick_duration) = tick_source else { return };
+            let mut interval = tokio::time::interval(tick_duration);
+            interval.set_missed_tick_behavior(world.config().missed_ticks);
+            let mut last_warning = Instant::now();
+            let mut overruns = 0;
             loop {
-                tick.tick().await;
-                let _ = sender.send(ServerMessage::Tick(tick_id));
-                tick_id += 1;
+                tokio::select! {
+                    _ = interval.tick() => (),
+                    _ = world.shutdown_requested() => break,
+                }
+                if tick(world, &sender2).await > tick_duration {
+                    overruns += 1;
+                }
+                if overruns > 0 && last_warning.elapsed() >= LAG_WARNING_INTERVAL {
+                    let stats = world.tick_stats().await;
+                    let slowest = stats.slowest().unwrap_or_default().as_millis();
+                    warn!("Can't keep up! {overruns} ticks took longer than {}ms in the last {}s, the slowest took {slowest}ms", tick_duration.as_millis(), last_warning.elapsed().as_secs());
+                    last_warning = Instant::now();
+                    overruns = 0;
+                }
             }
         });
 
         // Accept incoming connections
-        let world2: &World = world;
-        let receiver2 = receiver.resubscribe();
-        tokio::spawn(async move {
-            while let Ok((stream, addr)) = listener.accept().await {
-                // TODO(security): Limit player count
-                let server_msg_rcvr = receiver2.resubscribe();
+        let limiter = ConnectionLimiter::new(world.config().max_connections, world.config().max_connections_per_ip);
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            error!("Listener couldn't listen anymore: {e}");
+                            break;
+                        }
+                    },
+                    _ = world.shutdown_requested() => break,
+                };
+                let Some(permit) = limiter.try_acquire(addr.ip()) else {
+                    debug!("Refused connection from {addr}: too many connections");
+                    continue;
+                };
+                let server_msg_rcvr = receiver.resubscribe();
                 tokio::spawn(async move {
-                    handle_connection(stream, addr, server_msg_rcvr, world2).await;
+                    let _ = handle_connection(stream, addr, permit, server_msg_rcvr, world).await;
                 });
             }
-            error!("Listener couldn't listen anymore");
         });
 
-        ServerBehavior {
-            world,
-            message_receiver: receiver,
+        let step_sender = sender.clone();
+        let task = tokio::spawn(async move {
+            let reason = world.shutdown_requested().await;
+            info!("Shutting down: {reason}");
+            let _ = accept_task.await;
+            let _ = tick_task.await;
+            for task in [metrics_task, rcon_task, query_task].into_iter().flatten() {
+                let _ = task.await;
+            }
+
+            // Players save their data as they leave
+            let _ = sender.send(ServerMessage::Shutdown(reason));
+            let left = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+                while !world.players().await.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            }).await;
+            if left.is_err() {
+                warn!("Some players didn't leave in time");
+            }
+
+            world.ticks().stop();
+            world.abort_entity_tasks().await;
+            world.save_all().await;
+            info!("Server stopped");
+        });
+
+        Ok(ServerBehavior { world, addr, metrics_addr, rcon_addr, query_addr, task, sender: step_sender, tick_source })
+    }
+}
+
+/// Runs a tick of the world, wakes entities and players up, and waits until entities are done.
+/// Returns how long the tick took.
+async fn tick(world: &'static World, sender: &BroadcastSender<ServerMessage>) -> Duration {
+    let start = Instant::now();
+    world.tick().await;
+    let tick_id = world.ticks().advance();
+    let _ = sender.send(ServerMessage::Tick(tick_id));
+    world.ticks().processed().await;
+    let duration = start.elapsed();
+    world.record_tick(start, duration).await;
+    duration
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        ServerBuilder::new()
+    }
+}
+
+/// A running server
+pub struct ServerBehavior {
+    world: &'static World,
+    addr: SocketAddr,
+    metrics_addr: Option<SocketAddr>,
+    rcon_addr: Option<SocketAddr>,
+    query_addr: Option<SocketAddr>,
+    task: JoinHandle<()>,
+    sender: BroadcastSender<ServerMessage>,
+    tick_source: TickSource,
+}
+
+impl ServerBehavior {
+    /// Starts the server configured by `server.properties`.
+    pub async fn init() -> ServerBehavior {
+        let config = ServerConfig::load("server.properties").unwrap_or_else(|e| panic!("Invalid server.properties: {e}"));
+        let directory = PathBuf::from(&config.level_name);
+        let mut builder = ServerBuilder::new().config(config).storage(directory);
+        match load_favicon("server-icon.png") {
+            Ok(favicon) => builder = builder.favicon(favicon),
+            Err(e) => debug!("No server icon: {e}"),
         }
+        builder.start().await.expect("Failed to listen")
     }
 
+    pub fn world(&self) -> &'static World {
+        self.world
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the address metrics are served on, if they are.
+    pub fn metrics_addr(&self) -> Option<SocketAddr> {
+        self.metrics_addr
+    }
+
+    /// Returns the address RCON listens on, if it does.
+    pub fn rcon_addr(&self) -> Option<SocketAddr> {
+        self.rcon_addr
+    }
+
+    /// Returns the UDP address queries are answered on, if they are.
+    pub fn query_addr(&self) -> Option<SocketAddr> {
+        self.query_addr
+    }
+
+    /// Runs ticks one after the other, waiting until entities processed each of them.
+    ///
+    /// # Panics
+    ///
+    /// If the server wasn't built with [TickSource::Manual].
+    pub async fn step(&self, ticks: usize) {
+        assert_eq!(self.tick_source, TickSource::Manual, "Only servers with manual ticks can be stepped");
+        for _ in 0..ticks {
+            tick(self.world, &self.sender).await;
+        }
+    }
+
+    /// Shuts the server down and waits until the world is saved.
+    pub async fn stop(mut self) {
+        self.world.shutdown("Server closed");
+        futures::future::poll_fn(|cx| self.poll(cx)).await;
+    }
+
+    /// Ready once the server has shut down and saved the world.
     pub fn poll(
         &mut self,
         cx: &mut Context<'_>
     ) -> Poll<()> {
-        Pending
+        Pin::new(&mut self.task).poll(cx).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::player_handler::test_client::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let mut server = ServerBuilder::new().address(SocketAddr::from(([127, 0, 0, 1], 0))).start().await.unwrap();
+        let addr = server.local_addr();
+        assert!(TcpStream::connect(addr).await.is_ok());
+
+        server.world().shutdown("Test is over");
+        tokio::time::timeout(Duration::from_secs(5), futures::future::poll_fn(|cx| server.poll(cx))).await.unwrap();
+        assert!(server.world().is_shutting_down());
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_builder_order() {
+        let config = ServerConfig { server_port: 1, motd: String::from("Configured"), ..ServerConfig::default() };
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .query(0)
+            .config(config)
+            .start().await.unwrap();
+        assert_ne!(server.local_addr().port(), 1);
+        assert!(server.query_addr().is_some());
+        assert_eq!(server.world().config().motd, "Configured");
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_manual_ticks() {
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .tick_source(TickSource::Manual)
+            .start().await.unwrap();
+        let world = server.world();
+        let mut zombie = Zombie::default();
+        zombie.get_entity_mut().position = Position { x: 0.5, y: 0.0, z: 0.5 };
+        let eid = world.spawn_entity::<Zombie>(AnyEntity::Zombie(zombie)).await;
+
+        // Steps only return once physics ran, so the fall is the same on every run
+        for step in 1..=3 {
+            server.step(10).await;
+            let y = world.observe_entity(eid, |entity| entity.as_entity().position.y).await.unwrap();
+            assert!((y + step as f64 * 10.0 * 9.81 / 20.0).abs() < 1e-9, "Zombie is at y={y} after {step} steps");
+        }
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .metrics_address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .tick_source(TickSource::Manual)
+            .start().await.unwrap();
+        server.step(1).await;
+        let metrics_addr = server.metrics_addr().unwrap();
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(metrics_addr).await.unwrap();
+            stream.write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let response = get("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.lines().any(|line| line == "minecraft_players 0"));
+        assert!(response.lines().any(|line| line == "minecraft_tick_duration_seconds_count 1"));
+        assert!(get("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        server.stop().await;
+        assert!(TcpStream::connect(metrics_addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_operators() {
+        let server = ServerBuilder::new()
+            .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .operator(2, 2)
+            .tick_source(TickSource::Manual)
+            .start().await.unwrap();
+        let world = server.world();
+        let _player = connect(server.local_addr(), &join_packets("player", 1)).await;
+        let _operator = connect(server.local_addr(), &join_packets("operator", 2)).await;
+        let (_, player) = wait_for_player(world, "player").await;
+        let (_, operator) = wait_for_player(world, "operator").await;
+        let refused = |eid: Eid, command: &'static str| async move {
+            let result = world.commands().execute(world, CommandSender::Player(Handler::assume(eid, world)), command).await;
+            matches!(result, Err(error) if error.starts_with("Unknown command"))
+        };
+
+        // Players that aren't operators can't run commands that need a permission level
+        assert_eq!(world.permission_level(1).await, 0);
+        assert!(refused(player.eid, "tps").await);
+        assert!(!refused(player.eid, "help").await);
+        assert!(!refused(operator.eid, "tps").await);
+        assert!(refused(operator.eid, "save-all").await);
+
+        server.stop().await;
     }
 }
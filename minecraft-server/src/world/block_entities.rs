@@ -0,0 +1,375 @@
+use crate::prelude::*;
+use minecraft_protocol::{
+    components::{blocks::{BlockEntity as NetworkBlockEntity, BlockEntityType}, slots::SlotItem},
+    ids::{blocks::Block, items::Item},
+    nbt::arrays::NbtList,
+};
+
+/// The state of a block entity, stored by the map next to the block it belongs to.
+#[derive(Debug, Clone)]
+pub struct BlockEntity {
+    pub ty: BlockEntityType,
+    pub data: BlockEntityData,
+}
+
+#[derive(Debug, Clone)]
+pub enum BlockEntityData {
+    Sign {
+        front_text: SignText,
+        back_text: SignText,
+        is_waxed: bool,
+    },
+    /// Chests, barrels, shulker boxes, dispensers, droppers and hoppers
+    Container {
+        items: Vec<Slot>,
+        custom_name: Option<String>,
+    },
+    /// Furnaces, smokers and blast furnaces
+    Furnace {
+        /// Input, fuel and output slots
+        items: [Slot; 3],
+        /// Ticks left before the fuel burns out
+        burn_time: i16,
+        /// The number of ticks the last fuel lasted, which is not saved
+        burn_time_total: i16,
+        cook_time: i16,
+        cook_time_total: i16,
+        custom_name: Option<String>,
+    },
+    Banner {
+        patterns: Vec<BannerPattern>,
+        custom_name: Option<String>,
+    },
+    /// Block entities that have no dedicated representation yet keep their raw NBT.
+    Other(HashMap<String, NbtTag>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SignText {
+    /// The four lines, as JSON text components
+    pub messages: [String; 4],
+    pub color: String,
+    pub has_glowing_text: bool,
+}
+
+impl Default for SignText {
+    fn default() -> Self {
+        let empty = String::from("\"\"");
+        SignText {
+            messages: [empty.clone(), empty.clone(), empty.clone(), empty],
+            color: String::from("black"),
+            has_glowing_text: false,
+        }
+    }
+}
+
+impl SignText {
+    /// Replaces the lines with plain text, as sent by clients when editing a sign.
+    pub fn set_lines(&mut self, lines: [&str; 4]) {
+        for (message, line) in self.messages.iter_mut().zip(lines) {
+            *message = text_component(line);
+        }
+    }
+
+    fn to_nbt(&self) -> NbtTag {
+        let mut compound = HashMap::new();
+        compound.insert(String::from("messages"), NbtTag::List(NbtList::String(self.messages.to_vec())));
+        compound.insert(String::from("color"), NbtTag::String(self.color.clone()));
+        compound.insert(String::from("has_glowing_text"), NbtTag::Byte(self.has_glowing_text as i8));
+        NbtTag::Compound(compound)
+    }
+
+    fn from_nbt(tag: Option<&NbtTag>) -> SignText {
+        let mut text = SignText::default();
+        let Some(compound) = tag.and_then(|t| t.as_compound()) else { return text };
+        if let Some(NbtList::String(messages)) = compound.get("messages").and_then(|t| t.as_list()) {
+            for (message, value) in text.messages.iter_mut().zip(messages) {
+                *message = value.clone();
+            }
+        }
+        if let Some(color) = compound.get("color").and_then(|t| t.as_string()) {
+            text.color = color.clone();
+        }
+        text.has_glowing_text = compound.get("has_glowing_text").and_then(|t| t.as_byte()).map(|b| *b != 0).unwrap_or(false);
+        text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BannerPattern {
+    pub pattern: String,
+    pub color: i32,
+}
+
+/// Wraps plain text into a JSON text component.
+pub fn text_component(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 12);
+    json.push_str("{\"text\":\"");
+    push_json_escaped(&mut json, text);
+    json.push_str("\"}");
+    json
+}
+
+/// Appends text to a JSON string literal, escaping what needs to be.
+pub fn push_json_escaped(json: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+}
+
+fn container_size(ty: BlockEntityType) -> Option<usize> {
+    match ty {
+        BlockEntityType::Chest | BlockEntityType::TrappedChest | BlockEntityType::Barrel | BlockEntityType::ShulkerBox => Some(27),
+        BlockEntityType::Dispenser | BlockEntityType::Dropper => Some(9),
+        BlockEntityType::Hopper => Some(5),
+        _ => None,
+    }
+}
+
+pub(super) fn items_to_nbt(items: &[Slot]) -> NbtTag {
+    let mut list = Vec::new();
+    for (i, slot) in items.iter().enumerate() {
+        let Some(item) = &slot.item else { continue };
+        let mut compound = HashMap::new();
+        compound.insert(String::from("Slot"), NbtTag::Byte(i as i8));
+        compound.insert(String::from("id"), NbtTag::String(format!("minecraft:{}", item.item_id.text_id())));
+        compound.insert(String::from("Count"), NbtTag::Byte(item.item_count));
+        if !item.nbt_data.is_null() {
+            compound.insert(String::from("tag"), item.nbt_data.clone());
+        }
+        list.push(compound);
+    }
+    NbtTag::List(NbtList::Compound(list))
+}
+
+pub(super) fn items_from_nbt(tag: Option<&NbtTag>, items: &mut [Slot]) {
+    let Some(NbtList::Compound(list)) = tag.and_then(|t| t.as_list()) else { return };
+    for compound in list {
+        let Some(index) = compound.get("Slot").and_then(|t| t.as_byte()).and_then(|i| usize::try_from(*i).ok()) else { continue };
+        let Some(item_id) = compound.get("id").and_then(|t| t.as_string()).and_then(|id| Item::from_text_id(id)) else { continue };
+        let item_count = compound.get("Count").and_then(|t| t.as_byte()).copied().unwrap_or(1);
+        let nbt_data = compound.get("tag").cloned().unwrap_or(NbtTag::Null);
+        if let Some(slot) = items.get_mut(index) {
+            slot.item = Some(SlotItem { item_id, item_count, nbt_data });
+        }
+    }
+}
+
+fn insert_custom_name(compound: &mut HashMap<String, NbtTag>, custom_name: &Option<String>) {
+    if let Some(custom_name) = custom_name {
+        compound.insert(String::from("CustomName"), NbtTag::String(custom_name.clone()));
+    }
+}
+
+fn read_custom_name(compound: &HashMap<String, NbtTag>) -> Option<String> {
+    compound.get("CustomName").and_then(|t| t.as_string()).cloned()
+}
+
+impl BlockEntity {
+    /// Creates an empty block entity of the given type.
+    pub fn new(ty: BlockEntityType) -> BlockEntity {
+        let data = match ty {
+            BlockEntityType::Sign | BlockEntityType::HangingSign => BlockEntityData::Sign {
+                front_text: SignText::default(),
+                back_text: SignText::default(),
+                is_waxed: false,
+            },
+            BlockEntityType::Furnace | BlockEntityType::Smoker | BlockEntityType::BlastFurnace => BlockEntityData::Furnace {
+                items: Default::default(),
+                burn_time: 0,
+                burn_time_total: 0,
+                cook_time: 0,
+                cook_time_total: 0,
+                custom_name: None,
+            },
+            BlockEntityType::Banner => BlockEntityData::Banner {
+                patterns: Vec::new(),
+                custom_name: None,
+            },
+            ty => match container_size(ty) {
+                Some(size) => BlockEntityData::Container {
+                    items: vec![Slot::default(); size],
+                    custom_name: None,
+                },
+                None => BlockEntityData::Other(HashMap::new()),
+            }
+        };
+        BlockEntity { ty, data }
+    }
+
+    /// Creates the empty block entity that comes with a block, if that block has one.
+    pub fn for_block(block: &BlockWithState) -> Option<BlockEntity> {
+        let block: Block = block.clone().into();
+        BlockEntityType::from_block(block).map(BlockEntity::new)
+    }
+
+    /// Returns the slots of chests, furnaces and other containers.
+    pub fn slots(&self) -> Option<&[Slot]> {
+        match &self.data {
+            BlockEntityData::Container { items, .. } => Some(items),
+            BlockEntityData::Furnace { items, .. } => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn slots_mut(&mut self) -> Option<&mut [Slot]> {
+        match &mut self.data {
+            BlockEntityData::Container { items, .. } => Some(items),
+            BlockEntityData::Furnace { items, .. } => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Serializes the full state, as stored on disk.
+    /// The position is not included.
+    fn data_to_nbt(&self) -> HashMap<String, NbtTag> {
+        let mut compound = HashMap::new();
+        match &self.data {
+            BlockEntityData::Sign { front_text, back_text, is_waxed } => {
+                compound.insert(String::from("front_text"), front_text.to_nbt());
+                compound.insert(String::from("back_text"), back_text.to_nbt());
+                compound.insert(String::from("is_waxed"), NbtTag::Byte(*is_waxed as i8));
+            }
+            BlockEntityData::Container { items, custom_name } => {
+                compound.insert(String::from("Items"), items_to_nbt(items));
+                insert_custom_name(&mut compound, custom_name);
+            }
+            BlockEntityData::Furnace { items, burn_time, cook_time, cook_time_total, custom_name, .. } => {
+                compound.insert(String::from("Items"), items_to_nbt(items));
+                compound.insert(String::from("BurnTime"), NbtTag::Short(*burn_time));
+                compound.insert(String::from("CookTime"), NbtTag::Short(*cook_time));
+                compound.insert(String::from("CookTimeTotal"), NbtTag::Short(*cook_time_total));
+                insert_custom_name(&mut compound, custom_name);
+            }
+            BlockEntityData::Banner { patterns, custom_name } => {
+                let patterns = patterns.iter().map(|pattern| {
+                    let mut compound = HashMap::new();
+                    compound.insert(String::from("Pattern"), NbtTag::String(pattern.pattern.clone()));
+                    compound.insert(String::from("Color"), NbtTag::Int(pattern.color));
+                    compound
+                }).collect();
+                compound.insert(String::from("Patterns"), NbtTag::List(NbtList::Compound(patterns)));
+                insert_custom_name(&mut compound, custom_name);
+            }
+            BlockEntityData::Other(other) => compound = other.clone(),
+        }
+        compound
+    }
+
+    /// Returns the data clients need to render this block entity.
+    /// Container contents are only sent to players that open them.
+    pub fn to_network_nbt(&self) -> NbtTag {
+        let mut compound = self.data_to_nbt();
+        compound.remove("Items");
+        NbtTag::Compound(compound)
+    }
+
+    pub fn to_network(&self, position: &BlockPositionInChunkColumn) -> NetworkBlockEntity {
+        NetworkBlockEntity::new(position.bx, position.y as i16, position.bz, self.ty, self.to_network_nbt())
+    }
+
+    /// Serializes this block entity in the format used by chunk storage.
+    pub fn to_nbt(&self, position: &BlockPosition) -> NbtTag {
+        let mut compound = self.data_to_nbt();
+        compound.insert(String::from("id"), NbtTag::String(self.ty.text_id().to_string()));
+        compound.insert(String::from("x"), NbtTag::Int(position.x));
+        compound.insert(String::from("y"), NbtTag::Int(position.y));
+        compound.insert(String::from("z"), NbtTag::Int(position.z));
+        NbtTag::Compound(compound)
+    }
+
+    /// Parses a block entity in the format used by chunk storage.
+    pub fn from_nbt(tag: &NbtTag) -> Option<(BlockPosition, BlockEntity)> {
+        let compound = tag.as_compound()?;
+        let ty = BlockEntityType::from_text_id(compound.get("id")?.as_string()?)?;
+        let position = BlockPosition {
+            x: *compound.get("x")?.as_int()?,
+            y: *compound.get("y")?.as_int()?,
+            z: *compound.get("z")?.as_int()?,
+        };
+
+        let mut block_entity = BlockEntity::new(ty);
+        match &mut block_entity.data {
+            BlockEntityData::Sign { front_text, back_text, is_waxed } => {
+                *front_text = SignText::from_nbt(compound.get("front_text"));
+                *back_text = SignText::from_nbt(compound.get("back_text"));
+                *is_waxed = compound.get("is_waxed").and_then(|t| t.as_byte()).map(|b| *b != 0).unwrap_or(false);
+            }
+            BlockEntityData::Container { items, custom_name } => {
+                items_from_nbt(compound.get("Items"), items);
+                *custom_name = read_custom_name(compound);
+            }
+            BlockEntityData::Furnace { items, burn_time, burn_time_total, cook_time, cook_time_total, custom_name } => {
+                items_from_nbt(compound.get("Items"), items);
+                *burn_time = compound.get("BurnTime").and_then(|t| t.as_short()).copied().unwrap_or(0);
+                *burn_time_total = *burn_time;
+                *cook_time = compound.get("CookTime").and_then(|t| t.as_short()).copied().unwrap_or(0);
+                *cook_time_total = compound.get("CookTimeTotal").and_then(|t| t.as_short()).copied().unwrap_or(0);
+                *custom_name = read_custom_name(compound);
+            }
+            BlockEntityData::Banner { patterns, custom_name } => {
+                if let Some(NbtList::Compound(list)) = compound.get("Patterns").and_then(|t| t.as_list()) {
+                    for pattern in list {
+                        let Some(name) = pattern.get("Pattern").and_then(|t| t.as_string()) else { continue };
+                        let color = pattern.get("Color").and_then(|t| t.as_int()).copied().unwrap_or(0);
+                        patterns.push(BannerPattern { pattern: name.clone(), color });
+                    }
+                }
+                *custom_name = read_custom_name(compound);
+            }
+            BlockEntityData::Other(other) => {
+                *other = compound.clone();
+                for key in ["id", "x", "y", "z"] {
+                    other.remove(key);
+                }
+            }
+        }
+
+        Some((position, block_entity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_component() {
+        assert_eq!(text_component("Hello"), r#"{"text":"Hello"}"#);
+        assert_eq!(text_component("a \"quote\" \\"), r#"{"text":"a \"quote\" \\"}"#);
+    }
+
+    #[test]
+    fn test_block_entity_nbt() {
+        let position = BlockPosition { x: -5, y: 70, z: 17 };
+
+        let mut sign = BlockEntity::for_block(&BlockWithState::from_state_id(Block::OakSign.default_state_id()).unwrap()).unwrap();
+        let BlockEntityData::Sign { front_text, .. } = &mut sign.data else { panic!("Sign has wrong data") };
+        front_text.set_lines(["Hello", "", "world", ""]);
+        let (parsed_position, parsed) = BlockEntity::from_nbt(&sign.to_nbt(&position)).unwrap();
+        assert_eq!(parsed_position, position);
+        let BlockEntityData::Sign { front_text, .. } = parsed.data else { panic!("Sign has wrong data") };
+        assert_eq!(front_text.messages[0], r#"{"text":"Hello"}"#);
+        assert_eq!(front_text.messages[2], r#"{"text":"world"}"#);
+
+        let mut chest = BlockEntity::new(BlockEntityType::Chest);
+        let BlockEntityData::Container { items, .. } = &mut chest.data else { panic!("Chest has wrong data") };
+        items[13].item = Some(SlotItem { item_id: Item::Diamond, item_count: 12, nbt_data: NbtTag::Null });
+        let (_, parsed) = BlockEntity::from_nbt(&chest.to_nbt(&position)).unwrap();
+        let BlockEntityData::Container { items, .. } = parsed.data else { panic!("Chest has wrong data") };
+        assert_eq!(items.len(), 27);
+        let item = items[13].item.as_ref().unwrap();
+        assert_eq!(item.item_id, Item::Diamond);
+        assert_eq!(item.item_count, 12);
+        assert!(items[0].item.is_none());
+        assert!(chest.to_network_nbt().as_compound().unwrap().get("Items").is_none());
+    }
+}
@@ -1,8 +1,45 @@
+use minecraft_protocol::{components::{blocks::BlockEntityType, slots::EquipmentSlot}, ids::blocks::Block};
 use crate::prelude::*;
 
 #[derive(Debug, Clone)]
 pub enum WorldChange {
     Block(BlockPosition, BlockWithState),
+    /// Several blocks changed in the same chunk section
+    SectionBlocks {
+        section: ChunkPosition,
+        blocks: Vec<(BlockPositionInChunk, BlockWithState)>,
+    },
+    BlockEntity {
+        position: BlockPosition,
+        ty: BlockEntityType,
+        /// The data clients need, as returned by [BlockEntity::to_network_nbt]
+        data: NbtTag,
+    },
+    /// An entity is breaking a block
+    BlockDestroyStage {
+        eid: Eid,
+        position: BlockPosition,
+        /// From 0 to 9, or `None` when the block is no longer being broken
+        stage: Option<u8>,
+    },
+    /// A block plays an animation, such as a chest opening
+    BlockAction {
+        position: BlockPosition,
+        action_id: u8,
+        action_param: u8,
+        block: Block,
+    },
+    /// Slots of a container block entity changed
+    ContainerSlots {
+        position: BlockPosition,
+        slots: Vec<(usize, Slot)>,
+    },
+    /// A window property of a container block entity changed, such as the progress of a furnace
+    ContainerProperty {
+        position: BlockPosition,
+        property: i16,
+        value: i16,
+    },
     EntitySpawned {
         eid: Eid,
         uuid: UUID,
@@ -13,14 +50,14 @@ pub enum WorldChange {
         head_yaw: f32,
         data: u32,
         velocity: Translation,
-        metadata: (),
+        metadata: BTreeMap<u8, EntityMetadataValue<'static>>,
     },
     EntityDispawned {
         eid: Eid,
     },
     EntityMetadata {
         eid: Eid,
-        metadata: (),
+        metadata: BTreeMap<u8, EntityMetadataValue<'static>>,
     },
     EntityPosition {
         eid: Eid,
@@ -36,6 +73,38 @@ pub enum WorldChange {
         yaw: f32,
         head_yaw: f32,
     },
+    /// The items an entity holds or wears changed
+    EntityEquipment {
+        eid: Eid,
+        equipment: Vec<(EquipmentSlot, Slot)>,
+    },
+    /// A player was added to the player list, sent to all loaders
+    PlayerInfoAdded {
+        uuid: UUID,
+        entry: PlayerListEntry,
+    },
+    /// An entry of the player list changed, sent to all loaders
+    PlayerInfoUpdated {
+        uuid: UUID,
+        update: PlayerListUpdate,
+    },
+    /// A player was removed from the player list, sent to all loaders
+    PlayerInfoRemoved {
+        uuid: UUID,
+    },
+    /// A chat message, sent to all loaders
+    Chat {
+        kind: ChatKind,
+        /// The player who wrote the message
+        sender: Option<UUID>,
+        text: ChatText,
+    },
+    /// An entity collected an item, which is only an animation
+    ItemPickedUp {
+        collected: Eid,
+        collector: Eid,
+        count: i8,
+    },
 }
 
 pub struct EntityChanges(u8);
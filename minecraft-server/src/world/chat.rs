@@ -0,0 +1,218 @@
+use std::time::Instant;
+use super::*;
+
+/// A piece of a chat message sharing the same color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatSpan {
+    pub text: String,
+    /// A color name such as `"gold"`, or a hex color such as `"#ff8800"`
+    pub color: Option<String>,
+}
+
+/// A formatted chat message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatText {
+    pub spans: Vec<ChatSpan>,
+}
+
+impl ChatText {
+    pub fn plain(text: impl Into<String>) -> ChatText {
+        ChatText::default().push(text)
+    }
+
+    pub fn colored(text: impl Into<String>, color: &str) -> ChatText {
+        ChatText::default().push_colored(text, color)
+    }
+
+    pub fn push(mut self, text: impl Into<String>) -> ChatText {
+        self.spans.push(ChatSpan { text: text.into(), color: None });
+        self
+    }
+
+    pub fn push_colored(mut self, text: impl Into<String>, color: &str) -> ChatText {
+        self.spans.push(ChatSpan { text: text.into(), color: Some(color.to_string()) });
+        self
+    }
+
+    /// Returns the message without formatting.
+    pub fn to_plain(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Returns the message as a JSON text component.
+    /// Colors are left out for players who disabled them.
+    pub fn to_json(&self, colors: bool) -> String {
+        let mut json = String::from("{\"text\":\"\",\"extra\":[");
+        for (i, span) in self.spans.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"text\":\"");
+            push_json_escaped(&mut json, &span.text);
+            json.push('"');
+            if let (true, Some(color)) = (colors, &span.color) {
+                json.push_str(",\"color\":\"");
+                push_json_escaped(&mut json, color);
+                json.push('"');
+            }
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// What a chat message is, which decides who sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatKind {
+    /// Written by a player, hidden from players who only want command feedback
+    Player,
+    /// Sent by the server, such as announcements and command feedback
+    System,
+    /// Shown above the hotbar, even to players who hid the chat
+    ActionBar,
+}
+
+/// Why a chat message wasn't sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatError {
+    Muted,
+    /// The chat filter dropped the message
+    Filtered,
+    UnknownSender,
+}
+
+/// Inspects messages written by players before they are broadcast.
+/// Returns the message to send, possibly modified, or `None` to drop it.
+pub type ChatFilter = Box<dyn Fn(UUID, &str) -> Option<String> + Send + Sync>;
+
+/// Limits how fast a player can chat, as vanilla does.
+///
+/// Each message adds 20 to a counter that goes down by 1 every tick.
+/// Messages are refused while the counter is above 200.
+#[derive(Debug, Clone)]
+pub struct ChatRateLimiter {
+    spam: u32,
+    last_update: Instant,
+}
+
+impl ChatRateLimiter {
+    const COST: u32 = 20;
+    const THRESHOLD: u32 = 200;
+
+    pub fn new() -> ChatRateLimiter {
+        ChatRateLimiter { spam: 0, last_update: Instant::now() }
+    }
+
+    /// Returns true if a message sent at `now` is allowed.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let elapsed_ticks = now.saturating_duration_since(self.last_update).as_millis() / 50;
+        if elapsed_ticks > 0 {
+            self.spam = self.spam.saturating_sub(elapsed_ticks.min(u32::MAX as u128) as u32);
+            self.last_update += Duration::from_millis(elapsed_ticks as u64 * 50);
+        }
+        if self.spam + Self::COST > Self::THRESHOLD {
+            return false;
+        }
+        self.spam += Self::COST;
+        true
+    }
+}
+
+impl Default for ChatRateLimiter {
+    fn default() -> Self {
+        ChatRateLimiter::new()
+    }
+}
+
+impl World {
+    /// Sets the hook that inspects messages written by players.
+    pub async fn set_chat_filter(&self, filter: Option<ChatFilter>) {
+        *self.chat_filter.write().await = filter;
+    }
+
+    pub async fn mute(&self, uuid: UUID) {
+        self.muted.write().await.insert(uuid);
+    }
+
+    pub async fn unmute(&self, uuid: UUID) -> bool {
+        self.muted.write().await.remove(&uuid)
+    }
+
+    pub async fn is_muted(&self, uuid: UUID) -> bool {
+        self.muted.read().await.contains(&uuid)
+    }
+
+    /// Filters, formats and broadcasts a message written by a player.
+    pub async fn chat(&self, sender: UUID, message: &str) -> Result<(), ChatError> {
+        if self.is_muted(sender).await {
+            return Err(ChatError::Muted);
+        }
+        let name = self.player(sender).await.ok_or(ChatError::UnknownSender)?.name;
+        let message = match &*self.chat_filter.read().await {
+            Some(filter) => filter(sender, message).ok_or(ChatError::Filtered)?,
+            None => message.to_string(),
+        };
+        info!("<{name}> {message}");
+        let text = ChatText::plain("<").push(name).push("> ").push(message);
+        self.notify_all(WorldChange::Chat { kind: ChatKind::Player, sender: Some(sender), text }).await;
+        Ok(())
+    }
+
+    /// Sends a message to all players.
+    pub async fn broadcast_message(&self, kind: ChatKind, text: ChatText) {
+        info!("{}", text.to_plain());
+        self.notify_all(WorldChange::Chat { kind, sender: None, text }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_text() {
+        let text = ChatText::colored("[Server]", "gold").push(" say \"hi\"");
+        assert_eq!(text.to_plain(), "[Server] say \"hi\"");
+        assert_eq!(text.to_json(true), r#"{"text":"","extra":[{"text":"[Server]","color":"gold"},{"text":" say \"hi\""}]}"#);
+        assert_eq!(text.to_json(false), r#"{"text":"","extra":[{"text":"[Server]"},{"text":" say \"hi\""}]}"#);
+    }
+
+    #[test]
+    fn test_chat_rate_limiter() {
+        let start = Instant::now();
+        let mut limiter = ChatRateLimiter { spam: 0, last_update: start };
+        for _ in 0..10 {
+            assert!(limiter.allow(start));
+        }
+        assert!(!limiter.allow(start));
+
+        // One message is allowed again once 20 ticks have passed
+        assert!(limiter.allow(start + Duration::from_secs(1)));
+        assert!(!limiter.allow(start + Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn test_chat() {
+        let world = World::new();
+        let mut receiver = world.add_loader(1).await;
+        let entry = PlayerListEntry { eid: 1, name: String::from("Steve"), properties: Vec::new(), gamemode: Gamemode::Survival, latency: 0, listed: true };
+        world.add_player(1, entry).await;
+        receiver.try_recv().unwrap();
+
+        world.chat(1, "hello").await.unwrap();
+        match receiver.try_recv() {
+            Ok(WorldChange::Chat { kind: ChatKind::Player, sender: Some(1), text }) => assert_eq!(text.to_plain(), "<Steve> hello"),
+            change => panic!("Unexpected change {change:?}"),
+        }
+
+        world.set_chat_filter(Some(Box::new(|_, message: &str| (!message.contains("spam")).then(|| message.replace("darn", "****"))))).await;
+        assert_eq!(world.chat(1, "buy spam").await, Err(ChatError::Filtered));
+        world.chat(1, "darn").await.unwrap();
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::Chat { text, .. }) if text.to_plain() == "<Steve> ****"));
+
+        world.mute(1).await;
+        assert_eq!(world.chat(1, "hello").await, Err(ChatError::Muted));
+        assert_eq!(world.chat(2, "hello").await, Err(ChatError::UnknownSender));
+    }
+}
@@ -38,6 +38,44 @@ pub struct ExcludingBlockRange {
     exclusion: BlockRange,
 }
 
+impl BlockRange {
+    /// Creates the range of blocks between two corners, both included.
+    pub fn new(corner1: BlockPosition, corner2: BlockPosition) -> BlockRange {
+        BlockRange {
+            x: corner1.x.min(corner2.x)..corner1.x.max(corner2.x) + 1,
+            y: corner1.y.min(corner2.y)..corner1.y.max(corner2.y) + 1,
+            z: corner1.z.min(corner2.z)..corner1.z.max(corner2.z) + 1,
+        }
+    }
+
+    /// Returns the corner with the lowest coordinates.
+    pub fn min(&self) -> BlockPosition {
+        BlockPosition { x: self.x.start, y: self.y.start, z: self.z.start }
+    }
+
+    /// Returns the number of blocks along each axis.
+    pub fn size(&self) -> (i32, i32, i32) {
+        (self.x.len() as i32, self.y.len() as i32, self.z.len() as i32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() || self.y.is_empty() || self.z.is_empty()
+    }
+
+    pub fn contains(&self, position: &BlockPosition) -> bool {
+        self.x.contains(&position.x) && self.y.contains(&position.y) && self.z.contains(&position.z)
+    }
+
+    /// Returns true if the position is inside the range and on one of its faces.
+    pub fn is_on_border(&self, position: &BlockPosition) -> bool {
+        self.contains(position) && (
+            position.x == self.x.start || position.x == self.x.end - 1 ||
+            position.y == self.y.start || position.y == self.y.end - 1 ||
+            position.z == self.z.start || position.z == self.z.end - 1
+        )
+    }
+}
+
 impl IntoIterator for BlockRange {
     type Item = BlockPosition;
     type IntoIter = BlockRangeIntoIter;
@@ -84,6 +122,9 @@ impl Iterator for BlockRangeIntoIter {
     type Item = BlockPosition;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.range.is_empty() || self.z >= self.range.z.end {
+            return None;
+        }
         if self.x >= self.range.x.end {
             self.x = self.range.x.start;
             self.y += 1;
@@ -134,6 +175,26 @@ impl Iterator for ExcludingBlockRangeIntoIter {
 }
 
 impl CollisionShape {
+    /// The shape of a full block at this position
+    pub fn full_block(position: &BlockPosition) -> CollisionShape {
+        CollisionShape {
+            x1: position.x as f64,
+            y1: position.y as f64,
+            z1: position.z as f64,
+            x2: position.x as f64 + 1.0,
+            y2: position.y as f64 + 1.0,
+            z2: position.z as f64 + 1.0,
+        }
+    }
+
+    /// Returns true if the shapes overlap.
+    /// Shapes that only touch each other don't intersect.
+    pub fn intersects(&self, other: &CollisionShape) -> bool {
+        self.x1 < other.x2 && other.x1 < self.x2
+            && self.y1 < other.y2 && other.y1 < self.y2
+            && self.z1 < other.z2 && other.z1 < self.z2
+    }
+
     const fn points(&self) -> PointIter {
         PointIter {
             shape: self,
@@ -501,6 +562,20 @@ impl std::ops::Mul<f64> for Translation {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_block_range() {
+        let range = BlockRange::new(BlockPosition { x: 2, y: 5, z: -1 }, BlockPosition { x: 0, y: 5, z: 1 });
+        assert_eq!(range.size(), (3, 1, 3));
+        assert_eq!(range.min(), BlockPosition { x: 0, y: 5, z: -1 });
+        assert_eq!(range.clone().into_iter().count(), 9);
+        assert!(range.contains(&BlockPosition { x: 1, y: 5, z: 0 }));
+        assert!(!range.is_on_border(&BlockPosition { x: 1, y: 6, z: 0 }));
+
+        let empty = BlockRange { x: 0..0, y: 0..4, z: 0..4 };
+        assert!(empty.is_empty());
+        assert_eq!(empty.into_iter().count(), 0);
+    }
+
     #[test]
     fn test_containing_blocks() {
         let shape = CollisionShape {
@@ -526,6 +601,16 @@ mod tests {
         assert_eq!(shape.containing_blocks().into_iter().collect::<Vec<_>>(), vec![BlockPosition {x: 0, y: 0, z: 0}, BlockPosition {x: 1, y: 0, z: 0}]);
     }
 
+    #[test]
+    fn test_intersects() {
+        let block = CollisionShape::full_block(&BlockPosition { x: 0, y: 0, z: 0 });
+        let player = CollisionShape { x1: 0.7, y1: 0.5, z1: 0.2, x2: 1.3, y2: 2.3, z2: 0.8 };
+        assert!(block.intersects(&player));
+        assert!(player.intersects(&block));
+        assert!(!block.intersects(&CollisionShape::full_block(&BlockPosition { x: 1, y: 0, z: 0 })));
+        assert!(!block.intersects(&CollisionShape { x1: 0.2, y1: 1.0, z1: 0.2, x2: 0.8, y2: 2.8, z2: 0.8 }));
+    }
+
     #[test]
     fn test() {
         let shape1 = CollisionShape {
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use crate::*;
 use minecraft_protocol::packets::UUID;
 use tokio::sync::RwLock;
@@ -37,13 +37,18 @@ impl Entities {
     /// Observe entities in a chunk through a closure
     /// That closure will be applied to each entity, and the results will be returned in a vector
     pub(super) async fn observe_entities<R>(&self, chunk: ChunkColumnPosition, mut observer: impl FnMut(&AnyEntity) -> Option<R>) -> Vec<R> {
+        self.observe_entities_with_eids(chunk, |_, entity| observer(entity)).await
+    }
+
+    /// Same as [Entities::observe_entities], but the closure also gets the id of each entity
+    pub(super) async fn observe_entities_with_eids<R>(&self, chunk: ChunkColumnPosition, mut observer: impl FnMut(Eid, &AnyEntity) -> Option<R>) -> Vec<R> {
         let entities = self.entities.read().await;
         let chunks = self.chunks.read().await;
         let Some(eids) = chunks.get(&chunk) else {return Vec::new()};
         let mut results = Vec::with_capacity(eids.len());
         for eid in eids {
             if let Some(entity) = entities.get(eid) {
-                if let Some(r) = observer(entity) {
+                if let Some(r) = observer(*eid, entity) {
                     results.push(r);
                 }
             }
@@ -51,6 +56,12 @@ impl Entities {
         results
     }
 
+    /// Observe every entity through a closure, wherever they are
+    pub(super) async fn observe_all_entities<R>(&self, mut observer: impl FnMut(Eid, &AnyEntity) -> Option<R>) -> Vec<R> {
+        let entities = self.entities.read().await;
+        entities.iter().filter_map(|(eid, entity)| observer(*eid, entity)).collect()
+    }
+
     /// Mutate an entity through a closure
     pub(super) async fn mutate_entity<R>(&self, eid: Eid, mutator: impl FnOnce(&mut AnyEntity) -> (R, EntityChanges)) -> Option<(R, EntityChanges)> {
         let mut entities = self.entities.write().await;
@@ -72,11 +83,21 @@ impl Entities {
         }
     }
 
-    pub(super) async fn spawn_entity<E>(&self, entity: AnyEntity, world: &'static World, receiver: BroadcastReceiver<ServerMessage>) -> (Eid, UUID)
+    pub(super) fn next_eid(&self) -> Eid {
+        self.eid_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub(super) fn next_uuid(&self) -> UUID {
+        self.uuid_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) as u128
+    }
+
+    pub(super) async fn uuid_of(&self, eid: Eid) -> Option<UUID> {
+        self.uuids.read().await.iter().find(|(_, id)| **id == eid).map(|(uuid, _)| *uuid)
+    }
+
+    pub(super) async fn spawn_entity<E>(&self, eid: Eid, uuid: UUID, entity: AnyEntity, world: &'static World, ticks: TickReceiver)
         where AnyEntity: TryAsEntityRef<E>, Handler<E>: EntityExt
     {
-        let eid = self.eid_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let uuid = self.uuid_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) as u128;
         let mut entities = self.entities.write().await;
         let mut chunks = self.chunks.write().await;
         let mut uuids = self.uuids.write().await;
@@ -87,8 +108,7 @@ impl Entities {
         drop(chunks);
         drop(uuids);
         let h = Handler::<E>::assume(eid, world);
-        h.init(receiver).await;
-        (eid, uuid)
+        h.init(ticks).await;
     }
 
     pub(super) async fn insert_entity_task(&self, eid: Eid, name: &'static str, handle: EntityTaskHandle) {
@@ -99,6 +119,38 @@ impl Entities {
         }
     }
 
+    /// Aborts the tasks of all entities.
+    pub(super) async fn abort_tasks(&self) {
+        for (_, tasks) in self.entity_tasks.write().await.drain() {
+            for (_, task) in tasks {
+                task.abort();
+            }
+        }
+    }
+
+    /// Counts entities by type.
+    pub(super) async fn count_by_type(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for entity in self.entities.read().await.values() {
+            let ty = entity.to_network().map(|ty| ty.text_id()).unwrap_or("unknown");
+            *counts.entry(ty).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Counts the running entity tasks by name.
+    pub(super) async fn count_tasks(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for tasks in self.entity_tasks.read().await.values() {
+            for (name, task) in tasks {
+                if !task.is_finished() {
+                    *counts.entry(*name).or_insert(0) += 1;
+                }
+            }
+        }
+        counts
+    }
+
     /// Remove an entity
     pub(super) async fn remove_entity(&self, eid: Eid) -> Option<AnyEntity> {
         let entity = self.entities.write().await.remove(&eid);
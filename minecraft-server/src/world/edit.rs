@@ -0,0 +1,344 @@
+use super::*;
+
+/// A rotation around the vertical axis, as seen from above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockRotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Counterclockwise90,
+}
+
+/// A mirroring of blocks, named after vanilla structure blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMirror {
+    None,
+    /// Swaps north and south
+    LeftRight,
+    /// Swaps east and west
+    FrontBack,
+}
+
+const HORIZONTAL_DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+fn rotate_direction(direction: &str, rotation: BlockRotation) -> Option<&'static str> {
+    let index = HORIZONTAL_DIRECTIONS.iter().position(|d| *d == direction)?;
+    let quarter_turns = match rotation {
+        BlockRotation::None => 0,
+        BlockRotation::Clockwise90 => 1,
+        BlockRotation::Clockwise180 => 2,
+        BlockRotation::Counterclockwise90 => 3,
+    };
+    Some(HORIZONTAL_DIRECTIONS[(index + quarter_turns) % 4])
+}
+
+fn mirror_direction(direction: &str, mirror: BlockMirror) -> Option<&'static str> {
+    let mirrored = match (mirror, direction) {
+        (BlockMirror::LeftRight, "north") => "south",
+        (BlockMirror::LeftRight, "south") => "north",
+        (BlockMirror::FrontBack, "east") => "west",
+        (BlockMirror::FrontBack, "west") => "east",
+        _ => return HORIZONTAL_DIRECTIONS.iter().find(|d| **d == direction).copied(),
+    };
+    Some(mirrored)
+}
+
+/// Transforms every direction-like part of a property value (`ascending_east`, `south_west`, `inner_left`...).
+fn transform_value(value: &str, map_token: impl Fn(&str) -> String) -> Vec<String> {
+    let tokens: Vec<String> = value.split('_').map(map_token).collect();
+    let mut candidates = vec![tokens.join("_")];
+    if tokens.len() == 2 {
+        // Rail corners are always written in the same order (`south_east` but never `east_south`)
+        candidates.push(format!("{}_{}", tokens[1], tokens[0]));
+    }
+    candidates
+}
+
+fn transform_block_with(block: &BlockWithState, map_direction: impl Fn(&str) -> Option<&'static str>, swap_axes: bool, swap_sides: bool, map_rotation: impl Fn(u8) -> u8) -> BlockWithState {
+    let mut transformed = block.clone();
+    let original_properties: Vec<(&str, &str)> = block.properties().iter().filter_map(|(name, _)| Some((*name, block.property(name)?))).collect();
+
+    for (name, value) in &original_properties {
+        let candidates = match *name {
+            // Fences, walls, panes and redstone store connections in properties named after directions
+            "north" | "east" | "south" | "west" => {
+                let Some(target) = map_direction(name) else { continue };
+                transformed = transformed.with_property(target, value).unwrap_or(transformed);
+                continue;
+            }
+            "axis" if swap_axes => match *value {
+                "x" => vec![String::from("z")],
+                "z" => vec![String::from("x")],
+                _ => continue,
+            },
+            "rotation" => match value.parse::<u8>() {
+                Ok(rotation) => vec![map_rotation(rotation).to_string()],
+                Err(_) => continue,
+            },
+            _ => transform_value(value, |token| match token {
+                "left" if swap_sides => String::from("right"),
+                "right" if swap_sides => String::from("left"),
+                token => map_direction(token).map(String::from).unwrap_or_else(|| token.to_string()),
+            }),
+        };
+        if let Some(new) = candidates.iter().find_map(|candidate| transformed.with_property(name, candidate)) {
+            transformed = new;
+        }
+    }
+
+    transformed
+}
+
+/// Rotates a block state, turning its facing, axis, rotation and connections.
+pub fn rotate_block(block: &BlockWithState, rotation: BlockRotation) -> BlockWithState {
+    if rotation == BlockRotation::None {
+        return block.clone();
+    }
+    let swap_axes = matches!(rotation, BlockRotation::Clockwise90 | BlockRotation::Counterclockwise90);
+    let quarter_turns = match rotation {
+        BlockRotation::None => 0,
+        BlockRotation::Clockwise90 => 1,
+        BlockRotation::Clockwise180 => 2,
+        BlockRotation::Counterclockwise90 => 3,
+    };
+    transform_block_with(block, |d| rotate_direction(d, rotation), swap_axes, false, |r| (r + 4 * quarter_turns) % 16)
+}
+
+/// Mirrors a block state, which also swaps left and right for stairs, doors and chests.
+pub fn mirror_block(block: &BlockWithState, mirror: BlockMirror) -> BlockWithState {
+    // Rotation 0 faces south and increases clockwise
+    match mirror {
+        BlockMirror::None => block.clone(),
+        BlockMirror::LeftRight => transform_block_with(block, |d| mirror_direction(d, mirror), false, true, |r| (24 - r) % 16),
+        BlockMirror::FrontBack => transform_block_with(block, |d| mirror_direction(d, mirror), false, true, |r| (16 - r) % 16),
+    }
+}
+
+/// A region of blocks that was copied from the world or loaded from a file.
+/// Blocks are stored in x, then z, then y order, like in Sponge schematics.
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    pub width: i32,
+    pub height: i32,
+    pub length: i32,
+    /// `None` means that the block is left untouched when pasting (structure void).
+    pub blocks: Vec<Option<BlockWithState>>,
+    /// Block entities, indexed by their position relatively to the lowest corner
+    pub block_entities: HashMap<BlockPosition, BlockEntity>,
+}
+
+impl Clipboard {
+    pub fn new(width: i32, height: i32, length: i32) -> Clipboard {
+        let volume = width.max(0) as usize * height.max(0) as usize * length.max(0) as usize;
+        Clipboard {
+            width,
+            height,
+            length,
+            blocks: vec![None; volume],
+            block_entities: HashMap::new(),
+        }
+    }
+
+    fn index(&self, position: &BlockPosition) -> Option<usize> {
+        if !(0..self.width).contains(&position.x) || !(0..self.height).contains(&position.y) || !(0..self.length).contains(&position.z) {
+            return None;
+        }
+        Some((position.x + position.z * self.width + position.y * self.width * self.length) as usize)
+    }
+
+    fn position(&self, index: usize) -> BlockPosition {
+        let index = index as i32;
+        BlockPosition {
+            x: index % self.width,
+            y: index / (self.width * self.length),
+            z: (index / self.width) % self.length,
+        }
+    }
+
+    pub fn get_block(&self, position: &BlockPosition) -> Option<&BlockWithState> {
+        self.blocks.get(self.index(position)?)?.as_ref()
+    }
+
+    pub fn set_block(&mut self, position: &BlockPosition, block: Option<BlockWithState>) {
+        if let Some(index) = self.index(position) {
+            self.blocks[index] = block;
+        }
+    }
+
+    /// Iterates over the blocks that are not structure voids, with their relative position.
+    pub fn iter(&self) -> impl Iterator<Item = (BlockPosition, &BlockWithState)> {
+        self.blocks.iter().enumerate().filter_map(|(i, block)| Some((self.position(i), block.as_ref()?)))
+    }
+
+    /// Returns a transformed copy of this clipboard.
+    /// Mirroring is applied before rotation, as vanilla structure blocks do.
+    pub fn transformed(&self, rotation: BlockRotation, mirror: BlockMirror) -> Clipboard {
+        let (width, length) = match rotation {
+            BlockRotation::Clockwise90 | BlockRotation::Counterclockwise90 => (self.length, self.width),
+            _ => (self.width, self.length),
+        };
+        let transform_position = |position: &BlockPosition| {
+            let (mut x, y, mut z) = (position.x, position.y, position.z);
+            match mirror {
+                BlockMirror::None => (),
+                BlockMirror::LeftRight => z = self.length - 1 - z,
+                BlockMirror::FrontBack => x = self.width - 1 - x,
+            }
+            let (x, z) = match rotation {
+                BlockRotation::None => (x, z),
+                BlockRotation::Clockwise90 => (self.length - 1 - z, x),
+                BlockRotation::Clockwise180 => (self.width - 1 - x, self.length - 1 - z),
+                BlockRotation::Counterclockwise90 => (z, self.width - 1 - x),
+            };
+            BlockPosition { x, y, z }
+        };
+
+        let mut transformed = Clipboard::new(width, self.height, length);
+        for (position, block) in self.iter() {
+            let block = rotate_block(&mirror_block(block, mirror), rotation);
+            transformed.set_block(&transform_position(&position), Some(block));
+        }
+        for (position, block_entity) in &self.block_entities {
+            transformed.block_entities.insert(transform_position(position), block_entity.clone());
+        }
+        transformed
+    }
+}
+
+impl World {
+    /// Sets all blocks of a region.
+    pub async fn fill(&self, range: BlockRange, block: BlockWithState) {
+        self.set_blocks(range.into_iter().map(|position| (position, block.clone()))).await;
+    }
+
+    /// Replaces the blocks of a region that match a condition.
+    /// Returns the number of replaced blocks.
+    pub async fn replace(&self, range: BlockRange, matches: impl Fn(&BlockWithState) -> bool, block: BlockWithState) -> usize {
+        let mut replaced = Vec::new();
+        for position in range {
+            if matches(&self.map.get_block(position.clone()).await) {
+                replaced.push((position, block.clone()));
+            }
+        }
+        let count = replaced.len();
+        self.set_blocks(replaced).await;
+        count
+    }
+
+    /// Builds the outer faces of a region with a block and fills the inside with air.
+    pub async fn hollow_box(&self, range: BlockRange, block: BlockWithState) {
+        let blocks = range.clone().into_iter().map(|position| match range.is_on_border(&position) {
+            true => (position, block.clone()),
+            false => (position, BlockWithState::Air),
+        });
+        self.set_blocks(blocks).await;
+    }
+
+    /// Copies a region, including its block entities.
+    pub async fn copy(&self, range: BlockRange) -> Clipboard {
+        let (width, height, length) = range.size();
+        let origin = range.min();
+        let mut clipboard = Clipboard::new(width, height, length);
+        for position in range {
+            let relative = BlockPosition { x: position.x - origin.x, y: position.y - origin.y, z: position.z - origin.z };
+            clipboard.set_block(&relative, Some(self.map.get_block(position.clone()).await));
+            if let Some(block_entity) = self.map.get_block_entity(position).await {
+                clipboard.block_entities.insert(relative, block_entity);
+            }
+        }
+        clipboard
+    }
+
+    /// Pastes a clipboard with its lowest corner at `origin`.
+    pub async fn paste(&self, clipboard: &Clipboard, origin: BlockPosition, rotation: BlockRotation, mirror: BlockMirror) {
+        let clipboard = clipboard.transformed(rotation, mirror);
+        let absolute = |position: &BlockPosition| BlockPosition { x: origin.x + position.x, y: origin.y + position.y, z: origin.z + position.z };
+
+        self.set_blocks(clipboard.iter().map(|(position, block)| (absolute(&position), block.clone()))).await;
+        for (position, block_entity) in clipboard.block_entities {
+            self.set_block_entity(absolute(&position), block_entity).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(s: &str) -> BlockWithState {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rotate_block() {
+        let stairs = state("minecraft:oak_stairs[facing=north,shape=inner_left]");
+        let rotated = rotate_block(&stairs, BlockRotation::Clockwise90);
+        assert_eq!(rotated.property("facing"), Some("east"));
+        assert_eq!(rotated.property("shape"), Some("inner_left"));
+
+        let log = state("minecraft:oak_log[axis=x]");
+        assert_eq!(rotate_block(&log, BlockRotation::Counterclockwise90).property("axis"), Some("z"));
+        assert_eq!(rotate_block(&log, BlockRotation::Clockwise180).property("axis"), Some("x"));
+
+        let fence = state("minecraft:oak_fence[north=true,east=false,south=false,west=false]");
+        let rotated = rotate_block(&fence, BlockRotation::Clockwise180);
+        assert_eq!(rotated.property("north"), Some("false"));
+        assert_eq!(rotated.property("south"), Some("true"));
+
+        let rail = state("minecraft:rail[shape=south_east]");
+        assert_eq!(rotate_block(&rail, BlockRotation::Clockwise90).property("shape"), Some("south_west"));
+
+        let sign = state("minecraft:oak_sign[rotation=2]");
+        assert_eq!(rotate_block(&sign, BlockRotation::Clockwise90).property("rotation"), Some("6"));
+    }
+
+    #[test]
+    fn test_mirror_block() {
+        let stairs = state("minecraft:oak_stairs[facing=north,shape=outer_right]");
+        let mirrored = mirror_block(&stairs, BlockMirror::LeftRight);
+        assert_eq!(mirrored.property("facing"), Some("south"));
+        assert_eq!(mirrored.property("shape"), Some("outer_left"));
+
+        let door = state("minecraft:oak_door[facing=east,hinge=left]");
+        let mirrored = mirror_block(&door, BlockMirror::FrontBack);
+        assert_eq!(mirrored.property("facing"), Some("west"));
+        assert_eq!(mirrored.property("hinge"), Some("right"));
+
+        let sign = state("minecraft:oak_sign[rotation=4]");
+        assert_eq!(mirror_block(&sign, BlockMirror::FrontBack).property("rotation"), Some("12"));
+        assert_eq!(mirror_block(&sign, BlockMirror::LeftRight).property("rotation"), Some("4"));
+    }
+
+    #[test]
+    fn test_clipboard_transform() {
+        let mut clipboard = Clipboard::new(3, 1, 2);
+        clipboard.set_block(&BlockPosition { x: 2, y: 0, z: 0 }, Some(BlockWithState::Dirt));
+
+        let rotated = clipboard.transformed(BlockRotation::Clockwise90, BlockMirror::None);
+        assert_eq!((rotated.width, rotated.length), (2, 3));
+        assert!(rotated.get_block(&BlockPosition { x: 1, y: 0, z: 2 }).is_some());
+        assert_eq!(rotated.iter().count(), 1);
+
+        let mirrored = clipboard.transformed(BlockRotation::None, BlockMirror::FrontBack);
+        assert!(mirrored.get_block(&BlockPosition { x: 0, y: 0, z: 0 }).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_region_operations() {
+        let world = World::new();
+        world.update_loaded_chunks(1, vec![ChunkColumnPosition{cx: 0, cz: 0}].into_iter().collect()).await;
+        let range = BlockRange::new(BlockPosition { x: 0, y: 0, z: 0 }, BlockPosition { x: 4, y: 4, z: 4 });
+
+        world.hollow_box(range.clone(), BlockWithState::Stone).await;
+        assert_eq!(world.get_block(BlockPosition { x: 0, y: 2, z: 2 }).await.unwrap().block_state_id(), BlockWithState::Stone.block_state_id());
+        assert_eq!(world.get_block(BlockPosition { x: 2, y: 2, z: 2 }).await.unwrap().block_state_id(), BlockWithState::Air.block_state_id());
+
+        let replaced = world.replace(range.clone(), |block| block.block_id() == BlockWithState::Stone.block_id(), BlockWithState::Dirt).await;
+        assert_eq!(replaced, 125 - 27);
+
+        let clipboard = world.copy(range).await;
+        world.paste(&clipboard, BlockPosition { x: 8, y: 0, z: 8 }, BlockRotation::Clockwise90, BlockMirror::None).await;
+        assert_eq!(world.get_block(BlockPosition { x: 12, y: 4, z: 12 }).await.unwrap().block_state_id(), BlockWithState::Dirt.block_state_id());
+        assert_eq!(world.get_block(BlockPosition { x: 10, y: 2, z: 10 }).await.unwrap().block_state_id(), BlockWithState::Air.block_state_id());
+    }
+}
@@ -0,0 +1,222 @@
+use minecraft_protocol::{components::{blocks::BlockEntityType, slots::SlotItem}, ids::items::Item};
+use super::*;
+
+/// Returns true if the item is made of overworld wood (nether wood doesn't burn).
+fn is_wooden(text_id: &str) -> bool {
+    const SUFFIXES: [&str; 9] = ["_slab", "_stairs", "_fence_gate", "_fence", "_door", "_trapdoor", "_pressure_plate", "_button", "_sign"];
+    let Some(prefix) = SUFFIXES.iter().find_map(|suffix| text_id.strip_suffix(suffix)) else { return false };
+    prefix != "crimson" && prefix != "warped" && Item::from_text_id(&format!("{prefix}_planks")).is_some()
+}
+
+/// Returns the number of ticks an item burns for, or `None` if it isn't a fuel.
+pub fn fuel_ticks(item: Item) -> Option<i16> {
+    let text_id = item.text_id();
+    let nether_wood = text_id.starts_with("crimson_") || text_id.starts_with("warped_");
+    Some(match text_id {
+        "lava_bucket" => 20000,
+        "coal_block" => 16000,
+        "dried_kelp_block" => 4001,
+        "blaze_rod" => 2400,
+        "coal" | "charcoal" => 1600,
+        "crafting_table" | "bookshelf" | "chest" | "trapped_chest" | "barrel" | "ladder" | "jukebox" | "note_block" | "daylight_detector" => 300,
+        "stick" | "bowl" | "dead_bush" | "bamboo_mosaic" => 100,
+        "bamboo" | "scaffolding" => 50,
+        _ if nether_wood => return None,
+        _ if text_id.ends_with("_log") || text_id.ends_with("_wood") || text_id.ends_with("_planks") => 300,
+        _ if text_id.ends_with("_boat") || text_id.ends_with("_raft") => 1200,
+        _ if text_id.ends_with("_sapling") || text_id.ends_with("_wool") => 100,
+        _ if text_id.ends_with("_carpet") => 67,
+        _ if text_id.ends_with("_slab") && is_wooden(text_id) => 150,
+        _ if (text_id.ends_with("_door") || text_id.ends_with("_sign")) && is_wooden(text_id) => 200,
+        _ if text_id.ends_with("_button") && is_wooden(text_id) => 100,
+        _ if is_wooden(text_id) => 300,
+        _ if text_id.starts_with("wooden_") => 200,
+        _ => return None,
+    })
+}
+
+/// Returns what an ore becomes when smelted (`deepslate_iron_ore` and `raw_iron` give `iron_ingot`).
+fn smelted_ore(text_id: &str) -> Option<Item> {
+    if text_id == "ancient_debris" {
+        return Some(Item::NetheriteScrap);
+    }
+    if let Some(metal) = text_id.strip_prefix("raw_").filter(|metal| !metal.ends_with("_block")) {
+        return Item::from_text_id(&format!("{metal}_ingot"));
+    }
+    let ore = text_id.strip_suffix("_ore")?;
+    let ore = ore.strip_prefix("deepslate_").or_else(|| ore.strip_prefix("nether_")).unwrap_or(ore);
+    match ore {
+        "lapis" => Some(Item::LapisLazuli),
+        ore => Item::from_text_id(&format!("{ore}_ingot")).or_else(|| Item::from_text_id(ore)),
+    }
+}
+
+/// Returns what a food becomes when cooked.
+fn cooked_food(text_id: &str) -> Option<Item> {
+    match text_id {
+        "potato" => Some(Item::BakedPotato),
+        "kelp" => Some(Item::DriedKelp),
+        text_id => Item::from_text_id(&format!("cooked_{text_id}")),
+    }
+}
+
+/// Returns what an item becomes in a furnace, smoker or blast furnace, and the number of ticks it takes.
+pub fn smelting_result(ty: BlockEntityType, item: Item) -> Option<(Item, i16)> {
+    let text_id = item.text_id();
+    match ty {
+        BlockEntityType::Smoker => return cooked_food(text_id).map(|result| (result, 100)),
+        BlockEntityType::BlastFurnace => return smelted_ore(text_id).map(|result| (result, 100)),
+        _ => (),
+    }
+    let result = match text_id {
+        "sand" | "red_sand" => Item::Glass,
+        "cobblestone" => Item::Stone,
+        "stone" => Item::SmoothStone,
+        "cobbled_deepslate" => Item::Deepslate,
+        "stone_bricks" => Item::CrackedStoneBricks,
+        "sandstone" => Item::SmoothSandstone,
+        "red_sandstone" => Item::SmoothRedSandstone,
+        "quartz_block" => Item::SmoothQuartz,
+        "basalt" => Item::SmoothBasalt,
+        "clay_ball" => Item::Brick,
+        "clay" => Item::Terracotta,
+        "netherrack" => Item::NetherBrick,
+        "cactus" => Item::GreenDye,
+        "sea_pickle" => Item::LimeDye,
+        "wet_sponge" => Item::Sponge,
+        "chorus_fruit" => Item::PoppedChorusFruit,
+        _ if (text_id.ends_with("_log") || text_id.ends_with("_wood")) && fuel_ticks(item).is_some() => Item::Charcoal,
+        _ => smelted_ore(text_id).or_else(|| cooked_food(text_id))?,
+    };
+    Some((result, 200))
+}
+
+/// What changed during a furnace tick
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FurnaceTick {
+    /// The furnace needs to be ticked again
+    pub active: bool,
+    /// The furnace started or stopped burning
+    pub lit_changed: bool,
+    pub slots_changed: bool,
+}
+
+/// Removes one item from a slot.
+fn consume_one(slot: &mut Slot) {
+    if let Some(item) = &mut slot.item {
+        item.item_count -= 1;
+        if item.item_count <= 0 {
+            slot.item = None;
+        }
+    }
+}
+
+/// Advances a furnace, smoker or blast furnace by one tick, as in vanilla.
+pub fn tick_furnace(ty: BlockEntityType, data: &mut BlockEntityData) -> FurnaceTick {
+    let BlockEntityData::Furnace { items, burn_time, burn_time_total, cook_time, cook_time_total, .. } = data else {
+        return FurnaceTick::default();
+    };
+    let mut tick = FurnaceTick::default();
+    let was_lit = *burn_time > 0;
+    if was_lit {
+        *burn_time -= 1;
+    }
+
+    let result = items[0].item.as_ref().and_then(|input| smelting_result(ty, input.item_id)).filter(|(result, _)| match &items[2].item {
+        Some(output) => output.item_id == *result && output.nbt_data.is_null() && output.item_count < result.max_stack_size() as i8,
+        None => true,
+    });
+    let has_fuel = items[1].item.is_some();
+    if *burn_time > 0 || (has_fuel && items[0].item.is_some()) {
+        if let (0, Some(_)) = (*burn_time, result) {
+            let fuel = items[1].item.as_ref().and_then(|fuel| fuel_ticks(fuel.item_id).map(|ticks| (fuel.item_id, ticks)));
+            if let Some((fuel, ticks)) = fuel {
+                *burn_time = ticks;
+                *burn_time_total = ticks;
+                match fuel {
+                    Item::LavaBucket => items[1].item = Some(SlotItem { item_id: Item::Bucket, item_count: 1, nbt_data: NbtTag::Null }),
+                    _ => consume_one(&mut items[1]),
+                }
+                tick.slots_changed = true;
+            }
+        }
+        match result {
+            Some((result, total)) if *burn_time > 0 => {
+                *cook_time_total = total;
+                *cook_time += 1;
+                if *cook_time >= total {
+                    *cook_time = 0;
+                    consume_one(&mut items[0]);
+                    match &mut items[2].item {
+                        Some(output) => output.item_count += 1,
+                        None => items[2].item = Some(SlotItem { item_id: result, item_count: 1, nbt_data: NbtTag::Null }),
+                    }
+                    tick.slots_changed = true;
+                }
+            }
+            _ => *cook_time = 0,
+        }
+    } else if *cook_time > 0 {
+        *cook_time = (*cook_time - 2).max(0);
+    }
+
+    tick.active = *burn_time > 0 || *cook_time > 0;
+    tick.lit_changed = was_lit != (*burn_time > 0);
+    tick
+}
+
+/// Returns the window properties of a furnace: fuel left, fuel total, progress and progress total.
+pub fn furnace_properties(data: &BlockEntityData) -> Option<[i16; 4]> {
+    match data {
+        BlockEntityData::Furnace { burn_time, burn_time_total, cook_time, cook_time_total, .. } => {
+            Some([*burn_time, *burn_time_total, *cook_time, *cook_time_total])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smelting_result() {
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::RawIron), Some((Item::IronIngot, 200)));
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::DeepslateGoldOre), Some((Item::GoldIngot, 200)));
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::NetherQuartzOre), Some((Item::Quartz, 200)));
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::Beef), Some((Item::CookedBeef, 200)));
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::OakLog), Some((Item::Charcoal, 200)));
+        assert_eq!(smelting_result(BlockEntityType::Smoker, Item::Potato), Some((Item::BakedPotato, 100)));
+        assert_eq!(smelting_result(BlockEntityType::Smoker, Item::RawIron), None);
+        assert_eq!(smelting_result(BlockEntityType::BlastFurnace, Item::RawCopper), Some((Item::CopperIngot, 100)));
+        assert_eq!(smelting_result(BlockEntityType::Furnace, Item::Diamond), None);
+    }
+
+    #[test]
+    fn test_fuel_ticks() {
+        assert_eq!(fuel_ticks(Item::Coal), Some(1600));
+        assert_eq!(fuel_ticks(Item::OakPlanks), Some(300));
+        assert_eq!(fuel_ticks(Item::OakSlab), Some(150));
+        assert_eq!(fuel_ticks(Item::CrimsonPlanks), None);
+        assert_eq!(fuel_ticks(Item::StoneSlab), None);
+    }
+
+    #[test]
+    fn test_tick_furnace() {
+        let stack = |item_id, item_count| Slot { item: Some(SlotItem { item_id, item_count, nbt_data: NbtTag::Null }) };
+        let mut furnace = BlockEntity::new(BlockEntityType::Furnace);
+        let BlockEntityData::Furnace { items, .. } = &mut furnace.data else { panic!("Furnace has wrong data") };
+        *items = [stack(Item::RawIron, 2), stack(Item::Coal, 1), Slot::default()];
+
+        let tick = tick_furnace(BlockEntityType::Furnace, &mut furnace.data);
+        assert_eq!(tick, FurnaceTick { active: true, lit_changed: true, slots_changed: true });
+        assert_eq!(furnace_properties(&furnace.data), Some([1600, 1600, 1, 200]));
+
+        for _ in 1..200 {
+            tick_furnace(BlockEntityType::Furnace, &mut furnace.data);
+        }
+        let BlockEntityData::Furnace { items, .. } = &furnace.data else { panic!("Furnace has wrong data") };
+        assert_eq!(items, &[stack(Item::RawIron, 1), Slot::default(), stack(Item::IronIngot, 1)]);
+        assert_eq!(furnace_properties(&furnace.data), Some([1401, 1600, 0, 200]));
+    }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+/// Creates the chunk columns that were never saved.
+/// Closures taking a [ChunkColumnPosition] and returning a [ChunkColumn] are generators.
+pub trait WorldGenerator: Send + Sync {
+    fn generate(&self, position: ChunkColumnPosition) -> ChunkColumn;
+}
+
+impl<F: Fn(ChunkColumnPosition) -> ChunkColumn + Send + Sync> WorldGenerator for F {
+    fn generate(&self, position: ChunkColumnPosition) -> ChunkColumn {
+        self(position)
+    }
+}
+
+/// Generates a flat world, with grass blocks from the bottom of the world up to y=-49
+pub struct FlatGenerator;
+
+impl WorldGenerator for FlatGenerator {
+    fn generate(&self, _position: ChunkColumnPosition) -> ChunkColumn {
+        ChunkColumn::flat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_custom_generator() {
+        let world = World::new().with_generator(Box::new(|position: ChunkColumnPosition| {
+            let mut column = ChunkColumn::empty();
+            if position.cx == 1 {
+                column.set_block(BlockPositionInChunkColumn { bx: 0, y: 10, bz: 0 }, BlockWithState::Stone);
+            }
+            column
+        }));
+        world.update_loaded_chunks(1, [ChunkColumnPosition { cx: 0, cz: 0 }, ChunkColumnPosition { cx: 1, cz: 0 }].into_iter().collect()).await;
+
+        let world = &world;
+        let block_at = move |x, y| world.get_block(BlockPosition { x, y, z: 0 });
+        assert_eq!(block_at(16, 10).await.unwrap().block_state_id(), BlockWithState::Stone.block_state_id());
+        assert_eq!(block_at(0, 10).await.unwrap().block_state_id(), BlockWithState::Air.block_state_id());
+        assert_eq!(block_at(16, -64).await.unwrap().block_state_id(), BlockWithState::Air.block_state_id());
+    }
+}
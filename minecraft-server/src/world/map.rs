@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use minecraft_protocol::components::chunk::PalettedData;
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use minecraft_protocol::{components::{blocks::BlockEntity as NetworkBlockEntity, chunk::PalettedData}, nbt::{arrays::NbtList, parse_nbt}};
 use tokio::sync::RwLock;
 use crate::prelude::*;
 
@@ -10,6 +10,16 @@ pub struct WorldMap {
     /// This allows high concurrency.
     shard_count: usize,
     shards: Vec<RwLock<HashMap<ChunkColumnPosition, ChunkColumn>>>,
+    /// Directory in which chunk columns are saved when unloaded.
+    /// When `None`, chunk columns are kept in memory forever.
+    storage: Option<PathBuf>,
+    /// Creates the chunk columns that aren't in storage
+    generator: Box<dyn WorldGenerator>,
+    /// Serialized chunk columns waiting to be written, which are more recent than what is on the disk.
+    /// Columns are serialized under the lock of their shard, but written once it is released.
+    pending_writes: Mutex<HashMap<ChunkColumnPosition, Arc<Vec<u8>>>>,
+    /// Makes sure writes of the same chunk column don't overtake each other
+    write_lock: tokio::sync::Mutex<()>,
 }
 
 #[derive(Clone)]
@@ -167,12 +177,15 @@ impl Chunk {
     }
 }
 
-struct ChunkColumn {
+/// The blocks and block entities of a 16x384x16 column
+pub struct ChunkColumn {
     chunks: Vec<Chunk>,
+    block_entities: HashMap<BlockPositionInChunkColumn, BlockEntity>,
 }
 
 impl ChunkColumn {
-    pub fn flat() -> Self {
+    /// Returns a column filled with air.
+    pub fn empty() -> Self {
         let empty_chunk = Chunk {
             data: NetworkChunk {
                 block_count: 0,
@@ -181,7 +194,12 @@ impl ChunkColumn {
             },
             palette_block_counts: Vec::new(),
         };
-        let dirt_chunk = Chunk {
+        ChunkColumn { chunks: vec![empty_chunk; 24], block_entities: HashMap::new() }
+    }
+
+    pub fn flat() -> Self {
+        let mut column = ChunkColumn::empty();
+        column.chunks[0] = Chunk {
             data: NetworkChunk {
                 block_count: 4096,
                 blocks: PalettedData::Single { value: minecraft_protocol::ids::blocks::Block::GrassBlock.default_state_id() },
@@ -189,12 +207,42 @@ impl ChunkColumn {
             },
             palette_block_counts: Vec::new(),
         };
-        let mut chunks = Vec::new();
-        chunks.push(dirt_chunk);
-        for _ in 0..23 {
-            chunks.push(empty_chunk.clone());
+        column
+    }
+
+    fn to_nbt(&self, position: &ChunkColumnPosition) -> Option<NbtTag> {
+        let network_chunks = self.chunks.iter().map(|chunk| chunk.as_network_chunk().clone()).collect();
+        let sections: Vec<u8> = NetworkChunk::into_data(network_chunks).ok()?;
+        let block_entities = self.block_entities.iter().map(|(position_in_column, block_entity)| {
+            let block_position = position.clone() + position_in_column.clone();
+            block_entity.to_nbt(&block_position).as_compound().cloned().unwrap_or_default()
+        }).collect();
+
+        let mut root = HashMap::new();
+        root.insert(String::from("xPos"), NbtTag::Int(position.cx));
+        root.insert(String::from("zPos"), NbtTag::Int(position.cz));
+        root.insert(String::from("sections"), NbtTag::ByteArray(sections.into_iter().map(|b| b as i8).collect()));
+        root.insert(String::from("block_entities"), NbtTag::List(NbtList::Compound(block_entities)));
+        Some(NbtTag::RootCompound(String::new(), root))
+    }
+
+    fn from_nbt(tag: &NbtTag) -> Option<ChunkColumn> {
+        let NbtTag::RootCompound(_, root) = tag else { return None };
+        let sections: Vec<u8> = root.get("sections")?.as_byte_array()?.iter().map(|b| *b as u8).collect();
+        let chunks = NetworkChunk::from_data(&sections).ok()?.into_iter().map(Chunk::from_chunk_data).collect();
+
+        let mut block_entities = HashMap::new();
+        if let Some(NbtList::Compound(list)) = root.get("block_entities").and_then(|t| t.as_list()) {
+            for compound in list {
+                let Some((position, block_entity)) = BlockEntity::from_nbt(&NbtTag::Compound(compound.clone())) else {
+                    warn!("Skipping invalid block entity in stored chunk column");
+                    continue;
+                };
+                block_entities.insert(position.in_chunk_column(), block_entity);
+            }
         }
-        ChunkColumn { chunks }
+
+        Some(ChunkColumn { chunks, block_entities })
     }
 
     fn get_block(&self, position: BlockPositionInChunkColumn) -> BlockWithState {
@@ -208,7 +256,9 @@ impl ChunkColumn {
         get_block_inner(self, position).unwrap_or(BlockWithState::Air)
     }
 
-    fn set_block(&mut self, position: BlockPositionInChunkColumn, block: BlockWithState) {
+    /// Sets a block, creating or removing its block entity.
+    /// Blocks outside of the column are ignored.
+    pub fn set_block(&mut self, position: BlockPositionInChunkColumn, block: BlockWithState) {
         fn set_block_innter(s: &mut ChunkColumn, position: BlockPositionInChunkColumn, block: BlockWithState) -> Option<()> {
             let cy = position.cy();
             let cy_in_vec: usize = cy.saturating_add(4).try_into().ok()?;
@@ -217,7 +267,22 @@ impl ChunkColumn {
             chunk.set_block(position, block);
             Some(())
         }
-        set_block_innter(self, position, block);
+        if set_block_innter(self, position.clone(), block.clone()).is_none() {
+            return;
+        }
+
+        // Block entities survive as long as the block keeps the same type of block entity (a chest being rotated for instance)
+        let new_block_entity = BlockEntity::for_block(&block);
+        let kept = match (self.block_entities.get(&position), &new_block_entity) {
+            (Some(previous), Some(new)) => previous.ty == new.ty,
+            _ => false,
+        };
+        if !kept {
+            match new_block_entity {
+                Some(new_block_entity) => { self.block_entities.insert(position, new_block_entity); },
+                None => { self.block_entities.remove(&position); },
+            }
+        }
     }
 }
 
@@ -227,7 +292,17 @@ impl WorldMap {
         for _ in 0..shard_count {
             shards.push(RwLock::new(HashMap::new()));
         }
-        WorldMap { shard_count, shards }
+        WorldMap { shard_count, shards, storage: None, generator: Box::new(FlatGenerator), pending_writes: Mutex::new(HashMap::new()), write_lock: tokio::sync::Mutex::new(()) }
+    }
+
+    /// Replaces the generator of chunk columns that aren't in storage.
+    pub fn with_generator(self, generator: Box<dyn WorldGenerator>) -> WorldMap {
+        WorldMap { generator, ..self }
+    }
+
+    /// Creates a map that saves chunk columns in `directory` when they are unloaded, and loads them back from there.
+    pub fn with_storage(shard_count: usize, directory: PathBuf) -> WorldMap {
+        WorldMap { storage: Some(directory), ..WorldMap::new(shard_count) }
     }
 
     pub async fn get_block(&self, position: BlockPosition) -> BlockWithState {
@@ -271,6 +346,63 @@ impl WorldMap {
         inner_get_block(self, position, block).await;
     }
 
+    /// Sets many blocks at once, locking each shard only once.
+    pub async fn set_blocks(&self, blocks: Vec<(BlockPosition, BlockWithState)>) {
+        let mut blocks_by_shard: HashMap<usize, Vec<(BlockPosition, BlockWithState)>> = HashMap::new();
+        for (position, block) in blocks {
+            let shard = position.chunk_column().shard(self.shard_count);
+            blocks_by_shard.entry(shard).or_default().push((position, block));
+        }
+
+        for (shard, blocks) in blocks_by_shard {
+            let mut shard = self.shards[shard].write().await;
+            for (position, block) in blocks {
+                let Some(chunk_column) = shard.get_mut(&position.chunk_column()) else { continue };
+                chunk_column.set_block(position.in_chunk_column(), block);
+            }
+        }
+    }
+
+    pub async fn get_block_entity(&self, position: BlockPosition) -> Option<BlockEntity> {
+        let chunk_column_position = position.chunk_column();
+        let shard = chunk_column_position.shard(self.shard_count);
+
+        let shard = self.shards[shard].read().await;
+        let chunk_column = shard.get(&chunk_column_position)?;
+        chunk_column.block_entities.get(&position.in_chunk_column()).cloned()
+    }
+
+    /// Replaces the block entity at `position`.
+    /// Returns false if the chunk is not loaded.
+    pub async fn set_block_entity(&self, position: BlockPosition, block_entity: BlockEntity) -> bool {
+        let chunk_column_position = position.chunk_column();
+        let shard = chunk_column_position.shard(self.shard_count);
+
+        let mut shard = self.shards[shard].write().await;
+        let Some(chunk_column) = shard.get_mut(&chunk_column_position) else { return false };
+        chunk_column.block_entities.insert(position.in_chunk_column(), block_entity);
+        true
+    }
+
+    /// Runs `mutator` on the block entity at `position`, if there is one.
+    pub async fn mutate_block_entity<R>(&self, position: BlockPosition, mutator: impl FnOnce(&mut BlockEntity) -> R) -> Option<R> {
+        let chunk_column_position = position.chunk_column();
+        let shard = chunk_column_position.shard(self.shard_count);
+
+        let mut shard = self.shards[shard].write().await;
+        let chunk_column = shard.get_mut(&chunk_column_position)?;
+        let block_entity = chunk_column.block_entities.get_mut(&position.in_chunk_column())?;
+        Some(mutator(block_entity))
+    }
+
+    pub async fn get_network_block_entities(&self, position: ChunkColumnPosition) -> Vec<NetworkBlockEntity> {
+        let shard = position.shard(self.shard_count);
+
+        let shard = self.shards[shard].read().await;
+        let Some(chunk_column) = shard.get(&position) else { return Vec::new() };
+        chunk_column.block_entities.iter().map(|(position, block_entity)| block_entity.to_network(position)).collect()
+    }
+
     pub async fn try_move(&self, object: &CollisionShape, movement: &Translation) -> Translation {
         // TODO(perf): Optimize Map.try_move by preventing block double-checking
         // Also lock the map only once
@@ -288,29 +420,116 @@ impl WorldMap {
         movement.clone() // Would be more logic if it returned validated, but this way we avoid precision errors
     }
 
+    fn chunk_column_path(&self, position: &ChunkColumnPosition) -> Option<PathBuf> {
+        Some(self.storage.as_ref()?.join(format!("c.{}.{}.nbt", position.cx, position.cz)))
+    }
+
+    async fn read_chunk_column(&self, position: &ChunkColumnPosition) -> Option<ChunkColumn> {
+        let path = self.chunk_column_path(position)?;
+        let pending = self.pending_writes.lock().unwrap().get(position).cloned();
+        let data = match pending {
+            Some(data) => data,
+            None => Arc::new(tokio::fs::read(&path).await.ok()?),
+        };
+        let column = parse_nbt(&data).ok().and_then(|(tag, _)| ChunkColumn::from_nbt(&tag));
+        if column.is_none() {
+            error!("Chunk column at {position:?} is corrupted and will be regenerated");
+        }
+        column
+    }
+
+    /// Serializes a chunk column so that it gets written by [WorldMap::write_pending].
+    /// This must be called while holding the lock of its shard, so that the most recent version of the column is always the one pending.
+    fn queue_write(&self, position: &ChunkColumnPosition, column: &ChunkColumn) {
+        let Some(tag) = column.to_nbt(position) else {
+            error!("Failed to serialize chunk column at {position:?}");
+            return;
+        };
+        let mut data = Vec::new();
+        tag.serialize(&mut data);
+        self.pending_writes.lock().unwrap().insert(position.clone(), Arc::new(data));
+    }
+
+    /// Writes the pending version of a chunk column, if it wasn't written already.
+    async fn write_pending(&self, position: &ChunkColumnPosition) {
+        let Some(path) = self.chunk_column_path(position) else { return };
+        let _write_lock = self.write_lock.lock().await;
+        let Some(data) = self.pending_writes.lock().unwrap().get(position).cloned() else { return };
+        if let Some(directory) = path.parent() {
+            let _ = tokio::fs::create_dir_all(directory).await;
+        }
+        if let Err(e) = tokio::fs::write(&path, data.as_slice()).await {
+            error!("Failed to save chunk column at {position:?}: {e}");
+        }
+
+        // The column might have been queued again in the meantime
+        let mut pending_writes = self.pending_writes.lock().unwrap();
+        if pending_writes.get(position).is_some_and(|pending| Arc::ptr_eq(pending, &data)) {
+            pending_writes.remove(position);
+        }
+    }
+
     pub async fn load(&self, position: ChunkColumnPosition) {
-        let chunk = ChunkColumn::flat(); // TODO: load from disk
         let shard = position.shard(self.shard_count);
-        
+        if self.shards[shard].read().await.contains_key(&position) {
+            return;
+        }
+
         trace!("Loading chunk column at {:?}", position);
+        let chunk = match self.read_chunk_column(&position).await {
+            Some(chunk) => chunk,
+            None => self.generator.generate(position.clone()),
+        };
         let mut shard = self.shards[shard].write().await;
         shard.entry(position).or_insert_with(|| chunk);
     }
 
-    pub async fn unload(&self, _position: ChunkColumnPosition) {
-        // Note: these are not unloaded yet in order to preserve map data
+    /// Returns how many chunk columns are loaded in each shard.
+    pub async fn loaded_columns(&self) -> Vec<usize> {
+        let mut counts = Vec::with_capacity(self.shard_count);
+        for shard in &self.shards {
+            counts.push(shard.read().await.len());
+        }
+        counts
+    }
 
-        //let shard = position.shard(self.shard_count);
+    /// Writes all loaded chunk columns, keeping them loaded.
+    pub async fn save_all(&self) {
+        if self.storage.is_none() {
+            return;
+        }
+        for shard in &self.shards {
+            let shard = shard.read().await;
+            let positions: Vec<ChunkColumnPosition> = shard.keys().cloned().collect();
+            for (position, column) in shard.iter() {
+                self.queue_write(position, column);
+            }
+            drop(shard);
+            for position in positions {
+                self.write_pending(&position).await;
+            }
+        }
+    }
+
+    pub async fn unload(&self, position: ChunkColumnPosition) {
+        // Without storage, chunk columns are not unloaded in order to preserve map data
+        if self.storage.is_none() {
+            return;
+        }
 
-        //let mut shard = self.shards[shard].write().await;
-        //shard.remove(&position);
-        // TODO: write to disk
+        let shard = position.shard(self.shard_count);
+        let mut shard = self.shards[shard].write().await;
+        let Some(column) = shard.remove(&position) else { return };
+        self.queue_write(&position, &column);
+        drop(shard);
+        self.write_pending(&position).await;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use minecraft_protocol::{components::blocks::BlockEntityType, ids::blocks::Block};
 
     #[test]
     fn test_get_block() {
@@ -449,6 +668,74 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_set_blocks() {
+        let map = WorldMap::new(4);
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                map.load(ChunkColumnPosition { cx, cz }).await;
+            }
+        }
+
+        let mut blocks = Vec::new();
+        for x in -16..32 {
+            for z in -16..32 {
+                blocks.push((BlockPosition { x, y: 10, z }, BlockWithState::RedstoneBlock));
+            }
+        }
+        map.set_blocks(blocks).await;
+
+        for x in -16..32 {
+            for z in -16..32 {
+                let block = map.get_block(BlockPosition { x, y: 10, z }).await;
+                assert_eq!(block.block_state_id(), BlockWithState::RedstoneBlock.block_state_id());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_entities() {
+        let map = WorldMap::new(1);
+        map.load(ChunkColumnPosition { cx: 0, cz: 0 }).await;
+        let position = BlockPosition { x: 3, y: 70, z: 5 };
+
+        map.set_block(position.clone(), BlockWithState::from_state_id(Block::Chest.default_state_id()).unwrap()).await;
+        assert_eq!(map.get_block_entity(position.clone()).await.unwrap().ty, BlockEntityType::Chest);
+        assert_eq!(map.get_network_block_entities(ChunkColumnPosition { cx: 0, cz: 0 }).await.len(), 1);
+
+        // Changing the block removes its block entity
+        map.set_block(position.clone(), BlockWithState::Air).await;
+        assert!(map.get_block_entity(position.clone()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chunk_column_storage() {
+        let directory = std::env::temp_dir().join(format!("minecraft-server-test-{}", std::process::id()));
+        let map = WorldMap::with_storage(1, directory.clone());
+        let column_position = ChunkColumnPosition { cx: -2, cz: 1 };
+        let position = BlockPosition { x: -30, y: 64, z: 20 };
+        map.load(column_position.clone()).await;
+
+        map.set_block(position.clone(), BlockWithState::from_state_id(Block::OakSign.default_state_id()).unwrap()).await;
+        map.mutate_block_entity(position.clone(), |block_entity| {
+            if let BlockEntityData::Sign { front_text, .. } = &mut block_entity.data {
+                front_text.set_lines(["stored", "", "", ""]);
+            }
+        }).await.unwrap();
+
+        map.unload(column_position.clone()).await;
+        assert!(map.get_block_entity(position.clone()).await.is_none());
+        assert!(map.pending_writes.lock().unwrap().is_empty());
+        map.load(column_position).await;
+
+        assert_eq!(Block::from(map.get_block(position.clone()).await), Block::OakSign);
+        let block_entity = map.get_block_entity(position).await.unwrap();
+        let BlockEntityData::Sign { front_text, .. } = block_entity.data else { panic!("Sign has wrong data") };
+        assert_eq!(front_text.messages[0], r#"{"text":"stored"}"#);
+
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
     #[tokio::test]
     async fn test_try_move() {
         let map = WorldMap::new(1);
@@ -0,0 +1,201 @@
+use std::{fmt::Write, sync::atomic::{AtomicU64, Ordering}};
+use super::*;
+
+/// The upper bounds of the tick duration histogram buckets, in seconds
+const TICK_DURATION_BUCKETS: [f64; 8] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Counters updated while the server runs, exported in the Prometheus text format by [World::render_metrics]
+pub struct Metrics {
+    /// Play packets received, by packet id
+    packets_in: [AtomicU64; 128],
+    /// Play packets sent, by packet id
+    packets_out: [AtomicU64; 128],
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    /// Tick counts by bucket of [TICK_DURATION_BUCKETS], plus one for slower ticks
+    tick_durations: [AtomicU64; TICK_DURATION_BUCKETS.len() + 1],
+    tick_duration_sum_nanos: AtomicU64,
+    /// World changes that found the channel of their loader full
+    world_changes_backlogged: AtomicU64,
+    /// Loaders dropped because too many world changes were waiting for them
+    world_change_loaders_dropped: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            packets_in: std::array::from_fn(|_| AtomicU64::new(0)),
+            packets_out: std::array::from_fn(|_| AtomicU64::new(0)),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            tick_durations: std::array::from_fn(|_| AtomicU64::new(0)),
+            tick_duration_sum_nanos: AtomicU64::new(0),
+            world_changes_backlogged: AtomicU64::new(0),
+            world_change_loaders_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts a play packet, given without its length prefix.
+    fn count_packet(counters: &[AtomicU64; 128], bytes: &AtomicU64, packet: &[u8]) {
+        bytes.fetch_add(packet.len() as u64, Ordering::Relaxed);
+        // Play packet ids all fit in a single VarInt byte
+        if let Some(counter) = packet.first().and_then(|id| counters.get(*id as usize)) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn packet_received(&self, packet: &[u8]) {
+        Metrics::count_packet(&self.packets_in, &self.bytes_in, packet);
+    }
+
+    pub fn packet_sent(&self, packet: &[u8]) {
+        Metrics::count_packet(&self.packets_out, &self.bytes_out, packet);
+    }
+
+    pub fn record_tick(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = TICK_DURATION_BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(TICK_DURATION_BUCKETS.len());
+        self.tick_durations[bucket].fetch_add(1, Ordering::Relaxed);
+        self.tick_duration_sum_nanos.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn world_change_backlogged(&self) {
+        self.world_changes_backlogged.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn world_change_loader_dropped(&self) {
+        self.world_change_loaders_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Writes the help and type lines of a metric.
+fn write_header(output: &mut String, name: &str, ty: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {ty}");
+}
+
+fn write_packet_counts(output: &mut String, name: &str, help: &str, counters: &[AtomicU64; 128]) {
+    write_header(output, name, "counter", help);
+    for (id, counter) in counters.iter().enumerate() {
+        let count = counter.load(Ordering::Relaxed);
+        if count > 0 {
+            let _ = writeln!(output, "{name}{{id=\"0x{id:02x}\"}} {count}");
+        }
+    }
+}
+
+impl World {
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Renders the state of the world and the counters of [World::metrics] in the Prometheus text format.
+    pub async fn render_metrics(&self) -> String {
+        let mut output = String::new();
+
+        write_header(&mut output, "minecraft_players", "gauge", "Connected players");
+        let _ = writeln!(output, "minecraft_players {}", self.players().await.len());
+
+        write_header(&mut output, "minecraft_loaded_chunk_columns", "gauge", "Loaded chunk columns, by map shard");
+        for (shard, count) in self.map.loaded_columns().await.into_iter().enumerate() {
+            let _ = writeln!(output, "minecraft_loaded_chunk_columns{{shard=\"{shard}\"}} {count}");
+        }
+
+        write_header(&mut output, "minecraft_entities", "gauge", "Entities, by type");
+        for (ty, count) in self.entities.count_by_type().await {
+            let _ = writeln!(output, "minecraft_entities{{type=\"{ty}\"}} {count}");
+        }
+
+        write_header(&mut output, "minecraft_entity_tasks", "gauge", "Running entity tasks, by task name");
+        for (name, count) in self.entities.count_tasks().await {
+            let _ = writeln!(output, "minecraft_entity_tasks{{task=\"{name}\"}} {count}");
+        }
+
+        let queued: Vec<usize> = self.change_senders.read().await.values()
+            .map(|sender| sender.sender.max_capacity() - sender.sender.capacity() + sender.backlog.len())
+            .collect();
+        write_header(&mut output, "minecraft_world_changes_queued", "gauge", "World changes waiting to be handled by loaders");
+        let _ = writeln!(output, "minecraft_world_changes_queued {}", queued.iter().sum::<usize>());
+        write_header(&mut output, "minecraft_world_changes_queued_max", "gauge", "World changes waiting to be handled by the slowest loader");
+        let _ = writeln!(output, "minecraft_world_changes_queued_max {}", queued.iter().max().unwrap_or(&0));
+
+        let metrics = &self.metrics;
+        write_header(&mut output, "minecraft_world_changes_backlogged_total", "counter", "World changes that found the channel of their loader full and waited in its backlog");
+        let _ = writeln!(output, "minecraft_world_changes_backlogged_total {}", metrics.world_changes_backlogged.load(Ordering::Relaxed));
+        write_header(&mut output, "minecraft_world_change_loaders_dropped_total", "counter", "Loaders dropped because their backlog of world changes was full");
+        let _ = writeln!(output, "minecraft_world_change_loaders_dropped_total {}", metrics.world_change_loaders_dropped.load(Ordering::Relaxed));
+
+        write_packet_counts(&mut output, "minecraft_packets_received_total", "Play packets received, by packet id", &metrics.packets_in);
+        write_packet_counts(&mut output, "minecraft_packets_sent_total", "Play packets sent, by packet id", &metrics.packets_out);
+        write_header(&mut output, "minecraft_bytes_received_total", "counter", "Bytes of play packets received");
+        let _ = writeln!(output, "minecraft_bytes_received_total {}", metrics.bytes_in.load(Ordering::Relaxed));
+        write_header(&mut output, "minecraft_bytes_sent_total", "counter", "Bytes of play packets sent");
+        let _ = writeln!(output, "minecraft_bytes_sent_total {}", metrics.bytes_out.load(Ordering::Relaxed));
+
+        write_header(&mut output, "minecraft_tick_duration_seconds", "histogram", "Time spent running ticks");
+        let mut cumulative = 0;
+        for (bucket, bound) in TICK_DURATION_BUCKETS.iter().enumerate() {
+            cumulative += metrics.tick_durations[bucket].load(Ordering::Relaxed);
+            let _ = writeln!(output, "minecraft_tick_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        cumulative += metrics.tick_durations[TICK_DURATION_BUCKETS.len()].load(Ordering::Relaxed);
+        let _ = writeln!(output, "minecraft_tick_duration_seconds_bucket{{le=\"+Inf\"}} {cumulative}");
+        let sum = metrics.tick_duration_sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(output, "minecraft_tick_duration_seconds_sum {sum}");
+        let _ = writeln!(output, "minecraft_tick_duration_seconds_count {cumulative}");
+
+        if let Some([tps_1m, tps_5m, tps_15m]) = self.tick_stats().await.tps() {
+            write_header(&mut output, "minecraft_tps", "gauge", "Ticks per second, averaged over a window");
+            for (window, tps) in [("1m", tps_1m), ("5m", tps_5m), ("15m", tps_15m)] {
+                let _ = writeln!(output, "minecraft_tps{{window=\"{window}\"}} {tps}");
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render_metrics() {
+        let world: &'static World = Box::leak(Box::new(World::new()));
+        world.update_loaded_chunks(1, [ChunkColumnPosition { cx: 0, cz: 0 }].into_iter().collect()).await;
+        world.spawn_entity::<Zombie>(AnyEntity::Zombie(Zombie::default())).await;
+        world.spawn_entity::<Zombie>(AnyEntity::Zombie(Zombie::default())).await;
+        world.metrics().packet_sent(&[0x25, 0, 0]);
+        world.metrics().record_tick(Duration::from_millis(20));
+        world.metrics().record_tick(Duration::from_millis(200));
+
+        let metrics = world.render_metrics().await;
+        for line in [
+            "minecraft_players 0",
+            "minecraft_entities{type=\"zombie\"} 2",
+            "minecraft_entity_tasks{task=\"zombie-ai\"} 2",
+            "minecraft_packets_sent_total{id=\"0x25\"} 1",
+            "minecraft_bytes_sent_total 3",
+            "minecraft_tick_duration_seconds_bucket{le=\"0.025\"} 1",
+            "minecraft_tick_duration_seconds_bucket{le=\"0.25\"} 2",
+            "minecraft_tick_duration_seconds_count 2",
+            "minecraft_world_changes_queued 0",
+            "minecraft_world_changes_queued_max 0",
+            "minecraft_world_changes_backlogged_total 0",
+            "minecraft_world_change_loaders_dropped_total 0",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "Missing {line:?} in:\n{metrics}");
+        }
+        let loaded_columns: usize = metrics.lines()
+            .filter_map(|line| line.strip_prefix("minecraft_loaded_chunk_columns{"))
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(loaded_columns, 1);
+    }
+}
@@ -0,0 +1,184 @@
+use minecraft_protocol::{components::slots::SlotItem, ids::{blocks::Block, items::Item}, nbt::arrays::NbtList};
+use super::*;
+
+/// Splits a tool text id into its tier speed and its kind (`diamond_pickaxe` gives `(8.0, "pickaxe")`).
+fn tool_tier_and_kind(item: &SlotItem) -> Option<(f32, &'static str)> {
+    let (tier, kind) = item.item_id.text_id().split_once('_')?;
+    let speed = match tier {
+        "wooden" => 2.0,
+        "stone" => 4.0,
+        "iron" => 6.0,
+        "diamond" => 8.0,
+        "netherite" => 9.0,
+        "golden" => 12.0,
+        _ => return None,
+    };
+    Some((speed, kind))
+}
+
+/// Returns the level of an enchantment on an item, or 0 if it isn't enchanted with it.
+pub fn enchantment_level(item: &SlotItem, enchantment: &str) -> i32 {
+    let Some(enchantments) = item.nbt_data.as_compound().and_then(|tag| tag.get("Enchantments")) else { return 0 };
+    let Some(NbtList::Compound(enchantments)) = enchantments.as_list() else { return 0 };
+    enchantments
+        .iter()
+        .find(|enchantment_tag| enchantment_tag.get("id").and_then(|id| id.as_string()).is_some_and(|id| id == enchantment))
+        .and_then(|enchantment_tag| match enchantment_tag.get("lvl") {
+            Some(NbtTag::Short(level)) => Some(*level as i32),
+            Some(NbtTag::Int(level)) => Some(*level),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Returns the speed multiplier of the held item when mining a block.
+pub fn tool_speed(item: Option<&SlotItem>, block: Block) -> f32 {
+    let Some(item) = item else { return 1.0 };
+    let text_id = block.text_id();
+    let mut speed = match (item.item_id.text_id(), tool_tier_and_kind(item)) {
+        ("shears", _) if text_id == "cobweb" || text_id.ends_with("_leaves") => 15.0,
+        ("shears", _) if text_id.ends_with("_wool") => 5.0,
+        (_, Some((_, "sword"))) if text_id == "cobweb" => 15.0,
+        (_, Some((tier_speed, kind))) if block.mineable_tools().contains(&kind) => tier_speed,
+        _ => 1.0,
+    };
+    if speed > 1.0 {
+        let efficiency = enchantment_level(item, "minecraft:efficiency");
+        if efficiency > 0 {
+            speed += (efficiency * efficiency + 1) as f32;
+        }
+    }
+    speed
+}
+
+/// Returns true if the block drops items when mined with the held item.
+pub fn can_harvest(item: Option<&SlotItem>, block: Block) -> bool {
+    let harvest_tools = block.compatible_harvest_tools();
+    harvest_tools.is_empty() || item.is_some_and(|item| harvest_tools.contains(&(item.item_id as u32)))
+}
+
+/// Returns a random count between `min` and `max` included.
+fn random_count(min: i8, max: i8) -> i8 {
+    min + (rand::random::<u8>() % (max - min + 1) as u8) as i8
+}
+
+/// Returns the items dropped by a block mined with a tool that can harvest it.
+///
+/// The dropped item comes from the game data, which already turns stone into cobblestone, ores into raw materials and leaves into nothing.
+/// This adds the counts of blocks dropping several items, silk touch, and shears harvesting plants.
+/// Fortune and the chance of dropping saplings, seeds or flint are not implemented.
+pub fn block_drops(block: Block, tool: Option<&SlotItem>) -> Vec<SlotItem> {
+    let text_id = block.text_id();
+    let silk_touch = tool.is_some_and(|tool| enchantment_level(tool, "minecraft:silk_touch") > 0);
+    let shears = tool.is_some_and(|tool| tool.item_id == Item::Shears);
+    let sheared = shears && (text_id.ends_with("_leaves") || matches!(text_id, "cobweb" | "grass" | "tall_grass" | "fern" | "large_fern" | "dead_bush" | "vine" | "seagrass" | "tall_seagrass"));
+    if silk_touch || sheared {
+        return Item::from_text_id(text_id).map(|item_id| SlotItem { item_id, item_count: 1, nbt_data: NbtTag::Null }).into_iter().collect();
+    }
+
+    let item_count = match text_id {
+        "copper_ore" | "deepslate_copper_ore" => random_count(2, 5),
+        "lapis_ore" | "deepslate_lapis_ore" => random_count(4, 9),
+        "redstone_ore" | "deepslate_redstone_ore" => random_count(4, 5),
+        "nether_gold_ore" => random_count(2, 6),
+        "glowstone" => random_count(2, 4),
+        "melon" => random_count(3, 7),
+        "bookshelf" => 3,
+        "clay" | "snow_block" => 4,
+        _ => 1,
+    };
+    match Item::from_id(block.associated_item_id()) {
+        // Blocks that drop nothing, such as glass, have an associated item id of 0
+        Some(item_id) if item_id != Item::Air => vec![SlotItem { item_id, item_count, nbt_data: NbtTag::Null }],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the number of ticks it takes to mine a block, or `None` if it can't be mined.
+/// Blocks that break instantly take 0 ticks.
+pub fn mining_ticks(item: Option<&SlotItem>, block: Block, on_ground: bool) -> Option<u32> {
+    let hardness = block.hardness();
+    if hardness < 0.0 {
+        return None;
+    }
+    if hardness == 0.0 {
+        return Some(0);
+    }
+    let mut speed = tool_speed(item, block);
+    if !on_ground {
+        speed /= 5.0;
+    }
+    let damage = speed / hardness / if can_harvest(item, block) { 30.0 } else { 100.0 };
+    if damage >= 1.0 {
+        return Some(0);
+    }
+    Some((1.0 / damage).ceil() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_id: Item) -> SlotItem {
+        SlotItem { item_id, item_count: 1, nbt_data: NbtTag::Null }
+    }
+
+    #[test]
+    fn test_mining_ticks() {
+        // Stone has a hardness of 1.5
+        assert_eq!(mining_ticks(None, Block::Stone, true), Some(150));
+        assert_eq!(mining_ticks(Some(&item(Item::WoodenPickaxe)), Block::Stone, true), Some(23));
+        assert_eq!(mining_ticks(Some(&item(Item::DiamondPickaxe)), Block::Stone, true), Some(6));
+        assert_eq!(mining_ticks(Some(&item(Item::DiamondPickaxe)), Block::Stone, false), Some(29));
+        assert_eq!(mining_ticks(Some(&item(Item::DiamondShovel)), Block::Stone, true), Some(150));
+        assert_eq!(mining_ticks(None, Block::Bedrock, true), None);
+        assert_eq!(mining_ticks(None, Block::Torch, true), Some(0));
+
+        let mut efficient_pickaxe = item(Item::DiamondPickaxe);
+        let mut enchantment = HashMap::new();
+        enchantment.insert(String::from("id"), NbtTag::String(String::from("minecraft:efficiency")));
+        enchantment.insert(String::from("lvl"), NbtTag::Short(5));
+        let mut tag = HashMap::new();
+        tag.insert(String::from("Enchantments"), NbtTag::List(NbtList::Compound(vec![enchantment])));
+        efficient_pickaxe.nbt_data = NbtTag::Compound(tag);
+        assert_eq!(enchantment_level(&efficient_pickaxe, "minecraft:efficiency"), 5);
+        assert_eq!(mining_ticks(Some(&efficient_pickaxe), Block::Stone, true), Some(2));
+    }
+
+    #[test]
+    fn test_block_drops() {
+        let pickaxe = item(Item::IronPickaxe);
+        let drops = |block: Block, tool: Option<&SlotItem>| block_drops(block, tool).into_iter().map(|drop| (drop.item_id, drop.item_count)).collect::<Vec<_>>();
+        assert_eq!(drops(Block::Stone, Some(&pickaxe)), vec![(Item::Cobblestone, 1)]);
+        assert_eq!(drops(Block::IronOre, Some(&pickaxe)), vec![(Item::RawIron, 1)]);
+        assert_eq!(drops(Block::DiamondOre, Some(&pickaxe)), vec![(Item::Diamond, 1)]);
+        assert!(matches!(drops(Block::LapisOre, Some(&pickaxe))[..], [(Item::LapisLazuli, 4..=9)]));
+        assert_eq!(drops(Block::Dirt, None), vec![(Item::Dirt, 1)]);
+        assert_eq!(drops(Block::GrassBlock, None), vec![(Item::Dirt, 1)]);
+        assert_eq!(drops(Block::Glass, None), vec![]);
+        assert_eq!(drops(Block::OakLeaves, None), vec![]);
+        assert_eq!(drops(Block::OakLeaves, Some(&item(Item::Shears))), vec![(Item::OakLeaves, 1)]);
+        assert_eq!(drops(Block::Cobweb, Some(&item(Item::IronSword))), vec![(Item::String, 1)]);
+        assert_eq!(drops(Block::Cobweb, Some(&item(Item::Shears))), vec![(Item::Cobweb, 1)]);
+        assert_eq!(drops(Block::Bookshelf, None), vec![(Item::Book, 3)]);
+        assert_eq!(drops(Block::Air, None), vec![]);
+
+        let mut silk_touch_pickaxe = pickaxe.clone();
+        let mut enchantment = HashMap::new();
+        enchantment.insert(String::from("id"), NbtTag::String(String::from("minecraft:silk_touch")));
+        enchantment.insert(String::from("lvl"), NbtTag::Short(1));
+        let mut tag = HashMap::new();
+        tag.insert(String::from("Enchantments"), NbtTag::List(NbtList::Compound(vec![enchantment])));
+        silk_touch_pickaxe.nbt_data = NbtTag::Compound(tag);
+        assert_eq!(drops(Block::Stone, Some(&silk_touch_pickaxe)), vec![(Item::Stone, 1)]);
+        assert_eq!(drops(Block::Glass, Some(&silk_touch_pickaxe)), vec![(Item::Glass, 1)]);
+    }
+
+    #[test]
+    fn test_can_harvest() {
+        assert!(!can_harvest(None, Block::Stone));
+        assert!(can_harvest(Some(&item(Item::WoodenPickaxe)), Block::Stone));
+        assert!(!can_harvest(Some(&item(Item::StonePickaxe)), Block::DiamondOre));
+        assert!(can_harvest(None, Block::Dirt));
+    }
+}
@@ -1,15 +1,44 @@
+use std::{collections::VecDeque, path::PathBuf};
+use minecraft_protocol::{components::{blocks::{BlockEntity as NetworkBlockEntity, BlockEntityType}, slots::EquipmentSlot}, ids::blocks::Block};
+use tokio::sync::mpsc::error::TrySendError;
 use crate::prelude::*;
 
+mod block_entities;
+pub use block_entities::*;
 mod change;
 pub use change::*;
 mod loading_manager;
 use loading_manager::*;
 mod map;
 use map::*;
+pub use map::ChunkColumn;
+mod generator;
+pub use generator::*;
 mod ecs;
 use ecs::*;
 mod collisions;
 pub use collisions::*;
+mod edit;
+pub use edit::*;
+mod schematics;
+mod placement;
+pub use placement::*;
+mod mining;
+pub use mining::*;
+mod furnace;
+pub use furnace::*;
+mod player_list;
+pub use player_list::*;
+mod chat;
+pub use chat::*;
+mod player_data;
+pub use player_data::*;
+mod ticks;
+pub use ticks::*;
+mod tick_stats;
+pub use tick_stats::*;
+mod metrics;
+pub use metrics::*;
 
 /// World is the union of the map and entities.
 /// World handles loaded chunks and entities.
@@ -19,21 +48,187 @@ pub struct World {
     entities: Entities,
 
     loading_manager: RwLock<WorldLoadingManager>,
-    change_senders: RwLock<HashMap<UUID, MpscSender<WorldChange>>>, // TODO: Add a way to select events you want to subscribe to
-    receiver: BroadcastReceiver<ServerMessage>,
+    change_senders: RwLock<HashMap<UUID, ChangeSender>>, // TODO: Add a way to select events you want to subscribe to
+    /// Block changes made by [World::set_blocks], sent to loaders on the next [World::tick].
+    pending_block_changes: RwLock<HashMap<ChunkPosition, HashMap<BlockPositionInChunk, BlockWithState>>>,
+    /// Block entities set by [World::set_block_entity] or [World::mutate_block_entity], sent to loaders on the next [World::tick] after block changes.
+    pending_block_entity_changes: RwLock<HashSet<BlockPosition>>,
+    /// The players looking inside each container
+    container_viewers: RwLock<HashMap<BlockPosition, HashSet<Eid>>>,
+    /// Furnaces that are burning or cooking, ticked on every [World::tick]
+    active_furnaces: RwLock<HashSet<BlockPosition>>,
+    /// The players shown in the tab list
+    players: RwLock<HashMap<UUID, PlayerListEntry>>,
+    /// Inspects messages written by players before they are broadcast
+    chat_filter: RwLock<Option<ChatFilter>>,
+    /// The players whose messages aren't broadcast
+    muted: RwLock<HashSet<UUID>>,
+    /// The permission levels of operators
+    permission_levels: RwLock<HashMap<UUID, u8>>,
+    commands: CommandDispatcher,
+    config: ServerConfig,
+    /// The PNG icon shown in the server list
+    favicon: Option<Vec<u8>>,
+    /// The directory the world is saved in
+    storage: Option<PathBuf>,
+    /// Holds the reason of the shutdown once it is requested
+    shutdown: tokio::sync::watch::Sender<Option<String>>,
+    /// Wakes entity tasks up every tick
+    ticks: TickClock,
+    tick_stats: RwLock<TickStats>,
+    metrics: Metrics,
+}
+
+/// How many changes can wait for a loader before it is dropped.
+/// Dropping the loader disconnects its player, who would otherwise miss changes.
+const MAX_BACKLOG: usize = 4096;
+
+/// The sending side of a loader's change channel.
+/// Changes that don't fit in the channel are kept in order in a backlog instead of being dropped.
+struct ChangeSender {
+    sender: MpscSender<WorldChange>,
+    backlog: VecDeque<WorldChange>,
+}
+
+impl ChangeSender {
+    /// Returns false if the loader has gone away or fell too far behind.
+    fn send(&mut self, change: WorldChange, metrics: &Metrics) -> bool {
+        if self.backlog.len() >= MAX_BACKLOG {
+            warn!("Dropping a loader that has {MAX_BACKLOG} world changes waiting");
+            metrics.world_change_loader_dropped();
+            return false;
+        }
+        if !self.backlog.is_empty() {
+            self.backlog.push_back(change);
+            metrics.world_change_backlogged();
+            return true;
+        }
+        match self.sender.try_send(change) {
+            Ok(()) => true,
+            Err(TrySendError::Full(change)) => {
+                self.backlog.push_back(change);
+                metrics.world_change_backlogged();
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Moves as many backlogged changes as possible to the channel.
+    /// Returns false if the loader has gone away.
+    fn flush(&mut self) -> bool {
+        while let Some(change) = self.backlog.pop_front() {
+            match self.sender.try_send(change) {
+                Ok(()) => (),
+                Err(TrySendError::Full(change)) => {
+                    self.backlog.push_front(change);
+                    return true;
+                }
+                Err(TrySendError::Closed(_)) => return false,
+            }
+        }
+        true
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
 }
 
 impl World {
-    pub fn new(receiver: BroadcastReceiver<ServerMessage>) -> World {
+    pub fn new() -> World {
         World {
             map: WorldMap::new(4),
             entities: Entities::new(),
             loading_manager: RwLock::new(WorldLoadingManager::default()),
             change_senders: RwLock::new(HashMap::new()),
-            receiver,
+            pending_block_changes: RwLock::new(HashMap::new()),
+            pending_block_entity_changes: RwLock::new(HashSet::new()),
+            container_viewers: RwLock::new(HashMap::new()),
+            active_furnaces: RwLock::new(HashSet::new()),
+            players: RwLock::new(HashMap::new()),
+            chat_filter: RwLock::new(None),
+            muted: RwLock::new(HashSet::new()),
+            permission_levels: RwLock::new(HashMap::new()),
+            commands: CommandDispatcher::new(),
+            config: ServerConfig::default(),
+            favicon: None,
+            storage: None,
+            shutdown: tokio::sync::watch::channel(None).0,
+            ticks: TickClock::new(),
+            tick_stats: RwLock::new(TickStats::default()),
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Creates a world saved in `directory`.
+    /// Chunk columns are saved when unloaded, and player data when players leave.
+    pub fn with_storage(directory: PathBuf) -> World {
+        World {
+            map: WorldMap::with_storage(4, directory.join("chunks")),
+            storage: Some(directory),
+            ..World::new()
         }
     }
 
+    /// Asks the server to stop, disconnecting players with this reason.
+    pub fn shutdown(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.shutdown.send_modify(|shutdown| {
+            shutdown.get_or_insert(reason);
+        });
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.borrow().is_some()
+    }
+
+    /// Waits until [World::shutdown] is called, returning the reason.
+    pub async fn shutdown_requested(&self) -> String {
+        let mut receiver = self.shutdown.subscribe();
+        let reason = receiver.wait_for(|reason| reason.is_some()).await.map(|reason| reason.clone().unwrap_or_default());
+        reason.unwrap_or_default()
+    }
+
+    /// Writes all loaded chunk columns to the disk.
+    pub async fn save_all(&self) {
+        self.map.save_all().await;
+    }
+
+    /// Stops the tasks of all entities, such as mob AI.
+    pub async fn abort_entity_tasks(&self) {
+        self.entities.abort_tasks().await;
+    }
+
+    /// Returns the clock waking entity tasks up.
+    pub fn ticks(&self) -> &TickClock {
+        &self.ticks
+    }
+
+    /// Replaces the generator of chunk columns that were never saved.
+    pub fn with_generator(self, generator: Box<dyn WorldGenerator>) -> World {
+        World { map: self.map.with_generator(generator), ..self }
+    }
+
+    /// Replaces the default settings of the world.
+    pub fn with_config(self, config: ServerConfig) -> World {
+        World { config, ..self }
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    pub fn with_favicon(self, favicon: Option<Vec<u8>>) -> World {
+        World { favicon, ..self }
+    }
+
+    pub fn favicon(&self) -> Option<&[u8]> {
+        self.favicon.as_deref()
+    }
+
     pub async fn get_block(&self, position: BlockPosition) -> Option<BlockWithState> {
         Some(self.map.get_block(position).await)
     }
@@ -44,16 +239,223 @@ impl World {
 
     pub async fn set_block(&self, position: BlockPosition, block: BlockWithState) {
         self.map.set_block(position.clone(), block.clone()).await;
+
+        // A pending batched change to that block is now outdated
+        let mut pending_block_changes = self.pending_block_changes.write().await;
+        if let Some(section) = pending_block_changes.get_mut(&position.chunk()) {
+            section.remove(&position.in_chunk());
+        }
+        drop(pending_block_changes);
+
         self.notify(&position.chunk_column(), WorldChange::Block(position, block)).await;
     }
 
+    /// Sets many blocks at once.
+    /// Players are notified on the next [World::tick], with one change per modified chunk section.
+    pub async fn set_blocks(&self, blocks: impl IntoIterator<Item = (BlockPosition, BlockWithState)>) {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        self.map.set_blocks(blocks.clone()).await;
+
+        let mut pending_block_changes = self.pending_block_changes.write().await;
+        for (position, block) in blocks {
+            pending_block_changes.entry(position.chunk()).or_default().insert(position.in_chunk(), block);
+        }
+    }
+
+    /// Sends the changes that were accumulated since the last tick.
+    pub async fn tick(&self) {
+        self.tick_furnaces().await;
+
+        let pending_block_changes = std::mem::take(&mut *self.pending_block_changes.write().await);
+        for (section, mut blocks) in pending_block_changes {
+            let change = match blocks.len() {
+                0 => continue,
+                1 => {
+                    let Some((position, block)) = blocks.drain().next() else { continue };
+                    WorldChange::Block(section.clone() + position, block)
+                }
+                _ => WorldChange::SectionBlocks { section: section.clone(), blocks: blocks.into_iter().collect() },
+            };
+            self.notify(&section.chunk_column(), change).await;
+        }
+
+        let pending_block_entity_changes = std::mem::take(&mut *self.pending_block_entity_changes.write().await);
+        for position in pending_block_entity_changes {
+            let Some(block_entity) = self.map.get_block_entity(position.clone()).await else { continue };
+            let change = WorldChange::BlockEntity { position: position.clone(), ty: block_entity.ty, data: block_entity.to_network_nbt() };
+            self.notify(&position.chunk_column(), change).await;
+        }
+
+        let mut senders = self.change_senders.write().await;
+        senders.retain(|_, sender| sender.flush());
+        let backlog: usize = senders.values().map(|sender| sender.backlog.len()).sum();
+        if backlog > 10_000 {
+            warn!("{backlog} world changes are waiting for slow loaders");
+        }
+    }
+
+    pub async fn get_block_entity(&self, position: BlockPosition) -> Option<BlockEntity> {
+        self.map.get_block_entity(position).await
+    }
+
+    /// Replaces the block entity at `position`.
+    /// Players are notified on the next [World::tick], so that it reaches them after batched block changes.
+    pub async fn set_block_entity(&self, position: BlockPosition, block_entity: BlockEntity) {
+        if self.map.set_block_entity(position.clone(), block_entity).await {
+            self.pending_block_entity_changes.write().await.insert(position);
+        }
+    }
+
+    /// Mutates the block entity at `position`.
+    /// Players are notified of its new state on the next [World::tick], like with [World::set_block_entity].
+    pub async fn mutate_block_entity<R>(&self, position: BlockPosition, mutator: impl FnOnce(&mut BlockEntity) -> R) -> Option<R> {
+        let r = self.map.mutate_block_entity(position.clone(), mutator).await?;
+        self.pending_block_entity_changes.write().await.insert(position);
+        Some(r)
+    }
+
+    /// Returns the slots of the chest, furnace or other container at `position`.
+    pub async fn get_container_slots(&self, position: BlockPosition) -> Option<Vec<Slot>> {
+        self.map.get_block_entity(position).await?.slots().map(|slots| slots.to_vec())
+    }
+
+    /// Replaces the slots of the container at `position`, unless they are no longer `expected` because someone else modified them.
+    /// Returns false if nothing was changed.
+    pub async fn set_container_slots(&self, position: BlockPosition, expected: &[Slot], new_slots: &[Slot]) -> bool {
+        let changed = self.map.mutate_block_entity(position.clone(), |block_entity| {
+            let is_furnace = matches!(block_entity.data, BlockEntityData::Furnace { .. });
+            let slots = block_entity.slots_mut()?;
+            if &*slots != expected || slots.len() != new_slots.len() {
+                return None;
+            }
+            let changed: Vec<(usize, Slot)> = new_slots.iter().enumerate()
+                .filter(|(index, slot)| slots[*index] != **slot)
+                .map(|(index, slot)| (index, slot.clone()))
+                .collect();
+            slots.clone_from_slice(new_slots);
+            Some((changed, is_furnace))
+        }).await.flatten();
+        let Some((changed, is_furnace)) = changed else { return false };
+
+        if is_furnace {
+            self.active_furnaces.write().await.insert(position.clone());
+        }
+        if !changed.is_empty() {
+            self.notify(&position.chunk_column(), WorldChange::ContainerSlots { position, slots: changed }).await;
+        }
+        true
+    }
+
+    /// Registers a player looking inside a container.
+    pub async fn open_container(&self, eid: Eid, position: BlockPosition) {
+        let mut container_viewers = self.container_viewers.write().await;
+        let viewers = container_viewers.entry(position.clone()).or_default();
+        viewers.insert(eid);
+        let viewer_count = viewers.len();
+        drop(container_viewers);
+        self.animate_container(position, viewer_count).await;
+    }
+
+    /// Unregisters a player looking inside a container.
+    pub async fn close_container(&self, eid: Eid, position: BlockPosition) {
+        let mut container_viewers = self.container_viewers.write().await;
+        let Some(viewers) = container_viewers.get_mut(&position) else { return };
+        if !viewers.remove(&eid) {
+            return;
+        }
+        let viewer_count = viewers.len();
+        if viewer_count == 0 {
+            container_viewers.remove(&position);
+        }
+        drop(container_viewers);
+        self.animate_container(position, viewer_count).await;
+    }
+
+    /// Returns the number of players looking inside a container.
+    pub async fn container_viewer_count(&self, position: BlockPosition) -> usize {
+        self.container_viewers.read().await.get(&position).map(|viewers| viewers.len()).unwrap_or(0)
+    }
+
+    /// Opens chests and barrels while players look inside, and closes them afterwards.
+    async fn animate_container(&self, position: BlockPosition, viewer_count: usize) {
+        let Some(block) = self.get_block(position.clone()).await else { return };
+        let block_type = Block::from(block.clone());
+        match BlockEntityType::from_block(block_type) {
+            Some(BlockEntityType::Chest | BlockEntityType::TrappedChest | BlockEntityType::EnderChest | BlockEntityType::ShulkerBox) => {
+                let action_param = viewer_count.min(u8::MAX as usize) as u8;
+                let change = WorldChange::BlockAction { position: position.clone(), action_id: 1, action_param, block: block_type };
+                self.notify(&position.chunk_column(), change).await;
+            }
+            Some(BlockEntityType::Barrel) => {
+                let open = if viewer_count > 0 { "true" } else { "false" };
+                let Some(new_block) = block.with_property("open", open) else { return };
+                if new_block.block_state_id() != block.block_state_id() {
+                    self.set_block(position, new_block).await;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Advances the furnaces that are burning or cooking, and shows their progress to the players looking inside.
+    async fn tick_furnaces(&self) {
+        let furnaces: Vec<BlockPosition> = self.active_furnaces.read().await.iter().cloned().collect();
+        for position in furnaces {
+            let ticked = self.map.mutate_block_entity(position.clone(), |block_entity| {
+                let properties_before = furnace_properties(&block_entity.data)?;
+                let slots_before = block_entity.slots()?.to_vec();
+                let tick = tick_furnace(block_entity.ty, &mut block_entity.data);
+                let properties = furnace_properties(&block_entity.data)?;
+                let changed_slots: Vec<(usize, Slot)> = block_entity.slots()?.iter().enumerate()
+                    .filter(|(index, slot)| slots_before[*index] != **slot)
+                    .map(|(index, slot)| (index, slot.clone()))
+                    .collect();
+                Some((tick, properties_before, properties, changed_slots))
+            }).await.flatten();
+            let Some((tick, properties_before, properties, changed_slots)) = ticked else {
+                self.active_furnaces.write().await.remove(&position);
+                continue;
+            };
+            if !tick.active {
+                self.active_furnaces.write().await.remove(&position);
+            }
+
+            if self.container_viewer_count(position.clone()).await > 0 {
+                for (property, (before, value)) in properties_before.into_iter().zip(properties).enumerate() {
+                    if before != value {
+                        self.notify(&position.chunk_column(), WorldChange::ContainerProperty { position: position.clone(), property: property as i16, value }).await;
+                    }
+                }
+                if !changed_slots.is_empty() {
+                    self.notify(&position.chunk_column(), WorldChange::ContainerSlots { position: position.clone(), slots: changed_slots }).await;
+                }
+            }
+            if tick.lit_changed {
+                let Some(block) = self.get_block(position.clone()).await else { continue };
+                let lit = if properties[0] > 0 { "true" } else { "false" };
+                if let Some(block) = block.with_property("lit", lit) {
+                    self.set_block(position, block).await;
+                }
+            }
+        }
+    }
+
+    /// Shows the progress of an entity breaking a block to nearby players.
+    pub async fn set_block_destroy_stage(&self, eid: Eid, position: BlockPosition, stage: Option<u8>) {
+        self.notify(&position.chunk_column(), WorldChange::BlockDestroyStage { eid, position, stage }).await;
+    }
+
+    pub async fn get_network_block_entities(&self, position: ChunkColumnPosition) -> Vec<NetworkBlockEntity> {
+        self.map.get_network_block_entities(position).await
+    }
+
     pub async fn try_move(&self, object: &CollisionShape, movement: &Translation) -> Translation {
         self.map.try_move(object, movement).await
     }
 
     pub async fn add_loader(&self, uuid: UUID) -> MpscReceiver<WorldChange> {
         let (sender, receiver) = mpsc_channel(100);
-        self.change_senders.write().await.insert(uuid, sender);
+        self.change_senders.write().await.insert(uuid, ChangeSender { sender, backlog: VecDeque::new() });
         receiver
     }
 
@@ -77,8 +479,22 @@ impl World {
         }
     }
 
+    /// Returns an unused entity id, to be given to [World::spawn_entity_with_ids].
+    /// Players need theirs before they are spawned.
+    pub fn reserve_eid(&self) -> Eid {
+        self.entities.next_eid()
+    }
+
     pub async fn spawn_entity<E>(&'static self, entity: AnyEntity) -> Eid
         where AnyEntity: TryAsEntityRef<E>, Handler<E>: EntityExt
+    {
+        let (eid, uuid) = (self.entities.next_eid(), self.entities.next_uuid());
+        self.spawn_entity_with_ids::<E>(eid, uuid, entity).await
+    }
+
+    /// Spawns an entity with a known id and UUID, such as a player.
+    pub async fn spawn_entity_with_ids<E>(&'static self, eid: Eid, uuid: UUID, entity: AnyEntity) -> Eid
+        where AnyEntity: TryAsEntityRef<E>, Handler<E>: EntityExt
     {
         let position = entity.as_entity().position.clone();
         let velocity = entity.as_entity().velocity.clone();
@@ -86,7 +502,8 @@ impl World {
         let pitch = entity.as_entity().pitch;
         let yaw = entity.as_entity().yaw;
         let head_yaw = entity.as_other::<LivingEntity>().map(|e| e.head_yaw).unwrap_or(0.0);
-        let (eid, uuid) = self.entities.spawn_entity::<E>(entity, self, self.receiver.resubscribe()).await;
+        let metadata = entity.network_metadata();
+        self.entities.spawn_entity::<E>(eid, uuid, entity, self, self.ticks.subscribe()).await;
         self.notify(&position.chunk_column(), WorldChange::EntitySpawned {
             eid,
             uuid,
@@ -97,19 +514,59 @@ impl World {
             head_yaw,
             data: 0,
             velocity,
-            metadata: (),
+            metadata,
         }).await;
         eid
     }
 
+    /// Spawns an item entity holding a stack of items.
+    /// It can't be picked up during the first `pickup_delay` ticks.
+    pub async fn spawn_item(&'static self, position: Position, velocity: Translation, item: SlotItem, pickup_delay: u16) -> Eid {
+        let mut item_entity = ItemEntity { item: Slot { item: Some(item) }, pickup_delay, ..Default::default() };
+        item_entity.entity.position = position;
+        item_entity.entity.velocity = velocity;
+        self.spawn_entity::<ItemEntity>(AnyEntity::ItemEntity(item_entity)).await
+    }
+
     pub async fn observe_entity<R>(&self, eid: Eid, observer: impl FnOnce(&AnyEntity) -> R) -> Option<R> {
         self.entities.observe_entity(eid, observer).await
     }
 
+    pub async fn entity_uuid(&self, eid: Eid) -> Option<UUID> {
+        self.entities.uuid_of(eid).await
+    }
+
     pub async fn observe_entities<R>(&self, chunk: ChunkColumnPosition, observer: impl FnMut(&AnyEntity) -> Option<R>) -> Vec<R> {
         self.entities.observe_entities(chunk, observer).await
     }
 
+    pub async fn observe_entities_with_eids<R>(&self, chunk: ChunkColumnPosition, observer: impl FnMut(Eid, &AnyEntity) -> Option<R>) -> Vec<R> {
+        self.entities.observe_entities_with_eids(chunk, observer).await
+    }
+
+    /// Observes every entity of the world, which is slower than observing the entities of a chunk.
+    pub async fn observe_all_entities<R>(&self, observer: impl FnMut(Eid, &AnyEntity) -> Option<R>) -> Vec<R> {
+        self.entities.observe_all_entities(observer).await
+    }
+
+    /// Removes an entity and tells clients it disappeared.
+    pub async fn remove_entity(&self, eid: Eid) -> Option<AnyEntity> {
+        let entity = self.entities.remove_entity(eid).await?;
+        self.notify(&entity.as_entity().position.chunk_column(), WorldChange::EntityDispawned { eid }).await;
+        Some(entity)
+    }
+
+    /// Tells clients what an entity holds and wears.
+    pub async fn notify_equipment(&self, eid: Eid, equipment: Vec<(EquipmentSlot, Slot)>) {
+        let Some(position) = self.entities.observe_entity(eid, |e| e.as_entity().position.clone()).await else { return };
+        self.notify(&position.chunk_column(), WorldChange::EntityEquipment { eid, equipment }).await;
+    }
+
+    /// Plays the animation of an item flying towards the entity that collected it.
+    pub async fn notify_item_pickup(&self, position: &Position, collected: Eid, collector: Eid, count: i8) {
+        self.notify(&position.chunk_column(), WorldChange::ItemPickedUp { collected, collector, count }).await;
+    }
+
     // TODO: add version that doesn't notify modified entity
     pub async fn mutate_entity<R>(&self, eid: Eid, mutator: impl FnOnce(&mut AnyEntity) -> (R, EntityChanges)) -> Option<R> {
         // TODO: change events
@@ -140,7 +597,11 @@ impl World {
                     }).await;
                 }
                 if changes.metadata_changed() {
-                    todo!()
+                    let metadata = self.entities.observe_entity(eid, |e| e.network_metadata()).await?;
+                    self.notify(&position.chunk_column(), WorldChange::EntityMetadata {
+                        eid,
+                        metadata,
+                    }).await;
                 }
                 Some(r)
             },
@@ -148,13 +609,21 @@ impl World {
         }
     }
 
+    /// Sends a change to all loaders, wherever they are.
+    async fn notify_all(&self, change: WorldChange) {
+        let mut senders = self.change_senders.write().await;
+        senders.retain(|_, sender| sender.send(change.clone(), &self.metrics));
+    }
+
     async fn notify(&self, position: &ChunkColumnPosition, change: WorldChange) {
         let loading_manager = self.loading_manager.read().await;
         let mut senders = self.change_senders.write().await;
         let Some(loaders) = loading_manager.get_loaders(position) else {return};
         for loader in loaders {
             if let Some(sender) = senders.get_mut(loader) {
-                let _ = sender.try_send(change.clone());
+                if !sender.send(change.clone(), &self.metrics) {
+                    senders.remove(loader);
+                }
             }
         }
     }
@@ -167,7 +636,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_world_notifications() {
-        let world = World::new(broadcast_channel(100).1);
+        let world = World::new();
 
         let mut receiver1 = world.add_loader(1).await;
         let mut receiver2 = world.add_loader(2).await;
@@ -178,4 +647,126 @@ mod tests {
         assert!(matches!(receiver1.try_recv(), Ok(WorldChange::Block(BlockPosition{x: 1, y: 1, z: 1}, BlockWithState::Air))));
         assert!(matches!(receiver2.try_recv(), Err(TryRecvError::Empty)));
     }
+
+    #[tokio::test]
+    async fn test_batched_notifications() {
+        let world = World::new();
+
+        let mut receiver = world.add_loader(1).await;
+        world.update_loaded_chunks(1, vec![ChunkColumnPosition{cx: 0, cz: 0}].into_iter().collect()).await;
+
+        // Changes are only sent on tick, grouped by section
+        let blocks = (0..16).map(|x| (BlockPosition{x, y: 1, z: 1}, BlockWithState::Dirt));
+        world.set_blocks(blocks).await;
+        world.set_blocks(vec![(BlockPosition{x: 0, y: 20, z: 0}, BlockWithState::Dirt)]).await;
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+        world.tick().await;
+        let mut section_blocks = 0;
+        let mut single_blocks = 0;
+        while let Ok(change) = receiver.try_recv() {
+            match change {
+                WorldChange::SectionBlocks { section, blocks } => {
+                    assert_eq!(section, ChunkPosition{cx: 0, cy: 0, cz: 0});
+                    assert_eq!(blocks.len(), 16);
+                    section_blocks += 1;
+                },
+                WorldChange::Block(BlockPosition{x: 0, y: 20, z: 0}, _) => single_blocks += 1,
+                change => panic!("Unexpected change {change:?}"),
+            }
+        }
+        assert_eq!((section_blocks, single_blocks), (1, 1));
+
+        // Changes are never dropped, even when the channel is full
+        for x in 0..300 {
+            world.set_block(BlockPosition{x: x % 16, y: x / 16, z: 0}, BlockWithState::Air).await;
+        }
+        let mut received = 0;
+        for _ in 0..10 {
+            while receiver.try_recv().is_ok() {
+                received += 1;
+            }
+            world.tick().await;
+        }
+        assert_eq!(received, 300);
+        let metrics = world.render_metrics().await;
+        assert!(metrics.lines().any(|line| line.starts_with("minecraft_world_changes_backlogged_total ") && !line.ends_with(" 0")));
+
+        // Loaders that fall too far behind are dropped instead of growing the backlog forever
+        for x in 0..(100 + MAX_BACKLOG + 1) {
+            world.set_block(BlockPosition{x: (x % 16) as i32, y: 0, z: 0}, BlockWithState::Air).await;
+        }
+        assert!(world.change_senders.read().await.is_empty());
+        let mut received = 0;
+        while receiver.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 100);
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Disconnected)));
+        let metrics = world.render_metrics().await;
+        assert!(metrics.lines().any(|line| line == "minecraft_world_change_loaders_dropped_total 1"));
+    }
+
+    #[tokio::test]
+    async fn test_block_entity_notifications() {
+        let world = World::new();
+
+        let mut receiver = world.add_loader(1).await;
+        world.update_loaded_chunks(1, vec![ChunkColumnPosition{cx: 0, cz: 0}].into_iter().collect()).await;
+        let position = BlockPosition{x: 1, y: 1, z: 1};
+        world.set_block(position.clone(), BlockWithState::from_state_id(Block::OakSign.default_state_id()).unwrap()).await;
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::Block(..))));
+
+        // Block entities reach loaders on tick, whether they are replaced or mutated
+        world.mutate_block_entity(position.clone(), |block_entity| {
+            if let BlockEntityData::Sign { front_text, .. } = &mut block_entity.data {
+                front_text.set_lines(["mutated", "", "", ""]);
+            }
+        }).await.unwrap();
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+        world.tick().await;
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::BlockEntity { position: BlockPosition{x: 1, y: 1, z: 1}, .. })));
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[tokio::test]
+    async fn test_item_entity_lifecycle() {
+        let world: &'static World = Box::leak(Box::new(World::new()));
+
+        let mut receiver = world.add_loader(1).await;
+        world.update_loaded_chunks(1, vec![ChunkColumnPosition{cx: 0, cz: 0}].into_iter().collect()).await;
+
+        let item = SlotItem { item_id: Item::Dirt, item_count: 3, nbt_data: NbtTag::Null };
+        let eid = world.spawn_item(Position { x: 1.5, y: 1.0, z: 1.5 }, Translation { x: 0.0, y: 0.0, z: 0.0 }, item, 10).await;
+        match receiver.try_recv() {
+            Ok(WorldChange::EntitySpawned { eid: spawned, metadata, .. }) => {
+                assert_eq!(spawned, eid);
+                assert!(matches!(metadata.get(&8), Some(EntityMetadataValue::Slot { slot: Slot { item: Some(SlotItem { item_count: 3, .. }) } })));
+            }
+            change => panic!("Unexpected change {change:?}"),
+        }
+
+        assert!(world.remove_entity(eid).await.is_some());
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::EntityDispawned { eid: removed }) if removed == eid));
+        assert!(world.remove_entity(eid).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_player_list() {
+        let world = World::new();
+
+        // Loaders get player list changes wherever they are
+        let mut receiver = world.add_loader(1).await;
+        let entry = PlayerListEntry { eid: 7, name: String::from("Steve"), properties: Vec::new(), gamemode: Gamemode::Survival, latency: 0, listed: true };
+        world.add_player(2, entry).await;
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::PlayerInfoAdded { uuid: 2, entry }) if entry.name == "Steve"));
+
+        world.update_player(2, PlayerListUpdate::Latency(42)).await;
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::PlayerInfoUpdated { uuid: 2, update: PlayerListUpdate::Latency(42) })));
+        assert_eq!(world.player(2).await.map(|entry| entry.latency), Some(42));
+
+        assert!(world.remove_player(2).await.is_some());
+        assert!(matches!(receiver.try_recv(), Ok(WorldChange::PlayerInfoRemoved { uuid: 2 })));
+        assert!(world.players().await.is_empty());
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
 }
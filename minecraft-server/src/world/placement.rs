@@ -0,0 +1,307 @@
+use std::sync::OnceLock;
+use minecraft_protocol::{components::blocks::BlockFace, ids::{blocks::Block, items::Item}};
+use super::*;
+
+/// Returns the block placed by an item, if any.
+///
+/// Most block items share their text id with their block.
+/// The others (`redstone`, `string`, seeds...) are found by looking for the block that drops them.
+pub fn block_for_item(item: Item) -> Option<Block> {
+    if let Some(block) = Block::from_text_id(item.text_id()) {
+        return Some(block);
+    }
+
+    static DROPPED_BY: OnceLock<HashMap<u32, Block>> = OnceLock::new();
+    let dropped_by = DROPPED_BY.get_or_init(|| {
+        let mut dropped_by = HashMap::new();
+        let mut id = 0;
+        while let Some(block) = Block::from_id(id) {
+            id += 1;
+            // Blocks with their own item (like ores) must not be placed with the item they drop
+            if block.associated_item_id() == 0 || Item::from_text_id(block.text_id()).is_some() {
+                continue;
+            }
+            dropped_by.entry(block.associated_item_id()).or_insert(block);
+        }
+        dropped_by
+    });
+    dropped_by.get(&(item as u32)).copied()
+}
+
+/// Returns true if placing a block at this position should replace the current block.
+pub fn is_replaceable(block: &BlockWithState) -> bool {
+    matches!(
+        Block::from(block.clone()).text_id(),
+        "air" | "cave_air" | "void_air" | "water" | "lava" | "grass" | "fern" | "dead_bush" | "seagrass" | "tall_seagrass"
+            | "tall_grass" | "large_fern" | "vine" | "glow_lichen" | "fire" | "soul_fire" | "structure_void" | "light"
+    )
+}
+
+/// Returns true if placing this block on top of the current block merges them into a double slab.
+pub fn merges_with(block: Block, current: &BlockWithState) -> bool {
+    Block::from(current.clone()) == block && current.property("type").is_some_and(|ty| ty != "double")
+}
+
+/// Returns the position of the neighbour block touching a face.
+pub fn offset_by_face(position: &BlockPosition, face: BlockFace) -> BlockPosition {
+    let (dx, dy, dz) = match face {
+        BlockFace::Bottom => (0, -1, 0),
+        BlockFace::Top => (0, 1, 0),
+        BlockFace::North => (0, 0, -1),
+        BlockFace::South => (0, 0, 1),
+        BlockFace::West => (-1, 0, 0),
+        BlockFace::East => (1, 0, 0),
+    };
+    BlockPosition { x: position.x + dx, y: position.y + dy, z: position.z + dz }
+}
+
+fn face_name(face: BlockFace) -> &'static str {
+    match face {
+        BlockFace::Bottom => "down",
+        BlockFace::Top => "up",
+        BlockFace::North => "north",
+        BlockFace::South => "south",
+        BlockFace::West => "west",
+        BlockFace::East => "east",
+    }
+}
+
+fn opposite(direction: &str) -> &'static str {
+    match direction {
+        "down" => "up",
+        "up" => "down",
+        "north" => "south",
+        "south" => "north",
+        "west" => "east",
+        _ => "west",
+    }
+}
+
+fn clockwise(direction: &str) -> &'static str {
+    match direction {
+        "north" => "east",
+        "east" => "south",
+        "south" => "west",
+        _ => "north",
+    }
+}
+
+/// Returns the position of the other half of a double chest.
+///
+/// The other half of a `left` chest is on its clockwise side, as seen from above.
+pub fn chest_partner(position: &BlockPosition, chest: &BlockWithState) -> Option<BlockPosition> {
+    let facing = chest.property("facing")?;
+    let direction = match chest.property("type")? {
+        "left" => clockwise(facing),
+        "right" => opposite(clockwise(facing)),
+        _ => return None,
+    };
+    let (dx, dz) = match direction {
+        "north" => (0, -1),
+        "south" => (0, 1),
+        "west" => (-1, 0),
+        _ => (1, 0),
+    };
+    Some(BlockPosition { x: position.x + dx, y: position.y, z: position.z + dz })
+}
+
+/// Returns the position of the other half of a two blocks high block, such as a door.
+pub fn double_block_partner(position: &BlockPosition, block: &BlockWithState) -> Option<BlockPosition> {
+    let dy = match block.property("half")? {
+        "lower" => 1,
+        "upper" => -1,
+        _ => return None,
+    };
+    Some(BlockPosition { x: position.x, y: position.y + dy, z: position.z })
+}
+
+/// Everything known about a placement that can affect the state of the placed block.
+#[derive(Debug, Clone)]
+pub struct PlacementContext {
+    /// The face of the block that was clicked
+    pub face: BlockFace,
+    /// The height of the crosshair on the clicked face, from 0 to 1
+    pub cursor_y: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// The block being replaced by the placement
+    pub replaced: BlockWithState,
+}
+
+impl PlacementContext {
+    /// The horizontal direction the player is looking at
+    fn horizontal_direction(&self) -> &'static str {
+        match ((self.yaw / 90.0).round() as i32).rem_euclid(4) {
+            0 => "south",
+            1 => "west",
+            2 => "north",
+            _ => "east",
+        }
+    }
+
+    /// The direction the player is looking at, including up and down
+    fn looking_direction(&self) -> &'static str {
+        match self.pitch {
+            pitch if pitch < -45.0 => "up",
+            pitch if pitch > 45.0 => "down",
+            _ => self.horizontal_direction(),
+        }
+    }
+
+    fn clicked_top_half(&self) -> bool {
+        match self.face {
+            BlockFace::Bottom => true,
+            BlockFace::Top => false,
+            _ => self.cursor_y > 0.5,
+        }
+    }
+}
+
+/// Picks the state of a block placed by a player.
+///
+/// Orientation properties (`facing`, `axis`, `half`, `type`, `rotation`) are chosen from the clicked face and the player's rotation.
+/// Blocks placed in water source blocks are waterlogged.
+/// Blocks placed against a wall use their wall variant when there is one (`torch` becomes `wall_torch`).
+pub fn placement_state(block: Block, context: &PlacementContext) -> BlockWithState {
+    let horizontal_face = !matches!(context.face, BlockFace::Top | BlockFace::Bottom);
+    let mut block = block;
+    if horizontal_face {
+        let wall_text_id = match block.text_id().rsplit_once('_') {
+            Some((prefix, suffix)) => format!("{prefix}_wall_{suffix}"),
+            None => format!("wall_{}", block.text_id()),
+        };
+        if let Some(wall_block) = Block::from_text_id(&wall_text_id) {
+            block = wall_block;
+        }
+    }
+    let text_id = block.text_id();
+
+    if merges_with(block, &context.replaced) {
+        return context.replaced.with_property("type", "double")
+            .and_then(|state| state.with_property("waterlogged", "false"))
+            .unwrap_or_else(|| context.replaced.clone());
+    }
+
+    let Some(mut state) = BlockWithState::from_state_id(block.default_state_id()) else { return BlockWithState::Air };
+    let mut set = |name: &str, value: &str| {
+        if let Some(new_state) = state.with_property(name, value) {
+            state = new_state;
+        }
+    };
+
+    for (name, values) in block_properties(block) {
+        match *name {
+            "facing" if text_id.contains("wall_") && horizontal_face => set("facing", face_name(context.face)),
+            "facing" if values.contains(&"up") => match text_id {
+                "end_rod" | "lightning_rod" | "amethyst_cluster" => set("facing", face_name(context.face)),
+                "observer" => set("facing", context.looking_direction()),
+                _ => set("facing", opposite(context.looking_direction())),
+            },
+            "facing" if values.contains(&"down") => set("facing", opposite(face_name(context.face))), // hoppers
+            "facing" => {
+                let follows_look = ["_stairs", "_door", "_fence_gate", "_bed"].iter().any(|suffix| text_id.ends_with(suffix));
+                if follows_look {
+                    set("facing", context.horizontal_direction())
+                } else if (text_id.ends_with("_trapdoor") || text_id == "ladder") && horizontal_face {
+                    set("facing", face_name(context.face))
+                } else {
+                    set("facing", opposite(context.horizontal_direction()))
+                }
+            }
+            "axis" => set("axis", match context.face {
+                BlockFace::Top | BlockFace::Bottom => "y",
+                BlockFace::North | BlockFace::South => "z",
+                BlockFace::West | BlockFace::East => "x",
+            }),
+            "half" if values.contains(&"top") => set("half", if context.clicked_top_half() { "top" } else { "bottom" }),
+            "type" if values.contains(&"double") => set("type", if context.clicked_top_half() { "top" } else { "bottom" }),
+            "rotation" => {
+                let rotation = (((180.0 + context.yaw) * 16.0 / 360.0 + 0.5).floor() as i32).rem_euclid(16);
+                set("rotation", &rotation.to_string())
+            }
+            "waterlogged" => {
+                let in_water = Block::from(context.replaced.clone()) == Block::Water && context.replaced.property("level") == Some("0");
+                set("waterlogged", if in_water { "true" } else { "false" })
+            }
+            _ => (),
+        }
+    }
+
+    state
+}
+
+fn block_properties(block: Block) -> &'static [(&'static str, &'static [&'static str])] {
+    BlockWithState::from_state_id(block.default_state_id()).map(|state| state.properties()).unwrap_or(&[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(face: BlockFace, cursor_y: f32, yaw: f32) -> PlacementContext {
+        PlacementContext { face, cursor_y, yaw, pitch: 0.0, replaced: BlockWithState::Air }
+    }
+
+    fn state(text: &str) -> BlockWithState {
+        text.parse().unwrap()
+    }
+
+    fn assert_places(block: Block, context: &PlacementContext, expected: &str) {
+        let placed = placement_state(block, context);
+        assert_eq!(placed.block_state_id(), state(expected).block_state_id(), "placed {placed} instead of {expected}");
+    }
+
+    #[test]
+    fn test_block_for_item() {
+        assert_eq!(block_for_item(Item::Stone), Some(Block::Stone));
+        assert_eq!(block_for_item(Item::OakStairs), Some(Block::OakStairs));
+        assert_eq!(block_for_item(Item::Redstone), Some(Block::RedstoneWire));
+        assert_eq!(block_for_item(Item::Diamond), None);
+    }
+
+    #[test]
+    fn test_placement_state() {
+        // Looking north (yaw 180)
+        assert_places(Block::OakStairs, &context(BlockFace::Top, 1.0, 180.0), "oak_stairs[facing=north,half=bottom]");
+        assert_places(Block::OakStairs, &context(BlockFace::South, 0.7, 180.0), "oak_stairs[facing=north,half=top]");
+        assert_places(Block::Furnace, &context(BlockFace::Top, 1.0, 180.0), "furnace[facing=south]");
+        assert_places(Block::OakLog, &context(BlockFace::East, 0.5, 0.0), "oak_log[axis=x]");
+        assert_places(Block::StoneSlab, &context(BlockFace::Bottom, 0.0, 0.0), "stone_slab[type=top]");
+        assert_places(Block::Torch, &context(BlockFace::West, 0.5, 0.0), "wall_torch[facing=west]");
+        assert_places(Block::OakSign, &context(BlockFace::Top, 1.0, 0.0), "oak_sign[rotation=8]");
+
+        let mut in_water = context(BlockFace::Top, 1.0, 0.0);
+        in_water.replaced = state("water[level=0]");
+        assert_places(Block::OakFence, &in_water, "oak_fence[waterlogged=true]");
+
+        let mut on_slab = context(BlockFace::Top, 0.5, 0.0);
+        on_slab.replaced = state("stone_slab[type=bottom]");
+        assert_places(Block::StoneSlab, &on_slab, "stone_slab[type=double]");
+    }
+
+    #[test]
+    fn test_chest_partner() {
+        let position = BlockPosition { x: 0, y: 0, z: 0 };
+        assert_eq!(chest_partner(&position, &state("chest[facing=north,type=left]")), Some(BlockPosition { x: 1, y: 0, z: 0 }));
+        assert_eq!(chest_partner(&position, &state("chest[facing=north,type=right]")), Some(BlockPosition { x: -1, y: 0, z: 0 }));
+        assert_eq!(chest_partner(&position, &state("chest[facing=east,type=left]")), Some(BlockPosition { x: 0, y: 0, z: 1 }));
+        assert_eq!(chest_partner(&position, &state("chest[facing=north,type=single]")), None);
+    }
+
+    #[test]
+    fn test_double_block_partner() {
+        let position = BlockPosition { x: 0, y: 0, z: 0 };
+        assert_eq!(double_block_partner(&position, &state("oak_door[half=lower]")), Some(BlockPosition { x: 0, y: 1, z: 0 }));
+        assert_eq!(double_block_partner(&position, &state("oak_door[half=upper]")), Some(BlockPosition { x: 0, y: -1, z: 0 }));
+        assert_eq!(double_block_partner(&position, &state("oak_stairs[half=top]")), None);
+        assert_eq!(double_block_partner(&position, &state("stone")), None);
+    }
+
+    #[test]
+    fn test_is_replaceable() {
+        assert!(is_replaceable(&BlockWithState::Air));
+        assert!(is_replaceable(&state("water[level=0]")));
+        assert!(is_replaceable(&state("tall_grass[half=lower]")));
+        assert!(!is_replaceable(&state("stone")));
+    }
+}
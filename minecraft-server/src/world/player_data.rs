@@ -0,0 +1,127 @@
+use minecraft_protocol::nbt::parse_nbt;
+use super::*;
+
+/// What is remembered about a player between sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerData {
+    pub position: Position,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub gamemode: Gamemode,
+    /// The slots of the inventory, numbered like in [PlayerInventory]
+    pub inventory: Vec<Slot>,
+    /// The selected hotbar slot, from 0 to 8
+    pub selected_hotbar_slot: u8,
+}
+
+impl PlayerData {
+    /// Returns the data of a player joining for the first time.
+    pub fn new(config: &ServerConfig) -> PlayerData {
+        PlayerData {
+            position: config.spawn_position(),
+            yaw: 0.0,
+            pitch: 0.0,
+            gamemode: config.gamemode.clone(),
+            inventory: vec![Slot::default(); PlayerInventory::SLOT_COUNT],
+            selected_hotbar_slot: 0,
+        }
+    }
+
+    fn to_nbt(&self) -> NbtTag {
+        let mut root = HashMap::new();
+        root.insert(String::from("x"), NbtTag::Double(self.position.x));
+        root.insert(String::from("y"), NbtTag::Double(self.position.y));
+        root.insert(String::from("z"), NbtTag::Double(self.position.z));
+        root.insert(String::from("yaw"), NbtTag::Float(self.yaw));
+        root.insert(String::from("pitch"), NbtTag::Float(self.pitch));
+        root.insert(String::from("gamemode"), NbtTag::Byte(self.gamemode.clone() as u8 as i8));
+        root.insert(String::from("Inventory"), items_to_nbt(&self.inventory));
+        root.insert(String::from("SelectedItemSlot"), NbtTag::Byte(self.selected_hotbar_slot as i8));
+        NbtTag::RootCompound(String::new(), root)
+    }
+
+    fn from_nbt(tag: &NbtTag) -> Option<PlayerData> {
+        let NbtTag::RootCompound(_, root) = tag else { return None };
+        let gamemode = match root.get("gamemode")?.as_byte()? {
+            0 => Gamemode::Survival,
+            1 => Gamemode::Creative,
+            2 => Gamemode::Adventure,
+            3 => Gamemode::Spectator,
+            _ => return None,
+        };
+        // Data saved before inventories were saved has no inventory
+        let mut inventory = vec![Slot::default(); PlayerInventory::SLOT_COUNT];
+        items_from_nbt(root.get("Inventory"), &mut inventory);
+        let selected_hotbar_slot = root.get("SelectedItemSlot").and_then(|t| t.as_byte()).map(|slot| *slot as u8).filter(|slot| *slot < 9).unwrap_or(0);
+        Some(PlayerData {
+            position: Position {
+                x: *root.get("x")?.as_double()?,
+                y: *root.get("y")?.as_double()?,
+                z: *root.get("z")?.as_double()?,
+            },
+            yaw: *root.get("yaw")?.as_float()?,
+            pitch: *root.get("pitch")?.as_float()?,
+            gamemode,
+            inventory,
+            selected_hotbar_slot,
+        })
+    }
+}
+
+impl World {
+    fn player_data_path(&self, uuid: UUID) -> Option<PathBuf> {
+        Some(self.storage.as_ref()?.join("playerdata").join(format!("{uuid:032x}.nbt")))
+    }
+
+    /// Reads what was saved when the player last left.
+    /// Returns `None` for new players and worlds without storage.
+    pub async fn load_player_data(&self, uuid: UUID) -> Option<PlayerData> {
+        let path = self.player_data_path(uuid)?;
+        let data = tokio::fs::read(&path).await.ok()?;
+        let player_data = parse_nbt(&data).ok().and_then(|(tag, _)| PlayerData::from_nbt(&tag));
+        if player_data.is_none() {
+            error!("Data of player {uuid:032x} is corrupted and will be reset");
+        }
+        player_data
+    }
+
+    pub async fn save_player_data(&self, uuid: UUID, player_data: &PlayerData) {
+        let Some(path) = self.player_data_path(uuid) else { return };
+        let mut data = Vec::new();
+        player_data.to_nbt().serialize(&mut data);
+        if let Some(directory) = path.parent() {
+            let _ = tokio::fs::create_dir_all(directory).await;
+        }
+        if let Err(e) = tokio::fs::write(&path, data).await {
+            error!("Failed to save data of player {uuid:032x}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_player_data_storage() {
+        let directory = std::env::temp_dir().join(format!("minecraft-server-player-data-test-{}", std::process::id()));
+        let world = World::with_storage(directory.clone());
+        assert_eq!(world.load_player_data(1).await, None);
+
+        let mut inventory = vec![Slot::default(); PlayerInventory::SLOT_COUNT];
+        inventory[PlayerInventory::HOTBAR_START + 2].item = Some(SlotItem { item_id: Item::DiamondPickaxe, item_count: 1, nbt_data: NbtTag::Null });
+        inventory[PlayerInventory::OFFHAND].item = Some(SlotItem { item_id: Item::Torch, item_count: 64, nbt_data: NbtTag::Null });
+        let player_data = PlayerData {
+            position: Position { x: 1.5, y: 64.0, z: -3.25 },
+            yaw: 90.0,
+            pitch: -10.0,
+            gamemode: Gamemode::Adventure,
+            inventory,
+            selected_hotbar_slot: 2,
+        };
+        world.save_player_data(1, &player_data).await;
+        assert_eq!(world.load_player_data(1).await, Some(player_data));
+
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
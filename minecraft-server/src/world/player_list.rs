@@ -0,0 +1,85 @@
+use super::*;
+
+/// A property of a player profile, such as the textures of its skin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+/// An entry of the player list, shown to clients when they press tab
+#[derive(Debug, Clone)]
+pub struct PlayerListEntry {
+    pub eid: Eid,
+    pub name: String,
+    pub properties: Vec<ProfileProperty>,
+    pub gamemode: Gamemode,
+    /// Measured in milliseconds
+    pub latency: i32,
+    /// Whether the player is shown in the tab list
+    pub listed: bool,
+}
+
+/// A change to an entry of the player list
+#[derive(Debug, Clone)]
+pub enum PlayerListUpdate {
+    Gamemode(Gamemode),
+    Latency(i32),
+    Listed(bool),
+}
+
+impl World {
+    /// Adds a player to the player list of every client.
+    /// This must be done before the player entity is spawned, as clients ignore players they don't know.
+    pub async fn add_player(&self, uuid: UUID, entry: PlayerListEntry) {
+        self.players.write().await.insert(uuid, entry.clone());
+        self.notify_all(WorldChange::PlayerInfoAdded { uuid, entry }).await;
+    }
+
+    /// Updates the player list entry of a player.
+    pub async fn update_player(&self, uuid: UUID, update: PlayerListUpdate) {
+        let mut players = self.players.write().await;
+        let Some(entry) = players.get_mut(&uuid) else { return };
+        match &update {
+            PlayerListUpdate::Gamemode(gamemode) => entry.gamemode = gamemode.clone(),
+            PlayerListUpdate::Latency(latency) => entry.latency = *latency,
+            PlayerListUpdate::Listed(listed) => entry.listed = *listed,
+        }
+        drop(players);
+        self.notify_all(WorldChange::PlayerInfoUpdated { uuid, update }).await;
+    }
+
+    /// Removes a player from the player list of every client.
+    pub async fn remove_player(&self, uuid: UUID) -> Option<PlayerListEntry> {
+        let entry = self.players.write().await.remove(&uuid)?;
+        self.notify_all(WorldChange::PlayerInfoRemoved { uuid }).await;
+        Some(entry)
+    }
+
+    /// Returns the entries of the player list.
+    pub async fn players(&self) -> Vec<(UUID, PlayerListEntry)> {
+        self.players.read().await.iter().map(|(uuid, entry)| (*uuid, entry.clone())).collect()
+    }
+
+    /// Returns the player list entry of a player.
+    pub async fn player(&self, uuid: UUID) -> Option<PlayerListEntry> {
+        self.players.read().await.get(&uuid).cloned()
+    }
+
+    /// Returns the permission level of a player, from 0 to 4.
+    pub async fn permission_level(&self, uuid: UUID) -> u8 {
+        self.permission_levels.read().await.get(&uuid).copied().unwrap_or(DEFAULT_PERMISSION_LEVEL)
+    }
+
+    /// Sets the permission level of a player.
+    /// Players get the commands of their new level when they join again.
+    pub async fn set_permission_level(&self, uuid: UUID, level: u8) {
+        self.permission_levels.write().await.insert(uuid, level.min(4));
+    }
+
+    /// Returns the commands available on the server.
+    pub fn commands(&self) -> &CommandDispatcher {
+        &self.commands
+    }
+}
@@ -0,0 +1,356 @@
+//! Import and export of [Clipboard]s in the [Sponge schematic](https://github.com/SpongePowered/Schematic-Specification) (`.schem`)
+//! and vanilla structure (`.nbt`) formats.
+
+use std::io::{Read, Write};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use minecraft_protocol::{ids::blocks::Block, nbt::{arrays::NbtList, parse_nbt}};
+use super::*;
+
+/// The data version of Minecraft 1.20.2
+const DATA_VERSION: i32 = 3578;
+/// The most blocks a file can contain, so that hostile files can't exhaust memory
+const MAX_VOLUME: usize = 1 << 24;
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return Ok(data.to_vec());
+    }
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decompressed).map_err(|_| "Invalid gzip data.")?;
+    Ok(decompressed)
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec cannot fail
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+fn parse_root(data: &[u8]) -> Result<HashMap<String, NbtTag>, &'static str> {
+    let data = decompress(data)?;
+    match parse_nbt(&data)?.0 {
+        NbtTag::RootCompound(_, root) => Ok(root),
+        _ => Err("Expected a root compound."),
+    }
+}
+
+fn serialize_root(name: &str, root: HashMap<String, NbtTag>) -> Vec<u8> {
+    let mut data = Vec::new();
+    NbtTag::RootCompound(name.to_string(), root).serialize(&mut data);
+    compress(&data)
+}
+
+/// Structure voids are blocks that are left untouched when pasting.
+fn parse_palette_entry(state: &str) -> Option<BlockWithState> {
+    match state.parse::<BlockWithState>() {
+        Ok(BlockWithState::StructureVoid) => None,
+        Ok(block) => Some(block),
+        Err(e) => {
+            warn!("Unsupported block state {state:?} in schematic: {e}");
+            None
+        }
+    }
+}
+
+/// Assembles a block entity from the fields stored in schematics.
+fn parse_block_entity(id: &str, position: &BlockPosition, mut data: HashMap<String, NbtTag>) -> Option<BlockEntity> {
+    data.insert(String::from("id"), NbtTag::String(id.to_string()));
+    data.insert(String::from("x"), NbtTag::Int(position.x));
+    data.insert(String::from("y"), NbtTag::Int(position.y));
+    data.insert(String::from("z"), NbtTag::Int(position.z));
+    BlockEntity::from_nbt(&NbtTag::Compound(data)).map(|(_, block_entity)| block_entity)
+}
+
+/// Returns the fields of a block entity that schematics store, without its id and position.
+fn block_entity_data(block_entity: &BlockEntity) -> HashMap<String, NbtTag> {
+    let mut data = block_entity.to_nbt(&BlockPosition::default()).as_compound().cloned().unwrap_or_default();
+    for key in ["id", "x", "y", "z"] {
+        data.remove(key);
+    }
+    data
+}
+
+fn read_position(tag: Option<&NbtTag>) -> Option<BlockPosition> {
+    let coordinates: Vec<i32> = match tag? {
+        NbtTag::IntArray(coordinates) => coordinates.clone(),
+        NbtTag::List(NbtList::Int(coordinates)) => coordinates.clone(),
+        _ => return None,
+    };
+    match coordinates.as_slice() {
+        [x, y, z] => Some(BlockPosition { x: *x, y: *y, z: *z }),
+        _ => None,
+    }
+}
+
+/// Creates an empty clipboard, refusing dimensions that are negative or too large.
+fn empty_clipboard(width: i32, height: i32, length: i32) -> Result<Clipboard, &'static str> {
+    if width < 0 || height < 0 || length < 0 {
+        return Err("Negative dimensions.");
+    }
+    let volume = (width as usize).checked_mul(height as usize).and_then(|area| area.checked_mul(length as usize));
+    match volume {
+        Some(volume) if volume <= MAX_VOLUME => Ok(Clipboard::new(width, height, length)),
+        _ => Err("Too many blocks."),
+    }
+}
+
+impl Clipboard {
+    /// Reads a Sponge schematic (version 2 or 3), compressed or not.
+    pub fn from_sponge_schematic(data: &[u8]) -> Result<Clipboard, &'static str> {
+        let root = parse_root(data)?;
+        // Version 3 wraps everything in a `Schematic` compound
+        let schematic = match root.get("Schematic").and_then(|t| t.as_compound()) {
+            Some(schematic) => schematic,
+            None => &root,
+        };
+        let version = schematic.get("Version").and_then(|t| t.as_int()).copied().unwrap_or(2);
+        let dimension = |name: &str| schematic.get(name).and_then(|t| t.as_short()).map(|d| *d as u16 as i32).ok_or("Missing schematic dimensions.");
+        let mut clipboard = empty_clipboard(dimension("Width")?, dimension("Height")?, dimension("Length")?)?;
+
+        let (palette, block_data, block_entities) = match version {
+            3 => {
+                let blocks = schematic.get("Blocks").and_then(|t| t.as_compound()).ok_or("Missing blocks in schematic.")?;
+                (blocks.get("Palette"), blocks.get("Data"), blocks.get("BlockEntities"))
+            }
+            _ => (schematic.get("Palette"), schematic.get("BlockData"), schematic.get("BlockEntities")),
+        };
+
+        // Read the palette
+        let palette = palette.and_then(|t| t.as_compound()).ok_or("Missing palette in schematic.")?;
+        let mut states = HashMap::new();
+        for (state, index) in palette {
+            let index = index.as_int().ok_or("Invalid palette index in schematic.")?;
+            states.insert(*index, parse_palette_entry(state));
+        }
+
+        // Read the block indexes, encoded as varints
+        let block_data = block_data.and_then(|t| t.as_byte_array()).ok_or("Missing block data in schematic.")?;
+        if block_data.len() < clipboard.blocks.len() {
+            return Err("Truncated block data in schematic.");
+        }
+        let mut bytes = block_data.iter().map(|b| *b as u8);
+        for block in clipboard.blocks.iter_mut() {
+            let mut index: i32 = 0;
+            for shift in (0..35).step_by(7) {
+                let byte = bytes.next().ok_or("Truncated block data in schematic.")?;
+                index |= ((byte & 0x7f) as i32) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            *block = states.get(&index).cloned().ok_or("Block data references an unknown palette entry.")?;
+        }
+
+        // Read the block entities
+        if let Some(NbtList::Compound(block_entities)) = block_entities.and_then(|t| t.as_list()) {
+            for block_entity in block_entities {
+                let Some(position) = read_position(block_entity.get("Pos")) else { continue };
+                let Some(id) = block_entity.get("Id").and_then(|t| t.as_string()) else { continue };
+                let data = match version {
+                    3 => block_entity.get("Data").and_then(|t| t.as_compound()).cloned().unwrap_or_default(),
+                    _ => block_entity.clone(),
+                };
+                if let Some(block_entity) = parse_block_entity(id, &position, data) {
+                    clipboard.block_entities.insert(position, block_entity);
+                }
+            }
+        }
+
+        Ok(clipboard)
+    }
+
+    /// Writes a compressed Sponge schematic (version 2).
+    pub fn to_sponge_schematic(&self) -> Vec<u8> {
+        let mut palette: HashMap<String, NbtTag> = HashMap::new();
+        let mut block_data = Vec::new();
+        for block in &self.blocks {
+            let state = match block {
+                Some(block) => block.to_string(),
+                None => String::from("minecraft:structure_void"),
+            };
+            let next_index = palette.len() as i32;
+            let mut index = *palette.entry(state).or_insert(NbtTag::Int(next_index)).as_int().unwrap_or(&0) as u32;
+            loop {
+                if index & !0x7f == 0 {
+                    block_data.push(index as i8);
+                    break;
+                }
+                block_data.push(((index & 0x7f) | 0x80) as u8 as i8);
+                index >>= 7;
+            }
+        }
+
+        let block_entities = self.block_entities.iter().map(|(position, block_entity)| {
+            let mut compound = block_entity_data(block_entity);
+            compound.insert(String::from("Id"), NbtTag::String(block_entity.ty.text_id().to_string()));
+            compound.insert(String::from("Pos"), NbtTag::IntArray(vec![position.x, position.y, position.z]));
+            compound
+        }).collect();
+
+        let mut schematic = HashMap::new();
+        schematic.insert(String::from("Version"), NbtTag::Int(2));
+        schematic.insert(String::from("DataVersion"), NbtTag::Int(DATA_VERSION));
+        schematic.insert(String::from("Width"), NbtTag::Short(self.width as u16 as i16));
+        schematic.insert(String::from("Height"), NbtTag::Short(self.height as u16 as i16));
+        schematic.insert(String::from("Length"), NbtTag::Short(self.length as u16 as i16));
+        schematic.insert(String::from("Offset"), NbtTag::IntArray(vec![0, 0, 0]));
+        schematic.insert(String::from("PaletteMax"), NbtTag::Int(palette.len() as i32));
+        schematic.insert(String::from("Palette"), NbtTag::Compound(palette));
+        schematic.insert(String::from("BlockData"), NbtTag::ByteArray(block_data));
+        schematic.insert(String::from("BlockEntities"), NbtTag::List(NbtList::Compound(block_entities)));
+        serialize_root("Schematic", schematic)
+    }
+
+    /// Reads a vanilla structure, compressed or not.
+    pub fn from_structure(data: &[u8]) -> Result<Clipboard, &'static str> {
+        let root = parse_root(data)?;
+        let size = read_position(root.get("size")).ok_or("Missing structure size.")?;
+        let mut clipboard = empty_clipboard(size.x, size.y, size.z)?;
+
+        // Structures with random variants have several palettes, the first one is used
+        let palette = match (root.get("palette"), root.get("palettes")) {
+            (Some(NbtTag::List(NbtList::Compound(palette))), _) => palette,
+            (_, Some(NbtTag::List(NbtList::List(palettes)))) => match palettes.first() {
+                Some(NbtList::Compound(palette)) => palette,
+                _ => return Err("Invalid structure palette."),
+            },
+            _ => return Err("Missing structure palette."),
+        };
+        let mut states = Vec::new();
+        for entry in palette {
+            let name = entry.get("Name").and_then(|t| t.as_string()).ok_or("Missing block name in structure palette.")?;
+            let properties = entry.get("Properties").and_then(|t| t.as_compound()).map(|properties| {
+                properties.iter().filter_map(|(k, v)| Some(format!("{k}={}", v.as_string()?))).collect::<Vec<_>>().join(",")
+            }).unwrap_or_default();
+            states.push(parse_palette_entry(&format!("{name}[{properties}]")));
+        }
+
+        let Some(NbtTag::List(NbtList::Compound(blocks))) = root.get("blocks") else { return Err("Missing structure blocks.") };
+        for block in blocks {
+            let position = read_position(block.get("pos")).ok_or("Missing block position in structure.")?;
+            let state = block.get("state").and_then(|t| t.as_int()).ok_or("Missing block state in structure.")?;
+            let state = states.get(*state as usize).ok_or("Structure block references an unknown palette entry.")?;
+            clipboard.set_block(&position, state.clone());
+
+            let Some(data) = block.get("nbt").and_then(|t| t.as_compound()) else { continue };
+            let Some(id) = data.get("id").and_then(|t| t.as_string()) else { continue };
+            if let Some(block_entity) = parse_block_entity(id, &position, data.clone()) {
+                clipboard.block_entities.insert(position, block_entity);
+            }
+        }
+
+        Ok(clipboard)
+    }
+
+    /// Writes a compressed vanilla structure.
+    pub fn to_structure(&self) -> Vec<u8> {
+        let mut palette = Vec::new();
+        let mut palette_indexes: HashMap<u32, i32> = HashMap::new();
+        let mut blocks = Vec::new();
+        for (position, block) in self.iter() {
+            let Some(state_id) = block.block_state_id() else { continue };
+            let index = *palette_indexes.entry(state_id).or_insert_with(|| {
+                let mut entry = HashMap::new();
+                let name = Block::from(block.clone()).text_id();
+                entry.insert(String::from("Name"), NbtTag::String(format!("minecraft:{name}")));
+                let properties: HashMap<String, NbtTag> = block.properties().iter()
+                    .filter_map(|(name, _)| Some((name.to_string(), NbtTag::String(block.property(name)?.to_string()))))
+                    .collect();
+                if !properties.is_empty() {
+                    entry.insert(String::from("Properties"), NbtTag::Compound(properties));
+                }
+                palette.push(entry);
+                palette.len() as i32 - 1
+            });
+
+            let mut compound = HashMap::new();
+            compound.insert(String::from("state"), NbtTag::Int(index));
+            compound.insert(String::from("pos"), NbtTag::List(NbtList::Int(vec![position.x, position.y, position.z])));
+            if let Some(block_entity) = self.block_entities.get(&position) {
+                let mut data = block_entity_data(block_entity);
+                data.insert(String::from("id"), NbtTag::String(block_entity.ty.text_id().to_string()));
+                compound.insert(String::from("nbt"), NbtTag::Compound(data));
+            }
+            blocks.push(compound);
+        }
+
+        let mut root = HashMap::new();
+        root.insert(String::from("DataVersion"), NbtTag::Int(DATA_VERSION));
+        root.insert(String::from("size"), NbtTag::List(NbtList::Int(vec![self.width, self.height, self.length])));
+        root.insert(String::from("palette"), NbtTag::List(NbtList::Compound(palette)));
+        root.insert(String::from("blocks"), NbtTag::List(NbtList::Compound(blocks)));
+        root.insert(String::from("entities"), NbtTag::List(NbtList::Compound(Vec::new())));
+        serialize_root("", root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_clipboard() -> Clipboard {
+        let mut clipboard = Clipboard::new(4, 2, 3);
+        for (i, position) in BlockRange::new(BlockPosition { x: 0, y: 0, z: 0 }, BlockPosition { x: 3, y: 1, z: 2 }).into_iter().enumerate() {
+            let block = match i % 3 {
+                0 => Some("minecraft:oak_stairs[facing=west,half=top]".parse().unwrap()),
+                1 => Some(BlockWithState::Dirt),
+                _ => None,
+            };
+            clipboard.set_block(&position, block);
+        }
+        let position = BlockPosition { x: 1, y: 1, z: 2 };
+        clipboard.set_block(&position, Some(BlockWithState::from(Block::Chest)));
+        let mut chest = BlockEntity::new(minecraft_protocol::components::blocks::BlockEntityType::Chest);
+        if let BlockEntityData::Container { custom_name, .. } = &mut chest.data {
+            *custom_name = Some(String::from("\"Loot\""));
+        }
+        clipboard.block_entities.insert(position, chest);
+        clipboard
+    }
+
+    fn assert_same(a: &Clipboard, b: &Clipboard) {
+        assert_eq!((a.width, a.height, a.length), (b.width, b.height, b.length));
+        for (block_a, block_b) in a.blocks.iter().zip(b.blocks.iter()) {
+            assert_eq!(block_a.as_ref().and_then(|b| b.block_state_id()), block_b.as_ref().and_then(|b| b.block_state_id()));
+        }
+        let position = BlockPosition { x: 1, y: 1, z: 2 };
+        let BlockEntityData::Container { custom_name, .. } = &b.block_entities.get(&position).unwrap().data else { panic!("Chest has wrong data") };
+        assert_eq!(custom_name.as_deref(), Some("\"Loot\""));
+    }
+
+    #[test]
+    fn test_sponge_schematic() {
+        let clipboard = sample_clipboard();
+        let parsed = Clipboard::from_sponge_schematic(&clipboard.to_sponge_schematic()).unwrap();
+        assert_same(&clipboard, &parsed);
+    }
+
+    #[test]
+    fn test_structure() {
+        let clipboard = sample_clipboard();
+        let parsed = Clipboard::from_structure(&clipboard.to_structure()).unwrap();
+        assert_same(&clipboard, &parsed);
+    }
+
+    #[test]
+    fn test_oversized_files() {
+        let mut schematic = HashMap::new();
+        for dimension in ["Width", "Height", "Length"] {
+            schematic.insert(String::from(dimension), NbtTag::Short(-1));
+        }
+        schematic.insert(String::from("Palette"), NbtTag::Compound(HashMap::new()));
+        schematic.insert(String::from("BlockData"), NbtTag::ByteArray(Vec::new()));
+        assert_eq!(Clipboard::from_sponge_schematic(&serialize_root("Schematic", schematic)).err(), Some("Too many blocks."));
+
+        let mut schematic = parse_root(&Clipboard::new(4, 4, 4).to_sponge_schematic()).unwrap();
+        schematic.insert(String::from("BlockData"), NbtTag::ByteArray(vec![0; 63]));
+        assert_eq!(Clipboard::from_sponge_schematic(&serialize_root("Schematic", schematic)).err(), Some("Truncated block data in schematic."));
+
+        let mut structure = HashMap::new();
+        structure.insert(String::from("size"), NbtTag::IntArray(vec![i32::MAX, i32::MAX, 2]));
+        assert_eq!(Clipboard::from_structure(&serialize_root("", structure.clone())).err(), Some("Too many blocks."));
+        structure.insert(String::from("size"), NbtTag::IntArray(vec![-1, 1, 1]));
+        assert_eq!(Clipboard::from_structure(&serialize_root("", structure)).err(), Some("Negative dimensions."));
+    }
+}
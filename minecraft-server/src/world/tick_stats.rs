@@ -0,0 +1,113 @@
+use std::time::Instant;
+use super::*;
+
+/// The windows of the rolling tick rates, in seconds
+const TPS_WINDOWS: [f64; 3] = [60.0, 300.0, 900.0];
+/// How many ticks the average tick duration is computed on
+const MSPT_SAMPLES: usize = 100;
+
+/// Measures how long ticks take and how often they happen
+#[derive(Debug, Clone, Default)]
+pub struct TickStats {
+    /// The durations of the latest ticks, oldest first
+    durations: VecDeque<Duration>,
+    last_start: Option<Instant>,
+    /// Exponential moving averages of the tick rate over [TPS_WINDOWS]
+    tps: Option<[f64; 3]>,
+    ticks: u64,
+}
+
+impl TickStats {
+    /// Records a tick that started at `start` and ran for `duration`.
+    pub fn record(&mut self, start: Instant, duration: Duration) {
+        self.ticks += 1;
+        if self.durations.len() >= MSPT_SAMPLES {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+
+        let Some(last_start) = self.last_start.replace(start) else { return };
+        let interval = start.saturating_duration_since(last_start).as_secs_f64();
+        if interval <= 0.0 {
+            return;
+        }
+        let tps = 1.0 / interval;
+        match &mut self.tps {
+            Some(averages) => {
+                for (average, window) in averages.iter_mut().zip(TPS_WINDOWS) {
+                    let decay = (-interval / window).exp();
+                    *average = *average * decay + tps * (1.0 - decay);
+                }
+            }
+            None => self.tps = Some([tps; 3]),
+        }
+    }
+
+    /// Returns the tick rate averaged over the last 1, 5 and 15 minutes, once two ticks ran.
+    pub fn tps(&self) -> Option<[f64; 3]> {
+        self.tps
+    }
+
+    /// Returns the average duration of the latest ticks, in milliseconds.
+    pub fn mspt(&self) -> f64 {
+        if self.durations.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.durations.iter().sum();
+        total.as_secs_f64() * 1000.0 / self.durations.len() as f64
+    }
+
+    /// Returns the duration of the slowest of the latest ticks.
+    pub fn slowest(&self) -> Option<Duration> {
+        self.durations.iter().max().copied()
+    }
+
+    /// Returns how many ticks ran since the server started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+impl World {
+    pub async fn record_tick(&self, start: Instant, duration: Duration) {
+        self.tick_stats.write().await.record(start, duration);
+        self.metrics.record_tick(duration);
+    }
+
+    pub async fn tick_stats(&self) -> TickStats {
+        self.tick_stats.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_stats() {
+        let start = Instant::now();
+        let mut stats = TickStats::default();
+        stats.record(start, Duration::from_millis(10));
+        assert_eq!(stats.tps(), None);
+
+        for i in 1..1200 {
+            let duration = Duration::from_millis(if i % 2 == 0 { 10 } else { 30 });
+            stats.record(start + Duration::from_millis(50) * i, duration);
+        }
+        let [tps_1m, tps_5m, tps_15m] = stats.tps().unwrap();
+        for tps in [tps_1m, tps_5m, tps_15m] {
+            assert!((tps - 20.0).abs() < 1e-6, "Measured {tps} TPS");
+        }
+        assert!((stats.mspt() - 20.0).abs() < 1e-6);
+        assert_eq!(stats.slowest(), Some(Duration::from_millis(30)));
+        assert_eq!(stats.ticks(), 1200);
+
+        // Ticks that happen half as often are noticed sooner by the 1 minute average
+        for i in 0..1800 {
+            stats.record(start + Duration::from_millis(50) * 1199 + Duration::from_millis(100) * (i + 1), Duration::from_millis(100));
+        }
+        let [tps_1m, tps_5m, tps_15m] = stats.tps().unwrap();
+        assert!(tps_1m < tps_5m && tps_5m < tps_15m && tps_15m < 20.0);
+        assert!((tps_1m - 10.0).abs() < 1.0);
+    }
+}
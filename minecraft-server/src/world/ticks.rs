@@ -0,0 +1,178 @@
+use tokio::sync::watch;
+use super::*;
+
+/// Decides when ticks happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickSource {
+    /// A tick happens every this duration
+    RealTime(Duration),
+    /// Ticks only happen when asked with [ServerBehavior::step], which makes tests reproducible
+    Manual,
+}
+
+impl Default for TickSource {
+    fn default() -> Self {
+        TickSource::RealTime(Duration::from_millis(50))
+    }
+}
+
+#[derive(Debug, Default)]
+struct TickState {
+    /// How many ticks were started
+    tick: usize,
+    receivers: usize,
+    /// How many receivers haven't finished the latest tick yet
+    busy: usize,
+    stopped: bool,
+}
+
+/// Starts ticks and keeps track of the entity tasks waiting for them.
+pub struct TickClock {
+    state: Arc<watch::Sender<TickState>>,
+}
+
+impl TickClock {
+    pub fn new() -> TickClock {
+        TickClock { state: Arc::new(watch::channel(TickState::default()).0) }
+    }
+
+    /// Returns a receiver woken up by the ticks started from now on.
+    pub fn subscribe(&self) -> TickReceiver {
+        TickReceiver::new(Arc::clone(&self.state))
+    }
+
+    /// Starts a tick, returning its id.
+    pub fn advance(&self) -> usize {
+        let mut tick = 0;
+        self.state.send_modify(|state| {
+            state.tick += 1;
+            state.busy = state.receivers;
+            tick = state.tick;
+        });
+        tick
+    }
+
+    /// Waits until every receiver is done with the latest tick and waits for the next one.
+    pub async fn processed(&self) {
+        let _ = self.state.subscribe().wait_for(|state| state.busy == 0 || state.stopped).await;
+    }
+
+    /// Stops ticking for good. Receivers then return `None`.
+    pub fn stop(&self) {
+        self.state.send_modify(|state| state.stopped = true);
+    }
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        TickClock::new()
+    }
+}
+
+/// Waits for ticks started by a [TickClock]
+pub struct TickReceiver {
+    state: Arc<watch::Sender<TickState>>,
+    receiver: watch::Receiver<TickState>,
+    /// The latest tick returned by [TickReceiver::next]
+    seen: usize,
+    /// The latest tick this receiver reported as finished
+    done: usize,
+}
+
+impl TickReceiver {
+    fn new(state: Arc<watch::Sender<TickState>>) -> TickReceiver {
+        let mut tick = 0;
+        state.send_modify(|state| {
+            state.receivers += 1;
+            tick = state.tick;
+        });
+        let receiver = state.subscribe();
+        TickReceiver { state, receiver, seen: tick, done: tick }
+    }
+
+    /// Returns another receiver for the same clock, like [BroadcastReceiver::resubscribe].
+    pub fn resubscribe(&self) -> TickReceiver {
+        TickReceiver::new(Arc::clone(&self.state))
+    }
+
+    /// Reports the previous tick as processed, and waits for the next one.
+    /// Receivers that are too slow skip the ticks they missed.
+    /// Returns `None` once the clock is stopped.
+    pub async fn next(&mut self) -> Option<usize> {
+        // Receivers are only waited for once they have seen the latest tick
+        let seen = self.seen;
+        let done = &mut self.done;
+        self.state.send_if_modified(|state| {
+            if state.tick == seen && seen > *done {
+                *done = seen;
+                state.busy -= 1;
+                return true;
+            }
+            false
+        });
+
+        let (tick, stopped) = {
+            let state = self.receiver.wait_for(|state| state.stopped || state.tick > seen).await.ok()?;
+            (state.tick, state.stopped)
+        };
+        if stopped {
+            return None;
+        }
+        self.seen = tick;
+        Some(tick)
+    }
+
+    /// Waits for this many ticks.
+    pub async fn sleep(&mut self, ticks: usize) -> Option<()> {
+        let target = self.seen + ticks;
+        while self.seen < target {
+            self.next().await?;
+        }
+        Some(())
+    }
+}
+
+impl Drop for TickReceiver {
+    fn drop(&mut self) {
+        let done = self.done;
+        self.state.send_modify(|state| {
+            state.receivers -= 1;
+            if state.tick > done {
+                state.busy -= 1;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tick_clock() {
+        let clock = TickClock::new();
+        let mut receiver = clock.subscribe();
+        let (processed_sender, mut processed_receiver) = mpsc_channel(10);
+        let task = tokio::spawn(async move {
+            while let Some(tick) = receiver.next().await {
+                processed_sender.send(tick).await.unwrap();
+            }
+        });
+
+        for tick in 1..=3 {
+            assert_eq!(clock.advance(), tick);
+            clock.processed().await;
+            assert_eq!(processed_receiver.try_recv(), Ok(tick));
+        }
+
+        // Dropped receivers are not waited for
+        let other_receiver = clock.subscribe();
+        clock.advance();
+        drop(other_receiver);
+        clock.processed().await;
+        assert_eq!(processed_receiver.try_recv(), Ok(4));
+
+        clock.stop();
+        task.await.unwrap();
+    }
+}
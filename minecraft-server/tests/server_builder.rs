@@ -0,0 +1,44 @@
+use minecraft_server::prelude::*;
+
+/// Starts a server on an ephemeral port and asks for its status like the server list does.
+#[tokio::test]
+async fn test_embedded_server() {
+    let server = ServerBuilder::new()
+        .address(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .tick_rate(100)
+        .generator(|_position: ChunkColumnPosition| ChunkColumn::empty())
+        .start()
+        .await
+        .unwrap();
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0);
+    assert_eq!(server.world().players().await.len(), 0);
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut request = Vec::new();
+    for packet in [
+        HandshakeServerbound::Hello {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_address: "localhost",
+            server_port: addr.port(),
+            next_state: ConnectionState::Status,
+        }.serialize_minecraft_packet().unwrap(),
+        StatusServerbound::Request.serialize_minecraft_packet().unwrap(),
+    ] {
+        request.extend(VarInt::from(packet.len()).serialize_minecraft_packet().unwrap());
+        request.extend(packet);
+    }
+    stream.write_all(&request).await.unwrap();
+
+    let expected = format!("\"protocol\":{PROTOCOL_VERSION}");
+    let mut response = Vec::new();
+    while !String::from_utf8_lossy(&response).contains(&expected) {
+        let mut buffer = [0; 1024];
+        let read = stream.read(&mut buffer).await.unwrap();
+        assert!(read > 0, "Connection closed before the status response was received");
+        response.extend_from_slice(&buffer[..read]);
+    }
+
+    server.stop().await;
+    assert!(TcpStream::connect(addr).await.is_err());
+}